@@ -270,3 +270,619 @@ CREATE TABLE ingredient_usages
     created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
     deleted_at TIMESTAMP DEFAULT NULL
 );
+
+CREATE TABLE ingredient_alerts
+(
+    alert_id SERIAL PRIMARY KEY,
+    ingredient_id INT REFERENCES ingredients(ingredient_id),
+    alert_type VARCHAR(20) NOT NULL,
+    stock_quantity DECIMAL NOT NULL,
+    reorder_level DECIMAL NOT NULL,
+    expiry_date DATE NULL,
+    shop_id INTEGER REFERENCES shops(id),
+    created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
+    resolved_at TIMESTAMP DEFAULT NULL
+);
+
+CREATE TABLE stock_takes
+(
+    stock_take_id SERIAL PRIMARY KEY,
+    shop_id INTEGER REFERENCES shops(id),
+    status VARCHAR(20) DEFAULT 'Open',
+    notes TEXT,
+    created_by INTEGER REFERENCES users(id),
+    created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
+    posted_at TIMESTAMP DEFAULT NULL,
+    deleted_at TIMESTAMP DEFAULT NULL
+);
+
+CREATE TABLE stock_take_items
+(
+    stock_take_item_id SERIAL PRIMARY KEY,
+    stock_take_id INT REFERENCES stock_takes(stock_take_id),
+    ingredient_id INT REFERENCES ingredients(ingredient_id),
+    expected_quantity DECIMAL NOT NULL,
+    counted_quantity DECIMAL NULL,
+    adjustment_type VARCHAR(20) DEFAULT 'count_variance',
+    unit_cost DECIMAL DEFAULT 0,
+    notes TEXT
+);
+
+CREATE TABLE stock_adjustments
+(
+    adjustment_id SERIAL PRIMARY KEY,
+    ingredient_id INT REFERENCES ingredients(ingredient_id),
+    stock_take_id INT REFERENCES stock_takes(stock_take_id),
+    adjustment_type VARCHAR(20) NOT NULL,
+    quantity DECIMAL NOT NULL,
+    unit_cost DECIMAL DEFAULT 0,
+    notes TEXT,
+    shop_id INTEGER REFERENCES shops(id),
+    created_by INTEGER REFERENCES users(id),
+    created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP
+);
+
+-- Append-only ledger of every stock change. ingredients.stock_quantity always equals the
+-- balance_after of the ingredient's latest movement.
+CREATE TABLE stock_movements
+(
+    movement_id SERIAL PRIMARY KEY,
+    ingredient_id INT REFERENCES ingredients(ingredient_id),
+    movement_type VARCHAR(20) NOT NULL,
+    quantity DECIMAL NOT NULL,
+    balance_after DECIMAL NOT NULL,
+    unit_cost DECIMAL DEFAULT 0,
+    reference_type VARCHAR(50) NOT NULL,
+    reference_id INT NULL,
+    notes TEXT,
+    shop_id INTEGER REFERENCES shops(id),
+    created_by INTEGER REFERENCES users(id),
+    created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP
+);
+
+ALTER TABLE orders ADD COLUMN stock_settled_at TIMESTAMP DEFAULT NULL;
+
+CREATE TABLE ingredient_lots
+(
+    lot_id SERIAL PRIMARY KEY,
+    ingredient_id INT REFERENCES ingredients(ingredient_id),
+    purchase_detail_id INT REFERENCES purchase_details(purchase_detail_id),
+    lot_number VARCHAR(100),
+    expiry_date DATE NULL,
+    quantity_received DECIMAL NOT NULL,
+    quantity_remaining DECIMAL NOT NULL,
+    quantity_wasted DECIMAL DEFAULT 0,
+    unit_cost DECIMAL DEFAULT 0,
+    shop_id INTEGER REFERENCES shops(id),
+    wasted_at TIMESTAMP DEFAULT NULL,
+    created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
+    deleted_at TIMESTAMP DEFAULT NULL
+);
+
+CREATE TABLE ingredient_lot_consumptions
+(
+    consumption_id SERIAL PRIMARY KEY,
+    lot_id INT REFERENCES ingredient_lots(lot_id),
+    quantity DECIMAL NOT NULL,
+    reference_type VARCHAR(50) NOT NULL,
+    reference_id INT NULL,
+    created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
+    restored_at TIMESTAMP DEFAULT NULL
+);
+
+CREATE TABLE stock_transfers
+(
+    transfer_id SERIAL PRIMARY KEY,
+    from_shop_id INTEGER REFERENCES shops(id),
+    to_shop_id INTEGER REFERENCES shops(id),
+    status VARCHAR(20) NOT NULL DEFAULT 'InTransit',
+    notes TEXT,
+    created_by INTEGER REFERENCES users(id),
+    received_by INTEGER REFERENCES users(id),
+    received_at TIMESTAMP DEFAULT NULL,
+    canceled_at TIMESTAMP DEFAULT NULL,
+    created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
+    deleted_at TIMESTAMP DEFAULT NULL
+);
+
+CREATE TABLE stock_transfer_items
+(
+    transfer_item_id SERIAL PRIMARY KEY,
+    transfer_id INT REFERENCES stock_transfers(transfer_id),
+    ingredient_id INT REFERENCES ingredients(ingredient_id),
+    to_ingredient_id INT REFERENCES ingredients(ingredient_id),
+    quantity DECIMAL NOT NULL,
+    unit_cost DECIMAL DEFAULT 0,
+    expiry_date DATE NULL,
+    created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE TABLE item_ingredients
+(
+    item_ingredient_id SERIAL PRIMARY KEY,
+    item_id INTEGER REFERENCES items(id),
+    ingredient_id INT REFERENCES ingredients(ingredient_id),
+    quantity DECIMAL NOT NULL,
+    created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
+    deleted_at TIMESTAMP DEFAULT NULL
+);
+
+CREATE TABLE report_jobs
+(
+    job_id SERIAL PRIMARY KEY,
+    report_type VARCHAR(50) NOT NULL,
+    status VARCHAR(20) NOT NULL DEFAULT 'Pending',
+    shop_id INTEGER REFERENCES shops(id),
+    from_date DATE NOT NULL,
+    to_date DATE NOT NULL,
+    error_message TEXT,
+    requested_by INTEGER REFERENCES users(id),
+    pdf_path TEXT,
+    excel_path TEXT,
+    created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
+    completed_at TIMESTAMP DEFAULT NULL,
+    expires_at TIMESTAMP DEFAULT NULL,
+    deleted_at TIMESTAMP DEFAULT NULL
+);
+
+ALTER TABLE orders ADD COLUMN covers INTEGER DEFAULT NULL;
+ALTER TABLE orders ADD COLUMN completed_at TIMESTAMP DEFAULT NULL;
+
+CREATE TABLE tip_pool_rules
+(
+    tip_pool_rule_id SERIAL PRIMARY KEY,
+    shop_id INTEGER UNIQUE REFERENCES shops(id),
+    distribution_method VARCHAR(20) NOT NULL DEFAULT 'Individual',
+    house_percent DECIMAL(5, 2) NOT NULL DEFAULT 0,
+    created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
+    deleted_at TIMESTAMP DEFAULT NULL
+);
+
+ALTER TABLE orders ADD COLUMN tip DECIMAL(10, 2) DEFAULT 0.0;
+ALTER TABLE orders ADD COLUMN served_at TIMESTAMP DEFAULT NULL;
+
+CREATE TABLE register_reports
+(
+    register_report_id SERIAL PRIMARY KEY,
+    shop_id INTEGER REFERENCES shops(id),
+    report_type VARCHAR(1) NOT NULL,
+    z_number INTEGER DEFAULT NULL,
+    period_start TIMESTAMP NOT NULL,
+    period_end TIMESTAMP NOT NULL,
+    order_count INTEGER NOT NULL DEFAULT 0,
+    gross_sales DECIMAL(12, 2) NOT NULL DEFAULT 0,
+    discounts DECIMAL(12, 2) NOT NULL DEFAULT 0,
+    net_sales DECIMAL(12, 2) NOT NULL DEFAULT 0,
+    tax DECIMAL(12, 2) NOT NULL DEFAULT 0,
+    tips DECIMAL(12, 2) NOT NULL DEFAULT 0,
+    void_count INTEGER NOT NULL DEFAULT 0,
+    void_amount DECIMAL(12, 2) NOT NULL DEFAULT 0,
+    refund_count INTEGER NOT NULL DEFAULT 0,
+    refund_amount DECIMAL(12, 2) NOT NULL DEFAULT 0,
+    expected_cash DECIMAL(12, 2) NOT NULL DEFAULT 0,
+    generated_by INTEGER REFERENCES users(id),
+    created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
+    UNIQUE (shop_id, z_number)
+);
+
+CREATE TABLE register_report_payments
+(
+    register_report_payment_id SERIAL PRIMARY KEY,
+    register_report_id INTEGER REFERENCES register_reports(register_report_id),
+    payment_type VARCHAR(10) NOT NULL,
+    order_count INTEGER NOT NULL DEFAULT 0,
+    amount DECIMAL(12, 2) NOT NULL DEFAULT 0
+);
+
+ALTER TABLE orders ADD COLUMN canceled_at TIMESTAMP DEFAULT NULL;
+
+CREATE TABLE cash_shifts
+(
+    shift_id SERIAL PRIMARY KEY,
+    shop_id INTEGER REFERENCES shops(id),
+    user_id INTEGER REFERENCES users(id),
+    register_name VARCHAR(100) DEFAULT NULL,
+    status VARCHAR(20) NOT NULL DEFAULT 'Open',
+    opening_float DECIMAL(12, 2) NOT NULL DEFAULT 0,
+    expected_cash DECIMAL(12, 2) DEFAULT NULL,
+    counted_cash DECIMAL(12, 2) DEFAULT NULL,
+    variance DECIMAL(12, 2) DEFAULT NULL,
+    notes TEXT,
+    closed_by INTEGER REFERENCES users(id),
+    opened_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
+    closed_at TIMESTAMP DEFAULT NULL
+);
+
+CREATE TABLE cash_movements
+(
+    cash_movement_id SERIAL PRIMARY KEY,
+    shift_id INTEGER REFERENCES cash_shifts(shift_id),
+    movement_type VARCHAR(10) NOT NULL,
+    amount DECIMAL(12, 2) NOT NULL,
+    reason TEXT,
+    created_by INTEGER REFERENCES users(id),
+    created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP
+);
+
+ALTER TABLE orders ADD COLUMN shift_id INTEGER REFERENCES cash_shifts(shift_id);
+ALTER TABLE orders ADD COLUMN refund_shift_id INTEGER REFERENCES cash_shifts(shift_id);
+
+CREATE TABLE tax_rules
+(
+    tax_rule_id SERIAL PRIMARY KEY,
+    shop_id INTEGER REFERENCES shops(id),
+    name VARCHAR(100) NOT NULL,
+    rate DECIMAL(5, 2) NOT NULL,
+    is_inclusive BOOLEAN NOT NULL DEFAULT FALSE,
+    is_compound BOOLEAN NOT NULL DEFAULT FALSE,
+    sort_order INTEGER NOT NULL DEFAULT 0,
+    created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
+    deleted_at TIMESTAMP DEFAULT NULL
+);
+
+CREATE TABLE tax_rule_exemptions
+(
+    tax_rule_exemption_id SERIAL PRIMARY KEY,
+    tax_rule_id INTEGER REFERENCES tax_rules(tax_rule_id),
+    item_id INTEGER REFERENCES items(id),
+    category_id INTEGER REFERENCES categories(id),
+    created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE TABLE tax_settings
+(
+    tax_setting_id SERIAL PRIMARY KEY,
+    shop_id INTEGER UNIQUE REFERENCES shops(id),
+    rounding_mode VARCHAR(10) NOT NULL DEFAULT 'None',
+    rounding_increment DECIMAL(10, 2) NOT NULL DEFAULT 0.01,
+    created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
+    deleted_at TIMESTAMP DEFAULT NULL
+);
+
+CREATE TABLE order_taxes
+(
+    order_tax_id SERIAL PRIMARY KEY,
+    order_id INTEGER REFERENCES orders(id),
+    tax_rule_id INTEGER REFERENCES tax_rules(tax_rule_id),
+    name VARCHAR(100) NOT NULL,
+    rate DECIMAL(5, 2) NOT NULL,
+    is_inclusive BOOLEAN NOT NULL DEFAULT FALSE,
+    taxable_amount DECIMAL(12, 2) NOT NULL DEFAULT 0,
+    amount DECIMAL(12, 2) NOT NULL DEFAULT 0
+);
+
+CREATE TABLE register_report_taxes
+(
+    register_report_tax_id SERIAL PRIMARY KEY,
+    register_report_id INTEGER REFERENCES register_reports(register_report_id),
+    name VARCHAR(100) NOT NULL,
+    is_inclusive BOOLEAN NOT NULL DEFAULT FALSE,
+    amount DECIMAL(12, 2) NOT NULL DEFAULT 0
+);
+
+ALTER TABLE orders ADD COLUMN rounding_adjustment DECIMAL(10, 2) DEFAULT 0.0;
+ALTER TABLE register_reports ADD COLUMN rounding DECIMAL(12, 2) NOT NULL DEFAULT 0;
+
+CREATE TABLE customers
+(
+    customer_id SERIAL PRIMARY KEY,
+    shop_id INTEGER REFERENCES shops(id),
+    name VARCHAR(255) NOT NULL,
+    phone VARCHAR(20) NOT NULL,
+    email VARCHAR(255),
+    notes TEXT,
+    allergies TEXT,
+    created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
+    deleted_at TIMESTAMP DEFAULT NULL
+);
+
+ALTER TABLE orders ADD COLUMN customer_id INTEGER REFERENCES customers(customer_id);
+
+CREATE TABLE loyalty_programs
+(
+    loyalty_program_id SERIAL PRIMARY KEY,
+    shop_id INTEGER UNIQUE REFERENCES shops(id),
+    is_active BOOLEAN NOT NULL DEFAULT FALSE,
+    points_per_unit DECIMAL(10, 4) NOT NULL DEFAULT 0,
+    point_value DECIMAL(10, 2) NOT NULL DEFAULT 0,
+    min_redeem_points INTEGER NOT NULL DEFAULT 0,
+    expiry_days INTEGER DEFAULT NULL,
+    created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
+    deleted_at TIMESTAMP DEFAULT NULL
+);
+
+CREATE TABLE loyalty_tiers
+(
+    loyalty_tier_id SERIAL PRIMARY KEY,
+    shop_id INTEGER NOT NULL REFERENCES shops(id),
+    name VARCHAR(100) NOT NULL,
+    min_points INTEGER NOT NULL DEFAULT 0,
+    earn_multiplier DECIMAL(5, 2) NOT NULL DEFAULT 1,
+    created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
+    deleted_at TIMESTAMP DEFAULT NULL
+);
+
+CREATE TABLE loyalty_memberships
+(
+    loyalty_membership_id SERIAL PRIMARY KEY,
+    customer_id INTEGER NOT NULL REFERENCES customers(customer_id),
+    shop_id INTEGER NOT NULL REFERENCES shops(id),
+    loyalty_tier_id INTEGER REFERENCES loyalty_tiers(loyalty_tier_id),
+    lifetime_points INTEGER NOT NULL DEFAULT 0,
+    promoted_at TIMESTAMP DEFAULT NULL,
+    created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
+    UNIQUE (customer_id, shop_id)
+);
+
+-- Earned entries keep the points not yet redeemed or expired in remaining_points.
+CREATE TABLE loyalty_ledger
+(
+    loyalty_ledger_id SERIAL PRIMARY KEY,
+    customer_id INTEGER NOT NULL REFERENCES customers(customer_id),
+    shop_id INTEGER NOT NULL REFERENCES shops(id),
+    order_id INTEGER REFERENCES orders(id),
+    entry_type VARCHAR(20) NOT NULL,
+    points INTEGER NOT NULL,
+    remaining_points INTEGER NOT NULL DEFAULT 0,
+    expires_at TIMESTAMP DEFAULT NULL,
+    created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP
+);
+
+ALTER TABLE orders ADD COLUMN points_earned INTEGER NOT NULL DEFAULT 0;
+ALTER TABLE orders ADD COLUMN points_redeemed INTEGER NOT NULL DEFAULT 0;
+ALTER TABLE orders ADD COLUMN loyalty_settled_at TIMESTAMP DEFAULT NULL;
+
+CREATE TABLE gift_cards
+(
+    gift_card_id SERIAL PRIMARY KEY,
+    shop_id INTEGER NOT NULL REFERENCES shops(id),
+    code VARCHAR(32) NOT NULL UNIQUE,
+    customer_id INTEGER REFERENCES customers(customer_id),
+    initial_amount DECIMAL(10, 2) NOT NULL DEFAULT 0.0,
+    balance DECIMAL(10, 2) NOT NULL DEFAULT 0.0,
+    expiry_date DATE DEFAULT NULL,
+    issued_by INTEGER REFERENCES users(id),
+    created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
+    deleted_at TIMESTAMP DEFAULT NULL
+);
+
+CREATE TABLE gift_card_transactions
+(
+    gift_card_transaction_id SERIAL PRIMARY KEY,
+    gift_card_id INTEGER NOT NULL REFERENCES gift_cards(gift_card_id),
+    order_id INTEGER REFERENCES orders(id),
+    transaction_type VARCHAR(20) NOT NULL,
+    amount DECIMAL(10, 2) NOT NULL,
+    balance_after DECIMAL(10, 2) NOT NULL,
+    user_id INTEGER REFERENCES users(id),
+    created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP
+);
+
+ALTER TABLE orders ADD COLUMN gift_card_id INTEGER REFERENCES gift_cards(gift_card_id);
+
+CREATE TABLE reservations
+(
+    reservation_id SERIAL PRIMARY KEY,
+    shop_id INTEGER NOT NULL REFERENCES shops(id),
+    table_id INTEGER NOT NULL REFERENCES tables(id),
+    customer_id INTEGER REFERENCES customers(customer_id),
+    guest_name VARCHAR(255) NOT NULL,
+    guest_phone VARCHAR(20) NOT NULL,
+    party_size INTEGER NOT NULL,
+    reserved_at TIMESTAMP NOT NULL,
+    duration_minutes INTEGER NOT NULL DEFAULT 90,
+    special_requests TEXT,
+    status VARCHAR(20) NOT NULL DEFAULT 'Booked',
+    created_by INTEGER REFERENCES users(id),
+    created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
+    deleted_at TIMESTAMP DEFAULT NULL
+);
+
+CREATE TABLE waitlist_entries
+(
+    waitlist_entry_id SERIAL PRIMARY KEY,
+    shop_id INTEGER NOT NULL REFERENCES shops(id),
+    customer_id INTEGER REFERENCES customers(customer_id),
+    guest_name VARCHAR(255) NOT NULL,
+    guest_phone VARCHAR(20) NOT NULL,
+    party_size INTEGER NOT NULL,
+    quoted_wait_minutes INTEGER,
+    notes TEXT,
+    status VARCHAR(20) NOT NULL DEFAULT 'Waiting',
+    table_id INTEGER REFERENCES tables(id),
+    seated_at TIMESTAMP DEFAULT NULL,
+    created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP
+);
+
+ALTER TABLE tables ADD COLUMN capacity INTEGER NOT NULL DEFAULT 4;
+
+CREATE TABLE floor_areas
+(
+    floor_area_id SERIAL PRIMARY KEY,
+    shop_id INTEGER NOT NULL REFERENCES shops(id),
+    name VARCHAR(100) NOT NULL,
+    sort_order INTEGER NOT NULL DEFAULT 0,
+    created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
+    deleted_at TIMESTAMP DEFAULT NULL
+);
+
+CREATE TABLE sections
+(
+    section_id SERIAL PRIMARY KEY,
+    shop_id INTEGER NOT NULL REFERENCES shops(id),
+    name VARCHAR(100) NOT NULL,
+    waiter_id INTEGER REFERENCES users(id),
+    created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
+    deleted_at TIMESTAMP DEFAULT NULL
+);
+
+ALTER TABLE tables ADD COLUMN floor_area_id INTEGER REFERENCES floor_areas(floor_area_id);
+ALTER TABLE tables ADD COLUMN section_id INTEGER REFERENCES sections(section_id);
+ALTER TABLE tables ADD COLUMN shape VARCHAR(20) NOT NULL DEFAULT 'Square';
+ALTER TABLE tables ADD COLUMN pos_x INTEGER NOT NULL DEFAULT 0;
+ALTER TABLE tables ADD COLUMN pos_y INTEGER NOT NULL DEFAULT 0;
+ALTER TABLE tables ADD COLUMN width INTEGER NOT NULL DEFAULT 1;
+ALTER TABLE tables ADD COLUMN height INTEGER NOT NULL DEFAULT 1;
+ALTER TABLE tables ADD COLUMN rotation INTEGER NOT NULL DEFAULT 0;
+
+ALTER TABLE orders ADD COLUMN shop_id INTEGER REFERENCES shops(id);
+-- Orders taken before they had a shop of their own belong to the shop of their table, or of their waiter when
+-- the table has none.
+UPDATE orders o SET shop_id = t.shop_id FROM tables t WHERE t.id = o.table_id AND o.shop_id IS NULL;
+UPDATE orders o SET shop_id = u.shop_id FROM users u WHERE u.id = o.waiter_id AND o.shop_id IS NULL;
+-- Any order left without a shop has to be given one by hand, the migration stops here and lists them.
+DO $$
+DECLARE
+    order_ids TEXT;
+BEGIN
+    SELECT string_agg(id::text, ', ' ORDER BY id) INTO order_ids FROM orders WHERE shop_id IS NULL;
+    IF order_ids IS NOT NULL THEN
+        RAISE EXCEPTION 'Orders without a shop from their table or waiter: %. Set orders.shop_id for them and run this again.', order_ids;
+    END IF;
+END $$;
+ALTER TABLE orders ALTER COLUMN shop_id SET NOT NULL;
+ALTER TABLE orders ADD COLUMN order_type VARCHAR(20) NOT NULL DEFAULT 'DineIn';
+ALTER TABLE orders ADD COLUMN contact_name VARCHAR(100) DEFAULT NULL;
+ALTER TABLE orders ADD COLUMN contact_phone VARCHAR(20) DEFAULT NULL;
+ALTER TABLE orders ADD COLUMN delivery_address TEXT DEFAULT NULL;
+ALTER TABLE orders ADD COLUMN delivery_fee DECIMAL(10, 2) NOT NULL DEFAULT 0.0;
+ALTER TABLE orders ADD COLUMN scheduled_at TIMESTAMP DEFAULT NULL;
+
+CREATE TABLE delivery_platforms
+(
+    platform_id SERIAL PRIMARY KEY,
+    shop_id INTEGER NOT NULL REFERENCES shops(id),
+    adapter VARCHAR(50) NOT NULL,
+    name VARCHAR(100) NOT NULL,
+    webhook_secret VARCHAR(255) NOT NULL,
+    waiter_id INTEGER NOT NULL REFERENCES users(id),
+    is_active BOOLEAN NOT NULL DEFAULT TRUE,
+    created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
+    deleted_at TIMESTAMP DEFAULT NULL
+);
+
+CREATE TABLE delivery_platform_items
+(
+    platform_id INTEGER NOT NULL REFERENCES delivery_platforms(platform_id),
+    external_sku VARCHAR(100) NOT NULL,
+    item_id INTEGER NOT NULL REFERENCES items(id),
+    PRIMARY KEY (platform_id, external_sku)
+);
+
+CREATE TABLE delivery_platform_orders
+(
+    platform_order_id SERIAL PRIMARY KEY,
+    platform_id INTEGER NOT NULL REFERENCES delivery_platforms(platform_id),
+    external_order_id VARCHAR(100) NOT NULL,
+    order_id INTEGER REFERENCES orders(id),
+    status VARCHAR(20) NOT NULL,
+    error TEXT DEFAULT NULL,
+    payload TEXT NOT NULL,
+    created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
+    updated_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
+    UNIQUE (platform_id, external_order_id)
+);
+
+CREATE TABLE webhook_subscriptions
+(
+    subscription_id SERIAL PRIMARY KEY,
+    shop_id INTEGER NOT NULL REFERENCES shops(id),
+    url TEXT NOT NULL,
+    events TEXT[] NOT NULL,
+    secret VARCHAR(255) NOT NULL,
+    is_active BOOLEAN NOT NULL DEFAULT TRUE,
+    created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
+    deleted_at TIMESTAMP DEFAULT NULL
+);
+
+CREATE TABLE webhook_deliveries
+(
+    delivery_id SERIAL PRIMARY KEY,
+    subscription_id INTEGER NOT NULL REFERENCES webhook_subscriptions(subscription_id),
+    event VARCHAR(50) NOT NULL,
+    payload TEXT NOT NULL,
+    status VARCHAR(20) NOT NULL DEFAULT 'Pending',
+    attempts INTEGER NOT NULL DEFAULT 0,
+    response_status INTEGER DEFAULT NULL,
+    error TEXT DEFAULT NULL,
+    next_attempt_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    delivered_at TIMESTAMP DEFAULT NULL,
+    created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE TABLE socket_events
+(
+    event_id SERIAL PRIMARY KEY,
+    namespace VARCHAR(50) NOT NULL,
+    event VARCHAR(50) NOT NULL,
+    rooms INTEGER[] NOT NULL,
+    payload TEXT NOT NULL,
+    attempts INTEGER NOT NULL DEFAULT 0,
+    error TEXT DEFAULT NULL,
+    created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP
+);
+
+ALTER TABLE socket_events ADD COLUMN shop_id INTEGER REFERENCES shops(id);
+ALTER TABLE socket_events ADD COLUMN roles VARCHAR(50)[] NOT NULL DEFAULT '{}';
+
+ALTER TABLE reservations ADD COLUMN reminder_sent_at TIMESTAMP DEFAULT NULL;
+
+CREATE TABLE device_tokens
+(
+    token TEXT PRIMARY KEY,
+    user_id INTEGER NOT NULL REFERENCES users(id),
+    platform VARCHAR(20) NOT NULL,
+    created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
+    updated_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE TABLE push_notifications
+(
+    notification_id SERIAL PRIMARY KEY,
+    user_id INTEGER NOT NULL REFERENCES users(id),
+    notification_type VARCHAR(50) NOT NULL,
+    title VARCHAR(255) NOT NULL,
+    body TEXT NOT NULL,
+    data TEXT NOT NULL,
+    status VARCHAR(20) NOT NULL DEFAULT 'Pending',
+    attempts INTEGER NOT NULL DEFAULT 0,
+    error TEXT DEFAULT NULL,
+    created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
+    sent_at TIMESTAMP DEFAULT NULL
+);
+
+-- Devices a notification reached, so a retry only goes to the ones it didn't.
+CREATE TABLE push_notification_deliveries
+(
+    notification_id INTEGER NOT NULL REFERENCES push_notifications(notification_id),
+    token TEXT NOT NULL,
+    sent_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
+    PRIMARY KEY(notification_id, token)
+);
+
+-- Rows changed after a tablet's last sync are sent to it again, deleted ones as tombstones.
+ALTER TABLE items ADD COLUMN updated_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP;
+ALTER TABLE categories ADD COLUMN updated_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP;
+ALTER TABLE tables ADD COLUMN updated_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP;
+ALTER TABLE orders ADD COLUMN updated_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP;
+
+-- Items, categories and tables moved to another shop, sent as tombstones to the shop they left.
+CREATE TABLE sync_shop_moves
+(
+    move_id SERIAL PRIMARY KEY,
+    entity VARCHAR(20) NOT NULL,
+    entity_id INTEGER NOT NULL,
+    shop_id INTEGER NOT NULL REFERENCES shops(id),
+    moved_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE TABLE idempotency_keys
+(
+    idempotency_key VARCHAR(255) NOT NULL,
+    user_id INTEGER NOT NULL REFERENCES users(id),
+    request_hash VARCHAR(64) NOT NULL,
+    response_status INTEGER DEFAULT NULL,
+    response_body TEXT DEFAULT NULL,
+    created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
+    PRIMARY KEY(idempotency_key, user_id)
+);
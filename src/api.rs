@@ -1,16 +1,41 @@
+mod analytics;
 mod auth;
+mod cash_shift;
 mod category;
+mod customer;
+mod delivery_platform;
 mod discount_type;
+mod floor_area;
+mod food_cost_report;
+mod gift_card;
 mod image;
 mod ingredient_usage;
 mod item;
+mod item_ingredient;
+mod loyalty;
 mod order;
+mod push_notification;
 mod role;
+mod section;
 mod shop;
+mod stock_take;
+mod stock_transfer;
+mod sync;
 mod table;
+mod tax_rule;
+mod tip_pool_rule;
 mod user;
+mod waiter_report;
+mod waitlist;
+mod webhook;
 mod ingredient;
+mod ingredient_alert;
+mod ingredient_lot;
 mod purchashe;
+mod realtime;
+mod register_report;
+mod report_job;
+mod reservation;
 
 
 use actix_web::web;
@@ -27,6 +52,8 @@ pub fn init(cfg: &mut web::ServiceConfig) {
     cfg.service(auth::verify_token);
     cfg.service(order::get_order_by_id);
     cfg.service(order::update_order);
+    cfg.service(order::update_order_customer);
+    cfg.service(order::get_order_queue);
     cfg.service(item::add_item);
     cfg.service(item::get_item_by_id);
     cfg.service(item::update_item);
@@ -60,19 +87,126 @@ pub fn init(cfg: &mut web::ServiceConfig) {
     // cfg.service(order::download_daily_sale_report);
     cfg.service(ingredient::get_ingredients);
     cfg.service(ingredient::add_ingredient);
+    cfg.service(ingredient_alert::get_ingredient_alerts);
+    cfg.service(ingredient_alert::get_purchase_suggestion);
     cfg.service(ingredient::get_ingredient_by_id);
     cfg.service(ingredient::update_ingredient);
     cfg.service(ingredient::delete_ingredient);
+    cfg.service(ingredient::get_ingredient_movements);
+    cfg.service(ingredient_lot::get_ingredient_lots);
+    cfg.service(ingredient_lot::get_lot_report);
+    cfg.service(ingredient_lot::write_off_lot);
     cfg.service(purchashe::get_purchases);
     cfg.service(purchashe::add_purchase);
     cfg.service(purchashe::get_purchase_by_id);
     cfg.service(purchashe::update_purchase);
     cfg.service(purchashe::delete_purchase);
     cfg.service(ingredient_usage::add_ingredient_usages);
-    cfg.service(order::daily_sale_report_pdf);
-    cfg.service(order::daily_sale_report_excel);
     cfg.service(ingredient_usage::get_ingredient_usages);
     cfg.service(ingredient_usage::get_ingredient_usage_by_id);
     cfg.service(ingredient_usage::update_ingredient_usage);
     cfg.service(ingredient_usage::delete_ingredient_usage);
+    cfg.service(stock_take::get_stock_takes);
+    cfg.service(stock_take::add_stock_take);
+    cfg.service(stock_take::get_stock_take_by_id);
+    cfg.service(stock_take::update_stock_take_counts);
+    cfg.service(stock_take::post_stock_take);
+    cfg.service(stock_take::get_variance_report);
+    cfg.service(stock_take::delete_stock_take);
+    cfg.service(stock_transfer::get_stock_transfers);
+    cfg.service(stock_transfer::add_stock_transfer);
+    cfg.service(stock_transfer::get_stock_transfer_by_id);
+    cfg.service(stock_transfer::receive_stock_transfer);
+    cfg.service(stock_transfer::cancel_stock_transfer);
+    cfg.service(item_ingredient::get_item_ingredients);
+    cfg.service(item_ingredient::update_item_ingredients);
+    cfg.service(food_cost_report::get_food_cost_report);
+    cfg.service(report_job::add_report_job);
+    cfg.service(report_job::get_report_job_by_id);
+    cfg.service(report_job::download_report_job);
+    cfg.service(report_job::daily_sale_report_pdf);
+    cfg.service(report_job::daily_sale_report_excel);
+    cfg.service(report_job::food_cost_report_pdf);
+    cfg.service(report_job::food_cost_report_excel);
+    cfg.service(analytics::get_sales_analytics);
+    cfg.service(waiter_report::get_waiter_report);
+    cfg.service(tip_pool_rule::get_tip_pool_rule);
+    cfg.service(tip_pool_rule::update_tip_pool_rule);
+    cfg.service(register_report::get_register_reports);
+    cfg.service(register_report::add_register_report);
+    cfg.service(register_report::get_register_report_by_id);
+    cfg.service(register_report::print_register_report);
+    cfg.service(cash_shift::get_cash_shifts);
+    cfg.service(cash_shift::get_current_cash_shift);
+    cfg.service(cash_shift::get_cash_shift_by_id);
+    cfg.service(cash_shift::open_cash_shift);
+    cfg.service(cash_shift::add_cash_movement);
+    cfg.service(cash_shift::close_cash_shift);
+    cfg.service(tax_rule::get_tax_rules);
+    cfg.service(tax_rule::add_tax_rule);
+    cfg.service(tax_rule::update_tax_rule);
+    cfg.service(tax_rule::delete_tax_rule);
+    cfg.service(tax_rule::get_tax_setting);
+    cfg.service(tax_rule::update_tax_setting);
+    cfg.service(customer::get_customers);
+    cfg.service(customer::add_customer);
+    cfg.service(customer::get_customer_by_id);
+    cfg.service(customer::update_customer);
+    cfg.service(customer::delete_customer);
+    cfg.service(customer::get_customer_orders);
+    cfg.service(loyalty::get_loyalty_program);
+    cfg.service(loyalty::update_loyalty_program);
+    cfg.service(loyalty::get_loyalty_tiers);
+    cfg.service(loyalty::add_loyalty_tier);
+    cfg.service(loyalty::update_loyalty_tier);
+    cfg.service(loyalty::delete_loyalty_tier);
+    cfg.service(loyalty::get_customer_loyalty);
+    cfg.service(loyalty::get_loyalty_ledger);
+    cfg.service(gift_card::get_gift_cards);
+    cfg.service(gift_card::issue_gift_card);
+    cfg.service(gift_card::get_gift_card_balance);
+    cfg.service(gift_card::get_gift_card_by_id);
+    cfg.service(gift_card::top_up_gift_card);
+    cfg.service(gift_card::redeem_gift_card);
+    cfg.service(gift_card::get_gift_card_liability);
+    cfg.service(reservation::get_reservations);
+    cfg.service(reservation::get_reservation_availability);
+    cfg.service(reservation::add_reservation);
+    cfg.service(reservation::get_reservation_by_id);
+    cfg.service(reservation::update_reservation);
+    cfg.service(reservation::update_reservation_status);
+    cfg.service(waitlist::get_waitlist);
+    cfg.service(waitlist::get_wait_estimate);
+    cfg.service(waitlist::add_waitlist_entry);
+    cfg.service(waitlist::update_waitlist_status);
+    cfg.service(floor_area::get_floor_areas);
+    cfg.service(floor_area::add_floor_area);
+    cfg.service(floor_area::update_floor_area);
+    cfg.service(floor_area::delete_floor_area);
+    cfg.service(floor_area::get_floor_plan);
+    cfg.service(floor_area::update_floor_plan);
+    cfg.service(section::get_sections);
+    cfg.service(section::add_section);
+    cfg.service(section::update_section);
+    cfg.service(section::delete_section);
+    cfg.service(delivery_platform::get_delivery_platforms);
+    cfg.service(delivery_platform::add_delivery_platform);
+    cfg.service(delivery_platform::update_delivery_platform);
+    cfg.service(delivery_platform::delete_delivery_platform);
+    cfg.service(delivery_platform::get_platform_items);
+    cfg.service(delivery_platform::update_platform_items);
+    cfg.service(delivery_platform::get_platform_orders);
+    cfg.service(delivery_platform::send_mock_platform_order);
+    cfg.service(delivery_platform::receive_platform_order);
+    cfg.service(webhook::get_webhook_subscriptions);
+    cfg.service(webhook::add_webhook_subscription);
+    cfg.service(webhook::update_webhook_subscription);
+    cfg.service(webhook::delete_webhook_subscription);
+    cfg.service(webhook::get_webhook_deliveries);
+    cfg.service(webhook::redeliver_webhook);
+    cfg.service(realtime::get_events);
+    cfg.service(push_notification::register_device_token);
+    cfg.service(push_notification::delete_device_token);
+    cfg.service(push_notification::get_notifications);
+    cfg.service(sync::get_sync_changes);
 }
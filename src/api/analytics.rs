@@ -0,0 +1,125 @@
+use actix_web::{get, web, HttpRequest, HttpResponse, Responder};
+use chrono::{Datelike, Duration, Local, NaiveDate};
+use serde::Deserialize;
+use std::sync::Arc;
+use tokio::sync::Mutex;
+use tokio_postgres::Client;
+
+use crate::{
+    models::{analytics, shop},
+    utils::{
+        common_struct::{BaseResponse, DataResponse},
+        jwt::verify_token_and_get_sub,
+    },
+};
+
+#[derive(Deserialize)]
+pub struct SalesAnalyticsQuery {
+    pub from_date: Option<NaiveDate>,
+    pub to_date: Option<NaiveDate>,
+    pub shop_id: Option<i32>,
+}
+
+#[get("/api/analytics/sales")]
+pub async fn get_sales_analytics(
+    req: HttpRequest,
+    query: web::Query<SalesAnalyticsQuery>,
+    data: web::Data<Arc<Mutex<Client>>>,
+) -> impl Responder {
+    let client = data.lock().await;
+    // Extract the token from the Authorization header
+    let token = match req.headers().get("Authorization") {
+        Some(value) => {
+            let parts: Vec<&str> = value.to_str().unwrap_or("").split_whitespace().collect();
+            if parts.len() == 2 && parts[0] == "Bearer" {
+                parts[1]
+            } else {
+                return HttpResponse::BadRequest().json(BaseResponse {
+                    code: 400,
+                    message: String::from("Invalid Authorization header format"),
+                });
+            }
+        }
+        None => {
+            return HttpResponse::Unauthorized().json(BaseResponse {
+                code: 401,
+                message: String::from("Authorization header missing"),
+            })
+        }
+    };
+
+    let sub = match verify_token_and_get_sub(token) {
+        Some(s) => s,
+        None => {
+            return HttpResponse::Unauthorized().json(BaseResponse {
+                code: 401,
+                message: String::from("Invalid token"),
+            })
+        }
+    };
+
+    // Parse the `sub` value
+    let parsed_values: Vec<&str> = sub.split(',').collect();
+    if parsed_values.len() != 3 {
+        return HttpResponse::InternalServerError().json(BaseResponse {
+            code: 500,
+            message: String::from("Invalid sub format in token"),
+        });
+    }
+    let role: &str = parsed_values[1];
+    let mut shop_id: i32 = parsed_values[2].parse().unwrap();
+
+    if role != "Admin" && role != "Manager" {
+        return HttpResponse::Unauthorized().json(BaseResponse {
+            code: 401,
+            message: String::from("Unauthorized!"),
+        });
+    }
+    if role == "Admin" {
+        shop_id = match query.shop_id {
+            Some(s) => s,
+            None => {
+                return HttpResponse::BadRequest().json(BaseResponse {
+                    code: 400,
+                    message: String::from("Shop Id must not be empty!"),
+                });
+            }
+        };
+    }
+
+    // Without a range the current week so far is compared with the previous week.
+    let today = Local::now().date_naive();
+    let from_date = query.from_date.unwrap_or(
+        today - Duration::days(today.weekday().num_days_from_monday() as i64),
+    );
+    let to_date = query.to_date.unwrap_or(today);
+    if from_date.gt(&to_date) {
+        return HttpResponse::BadRequest().json(BaseResponse {
+            code: 400,
+            message: String::from("Invalid Date Range!"),
+        });
+    }
+
+    if shop::get_shop_by_id(shop_id, &client).await.is_none() {
+        return HttpResponse::NotFound().json(BaseResponse {
+            code: 404,
+            message: String::from("Shop not found!"),
+        });
+    }
+
+    match analytics::get_sales_analytics(from_date, to_date, shop_id, &client).await {
+        Ok(data) => HttpResponse::Ok().json(DataResponse {
+            code: 200,
+            message: String::from("Successful."),
+            data: Some(data),
+        }),
+        Err(err) => {
+            // Log the error message here
+            println!("Error retrieving sales analytics: {:?}", err);
+            HttpResponse::InternalServerError().json(BaseResponse {
+                code: 500,
+                message: String::from("Error trying to read sales analytics from database"),
+            })
+        }
+    }
+}
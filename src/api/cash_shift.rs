@@ -0,0 +1,605 @@
+use std::sync::Arc;
+
+use actix_web::{get, post, web, HttpRequest, HttpResponse};
+use serde::Deserialize;
+use tokio::sync::Mutex;
+use tokio_postgres::Client;
+
+use crate::{
+    models::{
+        cash_shift::{
+            self, CashMovementRequest, CloseCashShiftRequest, OpenCashShiftRequest,
+            CASH_MOVEMENT_TYPES,
+        },
+        shop,
+    },
+    utils::{
+        common_struct::{BaseResponse, DataResponse, PaginationResponse},
+        jwt::verify_token_and_get_sub,
+    },
+};
+
+#[derive(Deserialize)]
+pub struct GetCashShiftsQuery {
+    pub page: Option<usize>,
+    pub per_page: Option<usize>,
+    pub shop_id: Option<i32>,
+    pub user_id: Option<i32>,
+    pub status: Option<String>,
+}
+
+#[get("/api/cash-shifts")]
+pub async fn get_cash_shifts(
+    req: HttpRequest,
+    query: web::Query<GetCashShiftsQuery>,
+    data: web::Data<Arc<Mutex<Client>>>,
+) -> HttpResponse {
+    let client = data.lock().await;
+    // Extract the token from the Authorization header
+    let token = match req.headers().get("Authorization") {
+        Some(value) => {
+            let parts: Vec<&str> = value.to_str().unwrap_or("").split_whitespace().collect();
+            if parts.len() == 2 && parts[0] == "Bearer" {
+                parts[1]
+            } else {
+                return HttpResponse::BadRequest().json(BaseResponse {
+                    code: 400,
+                    message: String::from("Invalid Authorization header format"),
+                });
+            }
+        }
+        None => {
+            return HttpResponse::Unauthorized().json(BaseResponse {
+                code: 401,
+                message: String::from("Authorization header missing"),
+            })
+        }
+    };
+
+    let sub = match verify_token_and_get_sub(token) {
+        Some(s) => s,
+        None => {
+            return HttpResponse::Unauthorized().json(BaseResponse {
+                code: 401,
+                message: String::from("Invalid token"),
+            })
+        }
+    };
+
+    // Parse the `sub` value
+    let parsed_values: Vec<&str> = sub.split(',').collect();
+    if parsed_values.len() != 3 {
+        return HttpResponse::InternalServerError().json(BaseResponse {
+            code: 500,
+            message: String::from("Invalid sub format in token"),
+        });
+    }
+    let user_id: i32 = parsed_values[0].parse().unwrap();
+    let role: &str = parsed_values[1];
+    let shop_id: i32 = parsed_values[2].parse().unwrap();
+
+    // Waiters only see their own shifts, Managers the shifts of their shop.
+    let (filter_shop_id, filter_user_id) = match role {
+        "Admin" => (query.shop_id, query.user_id),
+        "Manager" => (Some(shop_id), query.user_id),
+        _ => (None, Some(user_id)),
+    };
+    match cash_shift::get_cash_shifts(
+        query.page,
+        query.per_page,
+        filter_shop_id,
+        filter_user_id,
+        &query.status,
+        &client,
+    )
+    .await
+    {
+        Ok(result) => HttpResponse::Ok().json(PaginationResponse {
+            code: 200,
+            message: String::from("Successful."),
+            data: result.data,
+            total: result.total,
+            page: result.page,
+            per_page: result.per_page,
+            page_counts: result.page_counts,
+        }),
+        Err(err) => {
+            // Log the error message here
+            println!("Error retrieving cash shifts: {:?}", err);
+            HttpResponse::InternalServerError().json(BaseResponse {
+                code: 500,
+                message: String::from("Error trying to read all cash shifts from database"),
+            })
+        }
+    }
+}
+
+#[get("/api/cash-shifts/current")]
+pub async fn get_current_cash_shift(
+    req: HttpRequest,
+    data: web::Data<Arc<Mutex<Client>>>,
+) -> HttpResponse {
+    let client = data.lock().await;
+    // Extract the token from the Authorization header
+    let token = match req.headers().get("Authorization") {
+        Some(value) => {
+            let parts: Vec<&str> = value.to_str().unwrap_or("").split_whitespace().collect();
+            if parts.len() == 2 && parts[0] == "Bearer" {
+                parts[1]
+            } else {
+                return HttpResponse::BadRequest().json(BaseResponse {
+                    code: 400,
+                    message: String::from("Invalid Authorization header format"),
+                });
+            }
+        }
+        None => {
+            return HttpResponse::Unauthorized().json(BaseResponse {
+                code: 401,
+                message: String::from("Authorization header missing"),
+            })
+        }
+    };
+
+    let sub = match verify_token_and_get_sub(token) {
+        Some(s) => s,
+        None => {
+            return HttpResponse::Unauthorized().json(BaseResponse {
+                code: 401,
+                message: String::from("Invalid token"),
+            })
+        }
+    };
+
+    // Parse the `sub` value
+    let parsed_values: Vec<&str> = sub.split(',').collect();
+    if parsed_values.len() != 3 {
+        return HttpResponse::InternalServerError().json(BaseResponse {
+            code: 500,
+            message: String::from("Invalid sub format in token"),
+        });
+    }
+    let user_id: i32 = parsed_values[0].parse().unwrap();
+
+    let shift = match cash_shift::get_open_cash_shift(user_id, &client).await {
+        Some(s) => s,
+        None => {
+            return HttpResponse::NotFound().json(BaseResponse {
+                code: 404,
+                message: String::from("No open cash shift!"),
+            });
+        }
+    };
+    match cash_shift::get_cash_shift_detail(shift, &client).await {
+        Ok(detail) => HttpResponse::Ok().json(DataResponse {
+            code: 200,
+            message: String::from("Successful."),
+            data: Some(detail),
+        }),
+        Err(err) => {
+            // Log the error message here
+            println!("Error retrieving cash shift: {:?}", err);
+            HttpResponse::InternalServerError().json(BaseResponse {
+                code: 500,
+                message: String::from("Error trying to read cash shift from database"),
+            })
+        }
+    }
+}
+
+#[get("/api/cash-shifts/{shift_id}")]
+pub async fn get_cash_shift_by_id(
+    req: HttpRequest,
+    path: web::Path<i32>,
+    data: web::Data<Arc<Mutex<Client>>>,
+) -> HttpResponse {
+    let client = data.lock().await;
+    let shift_id = path.into_inner();
+    // Extract the token from the Authorization header
+    let token = match req.headers().get("Authorization") {
+        Some(value) => {
+            let parts: Vec<&str> = value.to_str().unwrap_or("").split_whitespace().collect();
+            if parts.len() == 2 && parts[0] == "Bearer" {
+                parts[1]
+            } else {
+                return HttpResponse::BadRequest().json(BaseResponse {
+                    code: 400,
+                    message: String::from("Invalid Authorization header format"),
+                });
+            }
+        }
+        None => {
+            return HttpResponse::Unauthorized().json(BaseResponse {
+                code: 401,
+                message: String::from("Authorization header missing"),
+            })
+        }
+    };
+
+    let sub = match verify_token_and_get_sub(token) {
+        Some(s) => s,
+        None => {
+            return HttpResponse::Unauthorized().json(BaseResponse {
+                code: 401,
+                message: String::from("Invalid token"),
+            })
+        }
+    };
+
+    // Parse the `sub` value
+    let parsed_values: Vec<&str> = sub.split(',').collect();
+    if parsed_values.len() != 3 {
+        return HttpResponse::InternalServerError().json(BaseResponse {
+            code: 500,
+            message: String::from("Invalid sub format in token"),
+        });
+    }
+    let user_id: i32 = parsed_values[0].parse().unwrap();
+    let role: &str = parsed_values[1];
+    let shop_id: i32 = parsed_values[2].parse().unwrap();
+
+    let shift = match cash_shift::get_cash_shift_by_id(shift_id, &client)
+        .await
+        .filter(|s| {
+            role == "Admin" || (role == "Manager" && s.shop_id == shop_id) || s.user_id == user_id
+        }) {
+        Some(s) => s,
+        None => {
+            return HttpResponse::NotFound().json(BaseResponse {
+                code: 404,
+                message: String::from("Cash shift not found!"),
+            });
+        }
+    };
+    match cash_shift::get_cash_shift_detail(shift, &client).await {
+        Ok(detail) => HttpResponse::Ok().json(DataResponse {
+            code: 200,
+            message: String::from("Successful."),
+            data: Some(detail),
+        }),
+        Err(err) => {
+            // Log the error message here
+            println!("Error retrieving cash shift: {:?}", err);
+            HttpResponse::InternalServerError().json(BaseResponse {
+                code: 500,
+                message: String::from("Error trying to read cash shift from database"),
+            })
+        }
+    }
+}
+
+#[post("/api/cash-shifts")]
+pub async fn open_cash_shift(
+    req: HttpRequest,
+    body: web::Json<OpenCashShiftRequest>,
+    data: web::Data<Arc<Mutex<Client>>>,
+) -> HttpResponse {
+    let client = data.lock().await;
+    // Extract the token from the Authorization header
+    let token = match req.headers().get("Authorization") {
+        Some(value) => {
+            let parts: Vec<&str> = value.to_str().unwrap_or("").split_whitespace().collect();
+            if parts.len() == 2 && parts[0] == "Bearer" {
+                parts[1]
+            } else {
+                return HttpResponse::BadRequest().json(BaseResponse {
+                    code: 400,
+                    message: String::from("Invalid Authorization header format"),
+                });
+            }
+        }
+        None => {
+            return HttpResponse::Unauthorized().json(BaseResponse {
+                code: 401,
+                message: String::from("Authorization header missing"),
+            })
+        }
+    };
+
+    let sub = match verify_token_and_get_sub(token) {
+        Some(s) => s,
+        None => {
+            return HttpResponse::Unauthorized().json(BaseResponse {
+                code: 401,
+                message: String::from("Invalid token"),
+            })
+        }
+    };
+
+    // Parse the `sub` value
+    let parsed_values: Vec<&str> = sub.split(',').collect();
+    if parsed_values.len() != 3 {
+        return HttpResponse::InternalServerError().json(BaseResponse {
+            code: 500,
+            message: String::from("Invalid sub format in token"),
+        });
+    }
+    let user_id: i32 = parsed_values[0].parse().unwrap();
+    let role: &str = parsed_values[1];
+    let mut shop_id: i32 = parsed_values[2].parse().unwrap();
+
+    if role == "Admin" {
+        shop_id = match body.shop_id {
+            Some(s) => s,
+            None => {
+                return HttpResponse::BadRequest().json(BaseResponse {
+                    code: 400,
+                    message: String::from("Shop Id must not be empty!"),
+                });
+            }
+        };
+    }
+    if body.opening_float < 0.0 {
+        return HttpResponse::BadRequest().json(BaseResponse {
+            code: 400,
+            message: String::from("Opening float must not be negative!"),
+        });
+    }
+    if shop::get_shop_by_id(shop_id, &client).await.is_none() {
+        return HttpResponse::NotFound().json(BaseResponse {
+            code: 404,
+            message: String::from("Shop not found!"),
+        });
+    }
+
+    match cash_shift::open_shift_exists(user_id, shop_id, &body.register_name, &client).await {
+        Ok(false) => {}
+        Ok(true) => {
+            return HttpResponse::BadRequest().json(BaseResponse {
+                code: 400,
+                message: String::from("A cash shift is already open for this user or register!"),
+            });
+        }
+        Err(err) => {
+            println!("{:?}", err);
+            return HttpResponse::InternalServerError().json(BaseResponse {
+                code: 500,
+                message: String::from("Error opening cash shift!"),
+            });
+        }
+    }
+
+    match cash_shift::open_cash_shift(&body, shop_id, user_id, &client).await {
+        Ok(id) => HttpResponse::Created().json(DataResponse {
+            code: 201,
+            message: String::from("Cash shift opened successfully"),
+            data: Some(id),
+        }),
+        Err(e) => {
+            eprintln!("Cash shift opening error: {}", e);
+            HttpResponse::InternalServerError().json(BaseResponse {
+                code: 500,
+                message: String::from("Error opening cash shift!"),
+            })
+        }
+    }
+}
+
+#[post("/api/cash-shifts/{shift_id}/movements")]
+pub async fn add_cash_movement(
+    req: HttpRequest,
+    path: web::Path<i32>,
+    body: web::Json<CashMovementRequest>,
+    data: web::Data<Arc<Mutex<Client>>>,
+) -> HttpResponse {
+    let client = data.lock().await;
+    let shift_id = path.into_inner();
+    // Extract the token from the Authorization header
+    let token = match req.headers().get("Authorization") {
+        Some(value) => {
+            let parts: Vec<&str> = value.to_str().unwrap_or("").split_whitespace().collect();
+            if parts.len() == 2 && parts[0] == "Bearer" {
+                parts[1]
+            } else {
+                return HttpResponse::BadRequest().json(BaseResponse {
+                    code: 400,
+                    message: String::from("Invalid Authorization header format"),
+                });
+            }
+        }
+        None => {
+            return HttpResponse::Unauthorized().json(BaseResponse {
+                code: 401,
+                message: String::from("Authorization header missing"),
+            })
+        }
+    };
+
+    let sub = match verify_token_and_get_sub(token) {
+        Some(s) => s,
+        None => {
+            return HttpResponse::Unauthorized().json(BaseResponse {
+                code: 401,
+                message: String::from("Invalid token"),
+            })
+        }
+    };
+
+    // Parse the `sub` value
+    let parsed_values: Vec<&str> = sub.split(',').collect();
+    if parsed_values.len() != 3 {
+        return HttpResponse::InternalServerError().json(BaseResponse {
+            code: 500,
+            message: String::from("Invalid sub format in token"),
+        });
+    }
+    let user_id: i32 = parsed_values[0].parse().unwrap();
+    let role: &str = parsed_values[1];
+    let shop_id: i32 = parsed_values[2].parse().unwrap();
+
+    if !CASH_MOVEMENT_TYPES.contains(&body.movement_type.as_str()) {
+        return HttpResponse::BadRequest().json(BaseResponse {
+            code: 400,
+            message: String::from("Please select a valid movement type: PayIn or PayOut."),
+        });
+    }
+    if body.amount <= 0.0 {
+        return HttpResponse::BadRequest().json(BaseResponse {
+            code: 400,
+            message: String::from("Amount must be greater than 0.0!"),
+        });
+    }
+    if body.reason.trim().is_empty() {
+        return HttpResponse::BadRequest().json(BaseResponse {
+            code: 400,
+            message: String::from("Reason must not be empty!"),
+        });
+    }
+
+    match cash_shift::get_cash_shift_by_id(shift_id, &client)
+        .await
+        .filter(|s| {
+            role == "Admin" || (role == "Manager" && s.shop_id == shop_id) || s.user_id == user_id
+        }) {
+        Some(s) => {
+            if s.status != "Open" {
+                return HttpResponse::BadRequest().json(BaseResponse {
+                    code: 400,
+                    message: String::from("Cash shift is already closed!"),
+                });
+            }
+        }
+        None => {
+            return HttpResponse::NotFound().json(BaseResponse {
+                code: 404,
+                message: String::from("Cash shift not found!"),
+            });
+        }
+    }
+
+    match cash_shift::add_cash_movement(shift_id, &body, user_id, &client).await {
+        Ok(()) => HttpResponse::Created().json(BaseResponse {
+            code: 201,
+            message: String::from("Cash movement added successfully"),
+        }),
+        Err(e) => {
+            eprintln!("Cash movement adding error: {}", e);
+            HttpResponse::InternalServerError().json(BaseResponse {
+                code: 500,
+                message: String::from("Error adding cash movement!"),
+            })
+        }
+    }
+}
+
+#[post("/api/cash-shifts/{shift_id}/close")]
+pub async fn close_cash_shift(
+    req: HttpRequest,
+    path: web::Path<i32>,
+    body: web::Json<CloseCashShiftRequest>,
+    data: web::Data<Arc<Mutex<Client>>>,
+) -> HttpResponse {
+    let client = data.lock().await;
+    let shift_id = path.into_inner();
+    // Extract the token from the Authorization header
+    let token = match req.headers().get("Authorization") {
+        Some(value) => {
+            let parts: Vec<&str> = value.to_str().unwrap_or("").split_whitespace().collect();
+            if parts.len() == 2 && parts[0] == "Bearer" {
+                parts[1]
+            } else {
+                return HttpResponse::BadRequest().json(BaseResponse {
+                    code: 400,
+                    message: String::from("Invalid Authorization header format"),
+                });
+            }
+        }
+        None => {
+            return HttpResponse::Unauthorized().json(BaseResponse {
+                code: 401,
+                message: String::from("Authorization header missing"),
+            })
+        }
+    };
+
+    let sub = match verify_token_and_get_sub(token) {
+        Some(s) => s,
+        None => {
+            return HttpResponse::Unauthorized().json(BaseResponse {
+                code: 401,
+                message: String::from("Invalid token"),
+            })
+        }
+    };
+
+    // Parse the `sub` value
+    let parsed_values: Vec<&str> = sub.split(',').collect();
+    if parsed_values.len() != 3 {
+        return HttpResponse::InternalServerError().json(BaseResponse {
+            code: 500,
+            message: String::from("Invalid sub format in token"),
+        });
+    }
+    let user_id: i32 = parsed_values[0].parse().unwrap();
+    let role: &str = parsed_values[1];
+    let shop_id: i32 = parsed_values[2].parse().unwrap();
+
+    if body.counted_cash < 0.0 {
+        return HttpResponse::BadRequest().json(BaseResponse {
+            code: 400,
+            message: String::from("Counted cash must not be negative!"),
+        });
+    }
+
+    let shift = match cash_shift::get_cash_shift_by_id(shift_id, &client)
+        .await
+        .filter(|s| {
+            role == "Admin" || (role == "Manager" && s.shop_id == shop_id) || s.user_id == user_id
+        }) {
+        Some(s) => s,
+        None => {
+            return HttpResponse::NotFound().json(BaseResponse {
+                code: 404,
+                message: String::from("Cash shift not found!"),
+            });
+        }
+    };
+    if shift.status != "Open" {
+        return HttpResponse::BadRequest().json(BaseResponse {
+            code: 400,
+            message: String::from("Cash shift is already closed!"),
+        });
+    }
+
+    let summary =
+        match cash_shift::get_cash_shift_summary(shift_id, shift.opening_float, &client).await {
+            Ok(s) => s,
+            Err(err) => {
+                println!("{:?}", err);
+                return HttpResponse::InternalServerError().json(BaseResponse {
+                    code: 500,
+                    message: String::from("Error closing cash shift!"),
+                });
+            }
+        };
+    if let Err(e) =
+        cash_shift::close_cash_shift(shift_id, summary.expected_cash, &body, user_id, &client).await
+    {
+        eprintln!("Cash shift closing error: {}", e);
+        return HttpResponse::InternalServerError().json(BaseResponse {
+            code: 500,
+            message: String::from("Error closing cash shift!"),
+        });
+    }
+
+    match cash_shift::get_cash_shift_by_id(shift_id, &client).await {
+        Some(s) => match cash_shift::get_cash_shift_detail(s, &client).await {
+            Ok(detail) => HttpResponse::Ok().json(DataResponse {
+                code: 200,
+                message: String::from("Cash shift closed successfully"),
+                data: Some(detail),
+            }),
+            Err(err) => {
+                println!("{:?}", err);
+                HttpResponse::InternalServerError().json(BaseResponse {
+                    code: 500,
+                    message: String::from("Error trying to read cash shift from database"),
+                })
+            }
+        },
+        None => HttpResponse::NotFound().json(BaseResponse {
+            code: 404,
+            message: String::from("Cash shift not found!"),
+        }),
+    }
+}
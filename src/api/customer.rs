@@ -0,0 +1,581 @@
+use std::sync::Arc;
+
+use actix_web::{delete, get, post, put, web, HttpRequest, HttpResponse};
+use serde::Deserialize;
+use tokio::sync::Mutex;
+use tokio_postgres::Client;
+
+use crate::{
+    models::{
+        customer::{self, Customer, CustomerRequest},
+        shop,
+    },
+    utils::{
+        common_struct::{BaseResponse, DataResponse, PaginationResponse},
+        jwt::verify_token_and_get_sub,
+        validator::{validate_email, validate_mobile},
+    },
+};
+
+#[derive(Deserialize)]
+pub struct GetCustomersQuery {
+    pub search: Option<String>,
+    pub page: Option<usize>,
+    pub per_page: Option<usize>,
+}
+
+fn validate_customer(body: &CustomerRequest) -> Option<HttpResponse> {
+    if body.name.trim().is_empty() {
+        return Some(HttpResponse::BadRequest().json(BaseResponse {
+            code: 400,
+            message: String::from("Name must not be empty!"),
+        }));
+    }
+    if !validate_mobile(&body.phone) {
+        return Some(HttpResponse::BadRequest().json(BaseResponse {
+            code: 400,
+            message: String::from("Invalid phone number!"),
+        }));
+    }
+    if let Some(email) = body.email.as_deref().filter(|e| !e.is_empty()) {
+        if !validate_email(email) {
+            return Some(HttpResponse::BadRequest().json(BaseResponse {
+                code: 400,
+                message: String::from("Invalid email!"),
+            }));
+        }
+    }
+    None
+}
+
+// Shared customers can be read by every shop but only changed by an Admin.
+fn can_edit_customer(customer: &Customer, role: &str, shop_id: i32) -> bool {
+    role == "Admin" || customer.shop_id == Some(shop_id)
+}
+
+fn can_view_customer(customer: &Customer, role: &str, shop_id: i32) -> bool {
+    customer.shop_id.is_none() || can_edit_customer(customer, role, shop_id)
+}
+
+#[get("/api/customers")]
+pub async fn get_customers(
+    req: HttpRequest,
+    query: web::Query<GetCustomersQuery>,
+    data: web::Data<Arc<Mutex<Client>>>,
+) -> HttpResponse {
+    let client = data.lock().await;
+    // Extract the token from the Authorization header
+    let token = match req.headers().get("Authorization") {
+        Some(value) => {
+            let parts: Vec<&str> = value.to_str().unwrap_or("").split_whitespace().collect();
+            if parts.len() == 2 && parts[0] == "Bearer" {
+                parts[1]
+            } else {
+                return HttpResponse::BadRequest().json(BaseResponse {
+                    code: 400,
+                    message: String::from("Invalid Authorization header format"),
+                });
+            }
+        }
+        None => {
+            return HttpResponse::Unauthorized().json(BaseResponse {
+                code: 401,
+                message: String::from("Authorization header missing"),
+            })
+        }
+    };
+
+    let sub = match verify_token_and_get_sub(token) {
+        Some(s) => s,
+        None => {
+            return HttpResponse::Unauthorized().json(BaseResponse {
+                code: 401,
+                message: String::from("Invalid token"),
+            })
+        }
+    };
+
+    // Parse the `sub` value
+    let parsed_values: Vec<&str> = sub.split(',').collect();
+    if parsed_values.len() != 3 {
+        return HttpResponse::InternalServerError().json(BaseResponse {
+            code: 500,
+            message: String::from("Invalid sub format in token"),
+        });
+    }
+    let role: &str = parsed_values[1];
+    let shop_id: i32 = parsed_values[2].parse().unwrap();
+
+    match customer::get_customers(
+        &query.search,
+        query.page,
+        query.per_page,
+        role,
+        shop_id,
+        &client,
+    )
+    .await
+    {
+        Ok(item_result) => HttpResponse::Ok().json(PaginationResponse {
+            code: 200,
+            message: String::from("Successful."),
+            data: item_result.data,
+            total: item_result.total,
+            page: item_result.page,
+            per_page: item_result.per_page,
+            page_counts: item_result.page_counts,
+        }),
+        Err(err) => {
+            // Log the error message here
+            println!("Error retrieving customers: {:?}", err);
+            HttpResponse::InternalServerError().json(BaseResponse {
+                code: 500,
+                message: String::from("Error trying to read all customers from database"),
+            })
+        }
+    }
+}
+
+#[post("/api/customers")]
+pub async fn add_customer(
+    req: HttpRequest,
+    body: web::Json<CustomerRequest>,
+    data: web::Data<Arc<Mutex<Client>>>,
+) -> HttpResponse {
+    let client = data.lock().await;
+    // Extract the token from the Authorization header
+    let token = match req.headers().get("Authorization") {
+        Some(value) => {
+            let parts: Vec<&str> = value.to_str().unwrap_or("").split_whitespace().collect();
+            if parts.len() == 2 && parts[0] == "Bearer" {
+                parts[1]
+            } else {
+                return HttpResponse::BadRequest().json(BaseResponse {
+                    code: 400,
+                    message: String::from("Invalid Authorization header format"),
+                });
+            }
+        }
+        None => {
+            return HttpResponse::Unauthorized().json(BaseResponse {
+                code: 401,
+                message: String::from("Authorization header missing"),
+            })
+        }
+    };
+
+    let sub = match verify_token_and_get_sub(token) {
+        Some(s) => s,
+        None => {
+            return HttpResponse::Unauthorized().json(BaseResponse {
+                code: 401,
+                message: String::from("Invalid token"),
+            })
+        }
+    };
+
+    // Parse the `sub` value
+    let parsed_values: Vec<&str> = sub.split(',').collect();
+    if parsed_values.len() != 3 {
+        return HttpResponse::InternalServerError().json(BaseResponse {
+            code: 500,
+            message: String::from("Invalid sub format in token"),
+        });
+    }
+    let role: &str = parsed_values[1];
+    let shop_id: i32 = parsed_values[2].parse().unwrap();
+
+    // Admins create shared customers unless a shop is given.
+    let customer_shop_id = if role == "Admin" {
+        body.shop_id
+    } else {
+        Some(shop_id)
+    };
+    if let Some(response) = validate_customer(&body) {
+        return response;
+    }
+    if let Some(s) = customer_shop_id {
+        if shop::get_shop_by_id(s, &client).await.is_none() {
+            return HttpResponse::NotFound().json(BaseResponse {
+                code: 404,
+                message: String::from("Shop not found!"),
+            });
+        }
+    }
+    match customer::phone_exists(&body.phone, customer_shop_id, None, &client).await {
+        Ok(true) => {
+            return HttpResponse::Conflict().json(BaseResponse {
+                code: 409,
+                message: String::from("Phone number already exists!"),
+            });
+        }
+        Ok(false) => {}
+        Err(err) => {
+            println!("{:?}", err);
+            return HttpResponse::InternalServerError().json(BaseResponse {
+                code: 500,
+                message: String::from("Error checking phone number!"),
+            });
+        }
+    }
+
+    match customer::add_customer(&body, customer_shop_id, &client).await {
+        Ok(id) => HttpResponse::Created().json(DataResponse {
+            code: 201,
+            message: String::from("Customer added successfully"),
+            data: Some(id),
+        }),
+        Err(e) => {
+            eprintln!("Customer adding error: {}", e);
+            HttpResponse::InternalServerError().json(BaseResponse {
+                code: 500,
+                message: String::from("Error adding customer!"),
+            })
+        }
+    }
+}
+
+#[get("/api/customers/{customer_id}")]
+pub async fn get_customer_by_id(
+    req: HttpRequest,
+    path: web::Path<i32>,
+    data: web::Data<Arc<Mutex<Client>>>,
+) -> HttpResponse {
+    let client = data.lock().await;
+    let customer_id = path.into_inner();
+    // Extract the token from the Authorization header
+    let token = match req.headers().get("Authorization") {
+        Some(value) => {
+            let parts: Vec<&str> = value.to_str().unwrap_or("").split_whitespace().collect();
+            if parts.len() == 2 && parts[0] == "Bearer" {
+                parts[1]
+            } else {
+                return HttpResponse::BadRequest().json(BaseResponse {
+                    code: 400,
+                    message: String::from("Invalid Authorization header format"),
+                });
+            }
+        }
+        None => {
+            return HttpResponse::Unauthorized().json(BaseResponse {
+                code: 401,
+                message: String::from("Authorization header missing"),
+            })
+        }
+    };
+
+    let sub = match verify_token_and_get_sub(token) {
+        Some(s) => s,
+        None => {
+            return HttpResponse::Unauthorized().json(BaseResponse {
+                code: 401,
+                message: String::from("Invalid token"),
+            })
+        }
+    };
+
+    // Parse the `sub` value
+    let parsed_values: Vec<&str> = sub.split(',').collect();
+    if parsed_values.len() != 3 {
+        return HttpResponse::InternalServerError().json(BaseResponse {
+            code: 500,
+            message: String::from("Invalid sub format in token"),
+        });
+    }
+    let role: &str = parsed_values[1];
+    let shop_id: i32 = parsed_values[2].parse().unwrap();
+
+    match customer::get_customer_by_id(customer_id, &client)
+        .await
+        .filter(|c| can_view_customer(c, role, shop_id))
+    {
+        Some(c) => HttpResponse::Ok().json(DataResponse {
+            code: 200,
+            message: String::from("Customer fetched successfully."),
+            data: Some(c),
+        }),
+        None => HttpResponse::NotFound().json(BaseResponse {
+            code: 404,
+            message: String::from("Customer not found!"),
+        }),
+    }
+}
+
+#[put("/api/customers/{customer_id}")]
+pub async fn update_customer(
+    req: HttpRequest,
+    path: web::Path<i32>,
+    body: web::Json<CustomerRequest>,
+    data: web::Data<Arc<Mutex<Client>>>,
+) -> HttpResponse {
+    let client = data.lock().await;
+    let customer_id = path.into_inner();
+    // Extract the token from the Authorization header
+    let token = match req.headers().get("Authorization") {
+        Some(value) => {
+            let parts: Vec<&str> = value.to_str().unwrap_or("").split_whitespace().collect();
+            if parts.len() == 2 && parts[0] == "Bearer" {
+                parts[1]
+            } else {
+                return HttpResponse::BadRequest().json(BaseResponse {
+                    code: 400,
+                    message: String::from("Invalid Authorization header format"),
+                });
+            }
+        }
+        None => {
+            return HttpResponse::Unauthorized().json(BaseResponse {
+                code: 401,
+                message: String::from("Authorization header missing"),
+            })
+        }
+    };
+
+    let sub = match verify_token_and_get_sub(token) {
+        Some(s) => s,
+        None => {
+            return HttpResponse::Unauthorized().json(BaseResponse {
+                code: 401,
+                message: String::from("Invalid token"),
+            })
+        }
+    };
+
+    // Parse the `sub` value
+    let parsed_values: Vec<&str> = sub.split(',').collect();
+    if parsed_values.len() != 3 {
+        return HttpResponse::InternalServerError().json(BaseResponse {
+            code: 500,
+            message: String::from("Invalid sub format in token"),
+        });
+    }
+    let role: &str = parsed_values[1];
+    let shop_id: i32 = parsed_values[2].parse().unwrap();
+
+    let customer_shop_id = match customer::get_customer_by_id(customer_id, &client)
+        .await
+        .filter(|c| can_edit_customer(c, role, shop_id))
+    {
+        Some(c) => c.shop_id,
+        None => {
+            return HttpResponse::NotFound().json(BaseResponse {
+                code: 404,
+                message: String::from("Customer not found!"),
+            });
+        }
+    };
+    if let Some(response) = validate_customer(&body) {
+        return response;
+    }
+    match customer::phone_exists(&body.phone, customer_shop_id, Some(customer_id), &client).await {
+        Ok(true) => {
+            return HttpResponse::Conflict().json(BaseResponse {
+                code: 409,
+                message: String::from("Phone number already exists!"),
+            });
+        }
+        Ok(false) => {}
+        Err(err) => {
+            println!("{:?}", err);
+            return HttpResponse::InternalServerError().json(BaseResponse {
+                code: 500,
+                message: String::from("Error checking phone number!"),
+            });
+        }
+    }
+
+    match customer::update_customer(customer_id, &body, &client).await {
+        Ok(()) => HttpResponse::Ok().json(BaseResponse {
+            code: 200,
+            message: String::from("Customer updated successfully"),
+        }),
+        Err(e) => {
+            eprintln!("Customer updating error: {}", e);
+            HttpResponse::InternalServerError().json(BaseResponse {
+                code: 500,
+                message: String::from("Error updating customer!"),
+            })
+        }
+    }
+}
+
+#[delete("/api/customers/{customer_id}")]
+pub async fn delete_customer(
+    req: HttpRequest,
+    path: web::Path<i32>,
+    data: web::Data<Arc<Mutex<Client>>>,
+) -> HttpResponse {
+    let client = data.lock().await;
+    let customer_id = path.into_inner();
+    // Extract the token from the Authorization header
+    let token = match req.headers().get("Authorization") {
+        Some(value) => {
+            let parts: Vec<&str> = value.to_str().unwrap_or("").split_whitespace().collect();
+            if parts.len() == 2 && parts[0] == "Bearer" {
+                parts[1]
+            } else {
+                return HttpResponse::BadRequest().json(BaseResponse {
+                    code: 400,
+                    message: String::from("Invalid Authorization header format"),
+                });
+            }
+        }
+        None => {
+            return HttpResponse::Unauthorized().json(BaseResponse {
+                code: 401,
+                message: String::from("Authorization header missing"),
+            })
+        }
+    };
+
+    let sub = match verify_token_and_get_sub(token) {
+        Some(s) => s,
+        None => {
+            return HttpResponse::Unauthorized().json(BaseResponse {
+                code: 401,
+                message: String::from("Invalid token"),
+            })
+        }
+    };
+
+    // Parse the `sub` value
+    let parsed_values: Vec<&str> = sub.split(',').collect();
+    if parsed_values.len() != 3 {
+        return HttpResponse::InternalServerError().json(BaseResponse {
+            code: 500,
+            message: String::from("Invalid sub format in token"),
+        });
+    }
+    let role: &str = parsed_values[1];
+    let shop_id: i32 = parsed_values[2].parse().unwrap();
+
+    if role != "Admin" && role != "Manager" {
+        return HttpResponse::Unauthorized().json(BaseResponse {
+            code: 401,
+            message: String::from("Unauthorized!"),
+        });
+    }
+    if customer::get_customer_by_id(customer_id, &client)
+        .await
+        .filter(|c| can_edit_customer(c, role, shop_id))
+        .is_none()
+    {
+        return HttpResponse::NotFound().json(BaseResponse {
+            code: 404,
+            message: String::from("Customer not found!"),
+        });
+    }
+
+    match customer::delete_customer(customer_id, &client).await {
+        Ok(()) => HttpResponse::Ok().json(BaseResponse {
+            code: 204,
+            message: String::from("Customer deleted successfully"),
+        }),
+        Err(e) => {
+            eprintln!("Customer deleting error: {}", e);
+            HttpResponse::InternalServerError().json(BaseResponse {
+                code: 500,
+                message: String::from("Error deleting customer!"),
+            })
+        }
+    }
+}
+
+#[derive(Deserialize)]
+pub struct GetCustomerOrdersQuery {
+    pub page: Option<usize>,
+    pub per_page: Option<usize>,
+}
+
+#[get("/api/customers/{customer_id}/orders")]
+pub async fn get_customer_orders(
+    req: HttpRequest,
+    path: web::Path<i32>,
+    query: web::Query<GetCustomerOrdersQuery>,
+    data: web::Data<Arc<Mutex<Client>>>,
+) -> HttpResponse {
+    let client = data.lock().await;
+    let customer_id = path.into_inner();
+    // Extract the token from the Authorization header
+    let token = match req.headers().get("Authorization") {
+        Some(value) => {
+            let parts: Vec<&str> = value.to_str().unwrap_or("").split_whitespace().collect();
+            if parts.len() == 2 && parts[0] == "Bearer" {
+                parts[1]
+            } else {
+                return HttpResponse::BadRequest().json(BaseResponse {
+                    code: 400,
+                    message: String::from("Invalid Authorization header format"),
+                });
+            }
+        }
+        None => {
+            return HttpResponse::Unauthorized().json(BaseResponse {
+                code: 401,
+                message: String::from("Authorization header missing"),
+            })
+        }
+    };
+
+    let sub = match verify_token_and_get_sub(token) {
+        Some(s) => s,
+        None => {
+            return HttpResponse::Unauthorized().json(BaseResponse {
+                code: 401,
+                message: String::from("Invalid token"),
+            })
+        }
+    };
+
+    // Parse the `sub` value
+    let parsed_values: Vec<&str> = sub.split(',').collect();
+    if parsed_values.len() != 3 {
+        return HttpResponse::InternalServerError().json(BaseResponse {
+            code: 500,
+            message: String::from("Invalid sub format in token"),
+        });
+    }
+    let role: &str = parsed_values[1];
+    let shop_id: i32 = parsed_values[2].parse().unwrap();
+
+    if customer::get_customer_by_id(customer_id, &client)
+        .await
+        .filter(|c| can_view_customer(c, role, shop_id))
+        .is_none()
+    {
+        return HttpResponse::NotFound().json(BaseResponse {
+            code: 404,
+            message: String::from("Customer not found!"),
+        });
+    }
+    // Shops only see the orders a shared customer placed with them.
+    let filter_shop_id = if role == "Admin" { None } else { Some(shop_id) };
+
+    match customer::get_customer_orders(
+        customer_id,
+        filter_shop_id,
+        query.page,
+        query.per_page,
+        &client,
+    )
+    .await
+    {
+        Ok(item_result) => HttpResponse::Ok().json(PaginationResponse {
+            code: 200,
+            message: String::from("Successful."),
+            data: item_result.data,
+            total: item_result.total,
+            page: item_result.page,
+            per_page: item_result.per_page,
+            page_counts: item_result.page_counts,
+        }),
+        Err(err) => {
+            // Log the error message here
+            println!("Error retrieving customer orders: {:?}", err);
+            HttpResponse::InternalServerError().json(BaseResponse {
+                code: 500,
+                message: String::from("Error trying to read customer orders from database"),
+            })
+        }
+    }
+}
@@ -0,0 +1,1055 @@
+use std::sync::Arc;
+
+use actix_web::{delete, get, http::header::HeaderMap, post, put, web, HttpRequest, HttpResponse};
+use serde::Deserialize;
+use tokio::sync::Mutex;
+use tokio_postgres::Client;
+use uuid::Uuid;
+
+use crate::{
+    models::{
+        delivery_platform::{
+            self, DeliveryPlatform, DeliveryPlatformRequest, PlatformItemRequest,
+            PLATFORM_ORDER_STATUSES,
+        },
+        order::{self, NewOrder, NewOrderItem},
+        push_notification, shop, user,
+    },
+    utils::{
+        common_struct::{BaseResponse, DataResponse, PaginationResponse},
+        delivery_platform::{
+            parse_order, signature_headers, verify_signature, ExternalOrder, ExternalOrderItem,
+            DELIVERY_PLATFORM_ADAPTERS,
+        },
+        jwt::verify_token_and_get_sub,
+    },
+};
+
+#[derive(Deserialize)]
+pub struct GetDeliveryPlatformsQuery {
+    pub shop_id: Option<i32>,
+}
+
+#[derive(Deserialize)]
+pub struct GetPlatformOrdersQuery {
+    pub status: Option<String>,
+    pub page: Option<usize>,
+    pub per_page: Option<usize>,
+}
+
+// Orders are taken under a user of the platform's shop.
+async fn validate_delivery_platform(
+    body: &DeliveryPlatformRequest,
+    shop_id: i32,
+    client: &Client,
+) -> Option<HttpResponse> {
+    if body.name.trim().is_empty() {
+        return Some(HttpResponse::BadRequest().json(BaseResponse {
+            code: 400,
+            message: String::from("Name must not be empty!"),
+        }));
+    }
+    if !DELIVERY_PLATFORM_ADAPTERS.contains(&body.adapter.as_str()) {
+        return Some(HttpResponse::BadRequest().json(BaseResponse {
+            code: 400,
+            message: String::from("Please select a valid adapter: Generic."),
+        }));
+    }
+    if body.webhook_secret.as_deref().is_some_and(|s| s.len() < 16) {
+        return Some(HttpResponse::BadRequest().json(BaseResponse {
+            code: 400,
+            message: String::from("Webhook secret must be at least 16 characters!"),
+        }));
+    }
+    if user::get_user_by_id(body.waiter_id, client)
+        .await
+        .filter(|u| u.shop_id == shop_id)
+        .is_none()
+    {
+        return Some(HttpResponse::BadRequest().json(BaseResponse {
+            code: 400,
+            message: String::from("Waiter must be a user of the shop!"),
+        }));
+    }
+    None
+}
+
+fn validate_external_order(external: &ExternalOrder) -> Option<String> {
+    if external.external_order_id.trim().is_empty() {
+        return Some(String::from("External order id must not be empty!"));
+    }
+    if external.order_type != "Delivery" && external.order_type != "Pickup" {
+        return Some(String::from("Order type must be Delivery or Pickup!"));
+    }
+    if external.order_type == "Delivery"
+        && external
+            .delivery_address
+            .as_deref()
+            .is_none_or(|a| a.trim().is_empty())
+    {
+        return Some(String::from("Delivery address must not be empty!"));
+    }
+    if external.delivery_fee.is_some_and(|f| f < 0.0) {
+        return Some(String::from("Delivery fee must not be negative!"));
+    }
+    if external.items.is_empty() {
+        return Some(String::from("Items must not be empty!"));
+    }
+    if external.items.iter().any(|i| i.quantity < 1) {
+        return Some(String::from("Quantity must be greater than zero!"));
+    }
+    None
+}
+
+// Turns a webhook delivery into an order. Platforms retry until they get a success, so an order that was already
+// accepted is acknowledged again instead of being created twice.
+async fn ingest_platform_order(
+    platform: &DeliveryPlatform,
+    headers: &HeaderMap,
+    body: &[u8],
+    client: &mut Client,
+) -> HttpResponse {
+    if !verify_signature(&platform.adapter, headers, body, &platform.webhook_secret) {
+        return HttpResponse::Unauthorized().json(BaseResponse {
+            code: 401,
+            message: String::from("Invalid signature!"),
+        });
+    }
+    let external = match parse_order(&platform.adapter, body) {
+        Ok(o) => o,
+        Err(e) => {
+            return HttpResponse::BadRequest().json(BaseResponse {
+                code: 400,
+                message: format!("Invalid order payload: {e}"),
+            });
+        }
+    };
+    let payload = String::from_utf8_lossy(body).to_string();
+
+    if let Some(o) = delivery_platform::get_platform_order(
+        platform.platform_id,
+        &external.external_order_id,
+        client,
+    )
+    .await
+    .filter(|o| o.status == "Accepted")
+    {
+        return HttpResponse::Ok().json(DataResponse {
+            code: 200,
+            message: String::from("Order already received."),
+            data: o.order_id,
+        });
+    }
+
+    let mut error = validate_external_order(&external);
+    let mut items: Vec<NewOrderItem> = vec![];
+    if error.is_none() {
+        let skus: Vec<String> = external.items.iter().map(|i| i.sku.clone()).collect();
+        let mapping =
+            match delivery_platform::map_external_skus(platform.platform_id, &skus, client).await {
+                Ok(m) => m,
+                Err(e) => {
+                    eprintln!("Database error: {}", e);
+                    return HttpResponse::InternalServerError().json(BaseResponse {
+                        code: 500,
+                        message: String::from("Something went wrong!"),
+                    });
+                }
+            };
+        let mut unmapped: Vec<&str> = vec![];
+        for external_item in &external.items {
+            match mapping.iter().find(|(sku, _)| sku == &external_item.sku) {
+                Some((_, item_id)) => items.push(NewOrderItem {
+                    item_id: *item_id,
+                    quantity: external_item.quantity,
+                    special_instructions: external_item.notes.clone().unwrap_or_default(),
+                }),
+                None => unmapped.push(&external_item.sku),
+            }
+        }
+        if !unmapped.is_empty() {
+            error = Some(format!("Unmapped SKUs: {}", unmapped.join(", ")));
+        }
+    }
+    let new_order = NewOrder {
+        table_id: None,
+        shop_id: Some(platform.shop_id),
+        order_type: Some(external.order_type.clone()),
+        covers: None,
+        customer_id: None,
+        contact_name: external.customer_name,
+        contact_phone: external.customer_phone,
+        delivery_address: external.delivery_address,
+        delivery_fee: external.delivery_fee,
+        scheduled_at: external.scheduled_at,
+        items,
+    };
+    // Platform orders are held to the same rules as the ones taken in the shop.
+    if error.is_none() {
+        error = order::validate_new_order(&new_order, &external.order_type).map(String::from);
+    }
+    if let Some(error) = error {
+        if let Err(e) = delivery_platform::save_platform_order(
+            platform.platform_id,
+            &external.external_order_id,
+            None,
+            Some(&error),
+            &payload,
+            client,
+        )
+        .await
+        {
+            eprintln!("Platform order saving error: {}", e);
+        }
+        return HttpResponse::BadRequest().json(BaseResponse {
+            code: 400,
+            message: error,
+        });
+    }
+
+    match delivery_platform::accept_platform_order(
+        platform.platform_id,
+        platform.waiter_id,
+        platform.shop_id,
+        &external.external_order_id,
+        &payload,
+        new_order,
+        client,
+    )
+    .await
+    {
+        Ok(None) => {
+            let order_id = delivery_platform::get_platform_order(
+                platform.platform_id,
+                &external.external_order_id,
+                client,
+            )
+            .await
+            .and_then(|o| o.order_id);
+            HttpResponse::Ok().json(DataResponse {
+                code: 200,
+                message: String::from("Order already received."),
+                data: order_id,
+            })
+        }
+        Ok(Some(id)) => {
+            if let Err(e) =
+                push_notification::notify_new_order(id, platform.shop_id, None, client).await
+            {
+                eprintln!("Order push notification error: {}", e);
+            }
+            HttpResponse::Created().json(DataResponse {
+                code: 201,
+                message: String::from("Order created successfully"),
+                data: Some(id),
+            })
+        }
+        Err(e) => {
+            eprintln!("Platform order creating error: {}", e);
+            if let Err(e) = delivery_platform::save_platform_order(
+                platform.platform_id,
+                &external.external_order_id,
+                None,
+                Some(&e.to_string()),
+                &payload,
+                client,
+            )
+            .await
+            {
+                eprintln!("Platform order saving error: {}", e);
+            }
+            HttpResponse::InternalServerError().json(BaseResponse {
+                code: 500,
+                message: String::from("Error creating order!"),
+            })
+        }
+    }
+}
+
+#[get("/api/delivery-platforms")]
+pub async fn get_delivery_platforms(
+    req: HttpRequest,
+    query: web::Query<GetDeliveryPlatformsQuery>,
+    data: web::Data<Arc<Mutex<Client>>>,
+) -> HttpResponse {
+    let client = data.lock().await;
+    // Extract the token from the Authorization header
+    let token = match req.headers().get("Authorization") {
+        Some(value) => {
+            let parts: Vec<&str> = value.to_str().unwrap_or("").split_whitespace().collect();
+            if parts.len() == 2 && parts[0] == "Bearer" {
+                parts[1]
+            } else {
+                return HttpResponse::BadRequest().json(BaseResponse {
+                    code: 400,
+                    message: String::from("Invalid Authorization header format"),
+                });
+            }
+        }
+        None => {
+            return HttpResponse::Unauthorized().json(BaseResponse {
+                code: 401,
+                message: String::from("Authorization header missing"),
+            })
+        }
+    };
+
+    let sub = match verify_token_and_get_sub(token) {
+        Some(s) => s,
+        None => {
+            return HttpResponse::Unauthorized().json(BaseResponse {
+                code: 401,
+                message: String::from("Invalid token"),
+            })
+        }
+    };
+
+    // Parse the `sub` value
+    let parsed_values: Vec<&str> = sub.split(',').collect();
+    if parsed_values.len() != 3 {
+        return HttpResponse::InternalServerError().json(BaseResponse {
+            code: 500,
+            message: String::from("Invalid sub format in token"),
+        });
+    }
+    let role: &str = parsed_values[1];
+    let shop_id: i32 = parsed_values[2].parse().unwrap();
+
+    if role != "Admin" && role != "Manager" {
+        return HttpResponse::Unauthorized().json(BaseResponse {
+            code: 401,
+            message: String::from("Unauthorized!"),
+        });
+    }
+    let filter_shop_id = if role == "Manager" {
+        Some(shop_id)
+    } else {
+        query.shop_id
+    };
+    match delivery_platform::get_delivery_platforms(filter_shop_id, &client).await {
+        Ok(platforms) => HttpResponse::Ok().json(DataResponse {
+            code: 200,
+            message: String::from("Successful."),
+            data: Some(platforms),
+        }),
+        Err(err) => {
+            // Log the error message here
+            println!("Error retrieving delivery platforms: {:?}", err);
+            HttpResponse::InternalServerError().json(BaseResponse {
+                code: 500,
+                message: String::from("Error trying to read all delivery platforms from database"),
+            })
+        }
+    }
+}
+
+#[post("/api/delivery-platforms")]
+pub async fn add_delivery_platform(
+    req: HttpRequest,
+    body: web::Json<DeliveryPlatformRequest>,
+    data: web::Data<Arc<Mutex<Client>>>,
+) -> HttpResponse {
+    let client = data.lock().await;
+    // Extract the token from the Authorization header
+    let token = match req.headers().get("Authorization") {
+        Some(value) => {
+            let parts: Vec<&str> = value.to_str().unwrap_or("").split_whitespace().collect();
+            if parts.len() == 2 && parts[0] == "Bearer" {
+                parts[1]
+            } else {
+                return HttpResponse::BadRequest().json(BaseResponse {
+                    code: 400,
+                    message: String::from("Invalid Authorization header format"),
+                });
+            }
+        }
+        None => {
+            return HttpResponse::Unauthorized().json(BaseResponse {
+                code: 401,
+                message: String::from("Authorization header missing"),
+            })
+        }
+    };
+
+    let sub = match verify_token_and_get_sub(token) {
+        Some(s) => s,
+        None => {
+            return HttpResponse::Unauthorized().json(BaseResponse {
+                code: 401,
+                message: String::from("Invalid token"),
+            })
+        }
+    };
+
+    // Parse the `sub` value
+    let parsed_values: Vec<&str> = sub.split(',').collect();
+    if parsed_values.len() != 3 {
+        return HttpResponse::InternalServerError().json(BaseResponse {
+            code: 500,
+            message: String::from("Invalid sub format in token"),
+        });
+    }
+    let role: &str = parsed_values[1];
+    let mut shop_id: i32 = parsed_values[2].parse().unwrap();
+
+    if role != "Admin" && role != "Manager" {
+        return HttpResponse::Unauthorized().json(BaseResponse {
+            code: 401,
+            message: String::from("Unauthorized!"),
+        });
+    }
+    if role == "Admin" {
+        shop_id = match body.shop_id {
+            Some(s) => s,
+            None => {
+                return HttpResponse::BadRequest().json(BaseResponse {
+                    code: 400,
+                    message: String::from("Shop Id must not be empty!"),
+                });
+            }
+        };
+    }
+    if body.webhook_secret.is_none() {
+        return HttpResponse::BadRequest().json(BaseResponse {
+            code: 400,
+            message: String::from("Webhook secret must not be empty!"),
+        });
+    }
+    if shop::get_shop_by_id(shop_id, &client).await.is_none() {
+        return HttpResponse::NotFound().json(BaseResponse {
+            code: 404,
+            message: String::from("Shop not found!"),
+        });
+    }
+    if let Some(response) = validate_delivery_platform(&body, shop_id, &client).await {
+        return response;
+    }
+
+    match delivery_platform::add_delivery_platform(&body, shop_id, &client).await {
+        Ok(id) => HttpResponse::Created().json(DataResponse {
+            code: 201,
+            message: String::from("Delivery platform added successfully"),
+            data: Some(id),
+        }),
+        Err(e) => {
+            eprintln!("Delivery platform adding error: {}", e);
+            HttpResponse::InternalServerError().json(BaseResponse {
+                code: 500,
+                message: String::from("Error adding delivery platform!"),
+            })
+        }
+    }
+}
+
+#[put("/api/delivery-platforms/{platform_id}")]
+pub async fn update_delivery_platform(
+    req: HttpRequest,
+    path: web::Path<i32>,
+    body: web::Json<DeliveryPlatformRequest>,
+    data: web::Data<Arc<Mutex<Client>>>,
+) -> HttpResponse {
+    let client = data.lock().await;
+    let platform_id = path.into_inner();
+    // Extract the token from the Authorization header
+    let token = match req.headers().get("Authorization") {
+        Some(value) => {
+            let parts: Vec<&str> = value.to_str().unwrap_or("").split_whitespace().collect();
+            if parts.len() == 2 && parts[0] == "Bearer" {
+                parts[1]
+            } else {
+                return HttpResponse::BadRequest().json(BaseResponse {
+                    code: 400,
+                    message: String::from("Invalid Authorization header format"),
+                });
+            }
+        }
+        None => {
+            return HttpResponse::Unauthorized().json(BaseResponse {
+                code: 401,
+                message: String::from("Authorization header missing"),
+            })
+        }
+    };
+
+    let sub = match verify_token_and_get_sub(token) {
+        Some(s) => s,
+        None => {
+            return HttpResponse::Unauthorized().json(BaseResponse {
+                code: 401,
+                message: String::from("Invalid token"),
+            })
+        }
+    };
+
+    // Parse the `sub` value
+    let parsed_values: Vec<&str> = sub.split(',').collect();
+    if parsed_values.len() != 3 {
+        return HttpResponse::InternalServerError().json(BaseResponse {
+            code: 500,
+            message: String::from("Invalid sub format in token"),
+        });
+    }
+    let role: &str = parsed_values[1];
+    let shop_id: i32 = parsed_values[2].parse().unwrap();
+
+    if role != "Admin" && role != "Manager" {
+        return HttpResponse::Unauthorized().json(BaseResponse {
+            code: 401,
+            message: String::from("Unauthorized!"),
+        });
+    }
+    let platform_shop_id =
+        match delivery_platform::get_delivery_platform_by_id(platform_id, &client)
+            .await
+            .filter(|p| role == "Admin" || p.shop_id == shop_id)
+        {
+            Some(p) => p.shop_id,
+            None => {
+                return HttpResponse::NotFound().json(BaseResponse {
+                    code: 404,
+                    message: String::from("Delivery platform not found!"),
+                });
+            }
+        };
+    if let Some(response) = validate_delivery_platform(&body, platform_shop_id, &client).await {
+        return response;
+    }
+
+    match delivery_platform::update_delivery_platform(platform_id, &body, &client).await {
+        Ok(()) => HttpResponse::Ok().json(BaseResponse {
+            code: 200,
+            message: String::from("Delivery platform updated successfully"),
+        }),
+        Err(e) => {
+            eprintln!("Delivery platform updating error: {}", e);
+            HttpResponse::InternalServerError().json(BaseResponse {
+                code: 500,
+                message: String::from("Error updating delivery platform!"),
+            })
+        }
+    }
+}
+
+#[delete("/api/delivery-platforms/{platform_id}")]
+pub async fn delete_delivery_platform(
+    req: HttpRequest,
+    path: web::Path<i32>,
+    data: web::Data<Arc<Mutex<Client>>>,
+) -> HttpResponse {
+    let client = data.lock().await;
+    let platform_id = path.into_inner();
+    // Extract the token from the Authorization header
+    let token = match req.headers().get("Authorization") {
+        Some(value) => {
+            let parts: Vec<&str> = value.to_str().unwrap_or("").split_whitespace().collect();
+            if parts.len() == 2 && parts[0] == "Bearer" {
+                parts[1]
+            } else {
+                return HttpResponse::BadRequest().json(BaseResponse {
+                    code: 400,
+                    message: String::from("Invalid Authorization header format"),
+                });
+            }
+        }
+        None => {
+            return HttpResponse::Unauthorized().json(BaseResponse {
+                code: 401,
+                message: String::from("Authorization header missing"),
+            })
+        }
+    };
+
+    let sub = match verify_token_and_get_sub(token) {
+        Some(s) => s,
+        None => {
+            return HttpResponse::Unauthorized().json(BaseResponse {
+                code: 401,
+                message: String::from("Invalid token"),
+            })
+        }
+    };
+
+    // Parse the `sub` value
+    let parsed_values: Vec<&str> = sub.split(',').collect();
+    if parsed_values.len() != 3 {
+        return HttpResponse::InternalServerError().json(BaseResponse {
+            code: 500,
+            message: String::from("Invalid sub format in token"),
+        });
+    }
+    let role: &str = parsed_values[1];
+    let shop_id: i32 = parsed_values[2].parse().unwrap();
+
+    if role != "Admin" && role != "Manager" {
+        return HttpResponse::Unauthorized().json(BaseResponse {
+            code: 401,
+            message: String::from("Unauthorized!"),
+        });
+    }
+    if delivery_platform::get_delivery_platform_by_id(platform_id, &client)
+        .await
+        .filter(|p| role == "Admin" || p.shop_id == shop_id)
+        .is_none()
+    {
+        return HttpResponse::NotFound().json(BaseResponse {
+            code: 404,
+            message: String::from("Delivery platform not found!"),
+        });
+    }
+
+    match delivery_platform::delete_delivery_platform(platform_id, &client).await {
+        Ok(()) => HttpResponse::Ok().json(BaseResponse {
+            code: 204,
+            message: String::from("Delivery platform deleted successfully"),
+        }),
+        Err(e) => {
+            eprintln!("Delivery platform deleting error: {}", e);
+            HttpResponse::InternalServerError().json(BaseResponse {
+                code: 500,
+                message: String::from("Error deleting delivery platform!"),
+            })
+        }
+    }
+}
+
+#[get("/api/delivery-platforms/{platform_id}/items")]
+pub async fn get_platform_items(
+    req: HttpRequest,
+    path: web::Path<i32>,
+    data: web::Data<Arc<Mutex<Client>>>,
+) -> HttpResponse {
+    let client = data.lock().await;
+    let platform_id = path.into_inner();
+    // Extract the token from the Authorization header
+    let token = match req.headers().get("Authorization") {
+        Some(value) => {
+            let parts: Vec<&str> = value.to_str().unwrap_or("").split_whitespace().collect();
+            if parts.len() == 2 && parts[0] == "Bearer" {
+                parts[1]
+            } else {
+                return HttpResponse::BadRequest().json(BaseResponse {
+                    code: 400,
+                    message: String::from("Invalid Authorization header format"),
+                });
+            }
+        }
+        None => {
+            return HttpResponse::Unauthorized().json(BaseResponse {
+                code: 401,
+                message: String::from("Authorization header missing"),
+            })
+        }
+    };
+
+    let sub = match verify_token_and_get_sub(token) {
+        Some(s) => s,
+        None => {
+            return HttpResponse::Unauthorized().json(BaseResponse {
+                code: 401,
+                message: String::from("Invalid token"),
+            })
+        }
+    };
+
+    // Parse the `sub` value
+    let parsed_values: Vec<&str> = sub.split(',').collect();
+    if parsed_values.len() != 3 {
+        return HttpResponse::InternalServerError().json(BaseResponse {
+            code: 500,
+            message: String::from("Invalid sub format in token"),
+        });
+    }
+    let role: &str = parsed_values[1];
+    let shop_id: i32 = parsed_values[2].parse().unwrap();
+
+    if role != "Admin" && role != "Manager" {
+        return HttpResponse::Unauthorized().json(BaseResponse {
+            code: 401,
+            message: String::from("Unauthorized!"),
+        });
+    }
+    if delivery_platform::get_delivery_platform_by_id(platform_id, &client)
+        .await
+        .filter(|p| role == "Admin" || p.shop_id == shop_id)
+        .is_none()
+    {
+        return HttpResponse::NotFound().json(BaseResponse {
+            code: 404,
+            message: String::from("Delivery platform not found!"),
+        });
+    }
+
+    match delivery_platform::get_platform_items(platform_id, &client).await {
+        Ok(items) => HttpResponse::Ok().json(DataResponse {
+            code: 200,
+            message: String::from("Successful."),
+            data: Some(items),
+        }),
+        Err(err) => {
+            // Log the error message here
+            println!("Error retrieving platform items: {:?}", err);
+            HttpResponse::InternalServerError().json(BaseResponse {
+                code: 500,
+                message: String::from("Error trying to read platform items from database"),
+            })
+        }
+    }
+}
+
+#[put("/api/delivery-platforms/{platform_id}/items")]
+pub async fn update_platform_items(
+    req: HttpRequest,
+    path: web::Path<i32>,
+    body: web::Json<Vec<PlatformItemRequest>>,
+    data: web::Data<Arc<Mutex<Client>>>,
+) -> HttpResponse {
+    let mut client = data.lock().await;
+    let platform_id = path.into_inner();
+    // Extract the token from the Authorization header
+    let token = match req.headers().get("Authorization") {
+        Some(value) => {
+            let parts: Vec<&str> = value.to_str().unwrap_or("").split_whitespace().collect();
+            if parts.len() == 2 && parts[0] == "Bearer" {
+                parts[1]
+            } else {
+                return HttpResponse::BadRequest().json(BaseResponse {
+                    code: 400,
+                    message: String::from("Invalid Authorization header format"),
+                });
+            }
+        }
+        None => {
+            return HttpResponse::Unauthorized().json(BaseResponse {
+                code: 401,
+                message: String::from("Authorization header missing"),
+            })
+        }
+    };
+
+    let sub = match verify_token_and_get_sub(token) {
+        Some(s) => s,
+        None => {
+            return HttpResponse::Unauthorized().json(BaseResponse {
+                code: 401,
+                message: String::from("Invalid token"),
+            })
+        }
+    };
+
+    // Parse the `sub` value
+    let parsed_values: Vec<&str> = sub.split(',').collect();
+    if parsed_values.len() != 3 {
+        return HttpResponse::InternalServerError().json(BaseResponse {
+            code: 500,
+            message: String::from("Invalid sub format in token"),
+        });
+    }
+    let role: &str = parsed_values[1];
+    let shop_id: i32 = parsed_values[2].parse().unwrap();
+
+    if role != "Admin" && role != "Manager" {
+        return HttpResponse::Unauthorized().json(BaseResponse {
+            code: 401,
+            message: String::from("Unauthorized!"),
+        });
+    }
+    let platform_shop_id =
+        match delivery_platform::get_delivery_platform_by_id(platform_id, &client)
+            .await
+            .filter(|p| role == "Admin" || p.shop_id == shop_id)
+        {
+            Some(p) => p.shop_id,
+            None => {
+                return HttpResponse::NotFound().json(BaseResponse {
+                    code: 404,
+                    message: String::from("Delivery platform not found!"),
+                });
+            }
+        };
+    if body.iter().any(|i| i.external_sku.trim().is_empty()) {
+        return HttpResponse::BadRequest().json(BaseResponse {
+            code: 400,
+            message: String::from("External SKU must not be empty!"),
+        });
+    }
+    let mut skus: Vec<&str> = body.iter().map(|i| i.external_sku.as_str()).collect();
+    skus.sort();
+    skus.dedup();
+    if skus.len() != body.len() {
+        return HttpResponse::BadRequest().json(BaseResponse {
+            code: 400,
+            message: String::from("External SKUs must not be repeated!"),
+        });
+    }
+    let item_ids: Vec<i32> = body.iter().map(|i| i.item_id).collect();
+    match delivery_platform::items_belong_to_shop(&item_ids, platform_shop_id, &client).await {
+        Ok(true) => {}
+        Ok(false) => {
+            return HttpResponse::BadRequest().json(BaseResponse {
+                code: 400,
+                message: String::from("Items must belong to the shop!"),
+            });
+        }
+        Err(err) => {
+            println!("{:?}", err);
+            return HttpResponse::InternalServerError().json(BaseResponse {
+                code: 500,
+                message: String::from("Error checking items!"),
+            });
+        }
+    }
+
+    match delivery_platform::set_platform_items(platform_id, &body, &mut client).await {
+        Ok(()) => HttpResponse::Ok().json(BaseResponse {
+            code: 200,
+            message: String::from("Platform items updated successfully"),
+        }),
+        Err(e) => {
+            eprintln!("Platform items updating error: {}", e);
+            HttpResponse::InternalServerError().json(BaseResponse {
+                code: 500,
+                message: String::from("Error updating platform items!"),
+            })
+        }
+    }
+}
+
+#[get("/api/delivery-platforms/{platform_id}/orders")]
+pub async fn get_platform_orders(
+    req: HttpRequest,
+    path: web::Path<i32>,
+    query: web::Query<GetPlatformOrdersQuery>,
+    data: web::Data<Arc<Mutex<Client>>>,
+) -> HttpResponse {
+    let client = data.lock().await;
+    let platform_id = path.into_inner();
+    // Extract the token from the Authorization header
+    let token = match req.headers().get("Authorization") {
+        Some(value) => {
+            let parts: Vec<&str> = value.to_str().unwrap_or("").split_whitespace().collect();
+            if parts.len() == 2 && parts[0] == "Bearer" {
+                parts[1]
+            } else {
+                return HttpResponse::BadRequest().json(BaseResponse {
+                    code: 400,
+                    message: String::from("Invalid Authorization header format"),
+                });
+            }
+        }
+        None => {
+            return HttpResponse::Unauthorized().json(BaseResponse {
+                code: 401,
+                message: String::from("Authorization header missing"),
+            })
+        }
+    };
+
+    let sub = match verify_token_and_get_sub(token) {
+        Some(s) => s,
+        None => {
+            return HttpResponse::Unauthorized().json(BaseResponse {
+                code: 401,
+                message: String::from("Invalid token"),
+            })
+        }
+    };
+
+    // Parse the `sub` value
+    let parsed_values: Vec<&str> = sub.split(',').collect();
+    if parsed_values.len() != 3 {
+        return HttpResponse::InternalServerError().json(BaseResponse {
+            code: 500,
+            message: String::from("Invalid sub format in token"),
+        });
+    }
+    let role: &str = parsed_values[1];
+    let shop_id: i32 = parsed_values[2].parse().unwrap();
+
+    if role != "Admin" && role != "Manager" {
+        return HttpResponse::Unauthorized().json(BaseResponse {
+            code: 401,
+            message: String::from("Unauthorized!"),
+        });
+    }
+    if let Some(s) = &query.status {
+        if !PLATFORM_ORDER_STATUSES.contains(&s.as_str()) {
+            return HttpResponse::BadRequest().json(BaseResponse {
+                code: 400,
+                message: String::from("Please select a valid status: Accepted or Rejected."),
+            });
+        }
+    }
+    if delivery_platform::get_delivery_platform_by_id(platform_id, &client)
+        .await
+        .filter(|p| role == "Admin" || p.shop_id == shop_id)
+        .is_none()
+    {
+        return HttpResponse::NotFound().json(BaseResponse {
+            code: 404,
+            message: String::from("Delivery platform not found!"),
+        });
+    }
+
+    match delivery_platform::get_platform_orders(
+        platform_id,
+        &query.status,
+        query.page,
+        query.per_page,
+        &client,
+    )
+    .await
+    {
+        Ok(result) => HttpResponse::Ok().json(PaginationResponse {
+            code: 200,
+            message: String::from("Successful."),
+            data: result.data,
+            total: result.total,
+            page: result.page,
+            per_page: result.per_page,
+            page_counts: result.page_counts,
+        }),
+        Err(err) => {
+            // Log the error message here
+            println!("Error retrieving platform orders: {:?}", err);
+            HttpResponse::InternalServerError().json(BaseResponse {
+                code: 500,
+                message: String::from("Error trying to read platform orders from database"),
+            })
+        }
+    }
+}
+
+// Plays the part of the platform for local testing: signs a delivery order of the mapped SKUs with the
+// platform's secret and feeds it through the same path as a real webhook.
+#[post("/api/delivery-platforms/{platform_id}/mock-order")]
+pub async fn send_mock_platform_order(
+    req: HttpRequest,
+    path: web::Path<i32>,
+    data: web::Data<Arc<Mutex<Client>>>,
+) -> HttpResponse {
+    let mut client = data.lock().await;
+    let platform_id = path.into_inner();
+    // Extract the token from the Authorization header
+    let token = match req.headers().get("Authorization") {
+        Some(value) => {
+            let parts: Vec<&str> = value.to_str().unwrap_or("").split_whitespace().collect();
+            if parts.len() == 2 && parts[0] == "Bearer" {
+                parts[1]
+            } else {
+                return HttpResponse::BadRequest().json(BaseResponse {
+                    code: 400,
+                    message: String::from("Invalid Authorization header format"),
+                });
+            }
+        }
+        None => {
+            return HttpResponse::Unauthorized().json(BaseResponse {
+                code: 401,
+                message: String::from("Authorization header missing"),
+            })
+        }
+    };
+
+    let sub = match verify_token_and_get_sub(token) {
+        Some(s) => s,
+        None => {
+            return HttpResponse::Unauthorized().json(BaseResponse {
+                code: 401,
+                message: String::from("Invalid token"),
+            })
+        }
+    };
+
+    // Parse the `sub` value
+    let parsed_values: Vec<&str> = sub.split(',').collect();
+    if parsed_values.len() != 3 {
+        return HttpResponse::InternalServerError().json(BaseResponse {
+            code: 500,
+            message: String::from("Invalid sub format in token"),
+        });
+    }
+    let role: &str = parsed_values[1];
+    let shop_id: i32 = parsed_values[2].parse().unwrap();
+
+    if role != "Admin" && role != "Manager" {
+        return HttpResponse::Unauthorized().json(BaseResponse {
+            code: 401,
+            message: String::from("Unauthorized!"),
+        });
+    }
+    let platform = match delivery_platform::get_delivery_platform_by_id(platform_id, &client)
+        .await
+        .filter(|p| role == "Admin" || p.shop_id == shop_id)
+    {
+        Some(p) => p,
+        None => {
+            return HttpResponse::NotFound().json(BaseResponse {
+                code: 404,
+                message: String::from("Delivery platform not found!"),
+            });
+        }
+    };
+    let items = match delivery_platform::get_platform_items(platform_id, &client).await {
+        Ok(items) => items,
+        Err(e) => {
+            eprintln!("Database error: {}", e);
+            return HttpResponse::InternalServerError().json(BaseResponse {
+                code: 500,
+                message: String::from("Something went wrong!"),
+            });
+        }
+    };
+    if items.is_empty() {
+        return HttpResponse::BadRequest().json(BaseResponse {
+            code: 400,
+            message: String::from("Map at least one SKU before sending a mock order!"),
+        });
+    }
+
+    let mock_order = ExternalOrder {
+        external_order_id: format!("MOCK-{}", Uuid::new_v4().simple()),
+        order_type: String::from("Delivery"),
+        customer_name: Some(String::from("Mock Customer")),
+        customer_phone: None,
+        delivery_address: Some(String::from("Mock delivery address")),
+        delivery_fee: Some(0.0),
+        scheduled_at: None,
+        items: items
+            .iter()
+            .take(3)
+            .map(|i| ExternalOrderItem {
+                sku: i.external_sku.clone(),
+                quantity: 1,
+                notes: None,
+            })
+            .collect(),
+    };
+    let body = serde_json::to_vec(&mock_order).unwrap();
+    let headers = signature_headers(&platform.adapter, &body, &platform.webhook_secret);
+    ingest_platform_order(&platform, &headers, &body, &mut client).await
+}
+
+#[post("/api/webhooks/delivery-platforms/{platform_id}")]
+pub async fn receive_platform_order(
+    req: HttpRequest,
+    path: web::Path<i32>,
+    body: web::Bytes,
+    data: web::Data<Arc<Mutex<Client>>>,
+) -> HttpResponse {
+    let mut client = data.lock().await;
+    let platform_id = path.into_inner();
+
+    let platform = match delivery_platform::get_delivery_platform_by_id(platform_id, &client)
+        .await
+        .filter(|p| p.is_active)
+    {
+        Some(p) => p,
+        None => {
+            return HttpResponse::NotFound().json(BaseResponse {
+                code: 404,
+                message: String::from("Delivery platform not found!"),
+            });
+        }
+    };
+    ingest_platform_order(&platform, req.headers(), &body, &mut client).await
+}
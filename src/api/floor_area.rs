@@ -0,0 +1,612 @@
+use std::sync::Arc;
+
+use actix_web::{delete, get, post, put, web, HttpRequest, HttpResponse};
+use serde::Deserialize;
+use tokio::sync::Mutex;
+use tokio_postgres::Client;
+
+use crate::{
+    models::{
+        floor_area::{self, FloorAreaRequest},
+        shop,
+        table::{self, TableLayoutRequest, TABLE_SHAPES},
+    },
+    utils::{
+        common_struct::{BaseResponse, DataResponse},
+        jwt::verify_token_and_get_sub,
+    },
+};
+
+#[derive(Deserialize)]
+pub struct GetFloorAreasQuery {
+    pub shop_id: Option<i32>,
+}
+
+fn validate_table_layouts(body: &[TableLayoutRequest]) -> Option<HttpResponse> {
+    if body.is_empty() {
+        return Some(HttpResponse::BadRequest().json(BaseResponse {
+            code: 400,
+            message: String::from("Tables must not be empty!"),
+        }));
+    }
+    for layout in body {
+        if !TABLE_SHAPES.contains(&layout.shape.as_str()) {
+            return Some(HttpResponse::BadRequest().json(BaseResponse {
+                code: 400,
+                message: String::from("Please select a valid shape: Square, Round, or Rectangle."),
+            }));
+        }
+        if layout.pos_x < 0 || layout.pos_y < 0 {
+            return Some(HttpResponse::BadRequest().json(BaseResponse {
+                code: 400,
+                message: String::from("Position must not be negative!"),
+            }));
+        }
+        if layout.width < 1 || layout.height < 1 {
+            return Some(HttpResponse::BadRequest().json(BaseResponse {
+                code: 400,
+                message: String::from("Width and height must be greater than zero!"),
+            }));
+        }
+        if layout.rotation < 0 || layout.rotation >= 360 {
+            return Some(HttpResponse::BadRequest().json(BaseResponse {
+                code: 400,
+                message: String::from("Rotation must be between 0 and 359!"),
+            }));
+        }
+    }
+    None
+}
+
+#[get("/api/floor-areas")]
+pub async fn get_floor_areas(
+    req: HttpRequest,
+    query: web::Query<GetFloorAreasQuery>,
+    data: web::Data<Arc<Mutex<Client>>>,
+) -> HttpResponse {
+    let client = data.lock().await;
+    // Extract the token from the Authorization header
+    let token = match req.headers().get("Authorization") {
+        Some(value) => {
+            let parts: Vec<&str> = value.to_str().unwrap_or("").split_whitespace().collect();
+            if parts.len() == 2 && parts[0] == "Bearer" {
+                parts[1]
+            } else {
+                return HttpResponse::BadRequest().json(BaseResponse {
+                    code: 400,
+                    message: String::from("Invalid Authorization header format"),
+                });
+            }
+        }
+        None => {
+            return HttpResponse::Unauthorized().json(BaseResponse {
+                code: 401,
+                message: String::from("Authorization header missing"),
+            })
+        }
+    };
+
+    let sub = match verify_token_and_get_sub(token) {
+        Some(s) => s,
+        None => {
+            return HttpResponse::Unauthorized().json(BaseResponse {
+                code: 401,
+                message: String::from("Invalid token"),
+            })
+        }
+    };
+
+    // Parse the `sub` value
+    let parsed_values: Vec<&str> = sub.split(',').collect();
+    if parsed_values.len() != 3 {
+        return HttpResponse::InternalServerError().json(BaseResponse {
+            code: 500,
+            message: String::from("Invalid sub format in token"),
+        });
+    }
+    let role: &str = parsed_values[1];
+    let shop_id: i32 = parsed_values[2].parse().unwrap();
+
+    let filter_shop_id = if role == "Admin" {
+        query.shop_id
+    } else {
+        Some(shop_id)
+    };
+    match floor_area::get_floor_areas(filter_shop_id, &client).await {
+        Ok(areas) => HttpResponse::Ok().json(DataResponse {
+            code: 200,
+            message: String::from("Successful."),
+            data: Some(areas),
+        }),
+        Err(err) => {
+            // Log the error message here
+            println!("Error retrieving floor areas: {:?}", err);
+            HttpResponse::InternalServerError().json(BaseResponse {
+                code: 500,
+                message: String::from("Error trying to read all floor areas from database"),
+            })
+        }
+    }
+}
+
+#[post("/api/floor-areas")]
+pub async fn add_floor_area(
+    req: HttpRequest,
+    body: web::Json<FloorAreaRequest>,
+    data: web::Data<Arc<Mutex<Client>>>,
+) -> HttpResponse {
+    let client = data.lock().await;
+    // Extract the token from the Authorization header
+    let token = match req.headers().get("Authorization") {
+        Some(value) => {
+            let parts: Vec<&str> = value.to_str().unwrap_or("").split_whitespace().collect();
+            if parts.len() == 2 && parts[0] == "Bearer" {
+                parts[1]
+            } else {
+                return HttpResponse::BadRequest().json(BaseResponse {
+                    code: 400,
+                    message: String::from("Invalid Authorization header format"),
+                });
+            }
+        }
+        None => {
+            return HttpResponse::Unauthorized().json(BaseResponse {
+                code: 401,
+                message: String::from("Authorization header missing"),
+            })
+        }
+    };
+
+    let sub = match verify_token_and_get_sub(token) {
+        Some(s) => s,
+        None => {
+            return HttpResponse::Unauthorized().json(BaseResponse {
+                code: 401,
+                message: String::from("Invalid token"),
+            })
+        }
+    };
+
+    // Parse the `sub` value
+    let parsed_values: Vec<&str> = sub.split(',').collect();
+    if parsed_values.len() != 3 {
+        return HttpResponse::InternalServerError().json(BaseResponse {
+            code: 500,
+            message: String::from("Invalid sub format in token"),
+        });
+    }
+    let role: &str = parsed_values[1];
+    let mut shop_id: i32 = parsed_values[2].parse().unwrap();
+
+    if role != "Admin" && role != "Manager" {
+        return HttpResponse::Unauthorized().json(BaseResponse {
+            code: 401,
+            message: String::from("Unauthorized!"),
+        });
+    }
+    if role == "Admin" {
+        shop_id = match body.shop_id {
+            Some(s) => s,
+            None => {
+                return HttpResponse::BadRequest().json(BaseResponse {
+                    code: 400,
+                    message: String::from("Shop Id must not be empty!"),
+                });
+            }
+        };
+    }
+    if body.name.trim().is_empty() {
+        return HttpResponse::BadRequest().json(BaseResponse {
+            code: 400,
+            message: String::from("Name must not be empty!"),
+        });
+    }
+    if shop::get_shop_by_id(shop_id, &client).await.is_none() {
+        return HttpResponse::NotFound().json(BaseResponse {
+            code: 404,
+            message: String::from("Shop not found!"),
+        });
+    }
+    match floor_area::floor_area_name_exists(&body.name, shop_id, None, &client).await {
+        Ok(true) => {
+            return HttpResponse::BadRequest().json(BaseResponse {
+                code: 400,
+                message: String::from("Floor area name already exists!"),
+            });
+        }
+        Ok(false) => {}
+        Err(e) => {
+            eprintln!("Database error: {}", e);
+            return HttpResponse::InternalServerError().json(BaseResponse {
+                code: 500,
+                message: String::from("Something went wrong!"),
+            });
+        }
+    }
+
+    match floor_area::add_floor_area(&body, shop_id, &client).await {
+        Ok(id) => HttpResponse::Created().json(DataResponse {
+            code: 201,
+            message: String::from("Floor area added successfully"),
+            data: Some(id),
+        }),
+        Err(e) => {
+            eprintln!("Floor area adding error: {}", e);
+            HttpResponse::InternalServerError().json(BaseResponse {
+                code: 500,
+                message: String::from("Error adding floor area!"),
+            })
+        }
+    }
+}
+
+#[put("/api/floor-areas/{floor_area_id}")]
+pub async fn update_floor_area(
+    req: HttpRequest,
+    path: web::Path<i32>,
+    body: web::Json<FloorAreaRequest>,
+    data: web::Data<Arc<Mutex<Client>>>,
+) -> HttpResponse {
+    let client = data.lock().await;
+    let floor_area_id = path.into_inner();
+    // Extract the token from the Authorization header
+    let token = match req.headers().get("Authorization") {
+        Some(value) => {
+            let parts: Vec<&str> = value.to_str().unwrap_or("").split_whitespace().collect();
+            if parts.len() == 2 && parts[0] == "Bearer" {
+                parts[1]
+            } else {
+                return HttpResponse::BadRequest().json(BaseResponse {
+                    code: 400,
+                    message: String::from("Invalid Authorization header format"),
+                });
+            }
+        }
+        None => {
+            return HttpResponse::Unauthorized().json(BaseResponse {
+                code: 401,
+                message: String::from("Authorization header missing"),
+            })
+        }
+    };
+
+    let sub = match verify_token_and_get_sub(token) {
+        Some(s) => s,
+        None => {
+            return HttpResponse::Unauthorized().json(BaseResponse {
+                code: 401,
+                message: String::from("Invalid token"),
+            })
+        }
+    };
+
+    // Parse the `sub` value
+    let parsed_values: Vec<&str> = sub.split(',').collect();
+    if parsed_values.len() != 3 {
+        return HttpResponse::InternalServerError().json(BaseResponse {
+            code: 500,
+            message: String::from("Invalid sub format in token"),
+        });
+    }
+    let role: &str = parsed_values[1];
+    let shop_id: i32 = parsed_values[2].parse().unwrap();
+
+    if role != "Admin" && role != "Manager" {
+        return HttpResponse::Unauthorized().json(BaseResponse {
+            code: 401,
+            message: String::from("Unauthorized!"),
+        });
+    }
+    if body.name.trim().is_empty() {
+        return HttpResponse::BadRequest().json(BaseResponse {
+            code: 400,
+            message: String::from("Name must not be empty!"),
+        });
+    }
+    let area_shop_id = match floor_area::get_floor_area_by_id(floor_area_id, &client)
+        .await
+        .filter(|a| role == "Admin" || a.shop_id == shop_id)
+    {
+        Some(a) => a.shop_id,
+        None => {
+            return HttpResponse::NotFound().json(BaseResponse {
+                code: 404,
+                message: String::from("Floor area not found!"),
+            });
+        }
+    };
+    match floor_area::floor_area_name_exists(&body.name, area_shop_id, Some(floor_area_id), &client)
+        .await
+    {
+        Ok(true) => {
+            return HttpResponse::BadRequest().json(BaseResponse {
+                code: 400,
+                message: String::from("Floor area name already exists!"),
+            });
+        }
+        Ok(false) => {}
+        Err(e) => {
+            eprintln!("Database error: {}", e);
+            return HttpResponse::InternalServerError().json(BaseResponse {
+                code: 500,
+                message: String::from("Something went wrong!"),
+            });
+        }
+    }
+
+    match floor_area::update_floor_area(floor_area_id, &body, &client).await {
+        Ok(()) => HttpResponse::Ok().json(BaseResponse {
+            code: 200,
+            message: String::from("Floor area updated successfully"),
+        }),
+        Err(e) => {
+            eprintln!("Floor area updating error: {}", e);
+            HttpResponse::InternalServerError().json(BaseResponse {
+                code: 500,
+                message: String::from("Error updating floor area!"),
+            })
+        }
+    }
+}
+
+#[delete("/api/floor-areas/{floor_area_id}")]
+pub async fn delete_floor_area(
+    req: HttpRequest,
+    path: web::Path<i32>,
+    data: web::Data<Arc<Mutex<Client>>>,
+) -> HttpResponse {
+    let mut client = data.lock().await;
+    let floor_area_id = path.into_inner();
+    // Extract the token from the Authorization header
+    let token = match req.headers().get("Authorization") {
+        Some(value) => {
+            let parts: Vec<&str> = value.to_str().unwrap_or("").split_whitespace().collect();
+            if parts.len() == 2 && parts[0] == "Bearer" {
+                parts[1]
+            } else {
+                return HttpResponse::BadRequest().json(BaseResponse {
+                    code: 400,
+                    message: String::from("Invalid Authorization header format"),
+                });
+            }
+        }
+        None => {
+            return HttpResponse::Unauthorized().json(BaseResponse {
+                code: 401,
+                message: String::from("Authorization header missing"),
+            })
+        }
+    };
+
+    let sub = match verify_token_and_get_sub(token) {
+        Some(s) => s,
+        None => {
+            return HttpResponse::Unauthorized().json(BaseResponse {
+                code: 401,
+                message: String::from("Invalid token"),
+            })
+        }
+    };
+
+    // Parse the `sub` value
+    let parsed_values: Vec<&str> = sub.split(',').collect();
+    if parsed_values.len() != 3 {
+        return HttpResponse::InternalServerError().json(BaseResponse {
+            code: 500,
+            message: String::from("Invalid sub format in token"),
+        });
+    }
+    let role: &str = parsed_values[1];
+    let shop_id: i32 = parsed_values[2].parse().unwrap();
+
+    if role != "Admin" && role != "Manager" {
+        return HttpResponse::Unauthorized().json(BaseResponse {
+            code: 401,
+            message: String::from("Unauthorized!"),
+        });
+    }
+    if floor_area::get_floor_area_by_id(floor_area_id, &client)
+        .await
+        .filter(|a| role == "Admin" || a.shop_id == shop_id)
+        .is_none()
+    {
+        return HttpResponse::NotFound().json(BaseResponse {
+            code: 404,
+            message: String::from("Floor area not found!"),
+        });
+    }
+
+    match floor_area::delete_floor_area(floor_area_id, &mut client).await {
+        Ok(()) => HttpResponse::Ok().json(BaseResponse {
+            code: 204,
+            message: String::from("Floor area deleted successfully"),
+        }),
+        Err(e) => {
+            eprintln!("Floor area deleting error: {}", e);
+            HttpResponse::InternalServerError().json(BaseResponse {
+                code: 500,
+                message: String::from("Error deleting floor area!"),
+            })
+        }
+    }
+}
+
+#[get("/api/shops/{shop_id}/floor-plan")]
+pub async fn get_floor_plan(
+    req: HttpRequest,
+    path: web::Path<i32>,
+    data: web::Data<Arc<Mutex<Client>>>,
+) -> HttpResponse {
+    let client = data.lock().await;
+    let request_shop_id = path.into_inner();
+    // Extract the token from the Authorization header
+    let token = match req.headers().get("Authorization") {
+        Some(value) => {
+            let parts: Vec<&str> = value.to_str().unwrap_or("").split_whitespace().collect();
+            if parts.len() == 2 && parts[0] == "Bearer" {
+                parts[1]
+            } else {
+                return HttpResponse::BadRequest().json(BaseResponse {
+                    code: 400,
+                    message: String::from("Invalid Authorization header format"),
+                });
+            }
+        }
+        None => {
+            return HttpResponse::Unauthorized().json(BaseResponse {
+                code: 401,
+                message: String::from("Authorization header missing"),
+            })
+        }
+    };
+
+    let sub = match verify_token_and_get_sub(token) {
+        Some(s) => s,
+        None => {
+            return HttpResponse::Unauthorized().json(BaseResponse {
+                code: 401,
+                message: String::from("Invalid token"),
+            })
+        }
+    };
+
+    // Parse the `sub` value
+    let parsed_values: Vec<&str> = sub.split(',').collect();
+    if parsed_values.len() != 3 {
+        return HttpResponse::InternalServerError().json(BaseResponse {
+            code: 500,
+            message: String::from("Invalid sub format in token"),
+        });
+    }
+    let role: &str = parsed_values[1];
+    let shop_id: i32 = parsed_values[2].parse().unwrap();
+
+    if (role != "Admin" && request_shop_id != shop_id)
+        || shop::get_shop_by_id(request_shop_id, &client)
+            .await
+            .is_none()
+    {
+        return HttpResponse::NotFound().json(BaseResponse {
+            code: 404,
+            message: String::from("Shop not found!"),
+        });
+    }
+
+    match table::get_floor_plan(request_shop_id, &client).await {
+        Ok(areas) => HttpResponse::Ok().json(DataResponse {
+            code: 200,
+            message: String::from("Successful."),
+            data: Some(areas),
+        }),
+        Err(err) => {
+            // Log the error message here
+            println!("Error retrieving floor plan: {:?}", err);
+            HttpResponse::InternalServerError().json(BaseResponse {
+                code: 500,
+                message: String::from("Error trying to read floor plan from database"),
+            })
+        }
+    }
+}
+
+#[put("/api/shops/{shop_id}/floor-plan")]
+pub async fn update_floor_plan(
+    req: HttpRequest,
+    path: web::Path<i32>,
+    body: web::Json<Vec<TableLayoutRequest>>,
+    data: web::Data<Arc<Mutex<Client>>>,
+) -> HttpResponse {
+    let mut client = data.lock().await;
+    let request_shop_id = path.into_inner();
+    // Extract the token from the Authorization header
+    let token = match req.headers().get("Authorization") {
+        Some(value) => {
+            let parts: Vec<&str> = value.to_str().unwrap_or("").split_whitespace().collect();
+            if parts.len() == 2 && parts[0] == "Bearer" {
+                parts[1]
+            } else {
+                return HttpResponse::BadRequest().json(BaseResponse {
+                    code: 400,
+                    message: String::from("Invalid Authorization header format"),
+                });
+            }
+        }
+        None => {
+            return HttpResponse::Unauthorized().json(BaseResponse {
+                code: 401,
+                message: String::from("Authorization header missing"),
+            })
+        }
+    };
+
+    let sub = match verify_token_and_get_sub(token) {
+        Some(s) => s,
+        None => {
+            return HttpResponse::Unauthorized().json(BaseResponse {
+                code: 401,
+                message: String::from("Invalid token"),
+            })
+        }
+    };
+
+    // Parse the `sub` value
+    let parsed_values: Vec<&str> = sub.split(',').collect();
+    if parsed_values.len() != 3 {
+        return HttpResponse::InternalServerError().json(BaseResponse {
+            code: 500,
+            message: String::from("Invalid sub format in token"),
+        });
+    }
+    let role: &str = parsed_values[1];
+    let shop_id: i32 = parsed_values[2].parse().unwrap();
+
+    if role != "Admin" && role != "Manager" {
+        return HttpResponse::Unauthorized().json(BaseResponse {
+            code: 401,
+            message: String::from("Unauthorized!"),
+        });
+    }
+    if let Some(response) = validate_table_layouts(&body) {
+        return response;
+    }
+    if (role == "Manager" && request_shop_id != shop_id)
+        || shop::get_shop_by_id(request_shop_id, &client)
+            .await
+            .is_none()
+    {
+        return HttpResponse::NotFound().json(BaseResponse {
+            code: 404,
+            message: String::from("Shop not found!"),
+        });
+    }
+    match table::layout_belongs_to_shop(&body, request_shop_id, &client).await {
+        Ok(true) => {}
+        Ok(false) => {
+            return HttpResponse::BadRequest().json(BaseResponse {
+                code: 400,
+                message: String::from("Tables, floor areas and sections must belong to the shop!"),
+            });
+        }
+        Err(err) => {
+            println!("{:?}", err);
+            return HttpResponse::InternalServerError().json(BaseResponse {
+                code: 500,
+                message: String::from("Error checking floor plan!"),
+            });
+        }
+    }
+
+    match table::update_table_layouts(&body, request_shop_id, &mut client).await {
+        Ok(()) => HttpResponse::Ok().json(BaseResponse {
+            code: 200,
+            message: String::from("Floor plan updated successfully"),
+        }),
+        Err(e) => {
+            eprintln!("Floor plan updating error: {}", e);
+            HttpResponse::InternalServerError().json(BaseResponse {
+                code: 500,
+                message: String::from("Error updating floor plan!"),
+            })
+        }
+    }
+}
@@ -0,0 +1,128 @@
+use actix_web::{get, web, HttpRequest, HttpResponse, Responder};
+use chrono::NaiveDate;
+use serde::Deserialize;
+use std::sync::Arc;
+use tokio::sync::Mutex;
+use tokio_postgres::Client;
+
+use crate::{
+    models::{food_cost_report, shop},
+    utils::{
+        common_struct::{BaseResponse, DataResponse},
+        jwt::verify_token_and_get_sub,
+    },
+};
+
+#[derive(Deserialize)]
+pub struct FoodCostReportQuery {
+    pub from_date: NaiveDate,
+    pub to_date: NaiveDate,
+    pub shop_id: Option<i32>,
+}
+
+#[get("/api/food-cost-report")]
+pub async fn get_food_cost_report(
+    req: HttpRequest,
+    query: web::Query<FoodCostReportQuery>,
+    data: web::Data<Arc<Mutex<Client>>>,
+) -> impl Responder {
+    let client = data.lock().await;
+    // Extract the token from the Authorization header
+    let token = match req.headers().get("Authorization") {
+        Some(value) => {
+            let parts: Vec<&str> = value.to_str().unwrap_or("").split_whitespace().collect();
+            if parts.len() == 2 && parts[0] == "Bearer" {
+                parts[1]
+            } else {
+                return HttpResponse::BadRequest().json(BaseResponse {
+                    code: 400,
+                    message: String::from("Invalid Authorization header format"),
+                });
+            }
+        }
+        None => {
+            return HttpResponse::Unauthorized().json(BaseResponse {
+                code: 401,
+                message: String::from("Authorization header missing"),
+            })
+        }
+    };
+
+    let sub = match verify_token_and_get_sub(token) {
+        Some(s) => s,
+        None => {
+            return HttpResponse::Unauthorized().json(BaseResponse {
+                code: 401,
+                message: String::from("Invalid token"),
+            })
+        }
+    };
+
+    // Parse the `sub` value
+    let parsed_values: Vec<&str> = sub.split(',').collect();
+    if parsed_values.len() != 3 {
+        return HttpResponse::InternalServerError().json(BaseResponse {
+            code: 500,
+            message: String::from("Invalid sub format in token"),
+        });
+    }
+    let role: &str = parsed_values[1];
+    let mut shop_id: i32 = parsed_values[2].parse().unwrap();
+
+    if role != "Admin" && role != "Manager" {
+        return HttpResponse::Unauthorized().json(BaseResponse {
+            code: 401,
+            message: String::from("Unauthorized!"),
+        });
+    }
+    if role == "Admin" {
+        shop_id = match query.shop_id {
+            Some(s) => s,
+            None => {
+                return HttpResponse::BadRequest().json(BaseResponse {
+                    code: 400,
+                    message: String::from("Shop Id must not be empty!"),
+                });
+            }
+        };
+    }
+
+    if query.from_date.gt(&query.to_date) {
+        return HttpResponse::BadRequest().json(BaseResponse {
+            code: 400,
+            message: String::from("Invalid Date Range!"),
+        });
+    }
+
+    match shop::get_shop_by_id(shop_id, &client).await {
+        Some(s) => {
+            match food_cost_report::get_food_cost_report(
+                query.from_date,
+                query.to_date,
+                shop_id,
+                s.name,
+                &client,
+            )
+            .await
+            {
+                Ok(data) => HttpResponse::Ok().json(DataResponse {
+                    code: 200,
+                    message: String::from("Successful."),
+                    data: Some(data),
+                }),
+                Err(err) => {
+                    // Log the error message here
+                    println!("Error retrieving food cost report: {:?}", err);
+                    HttpResponse::InternalServerError().json(BaseResponse {
+                        code: 500,
+                        message: String::from("Error trying to read food cost report from database"),
+                    })
+                }
+            }
+        }
+        None => HttpResponse::NotFound().json(BaseResponse {
+            code: 404,
+            message: String::from("Shop not found!"),
+        }),
+    }
+}
@@ -0,0 +1,704 @@
+use std::sync::Arc;
+
+use actix_web::{get, post, web, HttpRequest, HttpResponse};
+use chrono::Local;
+use serde::Deserialize;
+use tokio::sync::Mutex;
+use tokio_postgres::Client;
+
+use crate::{
+    models::{
+        customer,
+        gift_card::{self, GiftCardDetail, IssueGiftCardRequest},
+        shop,
+    },
+    utils::{
+        common_struct::{BaseResponse, DataResponse, PaginationResponse},
+        jwt::verify_token_and_get_sub,
+    },
+};
+
+#[derive(Deserialize)]
+pub struct GetGiftCardsQuery {
+    pub search: Option<String>,
+    pub page: Option<usize>,
+    pub per_page: Option<usize>,
+    pub shop_id: Option<i32>,
+}
+
+#[derive(Deserialize)]
+pub struct GiftCardAmountRequest {
+    pub amount: f64,
+}
+
+#[get("/api/gift-cards")]
+pub async fn get_gift_cards(
+    req: HttpRequest,
+    query: web::Query<GetGiftCardsQuery>,
+    data: web::Data<Arc<Mutex<Client>>>,
+) -> HttpResponse {
+    let client = data.lock().await;
+    // Extract the token from the Authorization header
+    let token = match req.headers().get("Authorization") {
+        Some(value) => {
+            let parts: Vec<&str> = value.to_str().unwrap_or("").split_whitespace().collect();
+            if parts.len() == 2 && parts[0] == "Bearer" {
+                parts[1]
+            } else {
+                return HttpResponse::BadRequest().json(BaseResponse {
+                    code: 400,
+                    message: String::from("Invalid Authorization header format"),
+                });
+            }
+        }
+        None => {
+            return HttpResponse::Unauthorized().json(BaseResponse {
+                code: 401,
+                message: String::from("Authorization header missing"),
+            })
+        }
+    };
+
+    let sub = match verify_token_and_get_sub(token) {
+        Some(s) => s,
+        None => {
+            return HttpResponse::Unauthorized().json(BaseResponse {
+                code: 401,
+                message: String::from("Invalid token"),
+            })
+        }
+    };
+
+    // Parse the `sub` value
+    let parsed_values: Vec<&str> = sub.split(',').collect();
+    if parsed_values.len() != 3 {
+        return HttpResponse::InternalServerError().json(BaseResponse {
+            code: 500,
+            message: String::from("Invalid sub format in token"),
+        });
+    }
+    let role: &str = parsed_values[1];
+    let shop_id: i32 = parsed_values[2].parse().unwrap();
+
+    if role != "Admin" && role != "Manager" {
+        return HttpResponse::Unauthorized().json(BaseResponse {
+            code: 401,
+            message: String::from("Unauthorized!"),
+        });
+    }
+    let filter_shop_id = if role == "Manager" {
+        Some(shop_id)
+    } else {
+        query.shop_id
+    };
+    match gift_card::get_gift_cards(
+        &query.search,
+        query.page,
+        query.per_page,
+        filter_shop_id,
+        &client,
+    )
+    .await
+    {
+        Ok(item_result) => HttpResponse::Ok().json(PaginationResponse {
+            code: 200,
+            message: String::from("Successful."),
+            data: item_result.data,
+            total: item_result.total,
+            page: item_result.page,
+            per_page: item_result.per_page,
+            page_counts: item_result.page_counts,
+        }),
+        Err(err) => {
+            // Log the error message here
+            println!("Error retrieving gift cards: {:?}", err);
+            HttpResponse::InternalServerError().json(BaseResponse {
+                code: 500,
+                message: String::from("Error trying to read all gift cards from database"),
+            })
+        }
+    }
+}
+
+#[post("/api/gift-cards")]
+pub async fn issue_gift_card(
+    req: HttpRequest,
+    body: web::Json<IssueGiftCardRequest>,
+    data: web::Data<Arc<Mutex<Client>>>,
+) -> HttpResponse {
+    let mut client = data.lock().await;
+    // Extract the token from the Authorization header
+    let token = match req.headers().get("Authorization") {
+        Some(value) => {
+            let parts: Vec<&str> = value.to_str().unwrap_or("").split_whitespace().collect();
+            if parts.len() == 2 && parts[0] == "Bearer" {
+                parts[1]
+            } else {
+                return HttpResponse::BadRequest().json(BaseResponse {
+                    code: 400,
+                    message: String::from("Invalid Authorization header format"),
+                });
+            }
+        }
+        None => {
+            return HttpResponse::Unauthorized().json(BaseResponse {
+                code: 401,
+                message: String::from("Authorization header missing"),
+            })
+        }
+    };
+
+    let sub = match verify_token_and_get_sub(token) {
+        Some(s) => s,
+        None => {
+            return HttpResponse::Unauthorized().json(BaseResponse {
+                code: 401,
+                message: String::from("Invalid token"),
+            })
+        }
+    };
+
+    // Parse the `sub` value
+    let parsed_values: Vec<&str> = sub.split(',').collect();
+    if parsed_values.len() != 3 {
+        return HttpResponse::InternalServerError().json(BaseResponse {
+            code: 500,
+            message: String::from("Invalid sub format in token"),
+        });
+    }
+    let user_id: i32 = parsed_values[0].parse().unwrap();
+    let role: &str = parsed_values[1];
+    let mut shop_id: i32 = parsed_values[2].parse().unwrap();
+
+    if role != "Admin" && role != "Manager" {
+        return HttpResponse::Unauthorized().json(BaseResponse {
+            code: 401,
+            message: String::from("Unauthorized!"),
+        });
+    }
+    if role == "Admin" {
+        shop_id = match body.shop_id {
+            Some(s) => s,
+            None => {
+                return HttpResponse::BadRequest().json(BaseResponse {
+                    code: 400,
+                    message: String::from("Shop Id must not be empty!"),
+                });
+            }
+        };
+    }
+    if body.amount <= 0.0 {
+        return HttpResponse::BadRequest().json(BaseResponse {
+            code: 400,
+            message: String::from("Amount must be greater than zero!"),
+        });
+    }
+    if body
+        .expiry_date
+        .is_some_and(|d| d < Local::now().date_naive())
+    {
+        return HttpResponse::BadRequest().json(BaseResponse {
+            code: 400,
+            message: String::from("Expiry date must not be in the past!"),
+        });
+    }
+    if shop::get_shop_by_id(shop_id, &client).await.is_none() {
+        return HttpResponse::NotFound().json(BaseResponse {
+            code: 404,
+            message: String::from("Shop not found!"),
+        });
+    }
+    if let Some(customer_id) = body.customer_id {
+        match customer::customer_visible_to_shop(customer_id, shop_id, &client).await {
+            Ok(true) => {}
+            Ok(false) => {
+                return HttpResponse::BadRequest().json(BaseResponse {
+                    code: 400,
+                    message: String::from("Customer not found!"),
+                });
+            }
+            Err(e) => {
+                eprintln!("Database error: {}", e);
+                return HttpResponse::InternalServerError().json(BaseResponse {
+                    code: 500,
+                    message: String::from("Something went wrong!"),
+                });
+            }
+        }
+    }
+    // Pre-printed cards keep their own code, the others get a generated one.
+    let code = match body.code.as_deref().map(str::trim) {
+        Some("") => {
+            return HttpResponse::BadRequest().json(BaseResponse {
+                code: 400,
+                message: String::from("Code must not be empty!"),
+            });
+        }
+        Some(c) => c.to_uppercase(),
+        None => gift_card::generate_gift_card_code(),
+    };
+    match gift_card::code_exists(&code, &client).await {
+        Ok(true) => {
+            return HttpResponse::Conflict().json(BaseResponse {
+                code: 409,
+                message: String::from("Gift card code already exists!"),
+            });
+        }
+        Ok(false) => {}
+        Err(err) => {
+            println!("{:?}", err);
+            return HttpResponse::InternalServerError().json(BaseResponse {
+                code: 500,
+                message: String::from("Error checking gift card code!"),
+            });
+        }
+    }
+
+    match gift_card::issue_gift_card(&body, &code, shop_id, user_id, &mut client).await {
+        Ok(id) => HttpResponse::Created().json(DataResponse {
+            code: 201,
+            message: String::from("Gift card issued successfully"),
+            data: Some(id),
+        }),
+        Err(e) => {
+            eprintln!("Gift card issuing error: {}", e);
+            HttpResponse::InternalServerError().json(BaseResponse {
+                code: 500,
+                message: String::from("Error issuing gift card!"),
+            })
+        }
+    }
+}
+
+#[get("/api/gift-cards/balance/{code}")]
+pub async fn get_gift_card_balance(
+    req: HttpRequest,
+    path: web::Path<String>,
+    data: web::Data<Arc<Mutex<Client>>>,
+) -> HttpResponse {
+    let client = data.lock().await;
+    let code = path.into_inner();
+    // Extract the token from the Authorization header
+    let token = match req.headers().get("Authorization") {
+        Some(value) => {
+            let parts: Vec<&str> = value.to_str().unwrap_or("").split_whitespace().collect();
+            if parts.len() == 2 && parts[0] == "Bearer" {
+                parts[1]
+            } else {
+                return HttpResponse::BadRequest().json(BaseResponse {
+                    code: 400,
+                    message: String::from("Invalid Authorization header format"),
+                });
+            }
+        }
+        None => {
+            return HttpResponse::Unauthorized().json(BaseResponse {
+                code: 401,
+                message: String::from("Authorization header missing"),
+            })
+        }
+    };
+
+    let sub = match verify_token_and_get_sub(token) {
+        Some(s) => s,
+        None => {
+            return HttpResponse::Unauthorized().json(BaseResponse {
+                code: 401,
+                message: String::from("Invalid token"),
+            })
+        }
+    };
+
+    // Parse the `sub` value
+    let parsed_values: Vec<&str> = sub.split(',').collect();
+    if parsed_values.len() != 3 {
+        return HttpResponse::InternalServerError().json(BaseResponse {
+            code: 500,
+            message: String::from("Invalid sub format in token"),
+        });
+    }
+    let role: &str = parsed_values[1];
+    let shop_id: i32 = parsed_values[2].parse().unwrap();
+
+    match gift_card::get_gift_card_by_code(&code.to_uppercase(), &client)
+        .await
+        .filter(|g| role == "Admin" || g.shop_id == shop_id)
+    {
+        Some(g) => HttpResponse::Ok().json(DataResponse {
+            code: 200,
+            message: String::from("Gift card fetched successfully."),
+            data: Some(g),
+        }),
+        None => HttpResponse::NotFound().json(BaseResponse {
+            code: 404,
+            message: String::from("Gift card not found!"),
+        }),
+    }
+}
+
+#[get("/api/gift-cards/{gift_card_id}")]
+pub async fn get_gift_card_by_id(
+    req: HttpRequest,
+    path: web::Path<i32>,
+    data: web::Data<Arc<Mutex<Client>>>,
+) -> HttpResponse {
+    let client = data.lock().await;
+    let gift_card_id = path.into_inner();
+    // Extract the token from the Authorization header
+    let token = match req.headers().get("Authorization") {
+        Some(value) => {
+            let parts: Vec<&str> = value.to_str().unwrap_or("").split_whitespace().collect();
+            if parts.len() == 2 && parts[0] == "Bearer" {
+                parts[1]
+            } else {
+                return HttpResponse::BadRequest().json(BaseResponse {
+                    code: 400,
+                    message: String::from("Invalid Authorization header format"),
+                });
+            }
+        }
+        None => {
+            return HttpResponse::Unauthorized().json(BaseResponse {
+                code: 401,
+                message: String::from("Authorization header missing"),
+            })
+        }
+    };
+
+    let sub = match verify_token_and_get_sub(token) {
+        Some(s) => s,
+        None => {
+            return HttpResponse::Unauthorized().json(BaseResponse {
+                code: 401,
+                message: String::from("Invalid token"),
+            })
+        }
+    };
+
+    // Parse the `sub` value
+    let parsed_values: Vec<&str> = sub.split(',').collect();
+    if parsed_values.len() != 3 {
+        return HttpResponse::InternalServerError().json(BaseResponse {
+            code: 500,
+            message: String::from("Invalid sub format in token"),
+        });
+    }
+    let role: &str = parsed_values[1];
+    let shop_id: i32 = parsed_values[2].parse().unwrap();
+
+    if role != "Admin" && role != "Manager" {
+        return HttpResponse::Unauthorized().json(BaseResponse {
+            code: 401,
+            message: String::from("Unauthorized!"),
+        });
+    }
+    let card = match gift_card::get_gift_card_by_id(gift_card_id, &client)
+        .await
+        .filter(|g| role == "Admin" || g.shop_id == shop_id)
+    {
+        Some(g) => g,
+        None => {
+            return HttpResponse::NotFound().json(BaseResponse {
+                code: 404,
+                message: String::from("Gift card not found!"),
+            });
+        }
+    };
+
+    match gift_card::get_gift_card_transactions(gift_card_id, &client).await {
+        Ok(transactions) => HttpResponse::Ok().json(DataResponse {
+            code: 200,
+            message: String::from("Gift card fetched successfully."),
+            data: Some(GiftCardDetail {
+                gift_card: card,
+                transactions,
+            }),
+        }),
+        Err(err) => {
+            // Log the error message here
+            println!("Error retrieving gift card transactions: {:?}", err);
+            HttpResponse::InternalServerError().json(BaseResponse {
+                code: 500,
+                message: String::from("Error trying to read gift card transactions from database"),
+            })
+        }
+    }
+}
+
+#[post("/api/gift-cards/{gift_card_id}/top-up")]
+pub async fn top_up_gift_card(
+    req: HttpRequest,
+    path: web::Path<i32>,
+    body: web::Json<GiftCardAmountRequest>,
+    data: web::Data<Arc<Mutex<Client>>>,
+) -> HttpResponse {
+    let mut client = data.lock().await;
+    let gift_card_id = path.into_inner();
+    // Extract the token from the Authorization header
+    let token = match req.headers().get("Authorization") {
+        Some(value) => {
+            let parts: Vec<&str> = value.to_str().unwrap_or("").split_whitespace().collect();
+            if parts.len() == 2 && parts[0] == "Bearer" {
+                parts[1]
+            } else {
+                return HttpResponse::BadRequest().json(BaseResponse {
+                    code: 400,
+                    message: String::from("Invalid Authorization header format"),
+                });
+            }
+        }
+        None => {
+            return HttpResponse::Unauthorized().json(BaseResponse {
+                code: 401,
+                message: String::from("Authorization header missing"),
+            })
+        }
+    };
+
+    let sub = match verify_token_and_get_sub(token) {
+        Some(s) => s,
+        None => {
+            return HttpResponse::Unauthorized().json(BaseResponse {
+                code: 401,
+                message: String::from("Invalid token"),
+            })
+        }
+    };
+
+    // Parse the `sub` value
+    let parsed_values: Vec<&str> = sub.split(',').collect();
+    if parsed_values.len() != 3 {
+        return HttpResponse::InternalServerError().json(BaseResponse {
+            code: 500,
+            message: String::from("Invalid sub format in token"),
+        });
+    }
+    let user_id: i32 = parsed_values[0].parse().unwrap();
+    let role: &str = parsed_values[1];
+    let shop_id: i32 = parsed_values[2].parse().unwrap();
+
+    if role != "Admin" && role != "Manager" {
+        return HttpResponse::Unauthorized().json(BaseResponse {
+            code: 401,
+            message: String::from("Unauthorized!"),
+        });
+    }
+    if body.amount <= 0.0 {
+        return HttpResponse::BadRequest().json(BaseResponse {
+            code: 400,
+            message: String::from("Amount must be greater than zero!"),
+        });
+    }
+    match gift_card::get_gift_card_by_id(gift_card_id, &client)
+        .await
+        .filter(|g| role == "Admin" || g.shop_id == shop_id)
+    {
+        Some(g) if g.is_expired => {
+            return HttpResponse::BadRequest().json(BaseResponse {
+                code: 400,
+                message: String::from("Gift card has expired!"),
+            });
+        }
+        Some(_) => {}
+        None => {
+            return HttpResponse::NotFound().json(BaseResponse {
+                code: 404,
+                message: String::from("Gift card not found!"),
+            });
+        }
+    }
+
+    match gift_card::top_up_gift_card(gift_card_id, body.amount, user_id, &mut client).await {
+        Ok(balance) => HttpResponse::Ok().json(DataResponse {
+            code: 200,
+            message: String::from("Gift card topped up successfully"),
+            data: Some(balance),
+        }),
+        Err(e) => {
+            eprintln!("Gift card top up error: {}", e);
+            HttpResponse::InternalServerError().json(BaseResponse {
+                code: 500,
+                message: String::from("Error topping up gift card!"),
+            })
+        }
+    }
+}
+
+#[post("/api/gift-cards/{gift_card_id}/redeem")]
+pub async fn redeem_gift_card(
+    req: HttpRequest,
+    path: web::Path<i32>,
+    body: web::Json<GiftCardAmountRequest>,
+    data: web::Data<Arc<Mutex<Client>>>,
+) -> HttpResponse {
+    let mut client = data.lock().await;
+    let gift_card_id = path.into_inner();
+    // Extract the token from the Authorization header
+    let token = match req.headers().get("Authorization") {
+        Some(value) => {
+            let parts: Vec<&str> = value.to_str().unwrap_or("").split_whitespace().collect();
+            if parts.len() == 2 && parts[0] == "Bearer" {
+                parts[1]
+            } else {
+                return HttpResponse::BadRequest().json(BaseResponse {
+                    code: 400,
+                    message: String::from("Invalid Authorization header format"),
+                });
+            }
+        }
+        None => {
+            return HttpResponse::Unauthorized().json(BaseResponse {
+                code: 401,
+                message: String::from("Authorization header missing"),
+            })
+        }
+    };
+
+    let sub = match verify_token_and_get_sub(token) {
+        Some(s) => s,
+        None => {
+            return HttpResponse::Unauthorized().json(BaseResponse {
+                code: 401,
+                message: String::from("Invalid token"),
+            })
+        }
+    };
+
+    // Parse the `sub` value
+    let parsed_values: Vec<&str> = sub.split(',').collect();
+    if parsed_values.len() != 3 {
+        return HttpResponse::InternalServerError().json(BaseResponse {
+            code: 500,
+            message: String::from("Invalid sub format in token"),
+        });
+    }
+    let user_id: i32 = parsed_values[0].parse().unwrap();
+    let role: &str = parsed_values[1];
+    let shop_id: i32 = parsed_values[2].parse().unwrap();
+
+    if body.amount <= 0.0 {
+        return HttpResponse::BadRequest().json(BaseResponse {
+            code: 400,
+            message: String::from("Amount must be greater than zero!"),
+        });
+    }
+    match gift_card::get_gift_card_by_id(gift_card_id, &client)
+        .await
+        .filter(|g| role == "Admin" || g.shop_id == shop_id)
+    {
+        Some(g) if g.is_expired => {
+            return HttpResponse::BadRequest().json(BaseResponse {
+                code: 400,
+                message: String::from("Gift card has expired!"),
+            });
+        }
+        Some(_) => {}
+        None => {
+            return HttpResponse::NotFound().json(BaseResponse {
+                code: 404,
+                message: String::from("Gift card not found!"),
+            });
+        }
+    }
+
+    match gift_card::redeem_gift_card(gift_card_id, body.amount, user_id, &mut client).await {
+        Ok(Some(balance)) => HttpResponse::Ok().json(DataResponse {
+            code: 200,
+            message: String::from("Gift card redeemed successfully"),
+            data: Some(balance),
+        }),
+        Ok(None) => HttpResponse::BadRequest().json(BaseResponse {
+            code: 400,
+            message: String::from("Insufficient gift card balance!"),
+        }),
+        Err(e) => {
+            eprintln!("Gift card redeeming error: {}", e);
+            HttpResponse::InternalServerError().json(BaseResponse {
+                code: 500,
+                message: String::from("Error redeeming gift card!"),
+            })
+        }
+    }
+}
+
+#[derive(Deserialize)]
+pub struct GiftCardLiabilityQuery {
+    pub shop_id: Option<i32>,
+}
+
+#[get("/api/gift-card-liability")]
+pub async fn get_gift_card_liability(
+    req: HttpRequest,
+    query: web::Query<GiftCardLiabilityQuery>,
+    data: web::Data<Arc<Mutex<Client>>>,
+) -> HttpResponse {
+    let client = data.lock().await;
+    // Extract the token from the Authorization header
+    let token = match req.headers().get("Authorization") {
+        Some(value) => {
+            let parts: Vec<&str> = value.to_str().unwrap_or("").split_whitespace().collect();
+            if parts.len() == 2 && parts[0] == "Bearer" {
+                parts[1]
+            } else {
+                return HttpResponse::BadRequest().json(BaseResponse {
+                    code: 400,
+                    message: String::from("Invalid Authorization header format"),
+                });
+            }
+        }
+        None => {
+            return HttpResponse::Unauthorized().json(BaseResponse {
+                code: 401,
+                message: String::from("Authorization header missing"),
+            })
+        }
+    };
+
+    let sub = match verify_token_and_get_sub(token) {
+        Some(s) => s,
+        None => {
+            return HttpResponse::Unauthorized().json(BaseResponse {
+                code: 401,
+                message: String::from("Invalid token"),
+            })
+        }
+    };
+
+    // Parse the `sub` value
+    let parsed_values: Vec<&str> = sub.split(',').collect();
+    if parsed_values.len() != 3 {
+        return HttpResponse::InternalServerError().json(BaseResponse {
+            code: 500,
+            message: String::from("Invalid sub format in token"),
+        });
+    }
+    let role: &str = parsed_values[1];
+    let shop_id: i32 = parsed_values[2].parse().unwrap();
+
+    if role != "Admin" && role != "Manager" {
+        return HttpResponse::Unauthorized().json(BaseResponse {
+            code: 401,
+            message: String::from("Unauthorized!"),
+        });
+    }
+    let filter_shop_id = if role == "Manager" {
+        Some(shop_id)
+    } else {
+        query.shop_id
+    };
+    match gift_card::get_gift_card_liability(filter_shop_id, &client).await {
+        Ok(liability) => HttpResponse::Ok().json(DataResponse {
+            code: 200,
+            message: String::from("Successful."),
+            data: Some(liability),
+        }),
+        Err(err) => {
+            // Log the error message here
+            println!("Error retrieving gift card liability: {:?}", err);
+            HttpResponse::InternalServerError().json(BaseResponse {
+                code: 500,
+                message: String::from("Error trying to read gift card liability from database"),
+            })
+        }
+    }
+}
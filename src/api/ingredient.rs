@@ -1,12 +1,14 @@
 use std::sync::Arc;
 
 use actix_web::{delete, get, post, put, web, HttpRequest, HttpResponse, Responder};
+use chrono::NaiveDate;
 use serde::Deserialize;
 use tokio::sync::Mutex;
 use tokio_postgres::Client;
 
 use crate::{
     models::ingredient::{self, IngredientRequest},
+    models::stock_movement::{self, MOVEMENT_TYPES},
 
     // models::{
     //     item,
@@ -23,6 +25,7 @@ pub struct GetIngredientsQuery {
     pub search: Option<String>,
     pub page: Option<usize>,
     pub per_page: Option<usize>,
+    pub shop_id: Option<i32>,
 }
 
 #[get("/api/ingredients")]
@@ -73,15 +76,26 @@ pub async fn get_ingredients(
     }
 
     let role: &str = parsed_values[1];
+    let shop_id: i32 = parsed_values[2].parse().unwrap();
 
-    if role != "Admin" {
+    if role != "Admin" && role != "Manager" {
         return HttpResponse::Unauthorized().json(BaseResponse {
             code: 401,
             message: String::from("Unauthorized!"),
         });
     }
 
-    match ingredient::get_ingredients(&query.search, query.page, query.per_page, &client).await {
+    match ingredient::get_ingredients(
+        &query.search,
+        query.page,
+        query.per_page,
+        role,
+        shop_id,
+        query.shop_id,
+        &client,
+    )
+    .await
+    {
         Ok(item_result) => HttpResponse::Ok().json(PaginationResponse {
             code: 200,
             message: String::from("Successful."),
@@ -108,7 +122,7 @@ pub async fn add_ingredient(
     body: web::Json<IngredientRequest>,
     data: web::Data<Arc<Mutex<Client>>>,
 ) -> HttpResponse {
-    let client = data.lock().await;
+    let mut client = data.lock().await;
     // Extract the token from the Authorization header
     let token = match req.headers().get("Authorization") {
         Some(value) => {
@@ -149,9 +163,11 @@ pub async fn add_ingredient(
         });
     }
 
+    let user_id: i32 = parsed_values[0].parse().unwrap();
     let role: &str = parsed_values[1];
+    let mut shop_id: i32 = parsed_values[2].parse().unwrap();
 
-    if role != "Admin" {
+    if role != "Admin" && role != "Manager" {
         return HttpResponse::Unauthorized().json(BaseResponse {
             code: 401,
             message: String::from("Unauthorized!"),
@@ -164,9 +180,20 @@ pub async fn add_ingredient(
             message: String::from("Name must not be empty!"),
         });
     }
-  
 
-    match ingredient::add_ingredient(&body, &client).await {
+    if role == "Admin" {
+        shop_id = match body.shop_id {
+            Some(s) => s,
+            None => {
+                return HttpResponse::BadRequest().json(BaseResponse {
+                    code: 400,
+                    message: String::from("Shop Id must not be empty!"),
+                });
+            }
+        };
+    }
+
+    match ingredient::add_ingredient(&body, shop_id, user_id, &mut client).await {
         Ok(()) => HttpResponse::Created().json(BaseResponse {
             code: 201,
             message: String::from("Ingredient added successfully"),
@@ -230,15 +257,19 @@ pub async fn get_ingredient_by_id(
     }
 
     let role: &str = parsed_values[1];
+    let shop_id: i32 = parsed_values[2].parse().unwrap();
 
-    if role != "Admin" {
+    if role != "Admin" && role != "Manager" {
         return HttpResponse::Unauthorized().json(BaseResponse {
             code: 401,
             message: String::from("Unauthorized!"),
         });
     }
 
-    match ingredient::get_ingredient_by_id(ingredient_id, &client).await {
+    match ingredient::get_ingredient_by_id(ingredient_id, &client)
+        .await
+        .filter(|i| role == "Admin" || i.shop_id == Some(shop_id))
+    {
         Some(s) => HttpResponse::Ok().json(DataResponse {
             code: 200,
             message: String::from("Ingredient fetched successfully."),
@@ -301,8 +332,9 @@ pub async fn update_ingredient(
     }
 
     let role: &str = parsed_values[1];
+    let shop_id: i32 = parsed_values[2].parse().unwrap();
 
-    if role != "Admin" {
+    if role != "Admin" && role != "Manager" {
         return HttpResponse::Unauthorized().json(BaseResponse {
             code: 401,
             message: String::from("Unauthorized!"),
@@ -317,7 +349,10 @@ pub async fn update_ingredient(
     }
  
 
-    match ingredient::get_ingredient_by_id(ingredient_id, &client).await {
+    match ingredient::get_ingredient_by_id(ingredient_id, &client)
+        .await
+        .filter(|i| role == "Admin" || i.shop_id == Some(shop_id))
+    {
         Some(_) => match ingredient::update_ingredient(ingredient_id, &body, &client).await {
             Ok(()) => HttpResponse::Ok().json(BaseResponse {
                 code: 200,
@@ -387,15 +422,19 @@ pub async fn delete_ingredient(
     }
 
     let role: &str = parsed_values[1];
+    let shop_id: i32 = parsed_values[2].parse().unwrap();
 
-    if role != "Admin" {
+    if role != "Admin" && role != "Manager" {
         return HttpResponse::Unauthorized().json(BaseResponse {
             code: 401,
             message: String::from("Unauthorized!"),
         });
     }
 
-    match ingredient::get_ingredient_by_id(ingredient_id, &client).await {
+    match ingredient::get_ingredient_by_id(ingredient_id, &client)
+        .await
+        .filter(|i| role == "Admin" || i.shop_id == Some(shop_id))
+    {
         Some(_) => match ingredient::delete_ingredient(ingredient_id, &client).await {
             Ok(()) => HttpResponse::Ok().json(BaseResponse {
                 code: 204,
@@ -415,3 +454,120 @@ pub async fn delete_ingredient(
         }),
     }
 }
+
+#[derive(Deserialize)]
+pub struct GetStockMovementsQuery {
+    pub page: Option<usize>,
+    pub per_page: Option<usize>,
+    pub movement_type: Option<String>,
+    pub from_date: Option<NaiveDate>,
+    pub to_date: Option<NaiveDate>,
+}
+
+#[get("/api/ingredients/{ingredient_id}/movements")]
+pub async fn get_ingredient_movements(
+    req: HttpRequest,
+    path: web::Path<i32>,
+    query: web::Query<GetStockMovementsQuery>,
+    data: web::Data<Arc<Mutex<Client>>>,
+) -> HttpResponse {
+    let client = data.lock().await;
+    let ingredient_id = path.into_inner();
+    // Extract the token from the Authorization header
+    let token = match req.headers().get("Authorization") {
+        Some(value) => {
+            let parts: Vec<&str> = value.to_str().unwrap_or("").split_whitespace().collect();
+            if parts.len() == 2 && parts[0] == "Bearer" {
+                parts[1]
+            } else {
+                return HttpResponse::BadRequest().json(BaseResponse {
+                    code: 400,
+                    message: String::from("Invalid Authorization header format"),
+                });
+            }
+        }
+        None => {
+            return HttpResponse::Unauthorized().json(BaseResponse {
+                code: 401,
+                message: String::from("Authorization header missing"),
+            })
+        }
+    };
+
+    let sub = match verify_token_and_get_sub(token) {
+        Some(s) => s,
+        None => {
+            return HttpResponse::Unauthorized().json(BaseResponse {
+                code: 401,
+                message: String::from("Invalid token"),
+            })
+        }
+    };
+
+    // Parse the `sub` value
+    let parsed_values: Vec<&str> = sub.split(',').collect();
+    if parsed_values.len() != 3 {
+        return HttpResponse::InternalServerError().json(BaseResponse {
+            code: 500,
+            message: String::from("Invalid sub format in token"),
+        });
+    }
+    let role: &str = parsed_values[1];
+    let shop_id: i32 = parsed_values[2].parse().unwrap();
+
+    if role != "Admin" && role != "Manager" {
+        return HttpResponse::Unauthorized().json(BaseResponse {
+            code: 401,
+            message: String::from("Unauthorized!"),
+        });
+    }
+    if let Some(t) = &query.movement_type {
+        if !MOVEMENT_TYPES.contains(&t.as_str()) {
+            return HttpResponse::BadRequest().json(BaseResponse {
+                code: 400,
+                message: String::from("Please select a valid movement type: purchase, usage, sale, adjustment, transfer, or waste."),
+            });
+        }
+    }
+
+    if ingredient::get_ingredient_by_id(ingredient_id, &client)
+        .await
+        .filter(|i| role == "Admin" || i.shop_id == Some(shop_id))
+        .is_none()
+    {
+        return HttpResponse::NotFound().json(BaseResponse {
+            code: 404,
+            message: String::from("Ingredient not found!"),
+        });
+    }
+
+    match stock_movement::get_stock_movements(
+        ingredient_id,
+        query.page,
+        query.per_page,
+        &query.movement_type,
+        &query.from_date,
+        &query.to_date,
+        &client,
+    )
+    .await
+    {
+        Ok(result) => HttpResponse::Ok().json(PaginationResponse {
+            code: 200,
+            message: String::from("Successful."),
+            data: result.data,
+            total: result.total,
+            page: result.page,
+            per_page: result.per_page,
+            page_counts: result.page_counts,
+        }),
+        Err(err) => {
+            // Log the error message here
+            println!("Error retrieving stock movements: {:?}", err);
+            HttpResponse::InternalServerError().json(BaseResponse {
+                code: 500,
+                message: String::from("Error trying to read stock movements from database"),
+            })
+        }
+    }
+}
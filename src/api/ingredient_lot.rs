@@ -0,0 +1,298 @@
+use std::sync::Arc;
+
+use actix_web::{get, post, web, HttpRequest, HttpResponse, Responder};
+use chrono::NaiveDate;
+use serde::Deserialize;
+use tokio::sync::Mutex;
+use tokio_postgres::Client;
+
+use crate::{
+    models::{ingredient, ingredient_lot},
+    utils::{
+        common_struct::{BaseResponse, DataResponse},
+        jwt::verify_token_and_get_sub,
+    },
+};
+
+#[derive(Deserialize)]
+pub struct GetLotsQuery {
+    pub include_empty: Option<bool>,
+}
+
+#[get("/api/ingredients/{ingredient_id}/lots")]
+pub async fn get_ingredient_lots(
+    req: HttpRequest,
+    path: web::Path<i32>,
+    query: web::Query<GetLotsQuery>,
+    data: web::Data<Arc<Mutex<Client>>>,
+) -> HttpResponse {
+    let client = data.lock().await;
+    let ingredient_id = path.into_inner();
+    // Extract the token from the Authorization header
+    let token = match req.headers().get("Authorization") {
+        Some(value) => {
+            let parts: Vec<&str> = value.to_str().unwrap_or("").split_whitespace().collect();
+            if parts.len() == 2 && parts[0] == "Bearer" {
+                parts[1]
+            } else {
+                return HttpResponse::BadRequest().json(BaseResponse {
+                    code: 400,
+                    message: String::from("Invalid Authorization header format"),
+                });
+            }
+        }
+        None => {
+            return HttpResponse::Unauthorized().json(BaseResponse {
+                code: 401,
+                message: String::from("Authorization header missing"),
+            })
+        }
+    };
+
+    let sub = match verify_token_and_get_sub(token) {
+        Some(s) => s,
+        None => {
+            return HttpResponse::Unauthorized().json(BaseResponse {
+                code: 401,
+                message: String::from("Invalid token"),
+            })
+        }
+    };
+
+    // Parse the `sub` value
+    let parsed_values: Vec<&str> = sub.split(',').collect();
+    if parsed_values.len() != 3 {
+        return HttpResponse::InternalServerError().json(BaseResponse {
+            code: 500,
+            message: String::from("Invalid sub format in token"),
+        });
+    }
+    let role: &str = parsed_values[1];
+    let shop_id: i32 = parsed_values[2].parse().unwrap();
+
+    if role != "Admin" && role != "Manager" {
+        return HttpResponse::Unauthorized().json(BaseResponse {
+            code: 401,
+            message: String::from("Unauthorized!"),
+        });
+    }
+    if ingredient::get_ingredient_by_id(ingredient_id, &client)
+        .await
+        .filter(|i| role == "Admin" || i.shop_id == Some(shop_id))
+        .is_none()
+    {
+        return HttpResponse::NotFound().json(BaseResponse {
+            code: 404,
+            message: String::from("Ingredient not found!"),
+        });
+    }
+
+    match ingredient_lot::get_lots(ingredient_id, query.include_empty.unwrap_or(false), &client).await {
+        Ok(lots) => HttpResponse::Ok().json(DataResponse {
+            code: 200,
+            message: String::from("Successful."),
+            data: Some(lots),
+        }),
+        Err(err) => {
+            // Log the error message here
+            println!("Error retrieving ingredient lots: {:?}", err);
+            HttpResponse::InternalServerError().json(BaseResponse {
+                code: 500,
+                message: String::from("Error trying to read ingredient lots from database"),
+            })
+        }
+    }
+}
+
+#[derive(Deserialize)]
+pub struct LotReportQuery {
+    pub from_date: NaiveDate,
+    pub to_date: NaiveDate,
+    pub shop_id: Option<i32>,
+}
+
+#[get("/api/ingredient-lots/report")]
+pub async fn get_lot_report(
+    req: HttpRequest,
+    query: web::Query<LotReportQuery>,
+    data: web::Data<Arc<Mutex<Client>>>,
+) -> impl Responder {
+    let client = data.lock().await;
+    // Extract the token from the Authorization header
+    let token = match req.headers().get("Authorization") {
+        Some(value) => {
+            let parts: Vec<&str> = value.to_str().unwrap_or("").split_whitespace().collect();
+            if parts.len() == 2 && parts[0] == "Bearer" {
+                parts[1]
+            } else {
+                return HttpResponse::BadRequest().json(BaseResponse {
+                    code: 400,
+                    message: String::from("Invalid Authorization header format"),
+                });
+            }
+        }
+        None => {
+            return HttpResponse::Unauthorized().json(BaseResponse {
+                code: 401,
+                message: String::from("Authorization header missing"),
+            })
+        }
+    };
+
+    let sub = match verify_token_and_get_sub(token) {
+        Some(s) => s,
+        None => {
+            return HttpResponse::Unauthorized().json(BaseResponse {
+                code: 401,
+                message: String::from("Invalid token"),
+            })
+        }
+    };
+
+    // Parse the `sub` value
+    let parsed_values: Vec<&str> = sub.split(',').collect();
+    if parsed_values.len() != 3 {
+        return HttpResponse::InternalServerError().json(BaseResponse {
+            code: 500,
+            message: String::from("Invalid sub format in token"),
+        });
+    }
+    let role: &str = parsed_values[1];
+    let shop_id: i32 = parsed_values[2].parse().unwrap();
+
+    if role != "Admin" && role != "Manager" {
+        return HttpResponse::Unauthorized().json(BaseResponse {
+            code: 401,
+            message: String::from("Unauthorized!"),
+        });
+    }
+    if query.from_date.gt(&query.to_date) {
+        return HttpResponse::BadRequest().json(BaseResponse {
+            code: 400,
+            message: String::from("Invalid Date Range!"),
+        });
+    }
+
+    match ingredient_lot::get_lot_report(
+        role,
+        shop_id,
+        query.shop_id,
+        query.from_date,
+        query.to_date,
+        &client,
+    )
+    .await
+    {
+        Ok(report) => HttpResponse::Ok().json(DataResponse {
+            code: 200,
+            message: String::from("Successful."),
+            data: Some(report),
+        }),
+        Err(err) => {
+            // Log the error message here
+            println!("Error retrieving lot report: {:?}", err);
+            HttpResponse::InternalServerError().json(BaseResponse {
+                code: 500,
+                message: String::from("Error trying to read lot report from database"),
+            })
+        }
+    }
+}
+
+#[derive(Deserialize)]
+pub struct WriteOffLotRequest {
+    pub notes: Option<String>,
+}
+
+#[post("/api/ingredient-lots/{lot_id}/write-off")]
+pub async fn write_off_lot(
+    req: HttpRequest,
+    path: web::Path<i32>,
+    body: web::Json<WriteOffLotRequest>,
+    data: web::Data<Arc<Mutex<Client>>>,
+) -> HttpResponse {
+    let mut client = data.lock().await;
+    let lot_id = path.into_inner();
+    // Extract the token from the Authorization header
+    let token = match req.headers().get("Authorization") {
+        Some(value) => {
+            let parts: Vec<&str> = value.to_str().unwrap_or("").split_whitespace().collect();
+            if parts.len() == 2 && parts[0] == "Bearer" {
+                parts[1]
+            } else {
+                return HttpResponse::BadRequest().json(BaseResponse {
+                    code: 400,
+                    message: String::from("Invalid Authorization header format"),
+                });
+            }
+        }
+        None => {
+            return HttpResponse::Unauthorized().json(BaseResponse {
+                code: 401,
+                message: String::from("Authorization header missing"),
+            })
+        }
+    };
+
+    let sub = match verify_token_and_get_sub(token) {
+        Some(s) => s,
+        None => {
+            return HttpResponse::Unauthorized().json(BaseResponse {
+                code: 401,
+                message: String::from("Invalid token"),
+            })
+        }
+    };
+
+    // Parse the `sub` value
+    let parsed_values: Vec<&str> = sub.split(',').collect();
+    if parsed_values.len() != 3 {
+        return HttpResponse::InternalServerError().json(BaseResponse {
+            code: 500,
+            message: String::from("Invalid sub format in token"),
+        });
+    }
+    let user_id: i32 = parsed_values[0].parse().unwrap();
+    let role: &str = parsed_values[1];
+    let shop_id: i32 = parsed_values[2].parse().unwrap();
+
+    if role != "Admin" && role != "Manager" {
+        return HttpResponse::Unauthorized().json(BaseResponse {
+            code: 401,
+            message: String::from("Unauthorized!"),
+        });
+    }
+    match ingredient_lot::get_lot_by_id(lot_id, &client).await {
+        Some(lot) => {
+            if role == "Manager" && lot.shop_id != Some(shop_id) {
+                return HttpResponse::NotFound().json(BaseResponse {
+                    code: 404,
+                    message: String::from("Lot not found!"),
+                });
+            }
+            if lot.quantity_remaining <= 0.0 {
+                return HttpResponse::BadRequest().json(BaseResponse {
+                    code: 400,
+                    message: String::from("Lot is already empty!"),
+                });
+            }
+            match ingredient_lot::write_off_lot(&lot, &body.notes, user_id, &mut client).await {
+                Ok(()) => HttpResponse::Ok().json(BaseResponse {
+                    code: 200,
+                    message: String::from("Lot written off successfully"),
+                }),
+                Err(e) => {
+                    eprintln!("Lot write-off error: {}", e);
+                    HttpResponse::InternalServerError().json(BaseResponse {
+                        code: 500,
+                        message: String::from("Error writing off lot!"),
+                    })
+                }
+            }
+        }
+        None => HttpResponse::NotFound().json(BaseResponse {
+            code: 404,
+            message: String::from("Lot not found!"),
+        }),
+    }
+}
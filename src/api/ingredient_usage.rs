@@ -6,7 +6,10 @@ use tokio::sync::Mutex;
 use tokio_postgres::Client;
 
 use crate::{
-    models::ingredient_usage::{self, IngredientUsagesRequest},
+    models::{
+        ingredient,
+        ingredient_usage::{self, IngredientUsagesRequest},
+    },
     utils::{
         common_struct::{BaseResponse, DataResponse, PaginationResponse},
         jwt::verify_token_and_get_sub,
@@ -18,6 +21,7 @@ pub struct GetIngredientUsagesQuery {
     pub search: Option<String>,
     pub page: Option<usize>,
     pub per_page: Option<usize>,
+    pub shop_id: Option<i32>,
 }
 
 #[get("/api/ingredient-usages")]
@@ -67,10 +71,23 @@ pub async fn get_ingredient_usages(
         });
     }
 
+    let role: &str = parsed_values[1];
+    let shop_id: i32 = parsed_values[2].parse().unwrap();
+
+    if role != "Admin" && role != "Manager" {
+        return HttpResponse::Unauthorized().json(BaseResponse {
+            code: 401,
+            message: String::from("Unauthorized!"),
+        });
+    }
+
     match ingredient_usage::get_ingredient_usages(
         &query.search,
         query.page,
         query.per_page,
+        role,
+        shop_id,
+        query.shop_id,
         &client,
     )
     .await
@@ -102,6 +119,7 @@ pub async fn add_ingredient_usages(
     data: web::Data<Arc<Mutex<Client>>>,
 ) -> impl Responder {
     let mut client = data.lock().await;
+    let mut body = body.into_inner();
 
     let token = match req.headers().get("Authorization") {
         Some(value) => {
@@ -142,9 +160,9 @@ pub async fn add_ingredient_usages(
         });
     }
 
-    // let user_id: &str = parsed_values[0];
+    let user_id: i32 = parsed_values[0].parse().unwrap();
     let role: &str = parsed_values[1];
-    // let shop_id: i32 = parsed_values[2].parse().unwrap();
+    let shop_id: i32 = parsed_values[2].parse().unwrap();
 
     if role == "Waiter" {
         return HttpResponse::Unauthorized().json(BaseResponse {
@@ -153,12 +171,18 @@ pub async fn add_ingredient_usages(
         });
     }
 
-    if body.shop_id.is_none() {
-        return HttpResponse::BadRequest().json(BaseResponse {
-            code: 400,
-            message: String::from("Shop Id must not be empty!"),
-        });
+    if role == "Manager" {
+        body.shop_id = Some(shop_id);
     }
+    let usage_shop_id = match body.shop_id {
+        Some(s) => s,
+        None => {
+            return HttpResponse::BadRequest().json(BaseResponse {
+                code: 400,
+                message: String::from("Shop Id must not be empty!"),
+            });
+        }
+    };
 
     for ingredient_usage in &body.ingredient_usages {
         if ingredient_usage.ingredient_id.is_none() || ingredient_usage.ingredient_id.unwrap() == 0
@@ -181,7 +205,29 @@ pub async fn add_ingredient_usages(
         }
     }
 
-    match ingredient_usage::add_ingredient_usages(&body, &mut client).await {
+    let ingredient_ids: Vec<i32> = body
+        .ingredient_usages
+        .iter()
+        .filter_map(|iur| iur.ingredient_id)
+        .collect();
+    match ingredient::ingredients_belong_to_shop(&ingredient_ids, usage_shop_id, &client).await {
+        Ok(true) => {}
+        Ok(false) => {
+            return HttpResponse::BadRequest().json(BaseResponse {
+                code: 400,
+                message: String::from("Ingredients must belong to the usage's shop!"),
+            });
+        }
+        Err(err) => {
+            println!("{:?}", err);
+            return HttpResponse::InternalServerError().json(BaseResponse {
+                code: 500,
+                message: String::from("Error checking usage ingredients!"),
+            });
+        }
+    }
+
+    match ingredient_usage::add_ingredient_usages(&body, user_id, &mut client).await {
         Ok(is_sufficient) => {
             if !is_sufficient {
                 return HttpResponse::BadRequest().json(BaseResponse {
@@ -253,6 +299,7 @@ pub async fn get_ingredient_usage_by_id(
     }
 
     let role: &str = parsed_values[1];
+    let shop_id: i32 = parsed_values[2].parse().unwrap();
 
     if role != "Admin" && role != "Manager" {
         return HttpResponse::Unauthorized().json(BaseResponse {
@@ -261,7 +308,10 @@ pub async fn get_ingredient_usage_by_id(
         });
     }
 
-    match ingredient_usage::get_ingredient_usage_by_id(ingredient_usage_id, &client).await {
+    match ingredient_usage::get_ingredient_usage_by_id(ingredient_usage_id, &client)
+        .await
+        .filter(|iu| role == "Admin" || iu.shop_id == Some(shop_id))
+    {
         Some(c) => HttpResponse::Ok().json(DataResponse {
             code: 200,
             message: String::from("IngredientUsages fetched successfully."),
@@ -321,7 +371,9 @@ pub async fn update_ingredient_usage(
         });
     }
 
+    let user_id: i32 = parsed_values[0].parse().unwrap();
     let role: &str = parsed_values[1];
+    let shop_id: i32 = parsed_values[2].parse().unwrap();
 
     if role == "Waiter" {
         return HttpResponse::Unauthorized().json(BaseResponse {
@@ -338,7 +390,9 @@ pub async fn update_ingredient_usage(
                 message: String::from("Usage ID must not be empty!"),
             });
         }
-        match ingredient_usage::get_ingredient_usage_by_id(ingredient_usage.usage_id.unwrap(), &client).await
+        match ingredient_usage::get_ingredient_usage_by_id(ingredient_usage.usage_id.unwrap(), &client)
+            .await
+            .filter(|iu| role == "Admin" || iu.shop_id == Some(shop_id))
         {
             Some(iur_db) => {
                 if iur_db.ingredient_id != ingredient_usage.ingredient_id.unwrap() {
@@ -375,7 +429,7 @@ pub async fn update_ingredient_usage(
         }
     }
 
-    match ingredient_usage::update_ingredient_usage(&body, &mut client).await {
+    match ingredient_usage::update_ingredient_usage(&body, user_id, &mut client).await {
         Ok(is_sufficient) => {
             if !is_sufficient {
                 return HttpResponse::BadRequest().json(BaseResponse {
@@ -404,7 +458,7 @@ pub async fn delete_ingredient_usage(
     path: web::Path<i32>,
     data: web::Data<Arc<Mutex<Client>>>,
 ) -> HttpResponse {
-    let client = data.lock().await;
+    let mut client = data.lock().await;
     let ingredient_usage_id = path.into_inner();
     // Extract the token from the Authorization header
     let token = match req.headers().get("Authorization") {
@@ -446,7 +500,9 @@ pub async fn delete_ingredient_usage(
         });
     }
 
+    let user_id: i32 = parsed_values[0].parse().unwrap();
     let role: &str = parsed_values[1];
+    let shop_id: i32 = parsed_values[2].parse().unwrap();
 
     if role != "Admin" && role != "Manager" {
         return HttpResponse::Unauthorized().json(BaseResponse {
@@ -455,12 +511,17 @@ pub async fn delete_ingredient_usage(
         });
     }
 
-    match ingredient_usage::get_ingredient_usage_by_id(ingredient_usage_id, &client).await {
+    match ingredient_usage::get_ingredient_usage_by_id(ingredient_usage_id, &client)
+        .await
+        .filter(|iu| role == "Admin" || iu.shop_id == Some(shop_id))
+    {
         Some(iur_db) => match ingredient_usage::delete_ingredient_usage(
             ingredient_usage_id,
             iur_db.ingredient_id,
             iur_db.quantity_used,
-            &client,
+            iur_db.shop_id,
+            user_id,
+            &mut client,
         )
         .await
         {
@@ -1,18 +1,22 @@
 use actix_web::{get, post, put, web, HttpRequest, HttpResponse, Responder};
 use chrono::NaiveDate;
 use serde::Deserialize;
-use serde_json::Value;
-use std::{collections::HashMap, fs, io, sync::Arc};
+use std::sync::Arc;
 use tokio::sync::Mutex;
 use tokio_postgres::Client;
 
 use crate::{
+    models::customer,
+    models::gift_card,
+    models::loyalty,
+    models::tax_rule,
     models::order::{self, NewOrder},
+    models::push_notification,
     models::shop::{self},
+    models::table,
     utils::{
         common_struct::{BaseResponse, DataResponse, PaginationResponse},
         jwt::verify_token_and_get_sub,
-        socketio,
     },
 };
 
@@ -64,51 +68,112 @@ pub async fn create_order(
     }
 
     let user_id: i32 = parsed_values[0].parse().unwrap();
-    // let role_name: &str = parsed_values[1];
-    // let shop_id: i32 = parsed_values[2].parse().unwrap();
-    match order::order_exists_in_table(&body.table_id, &client).await {
-        Ok(exists) => {
-            if exists {
+    let role: &str = parsed_values[1];
+    let mut shop_id: i32 = parsed_values[2].parse().unwrap();
+    if body.covers.is_some_and(|c| c < 1) {
+        return HttpResponse::BadRequest().json(BaseResponse {
+            code: 400,
+            message: String::from("Covers must be greater than zero!"),
+        });
+    }
+    let order_type = body.order_type.as_deref().unwrap_or("DineIn");
+    if !order::ORDER_TYPES.contains(&order_type) {
+        return HttpResponse::BadRequest().json(BaseResponse {
+            code: 400,
+            message: String::from(
+                "Please select a valid order type: DineIn, Takeaway, Delivery, or Pickup.",
+            ),
+        });
+    }
+    if let Some(message) = order::validate_new_order(&body, order_type) {
+        return HttpResponse::BadRequest().json(BaseResponse {
+            code: 400,
+            message: String::from(message),
+        });
+    }
+    // A dine-in order belongs to the shop of its table, the others to the user's shop.
+    if let Some(table_id) = body.table_id {
+        shop_id = match table::get_table_by_id(table_id, &client)
+            .await
+            .filter(|t| role == "Admin" || t.shop_id == shop_id)
+        {
+            Some(t) => t.shop_id,
+            None => {
+                return HttpResponse::NotFound().json(BaseResponse {
+                    code: 404,
+                    message: String::from("Table not found!"),
+                });
+            }
+        };
+    } else if role == "Admin" {
+        shop_id = match body.shop_id {
+            Some(s) => s,
+            None => {
                 return HttpResponse::BadRequest().json(BaseResponse {
                     code: 400,
-                    message: String::from("Order already exists in the request table!"),
+                    message: String::from("Shop Id must not be empty!"),
                 });
             }
-            match order::create_order(user_id, body.into_inner(), &mut client).await {
-                Ok(id) => {
-                    tokio::spawn(async move {
-                        let mut payload: HashMap<String, Value> = HashMap::new();
-                        payload.insert("order_id".to_string(), Value::Number(id.into()));
-                        match socketio::emit("/pos", "new-order", &vec![], Some(payload)).await {
-                            Ok(_) => {
-                                println!("new-order event sent successfully.");
-                            }
-                            Err(err) => {
-                                println!("{:?}", err);
-                            }
-                        };
-                    });
-                    HttpResponse::Ok().json(DataResponse {
-                        code: 200,
-                        message: String::from("Order created successfully"),
-                        data: Some(id),
-                    })
-                }
-                Err(err) => HttpResponse::InternalServerError().json(BaseResponse {
+        };
+        if shop::get_shop_by_id(shop_id, &client).await.is_none() {
+            return HttpResponse::NotFound().json(BaseResponse {
+                code: 404,
+                message: String::from("Shop not found!"),
+            });
+        }
+    }
+    if let Some(customer_id) = body.customer_id {
+        match customer::customer_visible_to_shop(customer_id, shop_id, &client).await {
+            Ok(true) => {}
+            Ok(false) => {
+                return HttpResponse::BadRequest().json(BaseResponse {
                     code: 400,
-                    message: err.to_string(),
-                }),
-              
+                    message: String::from("Customer not found!"),
+                });
+            }
+            Err(e) => {
+                eprintln!("Database error: {}", e);
+                return HttpResponse::InternalServerError().json(BaseResponse {
+                    code: 500,
+                    message: String::from("Something went wrong!"),
+                });
             }
         }
-        Err(e) => {
-            eprintln!("Database error: {}", e);
-            return HttpResponse::InternalServerError().json(BaseResponse {
-                code: 500,
-                message: String::from("Something went wrong!"),
-            });
+    }
+    if let Some(table_id) = body.table_id {
+        match order::order_exists_in_table(&table_id, &client).await {
+            Ok(true) => {
+                return HttpResponse::BadRequest().json(BaseResponse {
+                    code: 400,
+                    message: String::from("Order already exists in the request table!"),
+                });
+            }
+            Ok(false) => {}
+            Err(e) => {
+                eprintln!("Database error: {}", e);
+                return HttpResponse::InternalServerError().json(BaseResponse {
+                    code: 500,
+                    message: String::from("Something went wrong!"),
+                });
+            }
         }
     }
+    match order::create_order(user_id, shop_id, body.into_inner(), &mut client).await {
+        Ok(id) => {
+            if let Err(e) = push_notification::notify_new_order(id, shop_id, Some(user_id), &client).await {
+                eprintln!("Order push notification error: {}", e);
+            }
+            HttpResponse::Ok().json(DataResponse {
+                code: 200,
+                message: String::from("Order created successfully"),
+                data: Some(id),
+            })
+        }
+        Err(err) => HttpResponse::InternalServerError().json(BaseResponse {
+            code: 400,
+            message: err.to_string(),
+        }),
+    }
 }
 
 #[derive(Deserialize)]
@@ -119,6 +184,13 @@ pub struct GetOrdersQuery {
     pub from_date: Option<NaiveDate>,
     pub to_date: Option<NaiveDate>,
     pub status: Option<String>,
+    pub order_type: Option<String>,
+}
+
+#[derive(Deserialize)]
+pub struct GetOrderQueueQuery {
+    pub shop_id: Option<i32>,
+    pub order_type: Option<String>,
 }
 
 #[get("/api/orders")]
@@ -182,6 +254,7 @@ pub async fn get_orders(
         &query.from_date,
         &query.to_date,
         &query.status,
+        &query.order_type,
         &client,
     )
     .await
@@ -343,6 +416,10 @@ pub struct UpdateOrderRequest {
     pub status: String,
     pub tax: Option<f64>,
     pub discount: Option<f64>,
+    pub tip: Option<f64>,
+    pub payment_type: Option<String>,
+    pub redeem_points: Option<i32>,
+    pub gift_card_code: Option<String>,
 }
 
 #[put("/api/orders/{order_id}")]
@@ -352,7 +429,7 @@ pub async fn update_order(
     body: web::Json<UpdateOrderRequest>,
     data: web::Data<Arc<Mutex<Client>>>,
 ) -> HttpResponse {
-    let client = data.lock().await;
+    let mut client = data.lock().await;
     let order_id = path.into_inner();
     // Extract the token from the Authorization header
     let token = match req.headers().get("Authorization") {
@@ -421,20 +498,199 @@ pub async fn update_order(
         Some(o) => {
             let mut tax = o.tax;
             let mut discount = o.discount;
+            let mut tip = o.tip;
+            let mut payment_type = o.payment_type;
+            let mut tax_calculation = None;
+            let mut redeem_points = 0;
+            let mut gift_card_payment = None;
             if &body.status == "Completed" {
-                if let Some(t) = body.tax {
-                    tax = t;
-                }
                 if let Some(d) = body.discount {
                     discount = d;
                 }
+                // Redeemed loyalty points are taken off the order as a discount, before tax.
+                if let Some(points) = body.redeem_points.filter(|p| *p != 0) {
+                    if points < 0 {
+                        return HttpResponse::BadRequest().json(BaseResponse {
+                            code: 400,
+                            message: String::from("Redeem points must not be negative!"),
+                        });
+                    }
+                    if o.status == "Completed" {
+                        return HttpResponse::BadRequest().json(BaseResponse {
+                            code: 400,
+                            message: String::from("Order is already completed!"),
+                        });
+                    }
+                    let order_shop_id = match loyalty::get_order_loyalty_account(order_id, &client).await {
+                        Ok(Some((customer_id, order_shop_id))) => {
+                            match loyalty::get_points_balance(customer_id, order_shop_id, &client).await {
+                                Ok(balance) if balance >= points as i64 => order_shop_id,
+                                Ok(_) => {
+                                    return HttpResponse::BadRequest().json(BaseResponse {
+                                        code: 400,
+                                        message: String::from("Insufficient loyalty points!"),
+                                    });
+                                }
+                                Err(e) => {
+                                    eprintln!("Loyalty balance error: {}", e);
+                                    return HttpResponse::InternalServerError().json(BaseResponse {
+                                        code: 500,
+                                        message: String::from("Something went wrong!"),
+                                    });
+                                }
+                            }
+                        }
+                        Ok(None) => {
+                            return HttpResponse::BadRequest().json(BaseResponse {
+                                code: 400,
+                                message: String::from("Order has no customer!"),
+                            });
+                        }
+                        Err(e) => {
+                            eprintln!("Database error: {}", e);
+                            return HttpResponse::InternalServerError().json(BaseResponse {
+                                code: 500,
+                                message: String::from("Something went wrong!"),
+                            });
+                        }
+                    };
+                    let program = match loyalty::get_loyalty_program(order_shop_id, &client).await {
+                        Ok(p) => p,
+                        Err(e) => {
+                            eprintln!("Loyalty program error: {}", e);
+                            return HttpResponse::InternalServerError().json(BaseResponse {
+                                code: 500,
+                                message: String::from("Something went wrong!"),
+                            });
+                        }
+                    };
+                    if !program.is_active {
+                        return HttpResponse::BadRequest().json(BaseResponse {
+                            code: 400,
+                            message: String::from("Loyalty program is not active!"),
+                        });
+                    }
+                    if points < program.min_redeem_points {
+                        return HttpResponse::BadRequest().json(BaseResponse {
+                            code: 400,
+                            message: format!(
+                                "At least {} points must be redeemed!",
+                                program.min_redeem_points
+                            ),
+                        });
+                    }
+                    discount += points as f64 * program.point_value;
+                    if discount > o.sub_total {
+                        return HttpResponse::BadRequest().json(BaseResponse {
+                            code: 400,
+                            message: String::from("Discount must not be more than the order amount!"),
+                        });
+                    }
+                    redeem_points = points;
+                }
+                // Shops with tax rules get their tax worked out here, the others still send it.
+                match tax_rule::calculate_order_taxes(order_id, discount, &client).await {
+                    Ok(Some(c)) => {
+                        tax = c.tax;
+                        tax_calculation = Some(c);
+                    }
+                    Ok(None) => {
+                        if let Some(t) = body.tax {
+                            tax = t;
+                        }
+                    }
+                    Err(e) => {
+                        eprintln!("Order tax calculation error: {}", e);
+                        return HttpResponse::InternalServerError().json(BaseResponse {
+                            code: 500,
+                            message: String::from("Error calculating order tax!"),
+                        });
+                    }
+                }
+                // The tip is recorded with the payment when the order is completed.
+                if let Some(t) = body.tip {
+                    if t < 0.0 {
+                        return HttpResponse::BadRequest().json(BaseResponse {
+                            code: 400,
+                            message: String::from("Tip must not be negative!"),
+                        });
+                    }
+                    tip = t;
+                }
+                if let Some(p) = &body.payment_type {
+                    if !order::PAYMENT_TYPES.contains(&p.as_str()) {
+                        return HttpResponse::BadRequest().json(BaseResponse {
+                            code: 400,
+                            message: String::from(
+                                "Please select a valid payment type: CASH, CARD, MOBILE, or GIFT_CARD.",
+                            ),
+                        });
+                    }
+                    payment_type = p.clone();
+                }
+                // A gift card pays for the whole order, tip included.
+                if payment_type == "GIFT_CARD" && o.status != "Completed" {
+                    let code = match &body.gift_card_code {
+                        Some(c) => c,
+                        None => {
+                            return HttpResponse::BadRequest().json(BaseResponse {
+                                code: 400,
+                                message: String::from("Gift card code must not be empty!"),
+                            });
+                        }
+                    };
+                    let card = match gift_card::get_gift_card_for_order(code, order_id, &client).await {
+                        Some(c) => c,
+                        None => {
+                            return HttpResponse::NotFound().json(BaseResponse {
+                                code: 404,
+                                message: String::from("Gift card not found!"),
+                            });
+                        }
+                    };
+                    if card.is_expired {
+                        return HttpResponse::BadRequest().json(BaseResponse {
+                            code: 400,
+                            message: String::from("Gift card has expired!"),
+                        });
+                    }
+                    let rounding_adjustment = tax_calculation
+                        .as_ref()
+                        .map_or(o.rounding_adjustment, |c| c.rounding_adjustment);
+                    // The balance is checked when the card is charged, an order paid before isn't charged again.
+                    let amount = ((o.sub_total - discount + tax + rounding_adjustment + o.delivery_fee + tip) * 100.0).round() / 100.0;
+                    gift_card_payment = Some((card.gift_card_id, amount));
+                }
             }
 
-            match order::update_order(order_id, &body.status, tax, discount, &client).await {
-                Ok(()) => HttpResponse::Ok().json(BaseResponse {
-                    code: 200,
-                    message: String::from("Order updated successfully"),
+            let update = order::OrderUpdate {
+                status: &body.status,
+                previous_status: &o.status,
+                tax,
+                discount,
+                tip,
+                payment_type: &payment_type,
+                tax_calculation: tax_calculation.as_ref(),
+                redeem_points,
+                gift_card_payment,
+                user_id,
+            };
+            match order::update_order(order_id, &update, &mut client).await {
+                Ok(false) => HttpResponse::BadRequest().json(BaseResponse {
+                    code: 400,
+                    message: String::from("Insufficient gift card balance!"),
                 }),
+                Ok(true) => {
+                    if &body.status == "Served" && o.status != "Served" {
+                        if let Err(e) = push_notification::notify_order_ready(order_id, &client).await {
+                            eprintln!("Order push notification error: {}", e);
+                        }
+                    }
+                    HttpResponse::Ok().json(BaseResponse {
+                        code: 200,
+                        message: String::from("Order updated successfully"),
+                    })
+                }
                 Err(e) => {
                     eprintln!("Order updating error: {}", e);
                     return HttpResponse::InternalServerError().json(BaseResponse {
@@ -451,18 +707,12 @@ pub async fn update_order(
     }
 }
 
-#[derive(Deserialize)]
-pub struct ReportQuery {
-    pub from_date: NaiveDate,
-    pub to_date: NaiveDate,
-    pub shop_id: i32,
-}
-#[get("/api/daily-sale-report")]
-pub async fn get_daily_sale_report(
+#[get("/api/order-queue")]
+pub async fn get_order_queue(
     req: HttpRequest,
-    query: web::Query<ReportQuery>,
+    query: web::Query<GetOrderQueueQuery>,
     data: web::Data<Arc<Mutex<Client>>>,
-) -> impl Responder {
+) -> HttpResponse {
     let client = data.lock().await;
     // Extract the token from the Authorization header
     let token = match req.headers().get("Authorization") {
@@ -503,55 +753,53 @@ pub async fn get_daily_sale_report(
             message: String::from("Invalid sub format in token"),
         });
     }
+    let role: &str = parsed_values[1];
+    let shop_id: i32 = parsed_values[2].parse().unwrap();
 
-    if query.from_date.gt(&query.to_date) {
-        return HttpResponse::BadRequest().json(BaseResponse {
-            code: 400,
-            message: String::from("Invalid Date Range!"),
-        });
+    if let Some(t) = &query.order_type {
+        if t == "DineIn" || !order::ORDER_TYPES.contains(&t.as_str()) {
+            return HttpResponse::BadRequest().json(BaseResponse {
+                code: 400,
+                message: String::from("Please select a valid order type: Takeaway, Delivery, or Pickup."),
+            });
+        }
     }
-
-
-    let user_id: i32 = parsed_values[0].parse().unwrap();
-
-    match shop::get_shop_by_id(query.shop_id, &client).await {
-        Some(s) => {
-            match order::get_daily_sale_report(
-                query.from_date,
-                query.to_date,
-                query.shop_id,
-                s.name,
-                user_id,
-                &client,
-            )
-            .await
-            {
-                Ok(data) => HttpResponse::Ok().json(DataResponse {
-                    code: 200,
-                    message: String::from("Successful."),
-                    data: Some(data),
-                }),
-                Err(err) => {
-                    // Log the error message here
-                    println!("Error retrieving orders: {:?}", err);
-                    HttpResponse::InternalServerError().json(BaseResponse {
-                        code: 500,
-                        message: String::from("Error trying to read all orders from database"),
-                    })
-                }
-            }
-        },
-        None => HttpResponse::NotFound().json(BaseResponse {
-            code: 404,
-            message: String::from("Shop not found!"),
+    let filter_shop_id = if role == "Admin" {
+        query.shop_id
+    } else {
+        Some(shop_id)
+    };
+    match order::get_order_queue(filter_shop_id, &query.order_type, &client).await {
+        Ok(orders) => HttpResponse::Ok().json(DataResponse {
+            code: 200,
+            message: String::from("Successful."),
+            data: Some(orders),
         }),
+        Err(err) => {
+            // Log the error message here
+            println!("Error retrieving order queue: {:?}", err);
+            HttpResponse::InternalServerError().json(BaseResponse {
+                code: 500,
+                message: String::from("Error trying to read order queue from database"),
+            })
+        }
     }
 }
 
-#[get("/api/daily-sale-report-pdf")]
-pub async fn daily_sale_report_pdf(
+#[derive(Deserialize)]
+pub struct UpdateOrderCustomerRequest {
+    pub customer_id: Option<i32>,
+}
+
+#[put("/api/orders/{order_id}/customer")]
+pub async fn update_order_customer(
     req: HttpRequest,
-) -> Result<HttpResponse, Box<dyn std::error::Error>> {
+    path: web::Path<i32>,
+    body: web::Json<UpdateOrderCustomerRequest>,
+    data: web::Data<Arc<Mutex<Client>>>,
+) -> HttpResponse {
+    let mut client = data.lock().await;
+    let order_id = path.into_inner();
     // Extract the token from the Authorization header
     let token = match req.headers().get("Authorization") {
         Some(value) => {
@@ -559,65 +807,98 @@ pub async fn daily_sale_report_pdf(
             if parts.len() == 2 && parts[0] == "Bearer" {
                 parts[1]
             } else {
-                return Ok(HttpResponse::BadRequest().json(BaseResponse {
+                return HttpResponse::BadRequest().json(BaseResponse {
                     code: 400,
                     message: String::from("Invalid Authorization header format"),
-                }));
+                });
             }
         }
         None => {
-            return Ok(HttpResponse::Unauthorized().json(BaseResponse {
+            return HttpResponse::Unauthorized().json(BaseResponse {
                 code: 401,
                 message: String::from("Authorization header missing"),
-            }))
+            })
         }
     };
 
     let sub = match verify_token_and_get_sub(token) {
         Some(s) => s,
         None => {
-            return Ok(HttpResponse::Unauthorized().json(BaseResponse {
+            return HttpResponse::Unauthorized().json(BaseResponse {
                 code: 401,
                 message: String::from("Invalid token"),
-            }))
+            })
         }
     };
 
     // Parse the `sub` value
     let parsed_values: Vec<&str> = sub.split(',').collect();
     if parsed_values.len() != 3 {
-        return Ok(HttpResponse::InternalServerError().json(BaseResponse {
+        return HttpResponse::InternalServerError().json(BaseResponse {
             code: 500,
             message: String::from("Invalid sub format in token"),
-        }));
+        });
     }
     let user_id: i32 = parsed_values[0].parse().unwrap();
-    // Assuming you have the dynamically determined path to the PDF file
-    let file_path = format!("reports/{}dailysalereport.pdf", user_id); // Replace this with your dynamic path logic
-
-    // Read the file content
-    let file_content = fs::read(&file_path).map_err(|e| {
-        println!("Error reading PDF file: {:?}", e);
-        Box::new(io::Error::new(io::ErrorKind::Other, e)) as Box<dyn std::error::Error>
-    })?;
-
-    // Delete the file after it has been downloaded
-    if let Err(err) = fs::remove_file(&file_path) {
-        println!("Error deleting Excel file: {:?}", err);
+    let role: &str = parsed_values[1];
+    let shop_id: i32 = parsed_values[2].parse().unwrap();
+
+    if order::get_order_by_id(order_id, user_id, shop_id, role, &client)
+        .await
+        .is_none()
+    {
+        return HttpResponse::NotFound().json(BaseResponse {
+            code: 404,
+            message: String::from("Order not found!"),
+        });
+    }
+    if let Some(customer_id) = body.customer_id {
+        match customer::customer_visible_to_order(customer_id, order_id, &client).await {
+            Ok(true) => {}
+            Ok(false) => {
+                return HttpResponse::BadRequest().json(BaseResponse {
+                    code: 400,
+                    message: String::from("Customer not found!"),
+                });
+            }
+            Err(e) => {
+                eprintln!("Database error: {}", e);
+                return HttpResponse::InternalServerError().json(BaseResponse {
+                    code: 500,
+                    message: String::from("Something went wrong!"),
+                });
+            }
+        }
+    }
+
+    match customer::set_order_customer(order_id, body.customer_id, &mut client).await {
+        Ok(()) => HttpResponse::Ok().json(BaseResponse {
+            code: 200,
+            message: String::from("Order customer updated successfully"),
+        }),
+        Err(e) => {
+            eprintln!("Order customer updating error: {}", e);
+            HttpResponse::InternalServerError().json(BaseResponse {
+                code: 500,
+                message: String::from("Error updating order customer!"),
+            })
+        }
     }
-    // Serve the PDF file
-    Ok(HttpResponse::Ok()
-        .append_header((
-            "Content-Disposition",
-            "attachment; filename=dailysalereport.pdf",
-        ))
-        .content_type("application/pdf")
-        .body(file_content))
 }
-#[get("/api/daily-sale-report-excel")]
-pub async fn daily_sale_report_excel(
+
+#[derive(Deserialize)]
+pub struct ReportQuery {
+    pub from_date: NaiveDate,
+    pub to_date: NaiveDate,
+    pub shop_id: i32,
+}
+#[get("/api/daily-sale-report")]
+pub async fn get_daily_sale_report(
     req: HttpRequest,
-) -> Result<HttpResponse, Box<dyn std::error::Error>> {
+    query: web::Query<ReportQuery>,
+    data: web::Data<Arc<Mutex<Client>>>,
+) -> impl Responder {
+    let client = data.lock().await;
     // Extract the token from the Authorization header
     let token = match req.headers().get("Authorization") {
         Some(value) => {
@@ -625,62 +906,75 @@ pub async fn daily_sale_report_excel(
             if parts.len() == 2 && parts[0] == "Bearer" {
                 parts[1]
             } else {
-                return Ok(HttpResponse::BadRequest().json(BaseResponse {
+                return HttpResponse::BadRequest().json(BaseResponse {
                     code: 400,
                     message: String::from("Invalid Authorization header format"),
-                }));
+                });
             }
         }
         None => {
-            return Ok(HttpResponse::Unauthorized().json(BaseResponse {
+            return HttpResponse::Unauthorized().json(BaseResponse {
                 code: 401,
                 message: String::from("Authorization header missing"),
-            }))
+            })
         }
     };
 
     let sub = match verify_token_and_get_sub(token) {
         Some(s) => s,
         None => {
-            return Ok(HttpResponse::Unauthorized().json(BaseResponse {
+            return HttpResponse::Unauthorized().json(BaseResponse {
                 code: 401,
                 message: String::from("Invalid token"),
-            }))
+            })
         }
     };
 
     // Parse the `sub` value
     let parsed_values: Vec<&str> = sub.split(',').collect();
     if parsed_values.len() != 3 {
-        return Ok(HttpResponse::InternalServerError().json(BaseResponse {
+        return HttpResponse::InternalServerError().json(BaseResponse {
             code: 500,
             message: String::from("Invalid sub format in token"),
-        }));
+        });
     }
-    let user_id: i32 = parsed_values[0].parse().unwrap();
-    // Assuming you have the dynamically determined path to the Excel file
-    let file_path = format!("reports/{}dailysalereport.xlsx", user_id); // Replace this with your dynamic path logic
-
-    // Read the file content
-    let file_content = fs::read(&file_path).map_err(|e| {
-        println!("Error reading Excel file: {:?}", e);
-        Box::new(io::Error::new(io::ErrorKind::Other, e)) as Box<dyn std::error::Error>
-    })?;
-
-    // Serve the Excel file
-    let response = HttpResponse::Ok()
-        .append_header((
-            "Content-Disposition",
-            "attachment; filename=dailysalereport.xlsx",
-        ))
-        .content_type("application/vnd.openxmlformats-officedocument.spreadsheetml.sheet")
-        .body(file_content);
-
-    // Delete the file after it has been downloaded
-    if let Err(err) = fs::remove_file(&file_path) {
-        println!("Error deleting Excel file: {:?}", err);
+
+    if query.from_date.gt(&query.to_date) {
+        return HttpResponse::BadRequest().json(BaseResponse {
+            code: 400,
+            message: String::from("Invalid Date Range!"),
+        });
     }
 
-    Ok(response)
+    match shop::get_shop_by_id(query.shop_id, &client).await {
+        Some(s) => {
+            match order::get_daily_sale_report(
+                query.from_date,
+                query.to_date,
+                query.shop_id,
+                s.name,
+                &client,
+            )
+            .await
+            {
+                Ok(data) => HttpResponse::Ok().json(DataResponse {
+                    code: 200,
+                    message: String::from("Successful."),
+                    data: Some(data),
+                }),
+                Err(err) => {
+                    // Log the error message here
+                    println!("Error retrieving orders: {:?}", err);
+                    HttpResponse::InternalServerError().json(BaseResponse {
+                        code: 500,
+                        message: String::from("Error trying to read all orders from database"),
+                    })
+                }
+            }
+        },
+        None => HttpResponse::NotFound().json(BaseResponse {
+            code: 404,
+            message: String::from("Shop not found!"),
+        }),
+    }
 }
-
@@ -6,7 +6,10 @@ use tokio::sync::Mutex;
 use tokio_postgres::Client;
 
 use crate::{
-    models::purchase::{self, AddPurchaseRequest,UpdatePurchaseRequest},
+    models::{
+        ingredient,
+        purchase::{self, AddPurchaseRequest, UpdatePurchaseRequest},
+    },
     utils::{
         common_struct::{BaseResponse, DataResponse, PaginationResponse},
         jwt::verify_token_and_get_sub,
@@ -18,6 +21,7 @@ pub struct GetPurchasesQuery {
     pub search: Option<String>,
     pub page: Option<usize>,
     pub per_page: Option<usize>,
+    pub shop_id: Option<i32>,
 }
 
 #[get("/api/purchases")]
@@ -68,8 +72,9 @@ pub async fn get_purchases(
     }
 
     let role: &str = parsed_values[1];
+    let shop_id: i32 = parsed_values[2].parse().unwrap();
 
-    if role != "Admin" {
+    if role != "Admin" && role != "Manager" {
         return HttpResponse::Unauthorized().json(BaseResponse {
             code: 401,
             message: String::from("Unauthorized!"),
@@ -80,6 +85,9 @@ pub async fn get_purchases(
         &query.search,
         query.page,
         query.per_page,
+        role,
+        shop_id,
+        query.shop_id,
         &client,
     )
     .await
@@ -151,9 +159,11 @@ pub async fn add_purchase(
         });
     }
 
+    let user_id: i32 = parsed_values[0].parse().unwrap();
     let role: &str = parsed_values[1];
+    let shop_id: i32 = parsed_values[2].parse().unwrap();
 
-    if role != "Admin" {
+    if role != "Admin" && role != "Manager" {
         return HttpResponse::Unauthorized().json(BaseResponse {
             code: 401,
             message: String::from("Unauthorized!"),
@@ -167,7 +177,32 @@ pub async fn add_purchase(
         });
     }
 
-    match purchase::add_purchase(&body, &mut client).await {
+    if role == "Manager" && body.shop_id != shop_id {
+        return HttpResponse::Unauthorized().json(BaseResponse {
+            code: 401,
+            message: String::from("Unauthorized!"),
+        });
+    }
+
+    let ingredient_ids: Vec<i32> = body.purchase_details.iter().map(|d| d.ingredient_id).collect();
+    match ingredient::ingredients_belong_to_shop(&ingredient_ids, body.shop_id, &client).await {
+        Ok(true) => {}
+        Ok(false) => {
+            return HttpResponse::BadRequest().json(BaseResponse {
+                code: 400,
+                message: String::from("Ingredients must belong to the purchase's shop!"),
+            });
+        }
+        Err(err) => {
+            println!("{:?}", err);
+            return HttpResponse::InternalServerError().json(BaseResponse {
+                code: 500,
+                message: String::from("Error checking purchase ingredients!"),
+            });
+        }
+    }
+
+    match purchase::add_purchase(&body, user_id, &mut client).await {
         Ok(()) => HttpResponse::Created().json(BaseResponse {
             code: 201,
             message: String::from("Purcahse added successfully"),
@@ -231,15 +266,19 @@ pub async fn get_purchase_by_id(
     }
 
     let role: &str = parsed_values[1];
+    let shop_id: i32 = parsed_values[2].parse().unwrap();
 
-    if role != "Admin" {
+    if role != "Admin" && role != "Manager" {
         return HttpResponse::Unauthorized().json(BaseResponse {
             code: 401,
             message: String::from("Unauthorized!"),
         });
     }
 
-    match purchase::get_purchase_by_id(purchase_id, &client).await {
+    match purchase::get_purchase_by_id(purchase_id, &client)
+        .await
+        .filter(|p| role == "Admin" || p.shop_id == shop_id)
+    {
         Some(u) => HttpResponse::Ok().json(DataResponse {
             code: 200,
             message: String::from("Purchase fetched successfully."),
@@ -301,9 +340,11 @@ pub async fn update_purchase(
         });
     }
 
+    let user_id: i32 = parsed_values[0].parse().unwrap();
     let role: &str = parsed_values[1];
+    let shop_id: i32 = parsed_values[2].parse().unwrap();
 
-    if role != "Admin" {
+    if role != "Admin" && role != "Manager" {
         return HttpResponse::Unauthorized().json(BaseResponse {
             code: 401,
             message: String::from("Unauthorized!"),
@@ -317,8 +358,36 @@ pub async fn update_purchase(
         });
     }
 
-    match purchase::get_purchase_by_id(purchase_id, &client).await {
-        Some(_) => match purchase::update_purchase(&body, purchase_id, &mut client).await {
+    if role == "Manager" && body.shop_id != shop_id {
+        return HttpResponse::Unauthorized().json(BaseResponse {
+            code: 401,
+            message: String::from("Unauthorized!"),
+        });
+    }
+
+    let ingredient_ids: Vec<i32> = body.purchase_details.iter().map(|d| d.ingredient_id).collect();
+    match ingredient::ingredients_belong_to_shop(&ingredient_ids, body.shop_id, &client).await {
+        Ok(true) => {}
+        Ok(false) => {
+            return HttpResponse::BadRequest().json(BaseResponse {
+                code: 400,
+                message: String::from("Ingredients must belong to the purchase's shop!"),
+            });
+        }
+        Err(err) => {
+            println!("{:?}", err);
+            return HttpResponse::InternalServerError().json(BaseResponse {
+                code: 500,
+                message: String::from("Error checking purchase ingredients!"),
+            });
+        }
+    }
+
+    match purchase::get_purchase_by_id(purchase_id, &client)
+        .await
+        .filter(|p| role == "Admin" || p.shop_id == shop_id)
+    {
+        Some(_) => match purchase::update_purchase(&body, purchase_id, user_id, &mut client).await {
             Ok(()) => HttpResponse::Ok().json(BaseResponse {
                 code: 200,
                 message: String::from("Purchase updated successfully"),
@@ -344,7 +413,7 @@ pub async fn delete_purchase(
     path: web::Path<i32>,
     data: web::Data<Arc<Mutex<Client>>>,
 ) -> HttpResponse {
-    let client = data.lock().await;
+    let mut client = data.lock().await;
     let purchase_id = path.into_inner();
     // Extract the token from the Authorization header
     let token = match req.headers().get("Authorization") {
@@ -386,17 +455,22 @@ pub async fn delete_purchase(
         });
     }
 
+    let user_id: i32 = parsed_values[0].parse().unwrap();
     let role: &str = parsed_values[1];
+    let shop_id: i32 = parsed_values[2].parse().unwrap();
 
-    if role != "Admin" {
+    if role != "Admin" && role != "Manager" {
         return HttpResponse::Unauthorized().json(BaseResponse {
             code: 401,
             message: String::from("Unauthorized!"),
         });
     }
 
-    match purchase::get_purchase_by_id(purchase_id, &client).await {
-        Some(_) => match purchase::delete_purchase(purchase_id, &client).await {
+    match purchase::get_purchase_by_id(purchase_id, &client)
+        .await
+        .filter(|p| role == "Admin" || p.shop_id == shop_id)
+    {
+        Some(_) => match purchase::delete_purchase(purchase_id, user_id, &mut client).await {
             Ok(()) => HttpResponse::Ok().json(BaseResponse {
                 code: 204,
                 message: String::from("Purchase deleted successfully"),
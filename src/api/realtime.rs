@@ -0,0 +1,71 @@
+use actix_web::{get, web, HttpRequest, HttpResponse};
+use serde::Deserialize;
+
+use crate::utils::{
+    common_struct::BaseResponse, jwt::verify_token_and_get_sub, realtime::RealtimeHub,
+};
+
+#[derive(Deserialize)]
+pub struct GetEventsQuery {
+    pub token: Option<String>,
+}
+
+// Streams the socket events of the user's shop, role and user as Server-Sent Events. EventSource can't set
+// headers, so browsers pass the token as `?token=`.
+#[get("/api/events")]
+pub async fn get_events(
+    req: HttpRequest,
+    query: web::Query<GetEventsQuery>,
+    hub: web::Data<RealtimeHub>,
+) -> HttpResponse {
+    let header_token = req
+        .headers()
+        .get("Authorization")
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.strip_prefix("Bearer "));
+    let token = match header_token.or(query.token.as_deref()) {
+        Some(t) => t,
+        None => {
+            return HttpResponse::Unauthorized().json(BaseResponse {
+                code: 401,
+                message: String::from("Authorization header missing"),
+            })
+        }
+    };
+
+    let sub = match verify_token_and_get_sub(token) {
+        Some(s) => s,
+        None => {
+            return HttpResponse::Unauthorized().json(BaseResponse {
+                code: 401,
+                message: String::from("Invalid token"),
+            })
+        }
+    };
+
+    // Parse the `sub` value
+    let parsed_values: Vec<&str> = sub.split(',').collect();
+    if parsed_values.len() != 3 {
+        return HttpResponse::InternalServerError().json(BaseResponse {
+            code: 500,
+            message: String::from("Invalid sub format in token"),
+        });
+    }
+
+    let user_id: i32 = parsed_values[0].parse().unwrap();
+    let role: &str = parsed_values[1];
+    let shop_id: i32 = parsed_values[2].parse().unwrap();
+
+    let receiver = hub.connect(user_id, role, shop_id);
+    let stream = futures::stream::unfold(receiver, |mut receiver| async move {
+        receiver
+            .recv()
+            .await
+            .map(|message| (Ok::<_, actix_web::Error>(message), receiver))
+    });
+    HttpResponse::Ok()
+        .content_type("text/event-stream")
+        .insert_header(("Cache-Control", "no-cache"))
+        .insert_header(("X-Accel-Buffering", "no"))
+        .streaming(stream)
+}
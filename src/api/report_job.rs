@@ -0,0 +1,517 @@
+use std::sync::Arc;
+
+use actix_web::{
+    get,
+    http::header::{self, HeaderName, HeaderValue},
+    post, web, HttpRequest, HttpResponse,
+};
+use chrono::NaiveDate;
+use serde::Deserialize;
+use tokio::sync::Mutex;
+use tokio_postgres::Client;
+
+use crate::{
+    models::{
+        report_job::{self, ReportJob, ReportJobRequest, REPORT_TYPES},
+        shop,
+    },
+    utils::{
+        common_struct::{BaseResponse, DataResponse},
+        jwt::verify_token_and_get_sub,
+    },
+};
+
+#[post("/api/report-jobs")]
+pub async fn add_report_job(
+    req: HttpRequest,
+    body: web::Json<ReportJobRequest>,
+    data: web::Data<Arc<Mutex<Client>>>,
+) -> HttpResponse {
+    let client = data.lock().await;
+    // Extract the token from the Authorization header
+    let token = match req.headers().get("Authorization") {
+        Some(value) => {
+            let parts: Vec<&str> = value.to_str().unwrap_or("").split_whitespace().collect();
+            if parts.len() == 2 && parts[0] == "Bearer" {
+                parts[1]
+            } else {
+                return HttpResponse::BadRequest().json(BaseResponse {
+                    code: 400,
+                    message: String::from("Invalid Authorization header format"),
+                });
+            }
+        }
+        None => {
+            return HttpResponse::Unauthorized().json(BaseResponse {
+                code: 401,
+                message: String::from("Authorization header missing"),
+            })
+        }
+    };
+
+    let sub = match verify_token_and_get_sub(token) {
+        Some(s) => s,
+        None => {
+            return HttpResponse::Unauthorized().json(BaseResponse {
+                code: 401,
+                message: String::from("Invalid token"),
+            })
+        }
+    };
+
+    // Parse the `sub` value
+    let parsed_values: Vec<&str> = sub.split(',').collect();
+    if parsed_values.len() != 3 {
+        return HttpResponse::InternalServerError().json(BaseResponse {
+            code: 500,
+            message: String::from("Invalid sub format in token"),
+        });
+    }
+    let user_id: i32 = parsed_values[0].parse().unwrap();
+    let role: &str = parsed_values[1];
+    let mut shop_id: i32 = parsed_values[2].parse().unwrap();
+
+    if role != "Admin" && role != "Manager" {
+        return HttpResponse::Unauthorized().json(BaseResponse {
+            code: 401,
+            message: String::from("Unauthorized!"),
+        });
+    }
+    if !REPORT_TYPES.contains(&body.report_type.as_str()) {
+        return HttpResponse::BadRequest().json(BaseResponse {
+            code: 400,
+            message: String::from("Please select a valid report type: daily_sale, food_cost, or waiter_performance."),
+        });
+    }
+
+    if body.from_date.gt(&body.to_date) {
+        return HttpResponse::BadRequest().json(BaseResponse {
+            code: 400,
+            message: String::from("Invalid Date Range!"),
+        });
+    }
+
+    if role == "Admin" {
+        shop_id = match body.shop_id {
+            Some(s) => s,
+            None => {
+                return HttpResponse::BadRequest().json(BaseResponse {
+                    code: 400,
+                    message: String::from("Shop Id must not be empty!"),
+                });
+            }
+        };
+    }
+
+    if shop::get_shop_by_id(shop_id, &client).await.is_none() {
+        return HttpResponse::NotFound().json(BaseResponse {
+            code: 404,
+            message: String::from("Shop not found!"),
+        });
+    }
+
+    match report_job::add_report_job(&body, shop_id, user_id, &client).await {
+        Ok(job_id) => {
+            tokio::spawn(report_job::run_report_job(job_id, data.get_ref().clone()));
+            HttpResponse::Accepted().json(DataResponse {
+                code: 202,
+                message: String::from("Report job queued successfully"),
+                data: Some(job_id),
+            })
+        }
+        Err(e) => {
+            eprintln!("Report job adding error: {}", e);
+            HttpResponse::InternalServerError().json(BaseResponse {
+                code: 500,
+                message: String::from("Error queuing report job!"),
+            })
+        }
+    }
+}
+
+#[get("/api/report-jobs/{job_id}")]
+pub async fn get_report_job_by_id(
+    req: HttpRequest,
+    path: web::Path<i32>,
+    data: web::Data<Arc<Mutex<Client>>>,
+) -> HttpResponse {
+    let client = data.lock().await;
+    let job_id = path.into_inner();
+    // Extract the token from the Authorization header
+    let token = match req.headers().get("Authorization") {
+        Some(value) => {
+            let parts: Vec<&str> = value.to_str().unwrap_or("").split_whitespace().collect();
+            if parts.len() == 2 && parts[0] == "Bearer" {
+                parts[1]
+            } else {
+                return HttpResponse::BadRequest().json(BaseResponse {
+                    code: 400,
+                    message: String::from("Invalid Authorization header format"),
+                });
+            }
+        }
+        None => {
+            return HttpResponse::Unauthorized().json(BaseResponse {
+                code: 401,
+                message: String::from("Authorization header missing"),
+            })
+        }
+    };
+
+    let sub = match verify_token_and_get_sub(token) {
+        Some(s) => s,
+        None => {
+            return HttpResponse::Unauthorized().json(BaseResponse {
+                code: 401,
+                message: String::from("Invalid token"),
+            })
+        }
+    };
+
+    // Parse the `sub` value
+    let parsed_values: Vec<&str> = sub.split(',').collect();
+    if parsed_values.len() != 3 {
+        return HttpResponse::InternalServerError().json(BaseResponse {
+            code: 500,
+            message: String::from("Invalid sub format in token"),
+        });
+    }
+    let user_id: i32 = parsed_values[0].parse().unwrap();
+    let role: &str = parsed_values[1];
+
+    if role != "Admin" && role != "Manager" {
+        return HttpResponse::Unauthorized().json(BaseResponse {
+            code: 401,
+            message: String::from("Unauthorized!"),
+        });
+    }
+    match report_job::get_report_job_by_id(job_id, &client)
+        .await
+        .filter(|j| role == "Admin" || j.requested_by == user_id)
+    {
+        Some(j) => HttpResponse::Ok().json(DataResponse {
+            code: 200,
+            message: String::from("Report job fetched successfully."),
+            data: Some(j),
+        }),
+        None => HttpResponse::NotFound().json(BaseResponse {
+            code: 404,
+            message: String::from("Report job not found!"),
+        }),
+    }
+}
+
+// Reads the file of a finished job in the requested format.
+async fn report_file_response(job: ReportJob, format: &str) -> HttpResponse {
+    if job.status != "Completed" {
+        return HttpResponse::BadRequest().json(BaseResponse {
+            code: 400,
+            message: format!("Report job is {}!", job.status.to_lowercase()),
+        });
+    }
+
+    let (file_path, extension, content_type) = match format {
+        "pdf" => (job.pdf_path, "pdf", "application/pdf"),
+        "excel" => (
+            job.excel_path,
+            "xlsx",
+            "application/vnd.openxmlformats-officedocument.spreadsheetml.sheet",
+        ),
+        _ => {
+            return HttpResponse::BadRequest().json(BaseResponse {
+                code: 400,
+                message: String::from("Please select a valid format: pdf or excel."),
+            });
+        }
+    };
+
+    let file_content = match file_path {
+        Some(p) => match tokio::fs::read(&p).await {
+            Ok(content) => content,
+            Err(err) => {
+                println!("Error reading report file: {:?}", err);
+                return HttpResponse::NotFound().json(BaseResponse {
+                    code: 404,
+                    message: String::from("Report file not found!"),
+                });
+            }
+        },
+        None => {
+            return HttpResponse::NotFound().json(BaseResponse {
+                code: 404,
+                message: String::from("Report file not found!"),
+            });
+        }
+    };
+
+    HttpResponse::Ok()
+        .append_header((
+            "Content-Disposition",
+            format!(
+                "attachment; filename={}report-{}.{}",
+                job.report_type.replace('_', ""),
+                job.job_id,
+                extension
+            ),
+        ))
+        .content_type(content_type)
+        .body(file_content)
+}
+
+#[derive(Deserialize)]
+pub struct DownloadReportQuery {
+    pub format: String,
+}
+
+#[get("/api/report-jobs/{job_id}/download")]
+pub async fn download_report_job(
+    req: HttpRequest,
+    path: web::Path<i32>,
+    query: web::Query<DownloadReportQuery>,
+    data: web::Data<Arc<Mutex<Client>>>,
+) -> HttpResponse {
+    let client = data.lock().await;
+    let job_id = path.into_inner();
+    // Extract the token from the Authorization header
+    let token = match req.headers().get("Authorization") {
+        Some(value) => {
+            let parts: Vec<&str> = value.to_str().unwrap_or("").split_whitespace().collect();
+            if parts.len() == 2 && parts[0] == "Bearer" {
+                parts[1]
+            } else {
+                return HttpResponse::BadRequest().json(BaseResponse {
+                    code: 400,
+                    message: String::from("Invalid Authorization header format"),
+                });
+            }
+        }
+        None => {
+            return HttpResponse::Unauthorized().json(BaseResponse {
+                code: 401,
+                message: String::from("Authorization header missing"),
+            })
+        }
+    };
+
+    let sub = match verify_token_and_get_sub(token) {
+        Some(s) => s,
+        None => {
+            return HttpResponse::Unauthorized().json(BaseResponse {
+                code: 401,
+                message: String::from("Invalid token"),
+            })
+        }
+    };
+
+    // Parse the `sub` value
+    let parsed_values: Vec<&str> = sub.split(',').collect();
+    if parsed_values.len() != 3 {
+        return HttpResponse::InternalServerError().json(BaseResponse {
+            code: 500,
+            message: String::from("Invalid sub format in token"),
+        });
+    }
+    let user_id: i32 = parsed_values[0].parse().unwrap();
+    let role: &str = parsed_values[1];
+
+    if role != "Admin" && role != "Manager" {
+        return HttpResponse::Unauthorized().json(BaseResponse {
+            code: 401,
+            message: String::from("Unauthorized!"),
+        });
+    }
+    let job = match report_job::get_report_job_by_id(job_id, &client)
+        .await
+        .filter(|j| role == "Admin" || j.requested_by == user_id)
+    {
+        Some(j) => j,
+        None => {
+            return HttpResponse::NotFound().json(BaseResponse {
+                code: 404,
+                message: String::from("Report job not found!"),
+            });
+        }
+    };
+    drop(client);
+
+    report_file_response(job, &query.format).await
+}
+
+// The report routes from before report jobs. Given a date range they run a job for it and return its file,
+// without one they return the file of the newest job of the type the user requested.
+#[derive(Deserialize)]
+pub struct DeprecatedReportQuery {
+    pub from_date: Option<NaiveDate>,
+    pub to_date: Option<NaiveDate>,
+    pub shop_id: Option<i32>,
+}
+
+async fn deprecated_report_download(
+    req: HttpRequest,
+    query: web::Query<DeprecatedReportQuery>,
+    data: web::Data<Arc<Mutex<Client>>>,
+    report_type: &str,
+    format: &str,
+) -> HttpResponse {
+    let client = data.lock().await;
+    // Extract the token from the Authorization header
+    let token = match req.headers().get("Authorization") {
+        Some(value) => {
+            let parts: Vec<&str> = value.to_str().unwrap_or("").split_whitespace().collect();
+            if parts.len() == 2 && parts[0] == "Bearer" {
+                parts[1]
+            } else {
+                return HttpResponse::BadRequest().json(BaseResponse {
+                    code: 400,
+                    message: String::from("Invalid Authorization header format"),
+                });
+            }
+        }
+        None => {
+            return HttpResponse::Unauthorized().json(BaseResponse {
+                code: 401,
+                message: String::from("Authorization header missing"),
+            })
+        }
+    };
+
+    let sub = match verify_token_and_get_sub(token) {
+        Some(s) => s,
+        None => {
+            return HttpResponse::Unauthorized().json(BaseResponse {
+                code: 401,
+                message: String::from("Invalid token"),
+            })
+        }
+    };
+
+    // Parse the `sub` value
+    let parsed_values: Vec<&str> = sub.split(',').collect();
+    if parsed_values.len() != 3 {
+        return HttpResponse::InternalServerError().json(BaseResponse {
+            code: 500,
+            message: String::from("Invalid sub format in token"),
+        });
+    }
+    let user_id: i32 = parsed_values[0].parse().unwrap();
+    let role: &str = parsed_values[1];
+    let mut shop_id: i32 = parsed_values[2].parse().unwrap();
+
+    // Each route keeps the access it had before: every signed in user gets their own files and runs a daily
+    // sale report for the shop they ask for, only admins and managers run a food cost report.
+    let job_id = match (query.from_date, query.to_date) {
+        (Some(from_date), Some(to_date)) => {
+            if report_type != "daily_sale" && role != "Admin" && role != "Manager" {
+                return HttpResponse::Unauthorized().json(BaseResponse {
+                    code: 401,
+                    message: String::from("Unauthorized!"),
+                });
+            }
+            if from_date.gt(&to_date) {
+                return HttpResponse::BadRequest().json(BaseResponse {
+                    code: 400,
+                    message: String::from("Invalid Date Range!"),
+                });
+            }
+            if report_type == "daily_sale" || role == "Admin" {
+                shop_id = match query.shop_id {
+                    Some(s) => s,
+                    None => {
+                        return HttpResponse::BadRequest().json(BaseResponse {
+                            code: 400,
+                            message: String::from("Shop Id must not be empty!"),
+                        });
+                    }
+                };
+            }
+            if shop::get_shop_by_id(shop_id, &client).await.is_none() {
+                return HttpResponse::NotFound().json(BaseResponse {
+                    code: 404,
+                    message: String::from("Shop not found!"),
+                });
+            }
+            let request = ReportJobRequest {
+                report_type: report_type.to_string(),
+                from_date,
+                to_date,
+                shop_id: Some(shop_id),
+            };
+            let job_id = match report_job::add_report_job(&request, shop_id, user_id, &client).await
+            {
+                Ok(id) => id,
+                Err(e) => {
+                    eprintln!("Report job adding error: {}", e);
+                    return HttpResponse::InternalServerError().json(BaseResponse {
+                        code: 500,
+                        message: String::from("Error queuing report job!"),
+                    });
+                }
+            };
+            drop(client);
+            report_job::run_report_job(job_id, data.get_ref().clone()).await;
+            Some(job_id)
+        }
+        _ => {
+            let job_id = report_job::get_latest_report_job_id(report_type, user_id, &client).await;
+            drop(client);
+            job_id
+        }
+    };
+
+    let job = match job_id {
+        Some(id) => report_job::get_report_job_by_id(id, &*data.lock().await).await,
+        None => None,
+    };
+    let mut response = match job {
+        Some(j) => report_file_response(j, format).await,
+        None => HttpResponse::NotFound().json(BaseResponse {
+            code: 404,
+            message: String::from("Report job not found!"),
+        }),
+    };
+    response.headers_mut().insert(
+        HeaderName::from_static("deprecation"),
+        HeaderValue::from_static("true"),
+    );
+    response.headers_mut().insert(
+        header::LINK,
+        HeaderValue::from_static("</api/report-jobs>; rel=\"successor-version\""),
+    );
+    response
+}
+
+#[get("/api/daily-sale-report-pdf")]
+pub async fn daily_sale_report_pdf(
+    req: HttpRequest,
+    query: web::Query<DeprecatedReportQuery>,
+    data: web::Data<Arc<Mutex<Client>>>,
+) -> HttpResponse {
+    deprecated_report_download(req, query, data, "daily_sale", "pdf").await
+}
+
+#[get("/api/daily-sale-report-excel")]
+pub async fn daily_sale_report_excel(
+    req: HttpRequest,
+    query: web::Query<DeprecatedReportQuery>,
+    data: web::Data<Arc<Mutex<Client>>>,
+) -> HttpResponse {
+    deprecated_report_download(req, query, data, "daily_sale", "excel").await
+}
+
+#[get("/api/food-cost-report-pdf")]
+pub async fn food_cost_report_pdf(
+    req: HttpRequest,
+    query: web::Query<DeprecatedReportQuery>,
+    data: web::Data<Arc<Mutex<Client>>>,
+) -> HttpResponse {
+    deprecated_report_download(req, query, data, "food_cost", "pdf").await
+}
+
+#[get("/api/food-cost-report-excel")]
+pub async fn food_cost_report_excel(
+    req: HttpRequest,
+    query: web::Query<DeprecatedReportQuery>,
+    data: web::Data<Arc<Mutex<Client>>>,
+) -> HttpResponse {
+    deprecated_report_download(req, query, data, "food_cost", "excel").await
+}
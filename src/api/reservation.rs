@@ -0,0 +1,723 @@
+use std::sync::Arc;
+
+use actix_web::{get, post, put, web, HttpRequest, HttpResponse};
+use chrono::{Local, NaiveDate, NaiveDateTime};
+use serde::Deserialize;
+use tokio::sync::Mutex;
+use tokio_postgres::Client;
+
+use crate::{
+    models::{
+        customer,
+        reservation::{self, ReservationRequest, DEFAULT_DURATION_MINUTES, RESERVATION_STATUSES},
+        shop, table,
+    },
+    utils::{
+        common_struct::{BaseResponse, DataResponse, PaginationResponse},
+        jwt::verify_token_and_get_sub,
+        validator::validate_mobile,
+    },
+};
+
+#[derive(Deserialize)]
+pub struct GetReservationsQuery {
+    pub shop_id: Option<i32>,
+    pub date: Option<NaiveDate>,
+    pub status: Option<String>,
+    pub page: Option<usize>,
+    pub per_page: Option<usize>,
+}
+
+#[derive(Deserialize)]
+pub struct ReservationAvailabilityQuery {
+    pub shop_id: Option<i32>,
+    pub party_size: i32,
+    pub reserved_at: NaiveDateTime,
+    pub duration_minutes: Option<i32>,
+}
+
+#[derive(Deserialize)]
+pub struct ReservationStatusRequest {
+    pub status: String,
+}
+
+fn validate_reservation(body: &ReservationRequest) -> Option<HttpResponse> {
+    if body.guest_name.trim().is_empty() {
+        return Some(HttpResponse::BadRequest().json(BaseResponse {
+            code: 400,
+            message: String::from("Guest name must not be empty!"),
+        }));
+    }
+    if !validate_mobile(&body.guest_phone) {
+        return Some(HttpResponse::BadRequest().json(BaseResponse {
+            code: 400,
+            message: String::from("Invalid phone number!"),
+        }));
+    }
+    if body.party_size < 1 {
+        return Some(HttpResponse::BadRequest().json(BaseResponse {
+            code: 400,
+            message: String::from("Party size must be greater than zero!"),
+        }));
+    }
+    if body.duration_minutes.is_some_and(|d| d < 15) {
+        return Some(HttpResponse::BadRequest().json(BaseResponse {
+            code: 400,
+            message: String::from("Duration must be at least 15 minutes!"),
+        }));
+    }
+    if body.reserved_at < Local::now().naive_local() {
+        return Some(HttpResponse::BadRequest().json(BaseResponse {
+            code: 400,
+            message: String::from("Reservation time must not be in the past!"),
+        }));
+    }
+    None
+}
+
+// Uses the requested table when it seats the party and is free for the time slot, otherwise the smallest free
+// table that seats the party.
+async fn assign_table(
+    body: &ReservationRequest,
+    shop_id: i32,
+    exclude_reservation_id: Option<i32>,
+    client: &Client,
+) -> Result<i32, HttpResponse> {
+    let duration_minutes = body.duration_minutes.unwrap_or(DEFAULT_DURATION_MINUTES);
+    if let Some(table_id) = body.table_id {
+        match table::get_table_by_id(table_id, client)
+            .await
+            .filter(|t| t.shop_id == shop_id)
+        {
+            Some(t) if t.capacity < body.party_size => {
+                return Err(HttpResponse::BadRequest().json(BaseResponse {
+                    code: 400,
+                    message: String::from("Table capacity is too small for the party!"),
+                }));
+            }
+            Some(_) => {}
+            None => {
+                return Err(HttpResponse::NotFound().json(BaseResponse {
+                    code: 404,
+                    message: String::from("Table not found!"),
+                }));
+            }
+        }
+    }
+    let available = match reservation::get_available_tables(
+        shop_id,
+        body.party_size,
+        body.reserved_at,
+        duration_minutes,
+        exclude_reservation_id,
+        client,
+    )
+    .await
+    {
+        Ok(tables) => tables,
+        Err(e) => {
+            eprintln!("Table availability error: {}", e);
+            return Err(HttpResponse::InternalServerError().json(BaseResponse {
+                code: 500,
+                message: String::from("Something went wrong!"),
+            }));
+        }
+    };
+    let table_id = match body.table_id {
+        Some(id) => available
+            .iter()
+            .find(|t| t.table_id == id)
+            .map(|t| t.table_id),
+        None => available.first().map(|t| t.table_id),
+    };
+    table_id.ok_or_else(|| {
+        HttpResponse::Conflict().json(BaseResponse {
+            code: 409,
+            message: String::from("No table is available for that time!"),
+        })
+    })
+}
+
+#[get("/api/reservations")]
+pub async fn get_reservations(
+    req: HttpRequest,
+    query: web::Query<GetReservationsQuery>,
+    data: web::Data<Arc<Mutex<Client>>>,
+) -> HttpResponse {
+    let client = data.lock().await;
+    // Extract the token from the Authorization header
+    let token = match req.headers().get("Authorization") {
+        Some(value) => {
+            let parts: Vec<&str> = value.to_str().unwrap_or("").split_whitespace().collect();
+            if parts.len() == 2 && parts[0] == "Bearer" {
+                parts[1]
+            } else {
+                return HttpResponse::BadRequest().json(BaseResponse {
+                    code: 400,
+                    message: String::from("Invalid Authorization header format"),
+                });
+            }
+        }
+        None => {
+            return HttpResponse::Unauthorized().json(BaseResponse {
+                code: 401,
+                message: String::from("Authorization header missing"),
+            })
+        }
+    };
+
+    let sub = match verify_token_and_get_sub(token) {
+        Some(s) => s,
+        None => {
+            return HttpResponse::Unauthorized().json(BaseResponse {
+                code: 401,
+                message: String::from("Invalid token"),
+            })
+        }
+    };
+
+    // Parse the `sub` value
+    let parsed_values: Vec<&str> = sub.split(',').collect();
+    if parsed_values.len() != 3 {
+        return HttpResponse::InternalServerError().json(BaseResponse {
+            code: 500,
+            message: String::from("Invalid sub format in token"),
+        });
+    }
+    let role: &str = parsed_values[1];
+    let shop_id: i32 = parsed_values[2].parse().unwrap();
+
+    if let Some(s) = &query.status {
+        if !RESERVATION_STATUSES.contains(&s.as_str()) {
+            return HttpResponse::BadRequest().json(BaseResponse {
+                code: 400,
+                message: String::from(
+                    "Please select a valid status: Booked, Seated, Completed, Canceled, or NoShow.",
+                ),
+            });
+        }
+    }
+    let filter_shop_id = if role == "Admin" {
+        query.shop_id
+    } else {
+        Some(shop_id)
+    };
+    match reservation::get_reservations(
+        filter_shop_id,
+        query.date,
+        &query.status,
+        query.page,
+        query.per_page,
+        &client,
+    )
+    .await
+    {
+        Ok(item_result) => HttpResponse::Ok().json(PaginationResponse {
+            code: 200,
+            message: String::from("Successful."),
+            data: item_result.data,
+            total: item_result.total,
+            page: item_result.page,
+            per_page: item_result.per_page,
+            page_counts: item_result.page_counts,
+        }),
+        Err(err) => {
+            // Log the error message here
+            println!("Error retrieving reservations: {:?}", err);
+            HttpResponse::InternalServerError().json(BaseResponse {
+                code: 500,
+                message: String::from("Error trying to read all reservations from database"),
+            })
+        }
+    }
+}
+
+#[get("/api/reservation-availability")]
+pub async fn get_reservation_availability(
+    req: HttpRequest,
+    query: web::Query<ReservationAvailabilityQuery>,
+    data: web::Data<Arc<Mutex<Client>>>,
+) -> HttpResponse {
+    let client = data.lock().await;
+    // Extract the token from the Authorization header
+    let token = match req.headers().get("Authorization") {
+        Some(value) => {
+            let parts: Vec<&str> = value.to_str().unwrap_or("").split_whitespace().collect();
+            if parts.len() == 2 && parts[0] == "Bearer" {
+                parts[1]
+            } else {
+                return HttpResponse::BadRequest().json(BaseResponse {
+                    code: 400,
+                    message: String::from("Invalid Authorization header format"),
+                });
+            }
+        }
+        None => {
+            return HttpResponse::Unauthorized().json(BaseResponse {
+                code: 401,
+                message: String::from("Authorization header missing"),
+            })
+        }
+    };
+
+    let sub = match verify_token_and_get_sub(token) {
+        Some(s) => s,
+        None => {
+            return HttpResponse::Unauthorized().json(BaseResponse {
+                code: 401,
+                message: String::from("Invalid token"),
+            })
+        }
+    };
+
+    // Parse the `sub` value
+    let parsed_values: Vec<&str> = sub.split(',').collect();
+    if parsed_values.len() != 3 {
+        return HttpResponse::InternalServerError().json(BaseResponse {
+            code: 500,
+            message: String::from("Invalid sub format in token"),
+        });
+    }
+    let role: &str = parsed_values[1];
+    let mut shop_id: i32 = parsed_values[2].parse().unwrap();
+
+    if role == "Admin" {
+        shop_id = match query.shop_id {
+            Some(s) => s,
+            None => {
+                return HttpResponse::BadRequest().json(BaseResponse {
+                    code: 400,
+                    message: String::from("Shop Id must not be empty!"),
+                });
+            }
+        };
+    }
+    if query.party_size < 1 {
+        return HttpResponse::BadRequest().json(BaseResponse {
+            code: 400,
+            message: String::from("Party size must be greater than zero!"),
+        });
+    }
+    match reservation::get_available_tables(
+        shop_id,
+        query.party_size,
+        query.reserved_at,
+        query.duration_minutes.unwrap_or(DEFAULT_DURATION_MINUTES),
+        None,
+        &client,
+    )
+    .await
+    {
+        Ok(tables) => HttpResponse::Ok().json(DataResponse {
+            code: 200,
+            message: String::from("Successful."),
+            data: Some(tables),
+        }),
+        Err(err) => {
+            // Log the error message here
+            println!("Error retrieving table availability: {:?}", err);
+            HttpResponse::InternalServerError().json(BaseResponse {
+                code: 500,
+                message: String::from("Error trying to read table availability from database"),
+            })
+        }
+    }
+}
+
+#[post("/api/reservations")]
+pub async fn add_reservation(
+    req: HttpRequest,
+    body: web::Json<ReservationRequest>,
+    data: web::Data<Arc<Mutex<Client>>>,
+) -> HttpResponse {
+    let client = data.lock().await;
+    // Extract the token from the Authorization header
+    let token = match req.headers().get("Authorization") {
+        Some(value) => {
+            let parts: Vec<&str> = value.to_str().unwrap_or("").split_whitespace().collect();
+            if parts.len() == 2 && parts[0] == "Bearer" {
+                parts[1]
+            } else {
+                return HttpResponse::BadRequest().json(BaseResponse {
+                    code: 400,
+                    message: String::from("Invalid Authorization header format"),
+                });
+            }
+        }
+        None => {
+            return HttpResponse::Unauthorized().json(BaseResponse {
+                code: 401,
+                message: String::from("Authorization header missing"),
+            })
+        }
+    };
+
+    let sub = match verify_token_and_get_sub(token) {
+        Some(s) => s,
+        None => {
+            return HttpResponse::Unauthorized().json(BaseResponse {
+                code: 401,
+                message: String::from("Invalid token"),
+            })
+        }
+    };
+
+    // Parse the `sub` value
+    let parsed_values: Vec<&str> = sub.split(',').collect();
+    if parsed_values.len() != 3 {
+        return HttpResponse::InternalServerError().json(BaseResponse {
+            code: 500,
+            message: String::from("Invalid sub format in token"),
+        });
+    }
+    let user_id: i32 = parsed_values[0].parse().unwrap();
+    let role: &str = parsed_values[1];
+    let mut shop_id: i32 = parsed_values[2].parse().unwrap();
+
+    if role == "Admin" {
+        shop_id = match body.shop_id {
+            Some(s) => s,
+            None => {
+                return HttpResponse::BadRequest().json(BaseResponse {
+                    code: 400,
+                    message: String::from("Shop Id must not be empty!"),
+                });
+            }
+        };
+    }
+    if let Some(response) = validate_reservation(&body) {
+        return response;
+    }
+    if shop::get_shop_by_id(shop_id, &client).await.is_none() {
+        return HttpResponse::NotFound().json(BaseResponse {
+            code: 404,
+            message: String::from("Shop not found!"),
+        });
+    }
+    if let Some(customer_id) = body.customer_id {
+        match customer::customer_visible_to_shop(customer_id, shop_id, &client).await {
+            Ok(true) => {}
+            Ok(false) => {
+                return HttpResponse::BadRequest().json(BaseResponse {
+                    code: 400,
+                    message: String::from("Customer not found!"),
+                });
+            }
+            Err(e) => {
+                eprintln!("Database error: {}", e);
+                return HttpResponse::InternalServerError().json(BaseResponse {
+                    code: 500,
+                    message: String::from("Something went wrong!"),
+                });
+            }
+        }
+    }
+    let table_id = match assign_table(&body, shop_id, None, &client).await {
+        Ok(id) => id,
+        Err(response) => return response,
+    };
+
+    match reservation::add_reservation(
+        &body,
+        shop_id,
+        table_id,
+        body.duration_minutes.unwrap_or(DEFAULT_DURATION_MINUTES),
+        user_id,
+        &client,
+    )
+    .await
+    {
+        Ok(id) => HttpResponse::Created().json(DataResponse {
+            code: 201,
+            message: String::from("Reservation added successfully"),
+            data: Some(id),
+        }),
+        Err(e) => {
+            eprintln!("Reservation adding error: {}", e);
+            HttpResponse::InternalServerError().json(BaseResponse {
+                code: 500,
+                message: String::from("Error adding reservation!"),
+            })
+        }
+    }
+}
+
+#[get("/api/reservations/{reservation_id}")]
+pub async fn get_reservation_by_id(
+    req: HttpRequest,
+    path: web::Path<i32>,
+    data: web::Data<Arc<Mutex<Client>>>,
+) -> HttpResponse {
+    let client = data.lock().await;
+    let reservation_id = path.into_inner();
+    // Extract the token from the Authorization header
+    let token = match req.headers().get("Authorization") {
+        Some(value) => {
+            let parts: Vec<&str> = value.to_str().unwrap_or("").split_whitespace().collect();
+            if parts.len() == 2 && parts[0] == "Bearer" {
+                parts[1]
+            } else {
+                return HttpResponse::BadRequest().json(BaseResponse {
+                    code: 400,
+                    message: String::from("Invalid Authorization header format"),
+                });
+            }
+        }
+        None => {
+            return HttpResponse::Unauthorized().json(BaseResponse {
+                code: 401,
+                message: String::from("Authorization header missing"),
+            })
+        }
+    };
+
+    let sub = match verify_token_and_get_sub(token) {
+        Some(s) => s,
+        None => {
+            return HttpResponse::Unauthorized().json(BaseResponse {
+                code: 401,
+                message: String::from("Invalid token"),
+            })
+        }
+    };
+
+    // Parse the `sub` value
+    let parsed_values: Vec<&str> = sub.split(',').collect();
+    if parsed_values.len() != 3 {
+        return HttpResponse::InternalServerError().json(BaseResponse {
+            code: 500,
+            message: String::from("Invalid sub format in token"),
+        });
+    }
+    let role: &str = parsed_values[1];
+    let shop_id: i32 = parsed_values[2].parse().unwrap();
+
+    match reservation::get_reservation_by_id(reservation_id, &client)
+        .await
+        .filter(|r| role == "Admin" || r.shop_id == shop_id)
+    {
+        Some(r) => HttpResponse::Ok().json(DataResponse {
+            code: 200,
+            message: String::from("Reservation fetched successfully."),
+            data: Some(r),
+        }),
+        None => HttpResponse::NotFound().json(BaseResponse {
+            code: 404,
+            message: String::from("Reservation not found!"),
+        }),
+    }
+}
+
+#[put("/api/reservations/{reservation_id}")]
+pub async fn update_reservation(
+    req: HttpRequest,
+    path: web::Path<i32>,
+    body: web::Json<ReservationRequest>,
+    data: web::Data<Arc<Mutex<Client>>>,
+) -> HttpResponse {
+    let client = data.lock().await;
+    let reservation_id = path.into_inner();
+    // Extract the token from the Authorization header
+    let token = match req.headers().get("Authorization") {
+        Some(value) => {
+            let parts: Vec<&str> = value.to_str().unwrap_or("").split_whitespace().collect();
+            if parts.len() == 2 && parts[0] == "Bearer" {
+                parts[1]
+            } else {
+                return HttpResponse::BadRequest().json(BaseResponse {
+                    code: 400,
+                    message: String::from("Invalid Authorization header format"),
+                });
+            }
+        }
+        None => {
+            return HttpResponse::Unauthorized().json(BaseResponse {
+                code: 401,
+                message: String::from("Authorization header missing"),
+            })
+        }
+    };
+
+    let sub = match verify_token_and_get_sub(token) {
+        Some(s) => s,
+        None => {
+            return HttpResponse::Unauthorized().json(BaseResponse {
+                code: 401,
+                message: String::from("Invalid token"),
+            })
+        }
+    };
+
+    // Parse the `sub` value
+    let parsed_values: Vec<&str> = sub.split(',').collect();
+    if parsed_values.len() != 3 {
+        return HttpResponse::InternalServerError().json(BaseResponse {
+            code: 500,
+            message: String::from("Invalid sub format in token"),
+        });
+    }
+    let role: &str = parsed_values[1];
+    let shop_id: i32 = parsed_values[2].parse().unwrap();
+
+    let reservation_shop_id = match reservation::get_reservation_by_id(reservation_id, &client)
+        .await
+        .filter(|r| role == "Admin" || r.shop_id == shop_id)
+    {
+        Some(r) if r.status != "Booked" => {
+            return HttpResponse::BadRequest().json(BaseResponse {
+                code: 400,
+                message: String::from("Only booked reservations can be changed!"),
+            });
+        }
+        Some(r) => r.shop_id,
+        None => {
+            return HttpResponse::NotFound().json(BaseResponse {
+                code: 404,
+                message: String::from("Reservation not found!"),
+            });
+        }
+    };
+    if let Some(response) = validate_reservation(&body) {
+        return response;
+    }
+    if let Some(customer_id) = body.customer_id {
+        match customer::customer_visible_to_shop(customer_id, reservation_shop_id, &client).await {
+            Ok(true) => {}
+            Ok(false) => {
+                return HttpResponse::BadRequest().json(BaseResponse {
+                    code: 400,
+                    message: String::from("Customer not found!"),
+                });
+            }
+            Err(e) => {
+                eprintln!("Database error: {}", e);
+                return HttpResponse::InternalServerError().json(BaseResponse {
+                    code: 500,
+                    message: String::from("Something went wrong!"),
+                });
+            }
+        }
+    }
+    let table_id =
+        match assign_table(&body, reservation_shop_id, Some(reservation_id), &client).await {
+            Ok(id) => id,
+            Err(response) => return response,
+        };
+
+    match reservation::update_reservation(
+        reservation_id,
+        &body,
+        table_id,
+        body.duration_minutes.unwrap_or(DEFAULT_DURATION_MINUTES),
+        &client,
+    )
+    .await
+    {
+        Ok(()) => HttpResponse::Ok().json(BaseResponse {
+            code: 200,
+            message: String::from("Reservation updated successfully"),
+        }),
+        Err(e) => {
+            eprintln!("Reservation updating error: {}", e);
+            HttpResponse::InternalServerError().json(BaseResponse {
+                code: 500,
+                message: String::from("Error updating reservation!"),
+            })
+        }
+    }
+}
+
+#[put("/api/reservations/{reservation_id}/status")]
+pub async fn update_reservation_status(
+    req: HttpRequest,
+    path: web::Path<i32>,
+    body: web::Json<ReservationStatusRequest>,
+    data: web::Data<Arc<Mutex<Client>>>,
+) -> HttpResponse {
+    let client = data.lock().await;
+    let reservation_id = path.into_inner();
+    // Extract the token from the Authorization header
+    let token = match req.headers().get("Authorization") {
+        Some(value) => {
+            let parts: Vec<&str> = value.to_str().unwrap_or("").split_whitespace().collect();
+            if parts.len() == 2 && parts[0] == "Bearer" {
+                parts[1]
+            } else {
+                return HttpResponse::BadRequest().json(BaseResponse {
+                    code: 400,
+                    message: String::from("Invalid Authorization header format"),
+                });
+            }
+        }
+        None => {
+            return HttpResponse::Unauthorized().json(BaseResponse {
+                code: 401,
+                message: String::from("Authorization header missing"),
+            })
+        }
+    };
+
+    let sub = match verify_token_and_get_sub(token) {
+        Some(s) => s,
+        None => {
+            return HttpResponse::Unauthorized().json(BaseResponse {
+                code: 401,
+                message: String::from("Invalid token"),
+            })
+        }
+    };
+
+    // Parse the `sub` value
+    let parsed_values: Vec<&str> = sub.split(',').collect();
+    if parsed_values.len() != 3 {
+        return HttpResponse::InternalServerError().json(BaseResponse {
+            code: 500,
+            message: String::from("Invalid sub format in token"),
+        });
+    }
+    let role: &str = parsed_values[1];
+    let shop_id: i32 = parsed_values[2].parse().unwrap();
+
+    if !RESERVATION_STATUSES.contains(&body.status.as_str()) {
+        return HttpResponse::BadRequest().json(BaseResponse {
+            code: 400,
+            message: String::from(
+                "Please select a valid status: Booked, Seated, Completed, Canceled, or NoShow.",
+            ),
+        });
+    }
+    // A booked reservation can move to any status, a seated one can only be completed.
+    match reservation::get_reservation_by_id(reservation_id, &client)
+        .await
+        .filter(|r| role == "Admin" || r.shop_id == shop_id)
+    {
+        Some(r) if r.status == "Booked" || (r.status == "Seated" && body.status == "Completed") => {
+        }
+        Some(_) => {
+            return HttpResponse::BadRequest().json(BaseResponse {
+                code: 400,
+                message: String::from("Reservation status can't be changed!"),
+            });
+        }
+        None => {
+            return HttpResponse::NotFound().json(BaseResponse {
+                code: 404,
+                message: String::from("Reservation not found!"),
+            });
+        }
+    }
+
+    match reservation::update_reservation_status(reservation_id, &body.status, &client).await {
+        Ok(()) => HttpResponse::Ok().json(BaseResponse {
+            code: 200,
+            message: String::from("Reservation status updated successfully"),
+        }),
+        Err(e) => {
+            eprintln!("Reservation status updating error: {}", e);
+            HttpResponse::InternalServerError().json(BaseResponse {
+                code: 500,
+                message: String::from("Error updating reservation status!"),
+            })
+        }
+    }
+}
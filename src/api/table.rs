@@ -16,6 +16,7 @@ use crate::{
 #[derive(Deserialize)]
 pub struct GetTablesQuery {
     pub search: Option<String>,
+    pub floor_area_id: Option<i32>,
     pub page: Option<usize>,
     pub per_page: Option<usize>,
 }
@@ -73,6 +74,7 @@ pub async fn get_tables(
 
     match table::get_tables(
         &query.search,
+        query.floor_area_id,
         query.page,
         query.per_page,
         role,
@@ -107,7 +109,7 @@ pub async fn add_table(
     body: web::Json<TableRequest>,
     data: web::Data<Arc<Mutex<Client>>>,
 ) -> HttpResponse {
-    let client = data.lock().await;
+    let mut client = data.lock().await;
     // Extract the token from the Authorization header
     let token = match req.headers().get("Authorization") {
         Some(value) => {
@@ -164,6 +166,12 @@ pub async fn add_table(
             message: String::from("Table number must not be empty!"),
         });
     }
+    if body.capacity.is_some_and(|c| c < 1) {
+        return HttpResponse::BadRequest().json(BaseResponse {
+            code: 400,
+            message: String::from("Capacity must be greater than zero!"),
+        });
+    }
     // if body.qr_code.is_empty() {
     //     return HttpResponse::BadRequest().json(BaseResponse {
     //         code: 400,
@@ -178,7 +186,7 @@ pub async fn add_table(
                     message: String::from("Table Name already exists!"),
                 });
             }
-            match table::add_table(&body, &client).await {
+            match table::add_table(&body, &mut client).await {
                 Ok(()) => HttpResponse::Created().json(BaseResponse {
                     code: 201,
                     message: String::from("Table added successfully"),
@@ -278,7 +286,7 @@ pub async fn update_table(
     body: web::Json<TableRequest>,
     data: web::Data<Arc<Mutex<Client>>>,
 ) -> HttpResponse {
-    let client = data.lock().await;
+    let mut client = data.lock().await;
     let table_id = path.into_inner();
     // Extract the token from the Authorization header
     let token = match req.headers().get("Authorization") {
@@ -335,6 +343,12 @@ pub async fn update_table(
             message: String::from("Table number must not be empty!"),
         });
     }
+    if body.capacity.is_some_and(|c| c < 1) {
+        return HttpResponse::BadRequest().json(BaseResponse {
+            code: 400,
+            message: String::from("Capacity must be greater than zero!"),
+        });
+    }
     // if body.qr_code.is_empty() {
     //     return HttpResponse::BadRequest().json(BaseResponse {
     //         code: 400,
@@ -363,7 +377,7 @@ pub async fn update_table(
                     }
                 }
             }
-            match table::update_table(table_id, &body, &client).await {
+            match table::update_table(table_id, &body, &mut client).await {
                 Ok(()) => HttpResponse::Ok().json(BaseResponse {
                     code: 200,
                     message: String::from("Table updated successfully"),
@@ -390,7 +404,7 @@ pub async fn delete_table(
     path: web::Path<i32>,
     data: web::Data<Arc<Mutex<Client>>>,
 ) -> HttpResponse {
-    let client = data.lock().await;
+    let mut client = data.lock().await;
     let table_id = path.into_inner();
     // Extract the token from the Authorization header
     let token = match req.headers().get("Authorization") {
@@ -442,7 +456,7 @@ pub async fn delete_table(
     }
 
     match table::get_table_by_id(table_id, &client).await {
-        Some(_) => match table::delete_table(table_id, &client).await {
+        Some(_) => match table::delete_table(table_id, &mut client).await {
             Ok(()) => HttpResponse::Ok().json(BaseResponse {
                 code: 204,
                 message: String::from("Table deleted successfully"),
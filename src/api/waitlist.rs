@@ -0,0 +1,444 @@
+use std::sync::Arc;
+
+use actix_web::{get, post, put, web, HttpRequest, HttpResponse};
+use serde::Deserialize;
+use tokio::sync::Mutex;
+use tokio_postgres::Client;
+
+use crate::{
+    models::{
+        customer, shop, table,
+        waitlist::{self, WaitlistEntryRequest, WaitlistStatusRequest, WAITLIST_STATUSES},
+    },
+    utils::{
+        common_struct::{BaseResponse, DataResponse},
+        jwt::verify_token_and_get_sub,
+        validator::validate_mobile,
+    },
+};
+
+#[derive(Deserialize)]
+pub struct GetWaitlistQuery {
+    pub shop_id: Option<i32>,
+    pub status: Option<String>,
+}
+
+#[derive(Deserialize)]
+pub struct WaitEstimateQuery {
+    pub shop_id: Option<i32>,
+    pub party_size: i32,
+}
+
+#[get("/api/waitlist")]
+pub async fn get_waitlist(
+    req: HttpRequest,
+    query: web::Query<GetWaitlistQuery>,
+    data: web::Data<Arc<Mutex<Client>>>,
+) -> HttpResponse {
+    let client = data.lock().await;
+    // Extract the token from the Authorization header
+    let token = match req.headers().get("Authorization") {
+        Some(value) => {
+            let parts: Vec<&str> = value.to_str().unwrap_or("").split_whitespace().collect();
+            if parts.len() == 2 && parts[0] == "Bearer" {
+                parts[1]
+            } else {
+                return HttpResponse::BadRequest().json(BaseResponse {
+                    code: 400,
+                    message: String::from("Invalid Authorization header format"),
+                });
+            }
+        }
+        None => {
+            return HttpResponse::Unauthorized().json(BaseResponse {
+                code: 401,
+                message: String::from("Authorization header missing"),
+            })
+        }
+    };
+
+    let sub = match verify_token_and_get_sub(token) {
+        Some(s) => s,
+        None => {
+            return HttpResponse::Unauthorized().json(BaseResponse {
+                code: 401,
+                message: String::from("Invalid token"),
+            })
+        }
+    };
+
+    // Parse the `sub` value
+    let parsed_values: Vec<&str> = sub.split(',').collect();
+    if parsed_values.len() != 3 {
+        return HttpResponse::InternalServerError().json(BaseResponse {
+            code: 500,
+            message: String::from("Invalid sub format in token"),
+        });
+    }
+    let role: &str = parsed_values[1];
+    let shop_id: i32 = parsed_values[2].parse().unwrap();
+
+    let status = query.status.as_deref().unwrap_or("Waiting");
+    if !WAITLIST_STATUSES.contains(&status) {
+        return HttpResponse::BadRequest().json(BaseResponse {
+            code: 400,
+            message: String::from("Please select a valid status: Waiting, Seated, or Left."),
+        });
+    }
+    let filter_shop_id = if role == "Admin" {
+        query.shop_id
+    } else {
+        Some(shop_id)
+    };
+    match waitlist::get_waitlist(filter_shop_id, status, &client).await {
+        Ok(entries) => HttpResponse::Ok().json(DataResponse {
+            code: 200,
+            message: String::from("Successful."),
+            data: Some(entries),
+        }),
+        Err(err) => {
+            // Log the error message here
+            println!("Error retrieving waitlist: {:?}", err);
+            HttpResponse::InternalServerError().json(BaseResponse {
+                code: 500,
+                message: String::from("Error trying to read waitlist from database"),
+            })
+        }
+    }
+}
+
+#[get("/api/waitlist/estimate")]
+pub async fn get_wait_estimate(
+    req: HttpRequest,
+    query: web::Query<WaitEstimateQuery>,
+    data: web::Data<Arc<Mutex<Client>>>,
+) -> HttpResponse {
+    let client = data.lock().await;
+    // Extract the token from the Authorization header
+    let token = match req.headers().get("Authorization") {
+        Some(value) => {
+            let parts: Vec<&str> = value.to_str().unwrap_or("").split_whitespace().collect();
+            if parts.len() == 2 && parts[0] == "Bearer" {
+                parts[1]
+            } else {
+                return HttpResponse::BadRequest().json(BaseResponse {
+                    code: 400,
+                    message: String::from("Invalid Authorization header format"),
+                });
+            }
+        }
+        None => {
+            return HttpResponse::Unauthorized().json(BaseResponse {
+                code: 401,
+                message: String::from("Authorization header missing"),
+            })
+        }
+    };
+
+    let sub = match verify_token_and_get_sub(token) {
+        Some(s) => s,
+        None => {
+            return HttpResponse::Unauthorized().json(BaseResponse {
+                code: 401,
+                message: String::from("Invalid token"),
+            })
+        }
+    };
+
+    // Parse the `sub` value
+    let parsed_values: Vec<&str> = sub.split(',').collect();
+    if parsed_values.len() != 3 {
+        return HttpResponse::InternalServerError().json(BaseResponse {
+            code: 500,
+            message: String::from("Invalid sub format in token"),
+        });
+    }
+    let role: &str = parsed_values[1];
+    let mut shop_id: i32 = parsed_values[2].parse().unwrap();
+
+    if role == "Admin" {
+        shop_id = match query.shop_id {
+            Some(s) => s,
+            None => {
+                return HttpResponse::BadRequest().json(BaseResponse {
+                    code: 400,
+                    message: String::from("Shop Id must not be empty!"),
+                });
+            }
+        };
+    }
+    if query.party_size < 1 {
+        return HttpResponse::BadRequest().json(BaseResponse {
+            code: 400,
+            message: String::from("Party size must be greater than zero!"),
+        });
+    }
+    match waitlist::estimate_wait_minutes(shop_id, query.party_size, &client).await {
+        Ok(Some(minutes)) => HttpResponse::Ok().json(DataResponse {
+            code: 200,
+            message: String::from("Successful."),
+            data: Some(minutes),
+        }),
+        Ok(None) => HttpResponse::BadRequest().json(BaseResponse {
+            code: 400,
+            message: String::from("No table can seat the party!"),
+        }),
+        Err(err) => {
+            // Log the error message here
+            println!("Error estimating wait time: {:?}", err);
+            HttpResponse::InternalServerError().json(BaseResponse {
+                code: 500,
+                message: String::from("Error estimating wait time!"),
+            })
+        }
+    }
+}
+
+#[post("/api/waitlist")]
+pub async fn add_waitlist_entry(
+    req: HttpRequest,
+    body: web::Json<WaitlistEntryRequest>,
+    data: web::Data<Arc<Mutex<Client>>>,
+) -> HttpResponse {
+    let client = data.lock().await;
+    // Extract the token from the Authorization header
+    let token = match req.headers().get("Authorization") {
+        Some(value) => {
+            let parts: Vec<&str> = value.to_str().unwrap_or("").split_whitespace().collect();
+            if parts.len() == 2 && parts[0] == "Bearer" {
+                parts[1]
+            } else {
+                return HttpResponse::BadRequest().json(BaseResponse {
+                    code: 400,
+                    message: String::from("Invalid Authorization header format"),
+                });
+            }
+        }
+        None => {
+            return HttpResponse::Unauthorized().json(BaseResponse {
+                code: 401,
+                message: String::from("Authorization header missing"),
+            })
+        }
+    };
+
+    let sub = match verify_token_and_get_sub(token) {
+        Some(s) => s,
+        None => {
+            return HttpResponse::Unauthorized().json(BaseResponse {
+                code: 401,
+                message: String::from("Invalid token"),
+            })
+        }
+    };
+
+    // Parse the `sub` value
+    let parsed_values: Vec<&str> = sub.split(',').collect();
+    if parsed_values.len() != 3 {
+        return HttpResponse::InternalServerError().json(BaseResponse {
+            code: 500,
+            message: String::from("Invalid sub format in token"),
+        });
+    }
+    let role: &str = parsed_values[1];
+    let mut shop_id: i32 = parsed_values[2].parse().unwrap();
+
+    if role == "Admin" {
+        shop_id = match body.shop_id {
+            Some(s) => s,
+            None => {
+                return HttpResponse::BadRequest().json(BaseResponse {
+                    code: 400,
+                    message: String::from("Shop Id must not be empty!"),
+                });
+            }
+        };
+    }
+    if body.guest_name.trim().is_empty() {
+        return HttpResponse::BadRequest().json(BaseResponse {
+            code: 400,
+            message: String::from("Guest name must not be empty!"),
+        });
+    }
+    if !validate_mobile(&body.guest_phone) {
+        return HttpResponse::BadRequest().json(BaseResponse {
+            code: 400,
+            message: String::from("Invalid phone number!"),
+        });
+    }
+    if body.party_size < 1 {
+        return HttpResponse::BadRequest().json(BaseResponse {
+            code: 400,
+            message: String::from("Party size must be greater than zero!"),
+        });
+    }
+    if shop::get_shop_by_id(shop_id, &client).await.is_none() {
+        return HttpResponse::NotFound().json(BaseResponse {
+            code: 404,
+            message: String::from("Shop not found!"),
+        });
+    }
+    if let Some(customer_id) = body.customer_id {
+        match customer::customer_visible_to_shop(customer_id, shop_id, &client).await {
+            Ok(true) => {}
+            Ok(false) => {
+                return HttpResponse::BadRequest().json(BaseResponse {
+                    code: 400,
+                    message: String::from("Customer not found!"),
+                });
+            }
+            Err(e) => {
+                eprintln!("Database error: {}", e);
+                return HttpResponse::InternalServerError().json(BaseResponse {
+                    code: 500,
+                    message: String::from("Something went wrong!"),
+                });
+            }
+        }
+    }
+    let quoted_wait_minutes =
+        match waitlist::estimate_wait_minutes(shop_id, body.party_size, &client).await {
+            Ok(Some(minutes)) => Some(minutes),
+            Ok(None) => {
+                return HttpResponse::BadRequest().json(BaseResponse {
+                    code: 400,
+                    message: String::from("No table can seat the party!"),
+                });
+            }
+            Err(e) => {
+                eprintln!("Wait estimate error: {}", e);
+                return HttpResponse::InternalServerError().json(BaseResponse {
+                    code: 500,
+                    message: String::from("Error estimating wait time!"),
+                });
+            }
+        };
+
+    // The created entry is returned so the host can tell the party the quoted wait.
+    match waitlist::add_waitlist_entry(&body, shop_id, quoted_wait_minutes, &client).await {
+        Ok(id) => HttpResponse::Created().json(DataResponse {
+            code: 201,
+            message: String::from("Waitlist entry added successfully"),
+            data: waitlist::get_waitlist_entry_by_id(id, &client).await,
+        }),
+        Err(e) => {
+            eprintln!("Waitlist entry adding error: {}", e);
+            HttpResponse::InternalServerError().json(BaseResponse {
+                code: 500,
+                message: String::from("Error adding waitlist entry!"),
+            })
+        }
+    }
+}
+
+#[put("/api/waitlist/{waitlist_entry_id}/status")]
+pub async fn update_waitlist_status(
+    req: HttpRequest,
+    path: web::Path<i32>,
+    body: web::Json<WaitlistStatusRequest>,
+    data: web::Data<Arc<Mutex<Client>>>,
+) -> HttpResponse {
+    let client = data.lock().await;
+    let waitlist_entry_id = path.into_inner();
+    // Extract the token from the Authorization header
+    let token = match req.headers().get("Authorization") {
+        Some(value) => {
+            let parts: Vec<&str> = value.to_str().unwrap_or("").split_whitespace().collect();
+            if parts.len() == 2 && parts[0] == "Bearer" {
+                parts[1]
+            } else {
+                return HttpResponse::BadRequest().json(BaseResponse {
+                    code: 400,
+                    message: String::from("Invalid Authorization header format"),
+                });
+            }
+        }
+        None => {
+            return HttpResponse::Unauthorized().json(BaseResponse {
+                code: 401,
+                message: String::from("Authorization header missing"),
+            })
+        }
+    };
+
+    let sub = match verify_token_and_get_sub(token) {
+        Some(s) => s,
+        None => {
+            return HttpResponse::Unauthorized().json(BaseResponse {
+                code: 401,
+                message: String::from("Invalid token"),
+            })
+        }
+    };
+
+    // Parse the `sub` value
+    let parsed_values: Vec<&str> = sub.split(',').collect();
+    if parsed_values.len() != 3 {
+        return HttpResponse::InternalServerError().json(BaseResponse {
+            code: 500,
+            message: String::from("Invalid sub format in token"),
+        });
+    }
+    let role: &str = parsed_values[1];
+    let shop_id: i32 = parsed_values[2].parse().unwrap();
+
+    if body.status != "Seated" && body.status != "Left" {
+        return HttpResponse::BadRequest().json(BaseResponse {
+            code: 400,
+            message: String::from("Please select a valid status: Seated or Left."),
+        });
+    }
+    let entry_shop_id = match waitlist::get_waitlist_entry_by_id(waitlist_entry_id, &client)
+        .await
+        .filter(|w| role == "Admin" || w.shop_id == shop_id)
+    {
+        Some(w) if w.status != "Waiting" => {
+            return HttpResponse::BadRequest().json(BaseResponse {
+                code: 400,
+                message: String::from("Party is no longer waiting!"),
+            });
+        }
+        Some(w) => w.shop_id,
+        None => {
+            return HttpResponse::NotFound().json(BaseResponse {
+                code: 404,
+                message: String::from("Waitlist entry not found!"),
+            });
+        }
+    };
+    if body.status == "Seated" {
+        let table_id = match body.table_id {
+            Some(t) => t,
+            None => {
+                return HttpResponse::BadRequest().json(BaseResponse {
+                    code: 400,
+                    message: String::from("Table Id must not be empty!"),
+                });
+            }
+        };
+        if table::get_table_by_id(table_id, &client)
+            .await
+            .filter(|t| t.shop_id == entry_shop_id)
+            .is_none()
+        {
+            return HttpResponse::NotFound().json(BaseResponse {
+                code: 404,
+                message: String::from("Table not found!"),
+            });
+        }
+    }
+
+    match waitlist::update_waitlist_status(waitlist_entry_id, &body, &client).await {
+        Ok(()) => HttpResponse::Ok().json(BaseResponse {
+            code: 200,
+            message: String::from("Waitlist entry updated successfully"),
+        }),
+        Err(e) => {
+            eprintln!("Waitlist entry updating error: {}", e);
+            HttpResponse::InternalServerError().json(BaseResponse {
+                code: 500,
+                message: String::from("Error updating waitlist entry!"),
+            })
+        }
+    }
+}
@@ -0,0 +1,28 @@
+mod idempotency_key;
+mod ingredient_alert;
+mod push_notification;
+mod realtime;
+mod report_job;
+mod reservation_reminder;
+mod socket_event;
+mod webhook;
+
+use std::sync::Arc;
+
+use tokio::sync::Mutex;
+use tokio_postgres::Client;
+
+use crate::utils::{fcm::FcmConfig, realtime::RealtimeHub};
+
+pub fn init(client: Arc<Mutex<Client>>, hub: Arc<RealtimeHub>) {
+    tokio::spawn(idempotency_key::run(client.clone()));
+    tokio::spawn(ingredient_alert::run(client.clone()));
+    tokio::spawn(realtime::run(hub.clone()));
+    tokio::spawn(report_job::run(client.clone()));
+    tokio::spawn(reservation_reminder::run(client.clone()));
+    tokio::spawn(socket_event::run(client.clone(), hub));
+    tokio::spawn(webhook::run(client.clone()));
+    if let Some(config) = FcmConfig::from_env() {
+        tokio::spawn(push_notification::run(client.clone(), config));
+    }
+}
@@ -0,0 +1,28 @@
+use std::{sync::Arc, time::Duration};
+
+use tokio::sync::Mutex;
+use tokio_postgres::Client;
+
+use crate::models::idempotency_key;
+
+// Clears keys past their TTL every hour. Expired keys are already ignored, this only keeps the table small.
+pub async fn run(client: Arc<Mutex<Client>>) {
+    if let Err(err) =
+        idempotency_key::release_unfinished_idempotency_keys(&*client.lock().await).await
+    {
+        println!("Error releasing unfinished idempotency keys: {:?}", err);
+    }
+
+    let mut interval = tokio::time::interval(Duration::from_secs(3600));
+    loop {
+        interval.tick().await;
+        let result = idempotency_key::delete_expired_idempotency_keys(
+            idempotency_key::idempotency_key_ttl(),
+            &*client.lock().await,
+        )
+        .await;
+        if let Err(err) = result {
+            println!("Error deleting expired idempotency keys: {:?}", err);
+        }
+    }
+}
@@ -0,0 +1,28 @@
+use std::{env, sync::Arc, time::Duration};
+
+use tokio::sync::Mutex;
+use tokio_postgres::Client;
+
+use crate::models::ingredient_alert;
+
+pub async fn run(client: Arc<Mutex<Client>>) {
+    let interval_secs: u64 = env::var("INGREDIENT_ALERT_INTERVAL")
+        .unwrap_or(String::from("600"))
+        .parse()
+        .unwrap_or(600);
+    let mut interval = tokio::time::interval(Duration::from_secs(interval_secs));
+    loop {
+        interval.tick().await;
+        let result = ingredient_alert::refresh_ingredient_alerts(&*client.lock().await).await;
+        match result {
+            Ok(alerts) => {
+                if !alerts.is_empty() {
+                    ingredient_alert::notify_ingredient_alerts(&alerts, &client).await;
+                }
+            }
+            Err(err) => {
+                println!("Error refreshing ingredient alerts: {:?}", err);
+            }
+        }
+    }
+}
@@ -0,0 +1,86 @@
+use std::{env, sync::Arc, time::Duration};
+
+use tokio::sync::Mutex;
+use tokio_postgres::Client;
+
+use crate::{
+    models::push_notification,
+    utils::fcm::{FcmClient, FcmConfig, FcmError},
+};
+
+// Sends queued push notifications to every device of their user. Tokens FCM no longer knows are forgotten,
+// devices that failed are retried on the next tick, the ones that got it aren't sent it again.
+pub async fn run(client: Arc<Mutex<Client>>, config: FcmConfig) {
+    let interval_secs: u64 = env::var("PUSH_NOTIFICATION_INTERVAL")
+        .unwrap_or(String::from("5"))
+        .parse()
+        .unwrap_or(5);
+    let fcm = FcmClient::new(config);
+    let mut interval = tokio::time::interval(Duration::from_secs(interval_secs));
+    loop {
+        interval.tick().await;
+        let result =
+            push_notification::get_pending_push_notifications(50, &*client.lock().await).await;
+        let notifications = match result {
+            Ok(n) => n,
+            Err(err) => {
+                println!("Error retrieving pending push notifications: {:?}", err);
+                continue;
+            }
+        };
+        for notification in notifications {
+            let mut delivered = notification.delivered;
+            let mut error = None;
+            let mut invalid_tokens = vec![];
+            for token in &notification.tokens {
+                match fcm
+                    .send(
+                        token,
+                        &notification.title,
+                        &notification.body,
+                        &notification.data,
+                    )
+                    .await
+                {
+                    Ok(()) => {
+                        delivered = true;
+                        if let Err(err) = push_notification::record_push_delivery(
+                            notification.notification_id,
+                            token,
+                            &*client.lock().await,
+                        )
+                        .await
+                        {
+                            println!("Error recording push delivery: {:?}", err);
+                        }
+                    }
+                    Err(FcmError::InvalidToken) => invalid_tokens.push(token.clone()),
+                    Err(FcmError::Failed(err)) => error = Some(err),
+                }
+            }
+            let client = client.lock().await;
+            if !invalid_tokens.is_empty() {
+                if let Err(err) =
+                    push_notification::delete_device_tokens(&invalid_tokens, &client).await
+                {
+                    println!("Error deleting device tokens: {:?}", err);
+                }
+            }
+            let error = match (delivered, error) {
+                (_, Some(err)) => Some(err),
+                (true, None) => None,
+                (false, None) => Some(String::from("No registered device")),
+            };
+            if let Err(err) = push_notification::record_push_notification_result(
+                &notification,
+                delivered,
+                error,
+                &client,
+            )
+            .await
+            {
+                println!("Error recording push notification: {:?}", err);
+            }
+        }
+    }
+}
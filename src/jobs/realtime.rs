@@ -0,0 +1,11 @@
+use std::{sync::Arc, time::Duration};
+
+use crate::utils::realtime::RealtimeHub;
+
+pub async fn run(hub: Arc<RealtimeHub>) {
+    let mut interval = tokio::time::interval(Duration::from_secs(15));
+    loop {
+        interval.tick().await;
+        hub.ping();
+    }
+}
@@ -0,0 +1,34 @@
+use std::{env, sync::Arc, time::Duration};
+
+use tokio::sync::Mutex;
+use tokio_postgres::Client;
+
+use crate::models::report_job;
+
+pub async fn run(client: Arc<Mutex<Client>>) {
+    if let Err(err) = report_job::fail_interrupted_report_jobs(&*client.lock().await).await {
+        println!("Error failing interrupted report jobs: {:?}", err);
+    }
+
+    let interval_secs: u64 = env::var("REPORT_JOB_CLEANUP_INTERVAL")
+        .unwrap_or(String::from("3600"))
+        .parse()
+        .unwrap_or(3600);
+    let mut interval = tokio::time::interval(Duration::from_secs(interval_secs));
+    loop {
+        interval.tick().await;
+        let result = report_job::expire_report_jobs(&*client.lock().await).await;
+        match result {
+            Ok(paths) => {
+                for path in paths {
+                    if let Err(err) = tokio::fs::remove_file(&path).await {
+                        println!("Error deleting report file {}: {:?}", path, err);
+                    }
+                }
+            }
+            Err(err) => {
+                println!("Error expiring report jobs: {:?}", err);
+            }
+        }
+    }
+}
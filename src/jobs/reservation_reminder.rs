@@ -0,0 +1,26 @@
+use std::{env, sync::Arc, time::Duration};
+
+use tokio::sync::Mutex;
+use tokio_postgres::Client;
+
+use crate::models::reservation;
+
+pub async fn run(client: Arc<Mutex<Client>>) {
+    let interval_secs: u64 = env::var("RESERVATION_REMINDER_INTERVAL")
+        .unwrap_or(String::from("60"))
+        .parse()
+        .unwrap_or(60);
+    let minutes: i32 = env::var("RESERVATION_REMINDER_MINUTES")
+        .unwrap_or(String::from("30"))
+        .parse()
+        .unwrap_or(30);
+    let mut interval = tokio::time::interval(Duration::from_secs(interval_secs));
+    loop {
+        interval.tick().await;
+        let result =
+            reservation::send_reservation_reminders(minutes, &mut *client.lock().await).await;
+        if let Err(err) = result {
+            println!("Error sending reservation reminders: {:?}", err);
+        }
+    }
+}
@@ -0,0 +1,109 @@
+use std::{env, sync::Arc, time::Duration};
+
+use tokio::sync::Mutex;
+use tokio_postgres::{Client, Error};
+
+use crate::{
+    models::{
+        socket_event::{self, SocketEvent},
+        user,
+    },
+    utils::{
+        realtime::{RealtimeHub, Room},
+        socketio,
+    },
+};
+
+fn event_rooms(event: &SocketEvent) -> Vec<Room> {
+    if !event.rooms.is_empty() {
+        event.rooms.iter().map(|id| Room::User(*id)).collect()
+    } else if let Some(shop_id) = event.shop_id.filter(|_| !event.roles.is_empty()) {
+        event
+            .roles
+            .iter()
+            .map(|role| Room::ShopRole(shop_id, role.clone()))
+            .collect()
+    } else if let Some(shop_id) = event.shop_id {
+        vec![Room::Shop(shop_id), Room::Role(String::from("Admin"))]
+    } else {
+        vec![]
+    }
+}
+
+// instantio only has user rooms, so an event for some roles of a shop goes to the users who have them.
+async fn instantio_rooms(event: &SocketEvent, client: &Mutex<Client>) -> Result<Vec<i32>, Error> {
+    match event.shop_id {
+        Some(shop_id) if event.rooms.is_empty() && !event.roles.is_empty() => {
+            user::get_user_ids_by_shop_and_roles(shop_id, &event.roles, &*client.lock().await).await
+        }
+        _ => Ok(event.rooms.clone()),
+    }
+}
+
+// Emits queued socket events in the order they were written. A failed event stops the round so later events
+// don't overtake it; it is retried on the next tick until instantio accepts it.
+// `REALTIME_TRANSPORT` picks where events go: `instantio` (default), `native` for the `/api/events` stream, or `both`.
+pub async fn run(client: Arc<Mutex<Client>>, hub: Arc<RealtimeHub>) {
+    let interval_secs: u64 = env::var("SOCKET_EVENT_DISPATCH_INTERVAL")
+        .unwrap_or(String::from("2"))
+        .parse()
+        .unwrap_or(2);
+    let transport = env::var("REALTIME_TRANSPORT").unwrap_or(String::from("instantio"));
+    let mut interval = tokio::time::interval(Duration::from_secs(interval_secs));
+    loop {
+        interval.tick().await;
+        let result = socket_event::get_pending_socket_events(100, &*client.lock().await).await;
+        let events = match result {
+            Ok(e) => e,
+            Err(err) => {
+                println!("Error retrieving socket events: {:?}", err);
+                continue;
+            }
+        };
+        for event in events {
+            // Native connections get the event once, retries are only for instantio.
+            if transport != "instantio" && event.attempts == 0 {
+                hub.publish(&event.event, &event_rooms(&event), &event.payload);
+            }
+            let result = if transport == "native" {
+                Ok(())
+            } else {
+                match instantio_rooms(&event, &client).await {
+                    // A role with no users left in the shop has no one to send the event to.
+                    Ok(rooms) if rooms.is_empty() && !event.roles.is_empty() => Ok(()),
+                    Ok(rooms) => {
+                        socketio::emit(&event.namespace, &event.event, &rooms, Some(event.payload))
+                            .await
+                            .map(|_| ())
+                            .map_err(|e| e.to_string())
+                    }
+                    Err(err) => Err(err.to_string()),
+                }
+            };
+            match result {
+                Ok(()) => {
+                    println!("{} event sent successfully.", event.event);
+                    if let Err(err) =
+                        socket_event::delete_socket_event(event.event_id, &*client.lock().await)
+                            .await
+                    {
+                        println!("Error deleting socket event: {:?}", err);
+                    }
+                }
+                Err(err) => {
+                    println!("{:?}", err);
+                    if let Err(err) = socket_event::record_socket_event_failure(
+                        event.event_id,
+                        &err,
+                        &*client.lock().await,
+                    )
+                    .await
+                    {
+                        println!("Error recording socket event failure: {:?}", err);
+                    }
+                    break;
+                }
+            }
+        }
+    }
+}
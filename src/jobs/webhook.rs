@@ -0,0 +1,55 @@
+use std::{env, sync::Arc, time::Duration};
+
+use tokio::sync::Mutex;
+use tokio_postgres::Client;
+
+use crate::{models::webhook, utils::webhook::send_webhook};
+
+// Sends due webhook deliveries. The client is only locked to read and record deliveries, not while waiting
+// on subscribers.
+pub async fn run(client: Arc<Mutex<Client>>) {
+    let interval_secs: u64 = env::var("WEBHOOK_DISPATCH_INTERVAL")
+        .unwrap_or(String::from("10"))
+        .parse()
+        .unwrap_or(10);
+    let mut interval = tokio::time::interval(Duration::from_secs(interval_secs));
+    loop {
+        interval.tick().await;
+        let result = webhook::get_due_webhook_deliveries(50, &*client.lock().await).await;
+        let deliveries = match result {
+            Ok(d) => d,
+            Err(err) => {
+                println!("Error retrieving due webhook deliveries: {:?}", err);
+                continue;
+            }
+        };
+        for delivery in deliveries {
+            let (response_status, error) = match send_webhook(
+                &delivery.url,
+                &delivery.secret,
+                &delivery.event,
+                delivery.delivery_id,
+                &delivery.payload,
+            )
+            .await
+            {
+                Ok(status) if (200..300).contains(&status) => (Some(status as i32), None),
+                Ok(status) => (
+                    Some(status as i32),
+                    Some(format!("Subscriber responded with status {status}")),
+                ),
+                Err(err) => (None, Some(err.to_string())),
+            };
+            if let Err(err) = webhook::record_webhook_attempt(
+                &delivery,
+                response_status,
+                error,
+                &*client.lock().await,
+            )
+            .await
+            {
+                println!("Error recording webhook attempt: {:?}", err);
+            }
+        }
+    }
+}
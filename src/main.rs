@@ -8,8 +8,10 @@ use actix_web::{web, App, HttpServer};
 use dotenv::dotenv;
 use tokio::sync::Mutex;
 use tokio_postgres::NoTls;
+use utils::{idempotency::Idempotency, realtime::RealtimeHub};
 
 mod api;
+mod jobs;
 mod models;
 mod utils;
 
@@ -32,6 +34,9 @@ async fn main() -> std::io::Result<()> {
         }
     });
 
+    let hub = Arc::new(RealtimeHub::default());
+    jobs::init(client.clone(), hub.clone());
+
     HttpServer::new(move || {
         // let default_size = env::var("DEFAULT_REQUEST_SIZE")
         //     .unwrap_or_else(|_| "2097152".to_string())
@@ -43,8 +48,10 @@ async fn main() -> std::io::Result<()> {
             .allow_any_header()
             .max_age(3600);
         App::new()
+            .wrap(Idempotency)
             .wrap(cors)
             .app_data(web::Data::new(client.clone()))
+            .app_data(web::Data::from(hub.clone()))
             .configure(api::init)
             .service(fs::Files::new("/images", "./images").show_files_listing())
     })
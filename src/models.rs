@@ -1,11 +1,38 @@
+pub mod analytics;
+pub mod cash_shift;
 pub mod category;
+pub mod customer;
+pub mod delivery_platform;
 pub mod discount_type;
+pub mod floor_area;
+pub mod food_cost_report;
+pub mod gift_card;
+pub mod idempotency_key;
 pub mod ingredient_usage;
 pub mod item;
+pub mod item_ingredient;
+pub mod loyalty;
 pub mod order;
+pub mod push_notification;
 pub mod role;
+pub mod section;
 pub mod shop;
+pub mod socket_event;
+pub mod stock_movement;
+pub mod stock_take;
+pub mod stock_transfer;
+pub mod sync;
 pub mod table;
+pub mod tax_rule;
+pub mod tip_pool_rule;
 pub mod user;
+pub mod waiter_report;
+pub mod waitlist;
+pub mod webhook;
 pub mod ingredient;
+pub mod ingredient_alert;
+pub mod ingredient_lot;
 pub mod purchase;
+pub mod register_report;
+pub mod report_job;
+pub mod reservation;
@@ -0,0 +1,246 @@
+use chrono::{Duration, NaiveDate};
+use serde::Serialize;
+use tokio_postgres::{types::ToSql, Client, Error};
+
+const WEEKDAYS: [&str; 7] = [
+    "Monday",
+    "Tuesday",
+    "Wednesday",
+    "Thursday",
+    "Friday",
+    "Saturday",
+    "Sunday",
+];
+
+// Non-canceled orders of the shop in the period. Net sale is the item subtotal less the order discount,
+// tax is left out. Parameters: $1 shop id, $2 from date, $3 to date.
+const SALES_ORDERS: &str = "with sales as (
+    select o.id, o.waiter_id, o.table_id, o.order_type, o.payment_type, o.covers, o.created_at, o.completed_at,
+    coalesce((select sum(oi.price * oi.quantity) from order_items oi where oi.order_id = o.id), 0) - coalesce(o.discount, 0) as net_sale
+    from orders o
+    where o.deleted_at is null and o.status != 'Canceled' and o.shop_id = $1 and o.created_at::date between $2 and $3
+)";
+
+#[derive(Debug, Serialize)]
+pub struct SalesBreakdown {
+    pub key: String,
+    pub label: String,
+    pub order_count: i64,
+    pub net_sales: f64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct SalesPeriodAnalytics {
+    pub from_date: NaiveDate,
+    pub to_date: NaiveDate,
+    pub order_count: i64,
+    pub net_sales: f64,
+    pub average_ticket: f64,
+    pub covers: i64,
+    pub average_per_cover: f64,
+    pub average_turn_minutes: f64,
+    pub by_hour: Vec<SalesBreakdown>,
+    pub by_weekday: Vec<SalesBreakdown>,
+    pub by_waiter: Vec<SalesBreakdown>,
+    pub by_table: Vec<SalesBreakdown>,
+    pub by_category: Vec<SalesBreakdown>,
+    pub by_payment_type: Vec<SalesBreakdown>,
+    pub by_order_type: Vec<SalesBreakdown>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct SalesComparison {
+    pub order_count_change_percent: Option<f64>,
+    pub net_sales_change_percent: Option<f64>,
+    pub average_ticket_change_percent: Option<f64>,
+    pub covers_change_percent: Option<f64>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct SalesAnalytics {
+    pub shop_id: i32,
+    pub current: SalesPeriodAnalytics,
+    pub previous: SalesPeriodAnalytics,
+    pub comparison: SalesComparison,
+}
+
+fn round2(value: f64) -> f64 {
+    (value * 100.0).round() / 100.0
+}
+
+// No change percentage can be given against an empty previous period.
+fn change_percent(current: f64, previous: f64) -> Option<f64> {
+    if previous == 0.0 {
+        None
+    } else {
+        Some(round2((current - previous) / previous * 100.0))
+    }
+}
+
+// Every breakdown query returns the columns key, label, order_count and net_sales.
+async fn get_breakdown(
+    query: &str,
+    params: &[&(dyn ToSql + Sync)],
+    client: &Client,
+) -> Result<Vec<SalesBreakdown>, Error> {
+    let rows = client.query(&format!("{} {}", SALES_ORDERS, query), params).await?;
+    Ok(rows
+        .iter()
+        .map(|row| {
+            let net_sales: &str = row.get("net_sales");
+            SalesBreakdown {
+                key: row.get("key"),
+                label: row.get("label"),
+                order_count: row.get("order_count"),
+                net_sales: round2(net_sales.parse().unwrap()),
+            }
+        })
+        .collect())
+}
+
+async fn get_period_analytics(
+    from_date: NaiveDate,
+    to_date: NaiveDate,
+    shop_id: i32,
+    client: &Client,
+) -> Result<SalesPeriodAnalytics, Error> {
+    let params: [&(dyn ToSql + Sync); 3] = [&shop_id, &from_date, &to_date];
+
+    // Turn time is only known for completed dine-in orders, and the spend per cover only for orders with a cover count.
+    let row = client
+        .query_one(
+            &format!(
+                "{} select count(*) as order_count, coalesce(sum(net_sale), 0)::text as net_sales, coalesce(sum(covers), 0)::bigint as covers,
+                coalesce(sum(net_sale) filter (where covers is not null), 0)::text as covered_sales,
+                coalesce(avg(extract(epoch from completed_at - created_at) / 60) filter (where completed_at is not null and order_type = 'DineIn'), 0)::text as average_turn_minutes
+                from sales",
+                SALES_ORDERS
+            ),
+            &params,
+        )
+        .await?;
+    let order_count: i64 = row.get("order_count");
+    let net_sales: &str = row.get("net_sales");
+    let net_sales: f64 = net_sales.parse().unwrap();
+    let covers: i64 = row.get("covers");
+    let covered_sales: &str = row.get("covered_sales");
+    let covered_sales: f64 = covered_sales.parse().unwrap();
+    let average_turn_minutes: &str = row.get("average_turn_minutes");
+    let average_turn_minutes: f64 = average_turn_minutes.parse().unwrap();
+
+    let by_hour = get_breakdown(
+        "select extract(hour from created_at)::int::text as key, lpad(extract(hour from created_at)::int::text, 2, '0') || ':00' as label,
+        count(*) as order_count, sum(net_sale)::text as net_sales from sales group by 1, 2 order by min(extract(hour from created_at))",
+        &params,
+        client,
+    )
+    .await?;
+    let mut by_weekday = get_breakdown(
+        "select extract(isodow from created_at)::int::text as key, '' as label,
+        count(*) as order_count, sum(net_sale)::text as net_sales from sales group by 1 order by 1",
+        &params,
+        client,
+    )
+    .await?;
+    for weekday in by_weekday.iter_mut() {
+        let day: usize = weekday.key.parse().unwrap();
+        weekday.label = WEEKDAYS[day - 1].to_string();
+    }
+    let by_waiter = get_breakdown(
+        "select s.waiter_id::text as key, coalesce(u.name, '') as label, count(*) as order_count, sum(s.net_sale)::text as net_sales
+        from sales s left join users u on u.id = s.waiter_id group by 1, 2 order by sum(s.net_sale) desc",
+        &params,
+        client,
+    )
+    .await?;
+    let by_table = get_breakdown(
+        "select s.table_id::text as key, t.table_number as label, count(*) as order_count, sum(s.net_sale)::text as net_sales
+        from sales s join tables t on t.id = s.table_id group by 1, 2 order by sum(s.net_sale) desc",
+        &params,
+        client,
+    )
+    .await?;
+    // Category sales are item line totals, an order discount can't be split across categories.
+    let by_category = get_breakdown(
+        "select c.id::text as key, c.name as label, count(distinct s.id) as order_count, sum(oi.price * oi.quantity)::text as net_sales
+        from sales s join order_items oi on oi.order_id = s.id join item_categories ic on ic.item_id = oi.item_id
+        join categories c on c.id = ic.category_id group by 1, 2 order by sum(oi.price * oi.quantity) desc",
+        &params,
+        client,
+    )
+    .await?;
+    let by_payment_type = get_breakdown(
+        "select coalesce(payment_type, '') as key, coalesce(payment_type, '') as label, count(*) as order_count, sum(net_sale)::text as net_sales
+        from sales group by 1, 2 order by sum(net_sale) desc",
+        &params,
+        client,
+    )
+    .await?;
+    let by_order_type = get_breakdown(
+        "select order_type as key, order_type as label, count(*) as order_count, sum(net_sale)::text as net_sales
+        from sales group by 1, 2 order by sum(net_sale) desc",
+        &params,
+        client,
+    )
+    .await?;
+
+    Ok(SalesPeriodAnalytics {
+        from_date,
+        to_date,
+        order_count,
+        net_sales: round2(net_sales),
+        average_ticket: if order_count > 0 {
+            round2(net_sales / order_count as f64)
+        } else {
+            0.0
+        },
+        covers,
+        average_per_cover: if covers > 0 {
+            round2(covered_sales / covers as f64)
+        } else {
+            0.0
+        },
+        average_turn_minutes: round2(average_turn_minutes),
+        by_hour,
+        by_weekday,
+        by_waiter,
+        by_table,
+        by_category,
+        by_payment_type,
+        by_order_type,
+    })
+}
+
+// The period is compared with the one of the same length right before it, so a week is compared with the
+// previous week.
+pub async fn get_sales_analytics(
+    from_date: NaiveDate,
+    to_date: NaiveDate,
+    shop_id: i32,
+    client: &Client,
+) -> Result<SalesAnalytics, Error> {
+    let previous_to_date = from_date - Duration::days(1);
+    let previous_from_date = previous_to_date - (to_date - from_date);
+
+    let current = get_period_analytics(from_date, to_date, shop_id, client).await?;
+    let previous = get_period_analytics(previous_from_date, previous_to_date, shop_id, client).await?;
+    let comparison = SalesComparison {
+        order_count_change_percent: change_percent(
+            current.order_count as f64,
+            previous.order_count as f64,
+        ),
+        net_sales_change_percent: change_percent(current.net_sales, previous.net_sales),
+        average_ticket_change_percent: change_percent(
+            current.average_ticket,
+            previous.average_ticket,
+        ),
+        covers_change_percent: change_percent(current.covers as f64, previous.covers as f64),
+    };
+
+    Ok(SalesAnalytics {
+        shop_id,
+        current,
+        previous,
+        comparison,
+    })
+}
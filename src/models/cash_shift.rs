@@ -0,0 +1,413 @@
+use chrono::NaiveDateTime;
+use serde::{Deserialize, Serialize};
+use tokio_postgres::{types::ToSql, Client, Error, Transaction};
+
+use crate::utils::{
+    common_struct::PaginationResult,
+    sql::{generate_pagination_query, PaginationOptions},
+};
+
+pub const CASH_MOVEMENT_TYPES: [&str; 2] = ["PayIn", "PayOut"];
+
+#[derive(Debug, Serialize)]
+pub struct CashShift {
+    pub shift_id: i32,
+    pub shop_id: i32,
+    pub shop_name: String,
+    pub user_id: i32,
+    pub user_name: String,
+    pub register_name: String,
+    pub status: String,
+    pub opening_float: f64,
+    pub expected_cash: Option<f64>,
+    pub counted_cash: Option<f64>,
+    pub variance: Option<f64>,
+    pub notes: String,
+    pub closed_by_name: String,
+    pub opened_at: NaiveDateTime,
+    pub closed_at: Option<NaiveDateTime>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct CashMovement {
+    pub cash_movement_id: i32,
+    pub movement_type: String,
+    pub amount: f64,
+    pub reason: String,
+    pub created_by_name: String,
+    pub created_at: NaiveDateTime,
+}
+
+#[derive(Debug, Serialize)]
+pub struct CashShiftPayment {
+    pub payment_type: String,
+    pub order_count: i64,
+    pub amount: f64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct CashShiftSummary {
+    pub opening_float: f64,
+    pub cash_sales: f64,
+    pub cash_refunds: f64,
+    pub pay_ins: f64,
+    pub pay_outs: f64,
+    pub expected_cash: f64,
+    pub payments: Vec<CashShiftPayment>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct CashShiftDetail {
+    pub cash_shift: CashShift,
+    pub summary: CashShiftSummary,
+    pub movements: Vec<CashMovement>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct OpenCashShiftRequest {
+    pub opening_float: f64,
+    pub register_name: Option<String>,
+    pub shop_id: Option<i32>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CashMovementRequest {
+    pub movement_type: String,
+    pub amount: f64,
+    pub reason: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CloseCashShiftRequest {
+    pub counted_cash: f64,
+    pub notes: Option<String>,
+}
+
+const SHIFT_SELECT_COLUMNS: &str = "cs.shift_id, cs.shop_id, s.name as shop_name, cs.user_id, u.name as user_name,
+    coalesce(cs.register_name, '') as register_name, cs.status, cs.opening_float::text, cs.expected_cash::text, cs.counted_cash::text,
+    cs.variance::text, coalesce(cs.notes, '') as notes, coalesce(cu.name, '') as closed_by_name, cs.opened_at, cs.closed_at";
+
+const SHIFT_BASE_QUERY: &str =
+    "from cash_shifts cs join shops s on s.id = cs.shop_id join users u on u.id = cs.user_id
+    left join users cu on cu.id = cs.closed_by where true";
+
+fn parse_decimal(row: &tokio_postgres::Row, column: &str) -> Option<f64> {
+    let value: Option<&str> = row.get(column);
+    value.map(|v| v.parse().unwrap())
+}
+
+fn row_to_shift(row: &tokio_postgres::Row) -> CashShift {
+    CashShift {
+        shift_id: row.get("shift_id"),
+        shop_id: row.get("shop_id"),
+        shop_name: row.get("shop_name"),
+        user_id: row.get("user_id"),
+        user_name: row.get("user_name"),
+        register_name: row.get("register_name"),
+        status: row.get("status"),
+        opening_float: parse_decimal(row, "opening_float").unwrap_or(0.0),
+        expected_cash: parse_decimal(row, "expected_cash"),
+        counted_cash: parse_decimal(row, "counted_cash"),
+        variance: parse_decimal(row, "variance"),
+        notes: row.get("notes"),
+        closed_by_name: row.get("closed_by_name"),
+        opened_at: row.get("opened_at"),
+        closed_at: row.get("closed_at"),
+    }
+}
+
+pub async fn get_cash_shifts(
+    page: Option<usize>,
+    per_page: Option<usize>,
+    filter_shop_id: Option<i32>,
+    filter_user_id: Option<i32>,
+    status: &Option<String>,
+    client: &Client,
+) -> Result<PaginationResult<CashShift>, Error> {
+    let mut base_query = SHIFT_BASE_QUERY.to_string();
+    let mut params: Vec<Box<dyn ToSql + Sync>> = vec![];
+
+    if let Some(s) = filter_shop_id {
+        params.push(Box::new(s));
+        base_query = format!("{base_query} and cs.shop_id = ${}", params.len());
+    }
+    if let Some(u) = filter_user_id {
+        params.push(Box::new(u));
+        base_query = format!("{base_query} and cs.user_id = ${}", params.len());
+    }
+    if let Some(s) = status {
+        params.push(Box::new(s));
+        base_query = format!("{base_query} and cs.status = ${}", params.len());
+    }
+
+    let result = generate_pagination_query(PaginationOptions {
+        select_columns: SHIFT_SELECT_COLUMNS,
+        base_query: &base_query,
+        search_columns: vec![],
+        search: None,
+        order_options: Some("cs.opened_at desc"),
+        page,
+        per_page,
+    });
+
+    let params_slice: Vec<&(dyn ToSql + Sync)> = params.iter().map(AsRef::as_ref).collect();
+
+    let row = client.query_one(&result.count_query, &params_slice).await?;
+    let total: i64 = row.get("total");
+
+    let mut page_counts = 0;
+    let mut current_page = 0;
+    let mut limit = 0;
+    if let (Some(p), Some(pp)) = (page, per_page) {
+        current_page = p;
+        limit = pp;
+        page_counts = (total as f64 / limit as f64).ceil() as usize;
+    }
+
+    let shifts: Vec<CashShift> = client
+        .query(&result.query, &params_slice)
+        .await?
+        .iter()
+        .map(row_to_shift)
+        .collect();
+
+    Ok(PaginationResult {
+        data: shifts,
+        total,
+        page: current_page,
+        per_page: limit,
+        page_counts,
+    })
+}
+
+pub async fn get_cash_shift_by_id(shift_id: i32, client: &Client) -> Option<CashShift> {
+    match client
+        .query_one(
+            &format!("select {SHIFT_SELECT_COLUMNS} {SHIFT_BASE_QUERY} and cs.shift_id = $1"),
+            &[&shift_id],
+        )
+        .await
+    {
+        Ok(row) => Some(row_to_shift(&row)),
+        Err(_) => None,
+    }
+}
+
+pub async fn get_open_cash_shift(user_id: i32, client: &Client) -> Option<CashShift> {
+    match client
+        .query_one(
+            &format!(
+                "select {SHIFT_SELECT_COLUMNS} {SHIFT_BASE_QUERY} and cs.user_id = $1 and cs.status = 'Open'"
+            ),
+            &[&user_id],
+        )
+        .await
+    {
+        Ok(row) => Some(row_to_shift(&row)),
+        Err(_) => None,
+    }
+}
+
+// A cashier works one drawer at a time, and a named register is only used by one open shift.
+pub async fn open_shift_exists(
+    user_id: i32,
+    shop_id: i32,
+    register_name: &Option<String>,
+    client: &Client,
+) -> Result<bool, Error> {
+    let row = client
+        .query_one(
+            "select count(*) as total from cash_shifts where status = 'Open'
+            and (user_id = $1 or (shop_id = $2 and $3::text is not null and register_name = $3))",
+            &[&user_id, &shop_id, register_name],
+        )
+        .await?;
+    let total: i64 = row.get("total");
+    Ok(total > 0)
+}
+
+pub async fn open_cash_shift(
+    data: &OpenCashShiftRequest,
+    shop_id: i32,
+    user_id: i32,
+    client: &Client,
+) -> Result<i32, Error> {
+    let query = format!(
+        "insert into cash_shifts (shop_id, user_id, register_name, opening_float) values ($1, $2, $3, {}) returning shift_id",
+        data.opening_float
+    );
+    let row = client
+        .query_one(&query, &[&shop_id, &user_id, &data.register_name])
+        .await?;
+    Ok(row.get("shift_id"))
+}
+
+pub async fn add_cash_movement(
+    shift_id: i32,
+    data: &CashMovementRequest,
+    user_id: i32,
+    client: &Client,
+) -> Result<(), Error> {
+    let query = format!(
+        "insert into cash_movements (shift_id, movement_type, amount, reason, created_by) values ($1, $2, {}, $3, $4)",
+        data.amount
+    );
+    client
+        .execute(
+            &query,
+            &[&shift_id, &data.movement_type, &data.reason, &user_id],
+        )
+        .await?;
+    Ok(())
+}
+
+pub async fn get_cash_movements(
+    shift_id: i32,
+    client: &Client,
+) -> Result<Vec<CashMovement>, Error> {
+    let rows = client
+        .query(
+            "select m.cash_movement_id, m.movement_type, m.amount::text, coalesce(m.reason, '') as reason,
+            u.name as created_by_name, m.created_at
+            from cash_movements m join users u on u.id = m.created_by where m.shift_id = $1 order by m.created_at",
+            &[&shift_id],
+        )
+        .await?;
+    Ok(rows
+        .iter()
+        .map(|row| {
+            let amount: &str = row.get("amount");
+            CashMovement {
+                cash_movement_id: row.get("cash_movement_id"),
+                movement_type: row.get("movement_type"),
+                amount: amount.parse().unwrap(),
+                reason: row.get("reason"),
+                created_by_name: row.get("created_by_name"),
+                created_at: row.get("created_at"),
+            }
+        })
+        .collect())
+}
+
+// Expected cash in the drawer: the opening float, plus cash taken for the shift's payments and pay-ins,
+// less cash handed back for refunds made during the shift and pay-outs.
+pub async fn get_cash_shift_summary(
+    shift_id: i32,
+    opening_float: f64,
+    client: &Client,
+) -> Result<CashShiftSummary, Error> {
+    let paid = "coalesce((select sum(price * quantity) from order_items where order_id = o.id), 0) - o.discount + o.tax + o.rounding_adjustment + o.delivery_fee + o.tip";
+    let payment_rows = client
+        .query(
+            &format!(
+                "select coalesce(o.payment_type, 'CASH') as payment_type, count(*) as order_count, sum({paid})::text as amount
+                from orders o where o.shift_id = $1 and o.deleted_at is null group by 1 order by 1"
+            ),
+            &[&shift_id],
+        )
+        .await?;
+    let payments: Vec<CashShiftPayment> = payment_rows
+        .iter()
+        .map(|row| {
+            let amount: &str = row.get("amount");
+            CashShiftPayment {
+                payment_type: row.get("payment_type"),
+                order_count: row.get("order_count"),
+                amount: amount.parse().unwrap(),
+            }
+        })
+        .collect();
+
+    let row = client
+        .query_one(
+            &format!(
+                "select coalesce((select sum({paid}) from orders o where o.refund_shift_id = $1 and o.deleted_at is null
+                and coalesce(o.payment_type, 'CASH') = 'CASH'), 0)::text as cash_refunds,
+                coalesce((select sum(amount) from cash_movements where shift_id = $1 and movement_type = 'PayIn'), 0)::text as pay_ins,
+                coalesce((select sum(amount) from cash_movements where shift_id = $1 and movement_type = 'PayOut'), 0)::text as pay_outs"
+            ),
+            &[&shift_id],
+        )
+        .await?;
+    let cash_refunds: &str = row.get("cash_refunds");
+    let cash_refunds: f64 = cash_refunds.parse().unwrap();
+    let pay_ins: &str = row.get("pay_ins");
+    let pay_ins: f64 = pay_ins.parse().unwrap();
+    let pay_outs: &str = row.get("pay_outs");
+    let pay_outs: f64 = pay_outs.parse().unwrap();
+    let cash_sales: f64 = payments
+        .iter()
+        .filter(|p| p.payment_type == "CASH")
+        .map(|p| p.amount)
+        .sum();
+
+    Ok(CashShiftSummary {
+        opening_float,
+        cash_sales,
+        cash_refunds,
+        pay_ins,
+        pay_outs,
+        expected_cash: ((opening_float + cash_sales - cash_refunds + pay_ins - pay_outs) * 100.0)
+            .round()
+            / 100.0,
+        payments,
+    })
+}
+
+pub async fn close_cash_shift(
+    shift_id: i32,
+    expected_cash: f64,
+    data: &CloseCashShiftRequest,
+    user_id: i32,
+    client: &Client,
+) -> Result<(), Error> {
+    let query = format!(
+        "update cash_shifts set status = 'Closed', expected_cash = {}, counted_cash = {}, variance = {} - {},
+        notes = $1, closed_by = $2, closed_at = CURRENT_TIMESTAMP where shift_id = $3 and status = 'Open'",
+        expected_cash, data.counted_cash, data.counted_cash, expected_cash
+    );
+    client
+        .execute(&query, &[&data.notes, &user_id, &shift_id])
+        .await?;
+    Ok(())
+}
+
+// Payments and refunds go to the drawer of the user taking them, when that user has a shift open in the
+// order's shop. Runs in the transaction that updates the order, so a payment is never left outside the shift.
+pub async fn link_order_to_shift(
+    order_id: i32,
+    user_id: i32,
+    transaction: &Transaction<'_>,
+) -> Result<(), Error> {
+    transaction
+        .execute(
+            "update orders o set shift_id = cs.shift_id, updated_at = CURRENT_TIMESTAMP from cash_shifts cs
+            where o.id = $1 and cs.shop_id = o.shop_id and cs.user_id = $2 and cs.status = 'Open'
+            and o.status = 'Completed' and o.shift_id is null",
+            &[&order_id, &user_id],
+        )
+        .await?;
+    transaction
+        .execute(
+            "update orders o set refund_shift_id = cs.shift_id, updated_at = CURRENT_TIMESTAMP from cash_shifts cs
+            where o.id = $1 and cs.shop_id = o.shop_id and cs.user_id = $2 and cs.status = 'Open'
+            and o.status = 'Canceled' and o.completed_at is not null and o.refund_shift_id is null",
+            &[&order_id, &user_id],
+        )
+        .await?;
+    Ok(())
+}
+
+pub async fn get_cash_shift_detail(
+    cash_shift: CashShift,
+    client: &Client,
+) -> Result<CashShiftDetail, Error> {
+    let summary =
+        get_cash_shift_summary(cash_shift.shift_id, cash_shift.opening_float, client).await?;
+    let movements = get_cash_movements(cash_shift.shift_id, client).await?;
+    Ok(CashShiftDetail {
+        cash_shift,
+        summary,
+        movements,
+    })
+}
@@ -135,7 +135,9 @@ pub async fn update_category(
 ) -> Result<(), Box<dyn std::error::Error>> {
     client
         .execute(
-            "update categories set name = $1, description = $2, shop_id = $3 where id = $4",
+            "with moved as (insert into sync_shop_moves (entity, entity_id, shop_id)
+                select 'categories', id, shop_id from categories where id = $4 and shop_id is distinct from $3 and shop_id is not null)
+            update categories set name = $1, description = $2, shop_id = $3, updated_at = CURRENT_TIMESTAMP where id = $4",
             &[&data.name, &data.description, &data.shop_id, &category_id],
         )
         .await?;
@@ -0,0 +1,353 @@
+use chrono::NaiveDateTime;
+use serde::{Deserialize, Serialize};
+use tokio_postgres::{types::ToSql, Client, Error};
+
+use crate::{
+    models::socket_event,
+    utils::{
+        common_struct::PaginationResult,
+        sql::{generate_pagination_query, PaginationOptions},
+    },
+};
+
+// A customer without a shop is shared by all shops.
+#[derive(Debug, Serialize)]
+pub struct Customer {
+    pub customer_id: i32,
+    pub shop_id: Option<i32>,
+    pub shop_name: String,
+    pub name: String,
+    pub phone: String,
+    pub email: String,
+    pub notes: String,
+    pub allergies: String,
+    pub order_count: i64,
+    pub total_spent: f64,
+    pub last_order_at: Option<NaiveDateTime>,
+    pub created_at: NaiveDateTime,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CustomerRequest {
+    pub shop_id: Option<i32>,
+    pub name: String,
+    pub phone: String,
+    pub email: Option<String>,
+    pub notes: Option<String>,
+    pub allergies: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct CustomerOrder {
+    pub id: i32,
+    pub shop_name: String,
+    pub order_type: String,
+    pub table_number: String,
+    pub waiter_name: String,
+    pub status: String,
+    pub total: f64,
+    pub created_at: NaiveDateTime,
+}
+
+// Canceled orders don't count towards the customer's spending.
+const CUSTOMER_SELECT_COLUMNS: &str = "c.customer_id, c.shop_id, coalesce(s.name, '') as shop_name, c.name, c.phone, coalesce(c.email, '') as email,
+    coalesce(c.notes, '') as notes, coalesce(c.allergies, '') as allergies,
+    (select count(*) from orders o where o.customer_id = c.customer_id and o.deleted_at is null and o.status != 'Canceled') as order_count,
+    coalesce((select sum(coalesce((select sum(price * quantity) from order_items where order_id = o.id), 0) - o.discount + o.tax + o.rounding_adjustment + o.delivery_fee)
+        from orders o where o.customer_id = c.customer_id and o.deleted_at is null and o.status != 'Canceled'), 0)::text as total_spent,
+    (select max(o.created_at) from orders o where o.customer_id = c.customer_id and o.deleted_at is null) as last_order_at, c.created_at";
+
+const CUSTOMER_BASE_QUERY: &str =
+    "from customers c left join shops s on s.id = c.shop_id where c.deleted_at is null";
+
+fn row_to_customer(row: &tokio_postgres::Row) -> Customer {
+    let total_spent: &str = row.get("total_spent");
+    Customer {
+        customer_id: row.get("customer_id"),
+        shop_id: row.get("shop_id"),
+        shop_name: row.get("shop_name"),
+        name: row.get("name"),
+        phone: row.get("phone"),
+        email: row.get("email"),
+        notes: row.get("notes"),
+        allergies: row.get("allergies"),
+        order_count: row.get("order_count"),
+        total_spent: total_spent.parse().unwrap(),
+        last_order_at: row.get("last_order_at"),
+        created_at: row.get("created_at"),
+    }
+}
+
+// Shops see their own customers and the shared ones.
+pub async fn get_customers(
+    search: &Option<String>,
+    page: Option<usize>,
+    per_page: Option<usize>,
+    role: &str,
+    shop_id: i32,
+    client: &Client,
+) -> Result<PaginationResult<Customer>, Error> {
+    let mut base_query = CUSTOMER_BASE_QUERY.to_string();
+    let mut params: Vec<Box<dyn ToSql + Sync>> = vec![];
+
+    if role != "Admin" {
+        params.push(Box::new(shop_id));
+        base_query = format!(
+            "{base_query} and (c.shop_id is null or c.shop_id = ${})",
+            params.len()
+        );
+    }
+
+    let result = generate_pagination_query(PaginationOptions {
+        select_columns: CUSTOMER_SELECT_COLUMNS,
+        base_query: &base_query,
+        search_columns: vec!["c.name", "c.phone", "c.email", "c.customer_id::varchar"],
+        search: search.as_deref(),
+        order_options: Some("c.name"),
+        page,
+        per_page,
+    });
+
+    let params_slice: Vec<&(dyn ToSql + Sync)> = params.iter().map(AsRef::as_ref).collect();
+
+    let row = client.query_one(&result.count_query, &params_slice).await?;
+    let total: i64 = row.get("total");
+
+    let mut page_counts = 0;
+    let mut current_page = 0;
+    let mut limit = 0;
+    if let (Some(p), Some(pp)) = (page, per_page) {
+        current_page = p;
+        limit = pp;
+        page_counts = (total as f64 / limit as f64).ceil() as usize;
+    }
+
+    let customers: Vec<Customer> = client
+        .query(&result.query, &params_slice)
+        .await?
+        .iter()
+        .map(row_to_customer)
+        .collect();
+
+    Ok(PaginationResult {
+        data: customers,
+        total,
+        page: current_page,
+        per_page: limit,
+        page_counts,
+    })
+}
+
+pub async fn get_customer_by_id(customer_id: i32, client: &Client) -> Option<Customer> {
+    match client
+        .query_one(
+            &format!(
+                "select {CUSTOMER_SELECT_COLUMNS} {CUSTOMER_BASE_QUERY} and c.customer_id = $1"
+            ),
+            &[&customer_id],
+        )
+        .await
+    {
+        Ok(row) => Some(row_to_customer(&row)),
+        Err(_) => None,
+    }
+}
+
+pub async fn customer_visible_to_shop(
+    customer_id: i32,
+    shop_id: i32,
+    client: &Client,
+) -> Result<bool, Error> {
+    let row = client
+        .query_one(
+            "select count(*) as total from customers where deleted_at is null and customer_id = $1 and (shop_id is null or shop_id = $2)",
+            &[&customer_id, &shop_id],
+        )
+        .await?;
+    let total: i64 = row.get("total");
+    Ok(total > 0)
+}
+
+pub async fn customer_visible_to_order(
+    customer_id: i32,
+    order_id: i32,
+    client: &Client,
+) -> Result<bool, Error> {
+    let row = client
+        .query_one(
+            "select count(*) as total from customers c, orders o
+            where c.deleted_at is null and c.customer_id = $1 and o.id = $2 and (c.shop_id is null or c.shop_id = o.shop_id)",
+            &[&customer_id, &order_id],
+        )
+        .await?;
+    let total: i64 = row.get("total");
+    Ok(total > 0)
+}
+
+// A phone number identifies one customer among the ones a shop can see, shared customers included.
+pub async fn phone_exists(
+    phone: &str,
+    shop_id: Option<i32>,
+    exclude_customer_id: Option<i32>,
+    client: &Client,
+) -> Result<bool, Error> {
+    let row = client
+        .query_one(
+            "select count(*) as total from customers where deleted_at is null and phone = $1
+            and ($2::int is null or shop_id is null or shop_id = $2)
+            and ($3::int is null or customer_id != $3)",
+            &[&phone, &shop_id, &exclude_customer_id],
+        )
+        .await?;
+    let total: i64 = row.get("total");
+    Ok(total > 0)
+}
+
+pub async fn add_customer(
+    data: &CustomerRequest,
+    shop_id: Option<i32>,
+    client: &Client,
+) -> Result<i32, Error> {
+    let row = client
+        .query_one(
+            "insert into customers (shop_id, name, phone, email, notes, allergies) values ($1, $2, $3, $4, $5, $6) returning customer_id",
+            &[
+                &shop_id,
+                &data.name,
+                &data.phone,
+                &data.email,
+                &data.notes,
+                &data.allergies,
+            ],
+        )
+        .await?;
+    Ok(row.get("customer_id"))
+}
+
+pub async fn update_customer(
+    customer_id: i32,
+    data: &CustomerRequest,
+    client: &Client,
+) -> Result<(), Error> {
+    client
+        .execute(
+            "update customers set name = $1, phone = $2, email = $3, notes = $4, allergies = $5 where customer_id = $6",
+            &[
+                &data.name,
+                &data.phone,
+                &data.email,
+                &data.notes,
+                &data.allergies,
+                &customer_id,
+            ],
+        )
+        .await?;
+    Ok(())
+}
+
+pub async fn delete_customer(customer_id: i32, client: &Client) -> Result<(), Error> {
+    client
+        .execute(
+            "update customers set deleted_at = CURRENT_TIMESTAMP where customer_id = $1",
+            &[&customer_id],
+        )
+        .await?;
+    Ok(())
+}
+
+// Order history of a customer, limited to one shop's orders unless `shop_id` is None.
+pub async fn get_customer_orders(
+    customer_id: i32,
+    shop_id: Option<i32>,
+    page: Option<usize>,
+    per_page: Option<usize>,
+    client: &Client,
+) -> Result<PaginationResult<CustomerOrder>, Error> {
+    let mut base_query =
+        "from orders o left join tables t on t.id = o.table_id join shops s on s.id = o.shop_id
+        join users u on u.id = o.waiter_id where o.deleted_at is null and o.customer_id = $1"
+            .to_string();
+    let mut params: Vec<Box<dyn ToSql + Sync>> = vec![Box::new(customer_id)];
+    if let Some(s) = shop_id {
+        params.push(Box::new(s));
+        base_query = format!("{base_query} and o.shop_id = ${}", params.len());
+    }
+
+    let result = generate_pagination_query(PaginationOptions {
+        select_columns: "o.id, s.name as shop_name, o.order_type, coalesce(t.table_number, '') as table_number, u.name as waiter_name, o.status,
+        coalesce((select sum(price * quantity) from order_items where order_id = o.id), 0) - o.discount + o.tax + o.rounding_adjustment + o.delivery_fee as total_amount,
+        (coalesce((select sum(price * quantity) from order_items where order_id = o.id), 0) - o.discount + o.tax + o.rounding_adjustment + o.delivery_fee)::text as total, o.created_at",
+        base_query: &base_query,
+        search_columns: vec![],
+        search: None,
+        order_options: Some("o.created_at desc"),
+        page,
+        per_page,
+    });
+
+    let params_slice: Vec<&(dyn ToSql + Sync)> = params.iter().map(AsRef::as_ref).collect();
+
+    let row = client.query_one(&result.count_query, &params_slice).await?;
+    let total: i64 = row.get("total");
+
+    let mut page_counts = 0;
+    let mut current_page = 0;
+    let mut limit = 0;
+    if let (Some(p), Some(pp)) = (page, per_page) {
+        current_page = p;
+        limit = pp;
+        page_counts = (total as f64 / limit as f64).ceil() as usize;
+    }
+
+    let orders: Vec<CustomerOrder> = client
+        .query(&result.query, &params_slice)
+        .await?
+        .iter()
+        .map(|row| {
+            let total: &str = row.get("total");
+            CustomerOrder {
+                id: row.get("id"),
+                shop_name: row.get("shop_name"),
+                order_type: row.get("order_type"),
+                table_number: row.get("table_number"),
+                waiter_name: row.get("waiter_name"),
+                status: row.get("status"),
+                total: total.parse().unwrap(),
+                created_at: row.get("created_at"),
+            }
+        })
+        .collect();
+
+    Ok(PaginationResult {
+        data: orders,
+        total,
+        page: current_page,
+        per_page: limit,
+        page_counts,
+    })
+}
+
+pub async fn set_order_customer(
+    order_id: i32,
+    customer_id: Option<i32>,
+    client: &mut Client,
+) -> Result<(), Error> {
+    let transaction = client.transaction().await?;
+    let row = transaction
+        .query_one(
+            "update orders set customer_id = $1, updated_at = CURRENT_TIMESTAMP where id = $2 returning shop_id",
+            &[&customer_id, &order_id],
+        )
+        .await?;
+    socket_event::enqueue_socket_event(
+        &transaction,
+        "/pos",
+        "order-updated",
+        row.get("shop_id"),
+        &[],
+        &[],
+        serde_json::json!({"order_id": order_id, "customer_id": customer_id}),
+    )
+    .await?;
+    transaction.commit().await
+}
@@ -0,0 +1,411 @@
+use chrono::NaiveDateTime;
+use serde::{Deserialize, Serialize};
+use tokio_postgres::{types::ToSql, Client, Error};
+
+use crate::{
+    models::order::{self, NewOrder},
+    utils::{
+        common_struct::PaginationResult,
+        sql::{generate_pagination_query, PaginationOptions},
+    },
+};
+
+// Accepted: an order was created. Rejected: the payload couldn't be turned into an order, e.g. an unmapped SKU,
+// and the platform's retry is processed again.
+pub const PLATFORM_ORDER_STATUSES: [&str; 2] = ["Accepted", "Rejected"];
+
+// Orders coming from the platform are created under `waiter_id`.
+#[derive(Debug, Serialize)]
+pub struct DeliveryPlatform {
+    pub platform_id: i32,
+    pub shop_id: i32,
+    pub shop_name: String,
+    pub adapter: String,
+    pub name: String,
+    #[serde(skip_serializing)]
+    pub webhook_secret: String,
+    pub waiter_id: i32,
+    pub waiter_name: String,
+    pub is_active: bool,
+    pub mapped_item_count: i64,
+    pub created_at: NaiveDateTime,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct DeliveryPlatformRequest {
+    pub shop_id: Option<i32>,
+    pub adapter: String,
+    pub name: String,
+    // Kept as is on update when left out.
+    pub webhook_secret: Option<String>,
+    pub waiter_id: i32,
+    pub is_active: bool,
+}
+
+#[derive(Debug, Serialize)]
+pub struct PlatformItem {
+    pub external_sku: String,
+    pub item_id: i32,
+    pub item_name: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct PlatformItemRequest {
+    pub external_sku: String,
+    pub item_id: i32,
+}
+
+#[derive(Debug, Serialize)]
+pub struct PlatformOrder {
+    pub platform_order_id: i32,
+    pub external_order_id: String,
+    pub order_id: Option<i32>,
+    pub status: String,
+    pub error: String,
+    pub payload: String,
+    pub created_at: NaiveDateTime,
+    pub updated_at: NaiveDateTime,
+}
+
+const PLATFORM_SELECT_QUERY: &str = "select p.platform_id, p.shop_id, s.name as shop_name, p.adapter, p.name, p.webhook_secret,
+    p.waiter_id, u.name as waiter_name, p.is_active,
+    (select count(*) from delivery_platform_items pi where pi.platform_id = p.platform_id) as mapped_item_count, p.created_at
+    from delivery_platforms p join shops s on s.id = p.shop_id join users u on u.id = p.waiter_id where p.deleted_at is null";
+
+fn row_to_platform(row: &tokio_postgres::Row) -> DeliveryPlatform {
+    DeliveryPlatform {
+        platform_id: row.get("platform_id"),
+        shop_id: row.get("shop_id"),
+        shop_name: row.get("shop_name"),
+        adapter: row.get("adapter"),
+        name: row.get("name"),
+        webhook_secret: row.get("webhook_secret"),
+        waiter_id: row.get("waiter_id"),
+        waiter_name: row.get("waiter_name"),
+        is_active: row.get("is_active"),
+        mapped_item_count: row.get("mapped_item_count"),
+        created_at: row.get("created_at"),
+    }
+}
+
+pub async fn get_delivery_platforms(
+    shop_id: Option<i32>,
+    client: &Client,
+) -> Result<Vec<DeliveryPlatform>, Error> {
+    let rows = client
+        .query(
+            &format!(
+                "{PLATFORM_SELECT_QUERY} and ($1::int is null or p.shop_id = $1) order by p.shop_id, p.name"
+            ),
+            &[&shop_id],
+        )
+        .await?;
+    Ok(rows.iter().map(row_to_platform).collect())
+}
+
+pub async fn get_delivery_platform_by_id(
+    platform_id: i32,
+    client: &Client,
+) -> Option<DeliveryPlatform> {
+    match client
+        .query_one(
+            &format!("{PLATFORM_SELECT_QUERY} and p.platform_id = $1"),
+            &[&platform_id],
+        )
+        .await
+    {
+        Ok(row) => Some(row_to_platform(&row)),
+        Err(_) => None,
+    }
+}
+
+pub async fn add_delivery_platform(
+    data: &DeliveryPlatformRequest,
+    shop_id: i32,
+    client: &Client,
+) -> Result<i32, Error> {
+    let row = client
+        .query_one(
+            "insert into delivery_platforms (shop_id, adapter, name, webhook_secret, waiter_id, is_active)
+            values ($1, $2, $3, $4, $5, $6) returning platform_id",
+            &[
+                &shop_id,
+                &data.adapter,
+                &data.name,
+                &data.webhook_secret,
+                &data.waiter_id,
+                &data.is_active,
+            ],
+        )
+        .await?;
+    Ok(row.get("platform_id"))
+}
+
+pub async fn update_delivery_platform(
+    platform_id: i32,
+    data: &DeliveryPlatformRequest,
+    client: &Client,
+) -> Result<(), Error> {
+    client
+        .execute(
+            "update delivery_platforms set adapter = $1, name = $2, webhook_secret = coalesce($3, webhook_secret),
+            waiter_id = $4, is_active = $5 where platform_id = $6",
+            &[
+                &data.adapter,
+                &data.name,
+                &data.webhook_secret,
+                &data.waiter_id,
+                &data.is_active,
+                &platform_id,
+            ],
+        )
+        .await?;
+    Ok(())
+}
+
+pub async fn delete_delivery_platform(platform_id: i32, client: &Client) -> Result<(), Error> {
+    client
+        .execute(
+            "update delivery_platforms set deleted_at = CURRENT_TIMESTAMP where platform_id = $1",
+            &[&platform_id],
+        )
+        .await?;
+    Ok(())
+}
+
+pub async fn get_platform_items(
+    platform_id: i32,
+    client: &Client,
+) -> Result<Vec<PlatformItem>, Error> {
+    let rows = client
+        .query(
+            "select pi.external_sku, pi.item_id, i.name as item_name from delivery_platform_items pi
+            join items i on i.id = pi.item_id where pi.platform_id = $1 order by pi.external_sku",
+            &[&platform_id],
+        )
+        .await?;
+    Ok(rows
+        .iter()
+        .map(|row| PlatformItem {
+            external_sku: row.get("external_sku"),
+            item_id: row.get("item_id"),
+            item_name: row.get("item_name"),
+        })
+        .collect())
+}
+
+pub async fn items_belong_to_shop(
+    item_ids: &[i32],
+    shop_id: i32,
+    client: &Client,
+) -> Result<bool, Error> {
+    let mut item_ids = item_ids.to_vec();
+    item_ids.sort();
+    item_ids.dedup();
+    let row = client
+        .query_one(
+            "select count(*) as total from items where id = any($1) and shop_id = $2 and deleted_at is null",
+            &[&item_ids, &shop_id],
+        )
+        .await?;
+    let total: i64 = row.get("total");
+    Ok(total == item_ids.len() as i64)
+}
+
+// Replaces the whole SKU mapping of the platform.
+pub async fn set_platform_items(
+    platform_id: i32,
+    items: &[PlatformItemRequest],
+    client: &mut Client,
+) -> Result<(), Error> {
+    let transaction = client.transaction().await?;
+    transaction
+        .execute(
+            "delete from delivery_platform_items where platform_id = $1",
+            &[&platform_id],
+        )
+        .await?;
+    for item in items {
+        transaction
+            .execute(
+                "insert into delivery_platform_items (platform_id, external_sku, item_id) values ($1, $2, $3)",
+                &[&platform_id, &item.external_sku, &item.item_id],
+            )
+            .await?;
+    }
+    transaction.commit().await?;
+    Ok(())
+}
+
+// Item ids of the given SKUs that are mapped and whose item is still on sale.
+pub async fn map_external_skus(
+    platform_id: i32,
+    skus: &[String],
+    client: &Client,
+) -> Result<Vec<(String, i32)>, Error> {
+    let rows = client
+        .query(
+            "select pi.external_sku, pi.item_id from delivery_platform_items pi join items i on i.id = pi.item_id
+            where pi.platform_id = $1 and pi.external_sku = any($2) and i.deleted_at is null",
+            &[&platform_id, &skus],
+        )
+        .await?;
+    Ok(rows
+        .iter()
+        .map(|row| (row.get("external_sku"), row.get("item_id")))
+        .collect())
+}
+
+const PLATFORM_ORDER_COLUMNS: &str =
+    "platform_order_id, external_order_id, order_id, status, coalesce(error, '') as error,
+    payload, created_at, updated_at";
+
+fn row_to_platform_order(row: &tokio_postgres::Row) -> PlatformOrder {
+    PlatformOrder {
+        platform_order_id: row.get("platform_order_id"),
+        external_order_id: row.get("external_order_id"),
+        order_id: row.get("order_id"),
+        status: row.get("status"),
+        error: row.get("error"),
+        payload: row.get("payload"),
+        created_at: row.get("created_at"),
+        updated_at: row.get("updated_at"),
+    }
+}
+
+pub async fn get_platform_order(
+    platform_id: i32,
+    external_order_id: &str,
+    client: &Client,
+) -> Option<PlatformOrder> {
+    match client
+        .query_one(
+            &format!(
+                "select {PLATFORM_ORDER_COLUMNS} from delivery_platform_orders where platform_id = $1 and external_order_id = $2"
+            ),
+            &[&platform_id, &external_order_id],
+        )
+        .await
+    {
+        Ok(row) => Some(row_to_platform_order(&row)),
+        Err(_) => None,
+    }
+}
+
+// Records what became of a webhook delivery. A platform retrying an order overwrites its earlier rejection.
+pub async fn save_platform_order(
+    platform_id: i32,
+    external_order_id: &str,
+    order_id: Option<i32>,
+    error: Option<&str>,
+    payload: &str,
+    client: &Client,
+) -> Result<(), Error> {
+    let status = if order_id.is_some() {
+        "Accepted"
+    } else {
+        "Rejected"
+    };
+    client
+        .execute(
+            "insert into delivery_platform_orders (platform_id, external_order_id, order_id, status, error, payload)
+            values ($1, $2, $3, $4, $5, $6)
+            on conflict (platform_id, external_order_id) do update set order_id = excluded.order_id,
+            status = excluded.status, error = excluded.error, payload = excluded.payload, updated_at = CURRENT_TIMESTAMP",
+            &[
+                &platform_id,
+                &external_order_id,
+                &order_id,
+                &status,
+                &error,
+                &payload,
+            ],
+        )
+        .await?;
+    Ok(())
+}
+
+// Creates the order of a webhook delivery and records it as accepted in one transaction. Returns None, with no
+// order created, when a concurrent delivery of the same platform order was accepted first.
+pub async fn accept_platform_order(
+    platform_id: i32,
+    waiter_id: i32,
+    shop_id: i32,
+    external_order_id: &str,
+    payload: &str,
+    order: NewOrder,
+    client: &mut Client,
+) -> Result<Option<i32>, Box<dyn std::error::Error>> {
+    let transaction = client.transaction().await?;
+    let order_id = order::insert_order(waiter_id, shop_id, order, &transaction).await?;
+    // A delivery racing this one waits on the platform order row until this transaction ends.
+    let accepted = transaction
+        .query_opt(
+            "insert into delivery_platform_orders (platform_id, external_order_id, order_id, status, payload)
+            values ($1, $2, $3, 'Accepted', $4)
+            on conflict (platform_id, external_order_id) do update set order_id = excluded.order_id,
+            status = excluded.status, error = null, payload = excluded.payload, updated_at = CURRENT_TIMESTAMP
+            where delivery_platform_orders.status <> 'Accepted' returning platform_order_id",
+            &[&platform_id, &external_order_id, &order_id, &payload],
+        )
+        .await?;
+    if accepted.is_none() {
+        return Ok(None);
+    }
+    transaction.commit().await?;
+    Ok(Some(order_id))
+}
+
+pub async fn get_platform_orders(
+    platform_id: i32,
+    status: &Option<String>,
+    page: Option<usize>,
+    per_page: Option<usize>,
+    client: &Client,
+) -> Result<PaginationResult<PlatformOrder>, Error> {
+    let mut base_query = "from delivery_platform_orders where platform_id = $1".to_string();
+    let mut params: Vec<Box<dyn ToSql + Sync>> = vec![Box::new(platform_id)];
+    if let Some(s) = status {
+        params.push(Box::new(s));
+        base_query = format!("{base_query} and status = ${}", params.len());
+    }
+
+    let result = generate_pagination_query(PaginationOptions {
+        select_columns: PLATFORM_ORDER_COLUMNS,
+        base_query: &base_query,
+        search_columns: vec![],
+        search: None,
+        order_options: Some("updated_at desc"),
+        page,
+        per_page,
+    });
+
+    let params_slice: Vec<&(dyn ToSql + Sync)> = params.iter().map(AsRef::as_ref).collect();
+
+    let row = client.query_one(&result.count_query, &params_slice).await?;
+    let total: i64 = row.get("total");
+
+    let mut page_counts = 0;
+    let mut current_page = 0;
+    let mut limit = 0;
+    if let (Some(p), Some(pp)) = (page, per_page) {
+        current_page = p;
+        limit = pp;
+        page_counts = (total as f64 / limit as f64).ceil() as usize;
+    }
+
+    let orders: Vec<PlatformOrder> = client
+        .query(&result.query, &params_slice)
+        .await?
+        .iter()
+        .map(row_to_platform_order)
+        .collect();
+
+    Ok(PaginationResult {
+        data: orders,
+        total,
+        page: current_page,
+        per_page: limit,
+        page_counts,
+    })
+}
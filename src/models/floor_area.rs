@@ -0,0 +1,140 @@
+use serde::{Deserialize, Serialize};
+use tokio_postgres::{Client, Error};
+
+use crate::models::socket_event;
+
+#[derive(Debug, Serialize)]
+pub struct FloorArea {
+    pub floor_area_id: i32,
+    pub shop_id: i32,
+    pub name: String,
+    pub sort_order: i32,
+    pub table_count: i64,
+    pub seats: i64,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct FloorAreaRequest {
+    pub shop_id: Option<i32>,
+    pub name: String,
+    pub sort_order: i32,
+}
+
+const FLOOR_AREA_SELECT_QUERY: &str = "select fa.floor_area_id, fa.shop_id, fa.name, fa.sort_order,
+    (select count(*) from tables t where t.floor_area_id = fa.floor_area_id and t.deleted_at is null) as table_count,
+    (select coalesce(sum(t.capacity), 0) from tables t where t.floor_area_id = fa.floor_area_id and t.deleted_at is null) as seats
+    from floor_areas fa where fa.deleted_at is null";
+
+fn row_to_floor_area(row: &tokio_postgres::Row) -> FloorArea {
+    FloorArea {
+        floor_area_id: row.get("floor_area_id"),
+        shop_id: row.get("shop_id"),
+        name: row.get("name"),
+        sort_order: row.get("sort_order"),
+        table_count: row.get("table_count"),
+        seats: row.get("seats"),
+    }
+}
+
+pub async fn get_floor_areas(
+    shop_id: Option<i32>,
+    client: &Client,
+) -> Result<Vec<FloorArea>, Error> {
+    let rows = client
+        .query(
+            &format!(
+                "{FLOOR_AREA_SELECT_QUERY} and ($1::int is null or fa.shop_id = $1) order by fa.shop_id, fa.sort_order, fa.name"
+            ),
+            &[&shop_id],
+        )
+        .await?;
+    Ok(rows.iter().map(row_to_floor_area).collect())
+}
+
+pub async fn get_floor_area_by_id(floor_area_id: i32, client: &Client) -> Option<FloorArea> {
+    match client
+        .query_one(
+            &format!("{FLOOR_AREA_SELECT_QUERY} and fa.floor_area_id = $1"),
+            &[&floor_area_id],
+        )
+        .await
+    {
+        Ok(row) => Some(row_to_floor_area(&row)),
+        Err(_) => None,
+    }
+}
+
+pub async fn floor_area_name_exists(
+    name: &str,
+    shop_id: i32,
+    exclude_floor_area_id: Option<i32>,
+    client: &Client,
+) -> Result<bool, Error> {
+    let row = client
+        .query_one(
+            "select count(*) as total from floor_areas where deleted_at is null and lower(name) = lower($1)
+            and shop_id = $2 and ($3::int is null or floor_area_id != $3)",
+            &[&name, &shop_id, &exclude_floor_area_id],
+        )
+        .await?;
+    let total: i64 = row.get("total");
+    Ok(total > 0)
+}
+
+pub async fn add_floor_area(
+    data: &FloorAreaRequest,
+    shop_id: i32,
+    client: &Client,
+) -> Result<i32, Error> {
+    let row = client
+        .query_one(
+            "insert into floor_areas (shop_id, name, sort_order) values ($1, $2, $3) returning floor_area_id",
+            &[&shop_id, &data.name, &data.sort_order],
+        )
+        .await?;
+    Ok(row.get("floor_area_id"))
+}
+
+pub async fn update_floor_area(
+    floor_area_id: i32,
+    data: &FloorAreaRequest,
+    client: &Client,
+) -> Result<(), Error> {
+    client
+        .execute(
+            "update floor_areas set name = $1, sort_order = $2 where floor_area_id = $3",
+            &[&data.name, &data.sort_order, &floor_area_id],
+        )
+        .await?;
+    Ok(())
+}
+
+// The area's tables stay on the floor plan as unassigned.
+pub async fn delete_floor_area(floor_area_id: i32, client: &mut Client) -> Result<(), Error> {
+    let transaction = client.transaction().await?;
+    let row = transaction
+        .query_one(
+            "update floor_areas set deleted_at = CURRENT_TIMESTAMP where floor_area_id = $1 returning shop_id",
+            &[&floor_area_id],
+        )
+        .await?;
+    let shop_id: i32 = row.get("shop_id");
+    transaction
+        .execute(
+            "update tables set floor_area_id = null, updated_at = CURRENT_TIMESTAMP where floor_area_id = $1",
+            &[&floor_area_id],
+        )
+        .await?;
+    socket_event::enqueue_socket_event(
+        &transaction,
+        "/pos",
+        "floor-plan-updated",
+        Some(shop_id),
+        &[],
+        &[],
+        serde_json::json!({"shop_id": shop_id}),
+    )
+    .await?;
+    transaction.commit().await?;
+    Ok(())
+}
@@ -0,0 +1,307 @@
+use chrono::NaiveDate;
+use rust_xlsxwriter::*;
+use serde::Serialize;
+use std::{collections::BTreeMap, env};
+
+use simple_pdf_generator::{Asset, AssetType, PrintOptions};
+use simple_pdf_generator_derive::PdfTemplate;
+
+#[derive(Serialize)]
+pub struct FoodCostReportData {
+    item_id: i32,
+    item_name: String,
+    quantity: i32,
+    netsale: f64,
+    unit_cost: f64,
+    food_cost: f64,
+    cost_percent: f64,
+    gross_margin: f64,
+}
+
+#[derive(Serialize)]
+pub struct FoodCostCategoryData {
+    category_id: i32,
+    category_name: String,
+    quantity: i32,
+    netsale: f64,
+    food_cost: f64,
+    cost_percent: f64,
+    gross_margin: f64,
+}
+
+#[derive(PdfTemplate, Serialize)]
+pub struct FoodCostReportSummaryData {
+    date_str: String,
+    shop_name: String,
+    total_quantity: i32,
+    total_netsale: f64,
+    total_food_cost: f64,
+    total_cost_percent: f64,
+    total_gross_margin: f64,
+    #[PdfTableData]
+    data_list: Vec<FoodCostReportData>,
+    #[PdfTableData]
+    category_list: Vec<FoodCostCategoryData>,
+}
+
+fn round2(value: f64) -> f64 {
+    (value * 100.0).round() / 100.0
+}
+
+fn cost_percent(food_cost: f64, netsale: f64) -> f64 {
+    if netsale == 0.0 {
+        0.0
+    } else {
+        round2(food_cost / netsale * 100.0)
+    }
+}
+
+// Theoretical food cost: what the items sold should have cost according to their recipes, with every
+// recipe line priced at the ingredient's weighted average purchase price. Items without a recipe cost 0.
+// An item listed under several categories counts towards each of them.
+pub async fn get_food_cost_report(
+    from_date: NaiveDate,
+    to_date: NaiveDate,
+    shop_id: i32,
+    shop_name: String,
+    client: &tokio_postgres::Client,
+) -> Result<FoodCostReportSummaryData, tokio_postgres::Error> {
+    let date_str = if !from_date.eq(&to_date) {
+        format!("From {} To {}", from_date, to_date)
+    } else {
+        format!("{}", to_date)
+    };
+    let query = format!(
+        "with item_costs as (
+        select r.item_id, sum(r.quantity * coalesce((select sum(pd.quantity_purchased * pd.buying_price_per_unit) / nullif(sum(pd.quantity_purchased), 0)
+            from purchase_details pd join purchases p on p.purchase_id = pd.purchase_id
+            where pd.ingredient_id = r.ingredient_id and pd.deleted_at is null and p.deleted_at is null), 0)) as unit_cost
+        from item_ingredients r where r.deleted_at is null group by r.item_id
+    )
+    select oi.item_id, i.name, sum(oi.quantity)::text as quantity,
+    (sum(oi.price*oi.quantity))::text as netsale,
+    coalesce(c.unit_cost, 0)::text as unit_cost,
+    sum(oi.price*oi.quantity) as netsaleorder
+    from orders o
+    join order_items oi on o.id = oi.order_id
+    join items i on i.id = oi.item_id
+    left join item_costs c on c.item_id = oi.item_id
+    where DATE_TRUNC('day', o.created_at)>='{}'
+    and DATE_TRUNC('day', o.created_at)<='{}'
+    and o.shop_id=$1
+    group by oi.item_id, i.name, c.unit_cost
+    order by netsaleorder desc",
+        &from_date, &to_date
+    );
+    let mut total_netsale: f64 = 0.0;
+    let mut total_food_cost: f64 = 0.0;
+    let mut total_quantity: i32 = 0;
+    let item_rows = client.query(&query, &[&shop_id]).await?;
+    let data_list: Vec<FoodCostReportData> = item_rows
+        .iter()
+        .map(|row| {
+            let netsale: &str = row.get("netsale");
+            let netsale: f64 = netsale.parse().unwrap();
+            let unit_cost: &str = row.get("unit_cost");
+            let unit_cost: f64 = unit_cost.parse().unwrap();
+            let quantity: &str = row.get("quantity");
+            let quantity: i32 = quantity.parse().unwrap();
+            let food_cost = unit_cost * quantity as f64;
+            total_netsale += netsale;
+            total_food_cost += food_cost;
+            total_quantity += quantity;
+            FoodCostReportData {
+                item_id: row.get("item_id"),
+                item_name: row.get("name"),
+                quantity,
+                netsale,
+                unit_cost: round2(unit_cost),
+                food_cost: round2(food_cost),
+                cost_percent: cost_percent(food_cost, netsale),
+                gross_margin: round2(netsale - food_cost),
+            }
+        })
+        .collect();
+
+    let item_ids: Vec<i32> = data_list.iter().map(|d| d.item_id).collect();
+    let category_rows = client
+        .query(
+            "select ic.item_id, c.id as category_id, c.name as category_name from item_categories ic
+            join categories c on c.id = ic.category_id where ic.item_id = any($1) and c.deleted_at is null",
+            &[&item_ids],
+        )
+        .await?;
+    let mut categories: BTreeMap<i32, FoodCostCategoryData> = BTreeMap::new();
+    for row in &category_rows {
+        let item_id: i32 = row.get("item_id");
+        let Some(item) = data_list.iter().find(|d| d.item_id == item_id) else {
+            continue;
+        };
+        let category_id: i32 = row.get("category_id");
+        let category = categories
+            .entry(category_id)
+            .or_insert_with(|| FoodCostCategoryData {
+                category_id,
+                category_name: row.get("category_name"),
+                quantity: 0,
+                netsale: 0.0,
+                food_cost: 0.0,
+                cost_percent: 0.0,
+                gross_margin: 0.0,
+            });
+        category.quantity += item.quantity;
+        category.netsale += item.netsale;
+        category.food_cost += item.food_cost;
+    }
+    let category_list: Vec<FoodCostCategoryData> = categories
+        .into_values()
+        .map(|mut c| {
+            c.cost_percent = cost_percent(c.food_cost, c.netsale);
+            c.gross_margin = round2(c.netsale - c.food_cost);
+            c.netsale = round2(c.netsale);
+            c.food_cost = round2(c.food_cost);
+            c
+        })
+        .collect();
+
+    let data = FoodCostReportSummaryData {
+        date_str,
+        shop_name,
+        total_quantity,
+        total_netsale: round2(total_netsale),
+        total_food_cost: round2(total_food_cost),
+        total_cost_percent: cost_percent(total_food_cost, total_netsale),
+        total_gross_margin: round2(total_netsale - total_food_cost),
+        data_list,
+        category_list,
+    };
+    Ok(data)
+}
+
+pub async fn write_food_cost_report_pdf(
+    data: &FoodCostReportSummaryData,
+    file_path: &str,
+) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let html_path = env::current_dir()
+        .unwrap()
+        .join("src/template/food-cost-report.html");
+
+    let assets = [Asset {
+        path: env::current_dir()
+            .unwrap()
+            .join("src/template/css/style.css"),
+        r#type: AssetType::Style,
+    }];
+
+    let print_options = PrintOptions {
+        paper_width: Some(210.0),
+        paper_height: Some(297.0),
+        margin_top: Some(10.0),
+        margin_bottom: Some(10.0),
+        margin_left: Some(10.0),
+        margin_right: Some(10.0),
+        ..PrintOptions::default()
+    };
+    let content = data
+        .generate_pdf(html_path, &assets, &print_options)
+        .await
+        .map_err(|e| e.to_string())?;
+    tokio::fs::write(file_path, content).await?;
+    Ok(())
+}
+
+pub fn write_food_cost_report_excel(data: &FoodCostReportSummaryData, file_path: &str) -> Result<(), XlsxError> {
+    // Create a new Excel file object.
+    let mut workbook = Workbook::new();
+
+    // Create some formats to use in the worksheet.
+    let bold_format = Format::new().set_bold().set_align(FormatAlign::Center);
+    let decimal_format = Format::new().set_num_format("0.00").set_align(FormatAlign::Right);
+    let decimal_bold_format = Format::new()
+        .set_num_format("0.00")
+        .set_align(FormatAlign::Right)
+        .set_bold();
+    let merge_center_format = Format::new().set_bold().set_align(FormatAlign::Center);
+    let merge_left_format = Format::new().set_bold().set_align(FormatAlign::Left);
+    let merge_right_format = Format::new().set_bold().set_align(FormatAlign::Right);
+
+    let center_format = Format::new().set_align(FormatAlign::Center);
+    let right_format = Format::new().set_align(FormatAlign::Right);
+    let left_format = Format::new().set_align(FormatAlign::Left);
+
+    // Items sheet.
+    let worksheet = workbook.add_worksheet().set_name("Items")?;
+
+    worksheet.set_column_width(0, 5)?;
+    worksheet.set_column_width(1, 20)?;
+    for col in 2..8 {
+        worksheet.set_column_width(col, 13)?;
+    }
+
+    worksheet.merge_range(0, 0, 0, 7, "Food Cost Report", &merge_center_format)?;
+    worksheet.merge_range(1, 0, 1, 2, &data.shop_name, &merge_left_format)?;
+    worksheet.merge_range(1, 5, 1, 7, &data.date_str, &merge_right_format)?;
+
+    worksheet.write_with_format(2, 0, "Id", &bold_format)?;
+    worksheet.write_with_format(2, 1, "Item Name", &bold_format)?;
+    worksheet.write_with_format(2, 2, "Quantity", &bold_format)?;
+    worksheet.write_with_format(2, 3, "Net Sale", &bold_format)?;
+    worksheet.write_with_format(2, 4, "Unit Cost", &bold_format)?;
+    worksheet.write_with_format(2, 5, "Food Cost", &bold_format)?;
+    worksheet.write_with_format(2, 6, "Cost %", &bold_format)?;
+    worksheet.write_with_format(2, 7, "Gross Margin", &bold_format)?;
+    let mut row_no = 3;
+    for item in &data.data_list {
+        worksheet.write_with_format(row_no, 0, item.item_id, &center_format)?;
+        worksheet.write_with_format(row_no, 1, &item.item_name, &left_format)?;
+        worksheet.write_with_format(row_no, 2, item.quantity, &right_format)?;
+        worksheet.write_with_format(row_no, 3, item.netsale, &decimal_format)?;
+        worksheet.write_with_format(row_no, 4, item.unit_cost, &decimal_format)?;
+        worksheet.write_with_format(row_no, 5, item.food_cost, &decimal_format)?;
+        worksheet.write_with_format(row_no, 6, item.cost_percent, &decimal_format)?;
+        worksheet.write_with_format(row_no, 7, item.gross_margin, &decimal_format)?;
+        row_no += 1;
+    }
+
+    worksheet.merge_range(row_no, 0, row_no, 1, "Total", &merge_center_format)?;
+    worksheet.write_with_format(row_no, 2, data.total_quantity, &bold_format)?;
+    worksheet.write_with_format(row_no, 3, data.total_netsale, &decimal_bold_format)?;
+    worksheet.write_with_format(row_no, 5, data.total_food_cost, &decimal_bold_format)?;
+    worksheet.write_with_format(row_no, 6, data.total_cost_percent, &decimal_bold_format)?;
+    worksheet.write_with_format(row_no, 7, data.total_gross_margin, &decimal_bold_format)?;
+
+    // Categories sheet.
+    let worksheet = workbook.add_worksheet().set_name("Categories")?;
+
+    worksheet.set_column_width(0, 5)?;
+    worksheet.set_column_width(1, 20)?;
+    for col in 2..7 {
+        worksheet.set_column_width(col, 13)?;
+    }
+
+    worksheet.merge_range(0, 0, 0, 6, "Food Cost Report by Category", &merge_center_format)?;
+    worksheet.merge_range(1, 0, 1, 2, &data.shop_name, &merge_left_format)?;
+    worksheet.merge_range(1, 4, 1, 6, &data.date_str, &merge_right_format)?;
+
+    worksheet.write_with_format(2, 0, "Id", &bold_format)?;
+    worksheet.write_with_format(2, 1, "Category", &bold_format)?;
+    worksheet.write_with_format(2, 2, "Quantity", &bold_format)?;
+    worksheet.write_with_format(2, 3, "Net Sale", &bold_format)?;
+    worksheet.write_with_format(2, 4, "Food Cost", &bold_format)?;
+    worksheet.write_with_format(2, 5, "Cost %", &bold_format)?;
+    worksheet.write_with_format(2, 6, "Gross Margin", &bold_format)?;
+    for (row_no, category) in (3..).zip(data.category_list.iter()) {
+        worksheet.write_with_format(row_no, 0, category.category_id, &center_format)?;
+        worksheet.write_with_format(row_no, 1, &category.category_name, &left_format)?;
+        worksheet.write_with_format(row_no, 2, category.quantity, &right_format)?;
+        worksheet.write_with_format(row_no, 3, category.netsale, &decimal_format)?;
+        worksheet.write_with_format(row_no, 4, category.food_cost, &decimal_format)?;
+        worksheet.write_with_format(row_no, 5, category.cost_percent, &decimal_format)?;
+        worksheet.write_with_format(row_no, 6, category.gross_margin, &decimal_format)?;
+    }
+
+    // Save the file to disk.
+    workbook.save(file_path)?;
+
+    Ok(())
+}
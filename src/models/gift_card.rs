@@ -0,0 +1,455 @@
+use chrono::{NaiveDate, NaiveDateTime};
+use serde::{Deserialize, Serialize};
+use tokio_postgres::{types::ToSql, Client, Error, Transaction};
+use uuid::Uuid;
+
+use crate::utils::{
+    common_struct::PaginationResult,
+    sql::{generate_pagination_query, PaginationOptions},
+};
+
+#[derive(Debug, Serialize)]
+pub struct GiftCard {
+    pub gift_card_id: i32,
+    pub shop_id: i32,
+    pub shop_name: String,
+    pub code: String,
+    pub customer_id: Option<i32>,
+    pub customer_name: String,
+    pub initial_amount: f64,
+    pub balance: f64,
+    pub expiry_date: Option<NaiveDate>,
+    pub is_expired: bool,
+    pub created_at: NaiveDateTime,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct IssueGiftCardRequest {
+    pub shop_id: Option<i32>,
+    pub code: Option<String>,
+    pub amount: f64,
+    pub customer_id: Option<i32>,
+    pub expiry_date: Option<NaiveDate>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct GiftCardTransaction {
+    pub gift_card_transaction_id: i32,
+    pub order_id: Option<i32>,
+    pub transaction_type: String,
+    pub amount: f64,
+    pub balance_after: f64,
+    pub user_name: String,
+    pub created_at: NaiveDateTime,
+}
+
+#[derive(Debug, Serialize)]
+pub struct GiftCardDetail {
+    pub gift_card: GiftCard,
+    pub transactions: Vec<GiftCardTransaction>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct GiftCardLiability {
+    pub shop_id: i32,
+    pub shop_name: String,
+    pub card_count: i64,
+    pub issued_amount: f64,
+    pub redeemed_amount: f64,
+    pub outstanding_balance: f64,
+    pub expired_balance: f64,
+}
+
+// A card can be used up to and including its expiry date.
+const GIFT_CARD_SELECT_COLUMNS: &str = "g.gift_card_id, g.shop_id, s.name as shop_name, g.code, g.customer_id, coalesce(c.name, '') as customer_name,
+    g.initial_amount::text as initial_amount, g.balance::text as balance, g.expiry_date,
+    coalesce(g.expiry_date < CURRENT_DATE, false) as is_expired, g.created_at";
+
+const GIFT_CARD_BASE_QUERY: &str = "from gift_cards g join shops s on s.id = g.shop_id
+    left join customers c on c.customer_id = g.customer_id where g.deleted_at is null";
+
+fn row_to_gift_card(row: &tokio_postgres::Row) -> GiftCard {
+    let initial_amount: &str = row.get("initial_amount");
+    let balance: &str = row.get("balance");
+    GiftCard {
+        gift_card_id: row.get("gift_card_id"),
+        shop_id: row.get("shop_id"),
+        shop_name: row.get("shop_name"),
+        code: row.get("code"),
+        customer_id: row.get("customer_id"),
+        customer_name: row.get("customer_name"),
+        initial_amount: initial_amount.parse().unwrap(),
+        balance: balance.parse().unwrap(),
+        expiry_date: row.get("expiry_date"),
+        is_expired: row.get("is_expired"),
+        created_at: row.get("created_at"),
+    }
+}
+
+pub async fn get_gift_cards(
+    search: &Option<String>,
+    page: Option<usize>,
+    per_page: Option<usize>,
+    shop_id: Option<i32>,
+    client: &Client,
+) -> Result<PaginationResult<GiftCard>, Error> {
+    let mut base_query = GIFT_CARD_BASE_QUERY.to_string();
+    let mut params: Vec<Box<dyn ToSql + Sync>> = vec![];
+    if let Some(s) = shop_id {
+        params.push(Box::new(s));
+        base_query = format!("{base_query} and g.shop_id = ${}", params.len());
+    }
+
+    let result = generate_pagination_query(PaginationOptions {
+        select_columns: GIFT_CARD_SELECT_COLUMNS,
+        base_query: &base_query,
+        search_columns: vec!["g.code", "c.name", "c.phone"],
+        search: search.as_deref(),
+        order_options: Some("g.created_at desc"),
+        page,
+        per_page,
+    });
+
+    let params_slice: Vec<&(dyn ToSql + Sync)> = params.iter().map(AsRef::as_ref).collect();
+
+    let row = client.query_one(&result.count_query, &params_slice).await?;
+    let total: i64 = row.get("total");
+
+    let mut page_counts = 0;
+    let mut current_page = 0;
+    let mut limit = 0;
+    if let (Some(p), Some(pp)) = (page, per_page) {
+        current_page = p;
+        limit = pp;
+        page_counts = (total as f64 / limit as f64).ceil() as usize;
+    }
+
+    let gift_cards: Vec<GiftCard> = client
+        .query(&result.query, &params_slice)
+        .await?
+        .iter()
+        .map(row_to_gift_card)
+        .collect();
+
+    Ok(PaginationResult {
+        data: gift_cards,
+        total,
+        page: current_page,
+        per_page: limit,
+        page_counts,
+    })
+}
+
+pub async fn get_gift_card_by_id(gift_card_id: i32, client: &Client) -> Option<GiftCard> {
+    match client
+        .query_one(
+            &format!(
+                "select {GIFT_CARD_SELECT_COLUMNS} {GIFT_CARD_BASE_QUERY} and g.gift_card_id = $1"
+            ),
+            &[&gift_card_id],
+        )
+        .await
+    {
+        Ok(row) => Some(row_to_gift_card(&row)),
+        Err(_) => None,
+    }
+}
+
+pub async fn get_gift_card_by_code(code: &str, client: &Client) -> Option<GiftCard> {
+    match client
+        .query_one(
+            &format!("select {GIFT_CARD_SELECT_COLUMNS} {GIFT_CARD_BASE_QUERY} and g.code = $1"),
+            &[&code],
+        )
+        .await
+    {
+        Ok(row) => Some(row_to_gift_card(&row)),
+        Err(_) => None,
+    }
+}
+
+pub async fn code_exists(code: &str, client: &Client) -> Result<bool, Error> {
+    let row = client
+        .query_one(
+            "select count(*) as total from gift_cards where code = $1",
+            &[&code],
+        )
+        .await?;
+    let total: i64 = row.get("total");
+    Ok(total > 0)
+}
+
+pub async fn get_gift_card_transactions(
+    gift_card_id: i32,
+    client: &Client,
+) -> Result<Vec<GiftCardTransaction>, Error> {
+    let rows = client
+        .query(
+            "select gt.gift_card_transaction_id, gt.order_id, gt.transaction_type, gt.amount::text as amount,
+            gt.balance_after::text as balance_after, coalesce(u.name, '') as user_name, gt.created_at
+            from gift_card_transactions gt left join users u on u.id = gt.user_id
+            where gt.gift_card_id = $1 order by gt.created_at, gt.gift_card_transaction_id",
+            &[&gift_card_id],
+        )
+        .await?;
+    Ok(rows
+        .iter()
+        .map(|row| {
+            let amount: &str = row.get("amount");
+            let balance_after: &str = row.get("balance_after");
+            GiftCardTransaction {
+                gift_card_transaction_id: row.get("gift_card_transaction_id"),
+                order_id: row.get("order_id"),
+                transaction_type: row.get("transaction_type"),
+                amount: amount.parse().unwrap(),
+                balance_after: balance_after.parse().unwrap(),
+                user_name: row.get("user_name"),
+                created_at: row.get("created_at"),
+            }
+        })
+        .collect())
+}
+
+pub fn generate_gift_card_code() -> String {
+    Uuid::new_v4().simple().to_string()[..16].to_uppercase()
+}
+
+pub async fn issue_gift_card(
+    data: &IssueGiftCardRequest,
+    code: &str,
+    shop_id: i32,
+    user_id: i32,
+    client: &mut Client,
+) -> Result<i32, Error> {
+    let transaction = client.transaction().await?;
+    let query = format!(
+        "insert into gift_cards (shop_id, code, customer_id, initial_amount, balance, expiry_date, issued_by)
+        values ($1, $2, $3, {0}, {0}, $4, $5) returning gift_card_id",
+        data.amount
+    );
+    let row = transaction
+        .query_one(
+            &query,
+            &[
+                &shop_id,
+                &code,
+                &data.customer_id,
+                &data.expiry_date,
+                &user_id,
+            ],
+        )
+        .await?;
+    let gift_card_id: i32 = row.get("gift_card_id");
+    let query = format!(
+        "insert into gift_card_transactions (gift_card_id, transaction_type, amount, balance_after, user_id)
+        values ($1, 'Issue', {0}, {0}, $2)",
+        data.amount
+    );
+    transaction
+        .execute(&query, &[&gift_card_id, &user_id])
+        .await?;
+    transaction.commit().await?;
+    Ok(gift_card_id)
+}
+
+pub async fn top_up_gift_card(
+    gift_card_id: i32,
+    amount: f64,
+    user_id: i32,
+    client: &mut Client,
+) -> Result<f64, Error> {
+    let transaction = client.transaction().await?;
+    let query = format!(
+        "update gift_cards set balance = balance + {} where gift_card_id = $1 returning balance::text as balance",
+        amount
+    );
+    let row = transaction.query_one(&query, &[&gift_card_id]).await?;
+    let balance: &str = row.get("balance");
+    let balance: f64 = balance.parse().unwrap();
+    let query = format!(
+        "insert into gift_card_transactions (gift_card_id, transaction_type, amount, balance_after, user_id)
+        values ($1, 'TopUp', {}, {}, $2)",
+        amount, balance
+    );
+    transaction
+        .execute(&query, &[&gift_card_id, &user_id])
+        .await?;
+    transaction.commit().await?;
+    Ok(balance)
+}
+
+// Returns the balance left on the card, or None when the balance doesn't cover the amount.
+async fn redeem(
+    gift_card_id: i32,
+    amount: f64,
+    order_id: Option<i32>,
+    user_id: i32,
+    transaction: &Transaction<'_>,
+) -> Result<Option<f64>, Error> {
+    let query = format!(
+        "update gift_cards set balance = balance - {0} where gift_card_id = $1 and balance >= {0}
+        returning balance::text as balance",
+        amount
+    );
+    let row = match transaction.query_opt(&query, &[&gift_card_id]).await? {
+        Some(row) => row,
+        None => return Ok(None),
+    };
+    let balance: &str = row.get("balance");
+    let balance: f64 = balance.parse().unwrap();
+    let query = format!(
+        "insert into gift_card_transactions (gift_card_id, order_id, transaction_type, amount, balance_after, user_id)
+        values ($1, $2, 'Redeem', {}, {}, $3)",
+        -amount, balance
+    );
+    transaction
+        .execute(&query, &[&gift_card_id, &order_id, &user_id])
+        .await?;
+    Ok(Some(balance))
+}
+
+pub async fn redeem_gift_card(
+    gift_card_id: i32,
+    amount: f64,
+    user_id: i32,
+    client: &mut Client,
+) -> Result<Option<f64>, Error> {
+    let transaction = client.transaction().await?;
+    let balance = redeem(gift_card_id, amount, None, user_id, &transaction).await?;
+    if balance.is_some() {
+        transaction.commit().await?;
+    }
+    Ok(balance)
+}
+
+// Pays for an order with a gift card, in the transaction that completes the order. An order that was taken back
+// from Completed and is completed again has already been paid for, so its card isn't charged a second time.
+pub async fn redeem_order_gift_card(
+    gift_card_id: i32,
+    amount: f64,
+    order_id: i32,
+    user_id: i32,
+    transaction: &Transaction<'_>,
+) -> Result<Option<f64>, Error> {
+    let row = transaction
+        .query_one(
+            "select coalesce(sum(amount), 0) < 0 as paid,
+            (select balance::text from gift_cards where gift_card_id = $2) as balance
+            from gift_card_transactions where order_id = $1 and transaction_type in ('Redeem', 'Refund')",
+            &[&order_id, &gift_card_id],
+        )
+        .await?;
+    let paid: bool = row.get("paid");
+    if paid {
+        let balance: &str = row.get("balance");
+        return Ok(Some(balance.parse().unwrap()));
+    }
+    let balance = redeem(gift_card_id, amount, Some(order_id), user_id, transaction).await?;
+    if balance.is_some() {
+        transaction
+            .execute(
+                "update orders set gift_card_id = $1, updated_at = CURRENT_TIMESTAMP where id = $2",
+                &[&gift_card_id, &order_id],
+            )
+            .await?;
+    }
+    Ok(balance)
+}
+
+// The gift card an order can be paid with must belong to the order's shop.
+pub async fn get_gift_card_for_order(
+    code: &str,
+    order_id: i32,
+    client: &Client,
+) -> Option<GiftCard> {
+    match client
+        .query_one(
+            &format!(
+                "select {GIFT_CARD_SELECT_COLUMNS} {GIFT_CARD_BASE_QUERY} and g.code = $1
+                and g.shop_id = (select shop_id from orders where id = $2)"
+            ),
+            &[&code, &order_id],
+        )
+        .await
+    {
+        Ok(row) => Some(row_to_gift_card(&row)),
+        Err(_) => None,
+    }
+}
+
+// Puts what a canceled order took off its gift cards back on the cards, in the transaction that cancels the order.
+pub async fn refund_order_gift_card(
+    order_id: i32,
+    user_id: i32,
+    transaction: &Transaction<'_>,
+) -> Result<(), Error> {
+    let rows = transaction
+        .query(
+            "select gift_card_id, (-sum(amount))::text as amount from gift_card_transactions
+            where order_id = $1 and transaction_type in ('Redeem', 'Refund') group by gift_card_id
+            having sum(amount) < 0 order by gift_card_id",
+            &[&order_id],
+        )
+        .await?;
+    for row in &rows {
+        let gift_card_id: i32 = row.get("gift_card_id");
+        let amount: &str = row.get("amount");
+        let amount: f64 = amount.parse().unwrap();
+        let query = format!(
+            "update gift_cards set balance = balance + {} where gift_card_id = $1 returning balance::text as balance",
+            amount
+        );
+        let row = transaction.query_one(&query, &[&gift_card_id]).await?;
+        let balance: &str = row.get("balance");
+        let query = format!(
+            "insert into gift_card_transactions (gift_card_id, order_id, transaction_type, amount, balance_after, user_id)
+            values ($1, $2, 'Refund', {}, {}, $3)",
+            amount, balance
+        );
+        transaction
+            .execute(&query, &[&gift_card_id, &order_id, &user_id])
+            .await?;
+    }
+    Ok(())
+}
+
+// Outstanding balances are what the shop still owes card holders; balances on expired cards are
+// shown apart since they are no longer redeemable.
+pub async fn get_gift_card_liability(
+    shop_id: Option<i32>,
+    client: &Client,
+) -> Result<Vec<GiftCardLiability>, Error> {
+    let rows = client
+        .query(
+            "select s.id as shop_id, s.name as shop_name, count(g.gift_card_id) as card_count,
+            coalesce((select sum(gt.amount) from gift_card_transactions gt join gift_cards gc on gc.gift_card_id = gt.gift_card_id
+                where gc.shop_id = s.id and gc.deleted_at is null and gt.transaction_type in ('Issue', 'TopUp')), 0)::text as issued_amount,
+            coalesce((select -sum(gt.amount) from gift_card_transactions gt join gift_cards gc on gc.gift_card_id = gt.gift_card_id
+                where gc.shop_id = s.id and gc.deleted_at is null and gt.transaction_type in ('Redeem', 'Refund')), 0)::text as redeemed_amount,
+            coalesce(sum(g.balance) filter (where g.expiry_date is null or g.expiry_date >= CURRENT_DATE), 0)::text as outstanding_balance,
+            coalesce(sum(g.balance) filter (where g.expiry_date < CURRENT_DATE), 0)::text as expired_balance
+            from shops s join gift_cards g on g.shop_id = s.id and g.deleted_at is null
+            where ($1::int is null or s.id = $1)
+            group by s.id, s.name order by s.name",
+            &[&shop_id],
+        )
+        .await?;
+    Ok(rows
+        .iter()
+        .map(|row| {
+            let issued_amount: &str = row.get("issued_amount");
+            let redeemed_amount: &str = row.get("redeemed_amount");
+            let outstanding_balance: &str = row.get("outstanding_balance");
+            let expired_balance: &str = row.get("expired_balance");
+            GiftCardLiability {
+                shop_id: row.get("shop_id"),
+                shop_name: row.get("shop_name"),
+                card_count: row.get("card_count"),
+                issued_amount: issued_amount.parse().unwrap(),
+                redeemed_amount: redeemed_amount.parse().unwrap(),
+                outstanding_balance: outstanding_balance.parse().unwrap(),
+                expired_balance: expired_balance.parse().unwrap(),
+            }
+        })
+        .collect())
+}
@@ -0,0 +1,107 @@
+use std::env;
+
+use tokio_postgres::{Client, Error};
+
+// A key without a response belongs to a request that is still being handled.
+pub struct IdempotencyKey {
+    pub request_hash: String,
+    pub response_status: Option<i32>,
+    pub response_body: Option<String>,
+}
+
+// Hours a key is remembered for.
+pub fn idempotency_key_ttl() -> i32 {
+    env::var("IDEMPOTENCY_KEY_TTL")
+        .unwrap_or(String::from("24"))
+        .parse()
+        .unwrap_or(24)
+}
+
+// Takes the key for a new request, or over from an expired use. Returns the key as already stored when it was
+// used before within `ttl_hours`.
+pub async fn claim_idempotency_key(
+    idempotency_key: &str,
+    user_id: i32,
+    request_hash: &str,
+    ttl_hours: i32,
+    client: &Client,
+) -> Result<Option<IdempotencyKey>, Error> {
+    let inserted = client
+        .execute(
+            "insert into idempotency_keys (idempotency_key, user_id, request_hash) values ($1, $2, $3)
+            on conflict (idempotency_key, user_id) do update set request_hash = excluded.request_hash,
+            response_status = null, response_body = null, created_at = CURRENT_TIMESTAMP
+            where idempotency_keys.created_at < CURRENT_TIMESTAMP - make_interval(hours => $4)",
+            &[&idempotency_key, &user_id, &request_hash, &ttl_hours],
+        )
+        .await?;
+    if inserted == 1 {
+        return Ok(None);
+    }
+    let row = client
+        .query_one(
+            "select request_hash, response_status, response_body from idempotency_keys
+            where idempotency_key = $1 and user_id = $2",
+            &[&idempotency_key, &user_id],
+        )
+        .await?;
+    Ok(Some(IdempotencyKey {
+        request_hash: row.get("request_hash"),
+        response_status: row.get("response_status"),
+        response_body: row.get("response_body"),
+    }))
+}
+
+pub async fn save_idempotency_response(
+    idempotency_key: &str,
+    user_id: i32,
+    response_status: i32,
+    response_body: &str,
+    client: &Client,
+) -> Result<(), Error> {
+    client
+        .execute(
+            "update idempotency_keys set response_status = $1, response_body = $2
+            where idempotency_key = $3 and user_id = $4",
+            &[&response_status, &response_body, &idempotency_key, &user_id],
+        )
+        .await?;
+    Ok(())
+}
+
+// Frees the key when the request failed on our side, so a retry runs it again.
+pub async fn release_idempotency_key(
+    idempotency_key: &str,
+    user_id: i32,
+    client: &Client,
+) -> Result<(), Error> {
+    client
+        .execute(
+            "delete from idempotency_keys where idempotency_key = $1 and user_id = $2",
+            &[&idempotency_key, &user_id],
+        )
+        .await?;
+    Ok(())
+}
+
+// Keys without a response when the server starts belong to requests it stopped handling.
+pub async fn release_unfinished_idempotency_keys(client: &Client) -> Result<u64, Error> {
+    client
+        .execute(
+            "delete from idempotency_keys where response_status is null",
+            &[],
+        )
+        .await
+}
+
+pub async fn delete_expired_idempotency_keys(
+    ttl_hours: i32,
+    client: &Client,
+) -> Result<u64, Error> {
+    client
+        .execute(
+            "delete from idempotency_keys where created_at < CURRENT_TIMESTAMP - make_interval(hours => $1)",
+            &[&ttl_hours],
+        )
+        .await
+}
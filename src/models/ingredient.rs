@@ -2,9 +2,15 @@ use chrono::{NaiveDateTime, NaiveDate};
 use serde::{Deserialize, Serialize};
 use tokio_postgres::{types::ToSql, Client, Error};
 
-use crate::utils::{
-    common_struct::PaginationResult,
-    sql::{generate_pagination_query, PaginationOptions},
+use crate::{
+    models::{
+        ingredient_lot::{self, NewIngredientLot},
+        stock_movement::{self, NewStockMovement},
+    },
+    utils::{
+        common_struct::PaginationResult,
+        sql::{generate_pagination_query, PaginationOptions},
+    },
 };
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -14,7 +20,8 @@ pub struct Ingredient {
     pub stock_quantity: f32,
     pub unit: String,
     pub reorder_level: f32,
-    pub expiry_date: NaiveDate,
+    pub expiry_date: Option<NaiveDate>,
+    pub shop_id: Option<i32>,
     pub created_at: NaiveDateTime,
 
 }
@@ -23,15 +30,26 @@ pub async fn get_ingredients(
     search: &Option<String>,
     page: Option<usize>,
     per_page: Option<usize>,
+    role: &str,
+    shop_id: i32,
+    filter_shop_id: Option<i32>,
     client: &Client,
 ) -> Result<PaginationResult<Ingredient>, Error> {
-    let base_query = "from ingredients where deleted_at is null".to_string();
-    let params: Vec<Box<dyn ToSql + Sync>> = vec![];
+    let mut base_query = "from ingredients where deleted_at is null".to_string();
+    let mut params: Vec<Box<dyn ToSql + Sync>> = vec![];
+
+    if role == "Manager" {
+        params.push(Box::new(shop_id));
+        base_query = format!("{base_query} and shop_id = ${}", params.len());
+    } else if let Some(s) = filter_shop_id {
+        params.push(Box::new(s));
+        base_query = format!("{base_query} and shop_id = ${}", params.len());
+    }
 
     let order_options = "created_at desc";
 
     let result = generate_pagination_query(PaginationOptions {
-        select_columns: "ingredient_id, name, stock_quantity::text as stock_quantity, unit, reorder_level::text as reorder_level, expiry_date::text as expiry_date, created_at",
+        select_columns: "ingredient_id, name, stock_quantity::text as stock_quantity, unit, reorder_level::text as reorder_level, expiry_date::text as expiry_date, shop_id, created_at",
         base_query: &base_query,
         search_columns: vec!["ingredient_id::varchar", "name","unit", "reorder_level::varchar","expiry_date::varchar"],
         search: search.as_deref(),
@@ -61,7 +79,7 @@ pub async fn get_ingredients(
     for row in &rows {
         let stock_quantity_str: &str = row.get("stock_quantity");
         let reorder_level_str: &str = row.get("reorder_level");
-        let expiry_date_str: &str = row.get("expiry_date");
+        let expiry_date_str: Option<&str> = row.get("expiry_date");
 
         ingredients.push(
             Ingredient {
@@ -70,7 +88,8 @@ pub async fn get_ingredients(
             stock_quantity: stock_quantity_str.parse().unwrap(),
             unit: row.get("unit"),
             reorder_level: reorder_level_str.parse().unwrap(),
-            expiry_date: expiry_date_str.parse().unwrap(),
+            expiry_date: expiry_date_str.map(|e| e.parse().unwrap()),
+            shop_id: row.get("shop_id"),
             created_at: row.get("created_at"),
             }
         );
@@ -92,25 +111,62 @@ pub struct IngredientRequest {
     pub unit: String,
     pub reorder_level: f32,
     pub expiry_date: NaiveDate,
+    pub shop_id: Option<i32>,
 }
 
+// The opening stock is booked as the first ledger movement instead of being written to `stock_quantity`.
 pub async fn add_ingredient(
     data: &IngredientRequest,
-    client: &Client,
+    shop_id: i32,
+    user_id: i32,
+    client: &mut Client,
 ) -> Result<(), Box<dyn std::error::Error>> {
-    let insert_query = format!("insert into ingredients (name, stock_quantity, unit, reorder_level, expiry_date ) values ($1,{}, $2, {}, $3)",data.stock_quantity,data.reorder_level);
-    client
-        .execute(&insert_query,
-            &[&data.name, &data.unit, &data.expiry_date],
+    let transaction = client.transaction().await?;
+    let insert_query = format!("insert into ingredients (name, stock_quantity, unit, reorder_level, expiry_date, shop_id) values ($1, 0, $2, {}, $3, $4) returning ingredient_id",data.reorder_level);
+    let ingredient_id: i32 = transaction
+        .query_one(&insert_query,
+            &[&data.name, &data.unit, &data.expiry_date, &shop_id],
+        )
+        .await?
+        .get("ingredient_id");
+    if data.stock_quantity != 0.0 {
+        stock_movement::record_stock_movement(
+            &transaction,
+            &NewStockMovement {
+                ingredient_id,
+                movement_type: "adjustment",
+                quantity: data.stock_quantity as f64,
+                unit_cost: 0.0,
+                reference_type: "ingredient",
+                reference_id: Some(ingredient_id),
+                notes: Some(String::from("Opening balance")),
+                shop_id: Some(shop_id),
+                created_by: Some(user_id),
+            },
+        )
+        .await?;
+        ingredient_lot::add_lot(
+            &transaction,
+            &NewIngredientLot {
+                ingredient_id,
+                purchase_detail_id: None,
+                lot_number: &None,
+                expiry_date: Some(data.expiry_date),
+                quantity: data.stock_quantity as f64,
+                unit_cost: 0.0,
+                shop_id: Some(shop_id),
+            },
         )
         .await?;
+    }
+    transaction.commit().await?;
     Ok(())
 }
 
 pub async fn get_ingredient_by_id(ingredient_id: i32, client: &Client) -> Option<Ingredient> {
     let result = client
         .query_one(
-            "select ingredient_id, name, stock_quantity::text as stock_quantity, unit, reorder_level::text as reorder_level, expiry_date::text as expiry_date, created_at from ingredients where deleted_at is null and ingredient_id = $1",
+            "select ingredient_id, name, stock_quantity::text as stock_quantity, unit, reorder_level::text as reorder_level, expiry_date::text as expiry_date, shop_id, created_at from ingredients where deleted_at is null and ingredient_id = $1",
             &[&ingredient_id],
         )
         .await;
@@ -119,14 +175,15 @@ pub async fn get_ingredient_by_id(ingredient_id: i32, client: &Client) -> Option
         Ok(row) =>{
             let stock_quantity_str: &str = row.get("stock_quantity");
             let reorder_level_str: &str = row.get("reorder_level");
-            let expiry_date_str: &str = row.get("expiry_date");
+            let expiry_date_str: Option<&str> = row.get("expiry_date");
             Some(Ingredient {
                 ingredient_id: row.get("ingredient_id"),
                 name: row.get("name"),
                 stock_quantity: stock_quantity_str.parse().unwrap(),
                 unit: row.get("unit"),
                 reorder_level: reorder_level_str.parse().unwrap(),
-                expiry_date: expiry_date_str.parse().unwrap(),
+                expiry_date: expiry_date_str.map(|e| e.parse().unwrap()),
+                shop_id: row.get("shop_id"),
                 created_at: row.get("created_at"),
             })
         },
@@ -139,7 +196,8 @@ pub async fn update_ingredient(
     data: &IngredientRequest,
     client: &Client,
 ) -> Result<(), Box<dyn std::error::Error>> {
-    let update_query = format!("update ingredients set name = $1, reorder_level = {}, expiry_date = $2 where ingredient_id = $3",data.reorder_level);
+    // Once an ingredient has lots its expiry date follows the earliest open lot instead of the request.
+    let update_query = format!("update ingredients set name = $1, reorder_level = {}, expiry_date = case when exists (select 1 from ingredient_lots where ingredient_id = $3) then expiry_date else $2 end where ingredient_id = $3",data.reorder_level);
     client
         .execute(
             &update_query,
@@ -163,3 +221,19 @@ pub async fn delete_ingredient(
 
     Ok(())
 }
+
+// True when every ingredient in `ingredient_ids` exists and is stocked by `shop_id`.
+pub async fn ingredients_belong_to_shop(
+    ingredient_ids: &[i32],
+    shop_id: i32,
+    client: &Client,
+) -> Result<bool, Error> {
+    let row = client
+        .query_one(
+            "select (select count(distinct ingredient_id) from ingredients where ingredient_id = any($1) and shop_id = $2 and deleted_at is null)
+            = (select count(distinct id) from unnest($1::int[]) as id) as belongs",
+            &[&ingredient_ids, &shop_id],
+        )
+        .await?;
+    Ok(row.get("belongs"))
+}
@@ -0,0 +1,277 @@
+use std::{collections::HashMap, env};
+
+use chrono::{NaiveDate, NaiveDateTime};
+use serde::Serialize;
+use tokio::sync::Mutex;
+use tokio_postgres::{types::ToSql, Client, Error};
+
+use crate::models::{push_notification, socket_event, user};
+
+#[derive(Debug, Serialize)]
+pub struct IngredientAlert {
+    pub alert_id: i32,
+    pub ingredient_id: i32,
+    pub ingredient_name: String,
+    pub alert_type: String,
+    pub stock_quantity: f64,
+    pub reorder_level: f64,
+    pub unit: String,
+    pub expiry_date: Option<NaiveDate>,
+    pub shop_id: Option<i32>,
+    pub created_at: NaiveDateTime,
+}
+
+fn expiry_alert_days() -> i32 {
+    env::var("INGREDIENT_EXPIRY_ALERT_DAYS")
+        .unwrap_or(String::from("3"))
+        .parse()
+        .unwrap_or(3)
+}
+
+pub async fn get_ingredient_alerts(
+    role: &str,
+    shop_id: i32,
+    filter_shop_id: Option<i32>,
+    alert_type: &Option<String>,
+    client: &Client,
+) -> Result<Vec<IngredientAlert>, Error> {
+    let mut params: Vec<Box<dyn ToSql + Sync>> = vec![];
+    let mut query = "select a.alert_id, a.ingredient_id, i.name as ingredient_name, a.alert_type, a.stock_quantity::text as stock_quantity, a.reorder_level::text as reorder_level, i.unit, a.expiry_date, a.shop_id, a.created_at from ingredient_alerts a join ingredients i on i.ingredient_id = a.ingredient_id where a.resolved_at is null and i.deleted_at is null".to_string();
+    if role == "Manager" {
+        params.push(Box::new(shop_id));
+        query = format!("{query} and a.shop_id = ${}", params.len());
+    } else if let Some(s) = filter_shop_id {
+        params.push(Box::new(s));
+        query = format!("{query} and a.shop_id = ${}", params.len());
+    }
+    if let Some(t) = alert_type {
+        params.push(Box::new(t));
+        query = format!("{query} and a.alert_type = ${}", params.len());
+    }
+    query = format!("{query} order by a.created_at desc");
+
+    let params_slice: Vec<&(dyn ToSql + Sync)> = params.iter().map(AsRef::as_ref).collect();
+    let alerts = client
+        .query(&query, &params_slice)
+        .await?
+        .iter()
+        .map(|row| {
+            let stock_quantity: &str = row.get("stock_quantity");
+            let reorder_level: &str = row.get("reorder_level");
+            IngredientAlert {
+                alert_id: row.get("alert_id"),
+                ingredient_id: row.get("ingredient_id"),
+                ingredient_name: row.get("ingredient_name"),
+                alert_type: row.get("alert_type"),
+                stock_quantity: stock_quantity.parse().unwrap(),
+                reorder_level: reorder_level.parse().unwrap(),
+                unit: row.get("unit"),
+                expiry_date: row.get("expiry_date"),
+                shop_id: row.get("shop_id"),
+                created_at: row.get("created_at"),
+            }
+        })
+        .collect();
+    Ok(alerts)
+}
+
+// Opens alerts for ingredients that dropped below their reorder level or are close to expiry,
+// resolves the ones that no longer apply and returns the newly opened alerts.
+pub async fn refresh_ingredient_alerts(client: &Client) -> Result<Vec<IngredientAlert>, Error> {
+    let expiry_days = expiry_alert_days();
+
+    client
+        .execute(
+            "update ingredient_alerts a set resolved_at = CURRENT_TIMESTAMP from ingredients i
+            where i.ingredient_id = a.ingredient_id and a.resolved_at is null and a.alert_type = 'low_stock'
+            and (i.deleted_at is not null or i.stock_quantity > i.reorder_level)",
+            &[],
+        )
+        .await?;
+    client
+        .execute(
+            "update ingredient_alerts a set resolved_at = CURRENT_TIMESTAMP from ingredients i
+            where i.ingredient_id = a.ingredient_id and a.resolved_at is null and a.alert_type = 'expiry'
+            and (i.deleted_at is not null or i.expiry_date is null or i.stock_quantity <= 0 or i.expiry_date > CURRENT_DATE + $1::int)",
+            &[&expiry_days],
+        )
+        .await?;
+
+    let rows = client
+        .query(
+            "insert into ingredient_alerts (ingredient_id, alert_type, stock_quantity, reorder_level, expiry_date, shop_id)
+            select i.ingredient_id, 'low_stock', i.stock_quantity, i.reorder_level, i.expiry_date, i.shop_id from ingredients i
+            where i.deleted_at is null and i.stock_quantity <= i.reorder_level
+            and not exists (select 1 from ingredient_alerts a where a.ingredient_id = i.ingredient_id and a.alert_type = 'low_stock' and a.resolved_at is null)
+            union all
+            select i.ingredient_id, 'expiry', i.stock_quantity, i.reorder_level, i.expiry_date, i.shop_id from ingredients i
+            where i.deleted_at is null and i.stock_quantity > 0 and i.expiry_date <= CURRENT_DATE + $1::int
+            and not exists (select 1 from ingredient_alerts a where a.ingredient_id = i.ingredient_id and a.alert_type = 'expiry' and a.resolved_at is null)
+            returning alert_id",
+            &[&expiry_days],
+        )
+        .await?;
+    let alert_ids: Vec<i32> = rows.iter().map(|row| row.get("alert_id")).collect();
+    if alert_ids.is_empty() {
+        return Ok(vec![]);
+    }
+
+    let alerts = client
+        .query(
+            "select a.alert_id, a.ingredient_id, i.name as ingredient_name, a.alert_type, a.stock_quantity::text as stock_quantity, a.reorder_level::text as reorder_level, i.unit, a.expiry_date, a.shop_id, a.created_at from ingredient_alerts a join ingredients i on i.ingredient_id = a.ingredient_id where a.alert_id = any($1)",
+            &[&alert_ids],
+        )
+        .await?
+        .iter()
+        .map(|row| {
+            let stock_quantity: &str = row.get("stock_quantity");
+            let reorder_level: &str = row.get("reorder_level");
+            IngredientAlert {
+                alert_id: row.get("alert_id"),
+                ingredient_id: row.get("ingredient_id"),
+                ingredient_name: row.get("ingredient_name"),
+                alert_type: row.get("alert_type"),
+                stock_quantity: stock_quantity.parse().unwrap(),
+                reorder_level: reorder_level.parse().unwrap(),
+                unit: row.get("unit"),
+                expiry_date: row.get("expiry_date"),
+                shop_id: row.get("shop_id"),
+                created_at: row.get("created_at"),
+            }
+        })
+        .collect();
+    Ok(alerts)
+}
+
+// Sends newly opened alerts to the managers of the shop the ingredient belongs to, and pushes low stock ones
+// to their devices.
+pub async fn notify_ingredient_alerts(alerts: &[IngredientAlert], client: &Mutex<Client>) {
+    let mut alerts_by_shop: HashMap<i32, Vec<&IngredientAlert>> = HashMap::new();
+    for alert in alerts {
+        if let Some(shop_id) = alert.shop_id {
+            alerts_by_shop.entry(shop_id).or_default().push(alert);
+        }
+    }
+
+    for (shop_id, shop_alerts) in alerts_by_shop {
+        let payload = serde_json::json!({"shop_id": shop_id, "alerts": shop_alerts});
+        if let Err(err) = socket_event::enqueue_socket_event(
+            &*client.lock().await,
+            "/pos",
+            "ingredient-alerts",
+            Some(shop_id),
+            &["Manager"],
+            &[],
+            payload,
+        )
+        .await
+        {
+            println!("{:?}", err);
+        }
+
+        let low_stock: Vec<&str> = shop_alerts
+            .iter()
+            .filter(|a| a.alert_type == "low_stock")
+            .map(|a| a.ingredient_name.as_str())
+            .collect();
+        if !low_stock.is_empty() {
+            let client = client.lock().await;
+            let manager_ids = match user::get_user_ids_by_shop_and_roles(
+                shop_id,
+                &[String::from("Manager")],
+                &client,
+            )
+            .await
+            {
+                Ok(ids) => ids,
+                Err(err) => {
+                    println!("{:?}", err);
+                    continue;
+                }
+            };
+            if let Err(err) = push_notification::enqueue_push_notification(
+                &*client,
+                &manager_ids,
+                "low-stock",
+                "Low stock",
+                &format!("{} running low.", low_stock.join(", ")),
+                serde_json::json!({"shop_id": shop_id}),
+            )
+            .await
+            {
+                println!("{:?}", err);
+            }
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct PurchaseSuggestion {
+    pub total_cost: f64,
+    pub purchase_date: NaiveDate,
+    pub shop_id: i32,
+    pub purchase_details: Vec<PurchaseSuggestionDetail>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct PurchaseSuggestionDetail {
+    pub ingredient_id: i32,
+    pub ingredient_name: String,
+    pub stock_quantity: f64,
+    pub reorder_level: f64,
+    pub quantity_purchased: f64,
+    pub unit: String,
+    pub buying_price_per_unit: f64,
+}
+
+// Drafts a purchase for every ingredient at or below its reorder level. The suggested quantity
+// refills stock up to twice the reorder level, priced at the last price the shop paid.
+// The result has the same shape as `AddPurchaseRequest` so it can be posted to `/api/purchases` after review.
+pub async fn get_purchase_suggestion(
+    shop_id: i32,
+    client: &Client,
+) -> Result<PurchaseSuggestion, Error> {
+    let rows = client
+        .query(
+            "select i.ingredient_id, i.name, i.unit, i.stock_quantity::text as stock_quantity, i.reorder_level::text as reorder_level,
+            (i.reorder_level * 2 - i.stock_quantity)::text as quantity_purchased,
+            coalesce((select pd.buying_price_per_unit from purchase_details pd join purchases p on p.purchase_id = pd.purchase_id
+                where pd.ingredient_id = i.ingredient_id and pd.deleted_at is null and p.deleted_at is null
+                order by p.purchase_date desc, pd.purchase_detail_id desc limit 1), 0)::text as buying_price_per_unit
+            from ingredients i
+            where i.deleted_at is null and i.shop_id = $1 and i.stock_quantity <= i.reorder_level
+            order by i.name",
+            &[&shop_id],
+        )
+        .await?;
+
+    let mut total_cost: f64 = 0.0;
+    let purchase_details: Vec<PurchaseSuggestionDetail> = rows
+        .iter()
+        .map(|row| {
+            let stock_quantity: &str = row.get("stock_quantity");
+            let reorder_level: &str = row.get("reorder_level");
+            let quantity_purchased: &str = row.get("quantity_purchased");
+            let quantity_purchased: f64 = quantity_purchased.parse().unwrap();
+            let buying_price_per_unit: &str = row.get("buying_price_per_unit");
+            let buying_price_per_unit: f64 = buying_price_per_unit.parse().unwrap();
+            total_cost += quantity_purchased * buying_price_per_unit;
+            PurchaseSuggestionDetail {
+                ingredient_id: row.get("ingredient_id"),
+                ingredient_name: row.get("name"),
+                stock_quantity: stock_quantity.parse().unwrap(),
+                reorder_level: reorder_level.parse().unwrap(),
+                quantity_purchased,
+                unit: row.get("unit"),
+                buying_price_per_unit,
+            }
+        })
+        .collect();
+
+    Ok(PurchaseSuggestion {
+        total_cost,
+        purchase_date: chrono::Local::now().date_naive(),
+        shop_id,
+        purchase_details,
+    })
+}
@@ -0,0 +1,427 @@
+use chrono::{NaiveDate, NaiveDateTime};
+use serde::Serialize;
+use tokio_postgres::{types::ToSql, Client, Error, Transaction};
+
+use crate::models::stock_movement::{self, NewStockMovement};
+
+pub struct NewIngredientLot<'a> {
+    pub ingredient_id: i32,
+    pub purchase_detail_id: Option<i32>,
+    pub lot_number: &'a Option<String>,
+    pub expiry_date: Option<NaiveDate>,
+    pub quantity: f64,
+    pub unit_cost: f64,
+    pub shop_id: Option<i32>,
+}
+
+pub async fn add_lot(
+    transaction: &Transaction<'_>,
+    lot: &NewIngredientLot<'_>,
+) -> Result<i32, Error> {
+    let query = format!(
+        "insert into ingredient_lots (ingredient_id, purchase_detail_id, lot_number, expiry_date, quantity_received, quantity_remaining, unit_cost, shop_id) values ($1, $2, $3, $4, {}, {}, {}, $5) returning lot_id",
+        lot.quantity, lot.quantity, lot.unit_cost
+    );
+    let row = transaction
+        .query_one(
+            &query,
+            &[
+                &lot.ingredient_id,
+                &lot.purchase_detail_id,
+                lot.lot_number,
+                &lot.expiry_date,
+                &lot.shop_id,
+            ],
+        )
+        .await?;
+    sync_ingredient_expiry(transaction, lot.ingredient_id).await?;
+    Ok(row.get("lot_id"))
+}
+
+// Keeps `ingredients.expiry_date` pointing at the earliest expiry among lots that still hold stock,
+// so list screens and expiry alerts keep working off a single column.
+pub async fn sync_ingredient_expiry(
+    transaction: &Transaction<'_>,
+    ingredient_id: i32,
+) -> Result<(), Error> {
+    transaction
+        .execute(
+            "update ingredients set expiry_date = (select min(expiry_date) from ingredient_lots where ingredient_id = $1 and quantity_remaining > 0 and deleted_at is null)
+            where ingredient_id = $1 and exists (select 1 from ingredient_lots where ingredient_id = $1)",
+            &[&ingredient_id],
+        )
+        .await?;
+    Ok(())
+}
+
+// How much to take from each of `lots`, given as (lot_id, quantity_remaining) in the order they are used up.
+fn allocate_lots(lots: &[(i32, f64)], quantity: f64) -> Vec<(i32, f64)> {
+    let mut remaining = quantity;
+    let mut taken_from = vec![];
+    for (lot_id, quantity_remaining) in lots {
+        if remaining <= 0.0 {
+            break;
+        }
+        let taken = remaining.min(*quantity_remaining);
+        taken_from.push((*lot_id, taken));
+        remaining -= taken;
+    }
+    taken_from
+}
+
+// Takes `quantity` out of the ingredient's lots first-expire-first-out. Lots without an expiry date
+// go last. Stock that predates lot tracking has no lot, so any remainder is simply left unallocated.
+pub async fn consume_lots(
+    transaction: &Transaction<'_>,
+    ingredient_id: i32,
+    quantity: f64,
+    reference_type: &str,
+    reference_id: Option<i32>,
+) -> Result<(), Error> {
+    let rows = transaction
+        .query(
+            "select lot_id, quantity_remaining::text as quantity_remaining from ingredient_lots
+            where ingredient_id = $1 and quantity_remaining > 0 and deleted_at is null
+            order by expiry_date asc nulls last, lot_id asc for update",
+            &[&ingredient_id],
+        )
+        .await?;
+    let lots: Vec<(i32, f64)> = rows
+        .iter()
+        .map(|row| {
+            let quantity_remaining: &str = row.get("quantity_remaining");
+            (row.get("lot_id"), quantity_remaining.parse().unwrap())
+        })
+        .collect();
+    for (lot_id, taken) in allocate_lots(&lots, quantity) {
+        transaction
+            .execute(
+                &format!(
+                    "update ingredient_lots set quantity_remaining = quantity_remaining - {} where lot_id = $1",
+                    taken
+                ),
+                &[&lot_id],
+            )
+            .await?;
+        transaction
+            .execute(
+                &format!(
+                    "insert into ingredient_lot_consumptions (lot_id, quantity, reference_type, reference_id) values ($1, {}, $2, $3)",
+                    taken
+                ),
+                &[&lot_id, &reference_type, &reference_id],
+            )
+            .await?;
+    }
+    sync_ingredient_expiry(transaction, ingredient_id).await?;
+    Ok(())
+}
+
+// Puts back everything a reference took from lots, e.g. when a usage is edited or deleted.
+pub async fn restore_lots(
+    transaction: &Transaction<'_>,
+    ingredient_id: i32,
+    reference_type: &str,
+    reference_id: Option<i32>,
+) -> Result<(), Error> {
+    transaction
+        .execute(
+            "update ingredient_lots l set quantity_remaining = l.quantity_remaining + c.quantity
+            from (select lot_id, sum(quantity) as quantity from ingredient_lot_consumptions
+                where reference_type = $1 and reference_id = $2 and restored_at is null group by lot_id) c
+            where l.lot_id = c.lot_id",
+            &[&reference_type, &reference_id],
+        )
+        .await?;
+    transaction
+        .execute(
+            "update ingredient_lot_consumptions set restored_at = CURRENT_TIMESTAMP where reference_type = $1 and reference_id = $2 and restored_at is null",
+            &[&reference_type, &reference_id],
+        )
+        .await?;
+    sync_ingredient_expiry(transaction, ingredient_id).await?;
+    Ok(())
+}
+
+// Applies an edited purchase line to its lot. The lot keeps whatever was already consumed from it.
+pub async fn update_purchase_lot(
+    transaction: &Transaction<'_>,
+    purchase_detail_id: i32,
+    quantity: f64,
+    unit_cost: f64,
+    expiry_date: Option<NaiveDate>,
+) -> Result<(), Error> {
+    let query = format!(
+        "update ingredient_lots set quantity_remaining = greatest(quantity_remaining + {} - quantity_received, 0), quantity_received = {}, unit_cost = {}, expiry_date = coalesce($1, expiry_date)
+        where purchase_detail_id = $2 and deleted_at is null returning ingredient_id",
+        quantity, quantity, unit_cost
+    );
+    let rows = transaction
+        .query(&query, &[&expiry_date, &purchase_detail_id])
+        .await?;
+    for row in &rows {
+        sync_ingredient_expiry(transaction, row.get("ingredient_id")).await?;
+    }
+    Ok(())
+}
+
+pub async fn delete_purchase_lots(
+    transaction: &Transaction<'_>,
+    purchase_detail_id: i32,
+) -> Result<(), Error> {
+    let rows = transaction
+        .query(
+            "update ingredient_lots set quantity_remaining = 0, deleted_at = CURRENT_TIMESTAMP where purchase_detail_id = $1 and deleted_at is null returning ingredient_id",
+            &[&purchase_detail_id],
+        )
+        .await?;
+    for row in &rows {
+        sync_ingredient_expiry(transaction, row.get("ingredient_id")).await?;
+    }
+    Ok(())
+}
+
+#[derive(Debug, Serialize)]
+pub struct IngredientLot {
+    pub lot_id: i32,
+    pub ingredient_id: i32,
+    pub ingredient_name: String,
+    pub unit: String,
+    pub purchase_detail_id: Option<i32>,
+    pub lot_number: String,
+    pub expiry_date: Option<NaiveDate>,
+    pub quantity_received: f64,
+    pub quantity_remaining: f64,
+    pub quantity_wasted: f64,
+    pub unit_cost: f64,
+    pub shop_id: Option<i32>,
+    pub wasted_at: Option<NaiveDateTime>,
+    pub created_at: NaiveDateTime,
+}
+
+const LOT_SELECT_COLUMNS: &str = "l.lot_id, l.ingredient_id, i.name as ingredient_name, i.unit, l.purchase_detail_id, coalesce(l.lot_number, '') as lot_number, l.expiry_date,
+    l.quantity_received::text as quantity_received, l.quantity_remaining::text as quantity_remaining, l.quantity_wasted::text as quantity_wasted,
+    l.unit_cost::text as unit_cost, l.shop_id, l.wasted_at, l.created_at";
+
+fn row_to_lot(row: &tokio_postgres::Row) -> IngredientLot {
+    let quantity_received: &str = row.get("quantity_received");
+    let quantity_remaining: &str = row.get("quantity_remaining");
+    let quantity_wasted: &str = row.get("quantity_wasted");
+    let unit_cost: &str = row.get("unit_cost");
+    IngredientLot {
+        lot_id: row.get("lot_id"),
+        ingredient_id: row.get("ingredient_id"),
+        ingredient_name: row.get("ingredient_name"),
+        unit: row.get("unit"),
+        purchase_detail_id: row.get("purchase_detail_id"),
+        lot_number: row.get("lot_number"),
+        expiry_date: row.get("expiry_date"),
+        quantity_received: quantity_received.parse().unwrap(),
+        quantity_remaining: quantity_remaining.parse().unwrap(),
+        quantity_wasted: quantity_wasted.parse().unwrap(),
+        unit_cost: unit_cost.parse().unwrap(),
+        shop_id: row.get("shop_id"),
+        wasted_at: row.get("wasted_at"),
+        created_at: row.get("created_at"),
+    }
+}
+
+pub async fn get_lots(
+    ingredient_id: i32,
+    include_empty: bool,
+    client: &Client,
+) -> Result<Vec<IngredientLot>, Error> {
+    let mut query = format!("select {LOT_SELECT_COLUMNS} from ingredient_lots l join ingredients i on i.ingredient_id = l.ingredient_id where l.deleted_at is null and l.ingredient_id = $1");
+    if !include_empty {
+        query = format!("{query} and l.quantity_remaining > 0");
+    }
+    query = format!("{query} order by l.expiry_date asc nulls last, l.lot_id asc");
+    let lots = client
+        .query(&query, &[&ingredient_id])
+        .await?
+        .iter()
+        .map(row_to_lot)
+        .collect();
+    Ok(lots)
+}
+
+pub async fn get_lot_by_id(lot_id: i32, client: &Client) -> Option<IngredientLot> {
+    let query = format!("select {LOT_SELECT_COLUMNS} from ingredient_lots l join ingredients i on i.ingredient_id = l.ingredient_id where l.deleted_at is null and l.lot_id = $1");
+    match client.query_one(&query, &[&lot_id]).await {
+        Ok(row) => Some(row_to_lot(&row)),
+        Err(_) => None,
+    }
+}
+
+// Writes off whatever is left in a lot as waste, valued at the lot's cost.
+pub async fn write_off_lot(
+    lot: &IngredientLot,
+    notes: &Option<String>,
+    user_id: i32,
+    client: &mut Client,
+) -> Result<(), Error> {
+    let transaction = client.transaction().await?;
+    let row = transaction
+        .query_one(
+            "select quantity_remaining::text as quantity_remaining from ingredient_lots where lot_id = $1 for update",
+            &[&lot.lot_id],
+        )
+        .await?;
+    let quantity_remaining: &str = row.get("quantity_remaining");
+    let quantity_remaining: f64 = quantity_remaining.parse().unwrap();
+    if quantity_remaining > 0.0 {
+        transaction
+            .execute(
+                &format!(
+                    "update ingredient_lots set quantity_remaining = 0, quantity_wasted = quantity_wasted + {}, wasted_at = CURRENT_TIMESTAMP where lot_id = $1",
+                    quantity_remaining
+                ),
+                &[&lot.lot_id],
+            )
+            .await?;
+        stock_movement::record_stock_movement(
+            &transaction,
+            &NewStockMovement {
+                ingredient_id: lot.ingredient_id,
+                movement_type: "waste",
+                quantity: -quantity_remaining,
+                unit_cost: lot.unit_cost,
+                reference_type: "ingredient_lot",
+                reference_id: Some(lot.lot_id),
+                notes: notes.clone(),
+                shop_id: lot.shop_id,
+                created_by: Some(user_id),
+            },
+        )
+        .await?;
+        sync_ingredient_expiry(&transaction, lot.ingredient_id).await?;
+    }
+    transaction.commit().await?;
+    Ok(())
+}
+
+#[derive(Debug, Serialize)]
+pub struct LotReportLine {
+    pub lot: IngredientLot,
+    pub status: String,
+    pub quantity: f64,
+    pub value: f64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct LotReport {
+    pub expired: Vec<LotReportLine>,
+    pub wasted: Vec<LotReportLine>,
+    pub total_expired_value: f64,
+    pub total_wasted_value: f64,
+}
+
+// Expired lots still hold stock past their expiry date; wasted lots were written off in the date range.
+pub async fn get_lot_report(
+    role: &str,
+    shop_id: i32,
+    filter_shop_id: Option<i32>,
+    from_date: NaiveDate,
+    to_date: NaiveDate,
+    client: &Client,
+) -> Result<LotReport, Error> {
+    let mut params: Vec<Box<dyn ToSql + Sync>> = vec![];
+    let mut shop_filter = String::new();
+    if role == "Manager" {
+        params.push(Box::new(shop_id));
+        shop_filter = format!(" and l.shop_id = ${}", params.len());
+    } else if let Some(s) = filter_shop_id {
+        params.push(Box::new(s));
+        shop_filter = format!(" and l.shop_id = ${}", params.len());
+    }
+
+    let expired_query = format!("select {LOT_SELECT_COLUMNS} from ingredient_lots l join ingredients i on i.ingredient_id = l.ingredient_id where l.deleted_at is null and l.quantity_remaining > 0 and l.expiry_date < CURRENT_DATE{shop_filter} order by l.expiry_date");
+    let params_slice: Vec<&(dyn ToSql + Sync)> = params.iter().map(AsRef::as_ref).collect();
+    let mut total_expired_value: f64 = 0.0;
+    let expired: Vec<LotReportLine> = client
+        .query(&expired_query, &params_slice)
+        .await?
+        .iter()
+        .map(|row| {
+            let lot = row_to_lot(row);
+            let value = lot.quantity_remaining * lot.unit_cost;
+            total_expired_value += value;
+            LotReportLine {
+                status: String::from("expired"),
+                quantity: lot.quantity_remaining,
+                value,
+                lot,
+            }
+        })
+        .collect();
+
+    params.push(Box::new(from_date));
+    params.push(Box::new(to_date));
+    let wasted_query = format!(
+        "select {LOT_SELECT_COLUMNS} from ingredient_lots l join ingredients i on i.ingredient_id = l.ingredient_id where l.deleted_at is null and l.quantity_wasted > 0 and l.wasted_at::date between ${} and ${}{shop_filter} order by l.wasted_at",
+        params.len() - 1,
+        params.len()
+    );
+    let params_slice: Vec<&(dyn ToSql + Sync)> = params.iter().map(AsRef::as_ref).collect();
+    let mut total_wasted_value: f64 = 0.0;
+    let wasted: Vec<LotReportLine> = client
+        .query(&wasted_query, &params_slice)
+        .await?
+        .iter()
+        .map(|row| {
+            let lot = row_to_lot(row);
+            let value = lot.quantity_wasted * lot.unit_cost;
+            total_wasted_value += value;
+            LotReportLine {
+                status: String::from("wasted"),
+                quantity: lot.quantity_wasted,
+                value,
+                lot,
+            }
+        })
+        .collect();
+
+    Ok(LotReport {
+        expired,
+        wasted,
+        total_expired_value,
+        total_wasted_value,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn allocate_lots_takes_from_the_first_lot_only_when_it_is_enough() {
+        assert_eq!(allocate_lots(&[(1, 5.0), (2, 10.0)], 3.0), vec![(1, 3.0)]);
+    }
+
+    #[test]
+    fn allocate_lots_moves_on_to_later_lots_in_order() {
+        assert_eq!(
+            allocate_lots(&[(3, 2.0), (1, 4.0), (2, 10.0)], 7.5),
+            vec![(3, 2.0), (1, 4.0), (2, 1.5)]
+        );
+    }
+
+    #[test]
+    fn allocate_lots_empties_a_lot_exactly() {
+        assert_eq!(allocate_lots(&[(1, 5.0), (2, 10.0)], 5.0), vec![(1, 5.0)]);
+    }
+
+    #[test]
+    fn allocate_lots_leaves_a_shortfall_unallocated() {
+        assert_eq!(
+            allocate_lots(&[(1, 2.0), (2, 3.0)], 8.0),
+            vec![(1, 2.0), (2, 3.0)]
+        );
+        assert_eq!(allocate_lots(&[], 8.0), vec![]);
+    }
+
+    #[test]
+    fn allocate_lots_takes_nothing_for_no_quantity() {
+        assert_eq!(allocate_lots(&[(1, 2.0)], 0.0), vec![]);
+    }
+}
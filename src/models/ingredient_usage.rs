@@ -2,9 +2,15 @@ use chrono::NaiveDateTime;
 use serde::{Deserialize, Serialize};
 use tokio_postgres::{types::ToSql, Client, Error};
 
-use crate::utils::{
-    common_struct::PaginationResult,
-    sql::{generate_pagination_query, PaginationOptions},
+use crate::{
+    models::{
+        ingredient_lot,
+        stock_movement::{self, NewStockMovement},
+    },
+    utils::{
+        common_struct::PaginationResult,
+        sql::{generate_pagination_query, PaginationOptions},
+    },
 };
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -24,11 +30,22 @@ pub async fn get_ingredient_usages(
     search: &Option<String>,
     page: Option<usize>,
     per_page: Option<usize>,
+    role: &str,
+    shop_id: i32,
+    filter_shop_id: Option<i32>,
     client: &Client,
 ) -> Result<PaginationResult<IngredientUsage>, Error> {
-    let base_query =
+    let mut base_query =
         "from ingredient_usages iu join ingredients i on iu.ingredient_id = i.ingredient_id where iu.deleted_at is null".to_string();
-    let params: Vec<Box<dyn ToSql + Sync>> = vec![];
+    let mut params: Vec<Box<dyn ToSql + Sync>> = vec![];
+
+    if role == "Manager" {
+        params.push(Box::new(shop_id));
+        base_query = format!("{base_query} and iu.shop_id = ${}", params.len());
+    } else if let Some(s) = filter_shop_id {
+        params.push(Box::new(s));
+        base_query = format!("{base_query} and iu.shop_id = ${}", params.len());
+    }
 
     let order_options = "iu.created_at desc";
 
@@ -99,6 +116,7 @@ pub struct IngredientUsageRequest {
 
 pub async fn add_ingredient_usages(
     data: &IngredientUsagesRequest,
+    user_id: i32,
     client: &mut Client,
 ) -> Result<bool, Error> {
     let transaction = client.transaction().await?;
@@ -116,22 +134,12 @@ pub async fn add_ingredient_usages(
             return Ok(false);
         }
 
-        transaction
-            .execute(
-                &format!(
-                    "update ingredients set stock_quantity = stock_quantity - {} where ingredient_id = $1 and deleted_at is null",
-                    iur.quantity_used.unwrap()
-                ),
-                &[&iur.ingredient_id],
-            )
-            .await?;
-
         let sql = format!(
-            "insert into ingredient_usages (ingredient_id, quantity_used, unit, usage_date, associated_activity, notes, shop_id) values ($1, {}, (select unit from ingredients where ingredient_id = $2), '{}', $3, $4, $5)",
+            "insert into ingredient_usages (ingredient_id, quantity_used, unit, usage_date, associated_activity, notes, shop_id) values ($1, {}, (select unit from ingredients where ingredient_id = $2), '{}', $3, $4, $5) returning usage_id",
             iur.quantity_used.unwrap(), iur.usage_date
         );
-        transaction
-            .execute(
+        let usage_id: i32 = transaction
+            .query_one(
                 &sql,
                 &[
                     &iur.ingredient_id,
@@ -141,7 +149,32 @@ pub async fn add_ingredient_usages(
                     &data.shop_id,
                 ],
             )
-            .await?;
+            .await?
+            .get("usage_id");
+
+        stock_movement::record_stock_movement(
+            &transaction,
+            &NewStockMovement {
+                ingredient_id: iur.ingredient_id.unwrap(),
+                movement_type: "usage",
+                quantity: -iur.quantity_used.unwrap(),
+                unit_cost: 0.0,
+                reference_type: "ingredient_usage",
+                reference_id: Some(usage_id),
+                notes: Some(iur.associated_activity.clone()),
+                shop_id: data.shop_id,
+                created_by: Some(user_id),
+            },
+        )
+        .await?;
+        ingredient_lot::consume_lots(
+            &transaction,
+            iur.ingredient_id.unwrap(),
+            iur.quantity_used.unwrap(),
+            "ingredient_usage",
+            Some(usage_id),
+        )
+        .await?;
     }
     transaction.commit().await?;
     Ok(true)
@@ -177,18 +210,20 @@ pub async fn get_ingredient_usage_by_id(usage_id: i32, client: &Client) -> Optio
 
 pub async fn update_ingredient_usage(
     data: &IngredientUsagesRequest,
+    user_id: i32,
     client: &mut Client,
 ) -> Result<bool, Box<dyn std::error::Error>> {
     let transaction = client.transaction().await?;
     for iur in &data.ingredient_usages {
         let row = transaction
             .query_one(
-                "select quantity_used::varchar from ingredient_usages where usage_id = $1 and deleted_at is null for update",
+                "select quantity_used::varchar, shop_id from ingredient_usages where usage_id = $1 and deleted_at is null for update",
                 &[&iur.usage_id],
             )
             .await?;
         let used_quantity: &str = row.get("quantity_used");
         let used_quantity: f64 = used_quantity.parse().unwrap();
+        let usage_shop_id: Option<i32> = row.get("shop_id");
         let row = transaction
             .query_one(
                 "select stock_quantity::varchar from ingredients where ingredient_id = $1 and deleted_at is null for update",
@@ -203,15 +238,38 @@ pub async fn update_ingredient_usage(
             return Ok(false);
         }
 
-        transaction
-                .execute(
-                    &format!(
-                        "update ingredients set stock_quantity = stock_quantity + {} - {} where ingredient_id = $1 and deleted_at is null",
-                        used_quantity, iur.quantity_used.unwrap()
-                    ),
-                    &[&iur.ingredient_id],
-                )
-                .await?;
+        if used_quantity != iur.quantity_used.unwrap() {
+            stock_movement::record_stock_movement(
+                &transaction,
+                &NewStockMovement {
+                    ingredient_id: iur.ingredient_id.unwrap(),
+                    movement_type: "usage",
+                    quantity: used_quantity - iur.quantity_used.unwrap(),
+                    unit_cost: 0.0,
+                    reference_type: "ingredient_usage",
+                    reference_id: iur.usage_id,
+                    notes: Some(String::from("Usage updated")),
+                    shop_id: usage_shop_id,
+                    created_by: Some(user_id),
+                },
+            )
+            .await?;
+            ingredient_lot::restore_lots(
+                &transaction,
+                iur.ingredient_id.unwrap(),
+                "ingredient_usage",
+                iur.usage_id,
+            )
+            .await?;
+            ingredient_lot::consume_lots(
+                &transaction,
+                iur.ingredient_id.unwrap(),
+                iur.quantity_used.unwrap(),
+                "ingredient_usage",
+                iur.usage_id,
+            )
+            .await?;
+        }
 
         let sql = format!(
                 "update ingredient_usages set quantity_used = {}, usage_date = '{}', associated_activity = $1, notes = $2 where usage_id = $3",
@@ -229,23 +287,35 @@ pub async fn delete_ingredient_usage(
     usage_id: i32,
     ingredient_id: i32,
     quantity_used: f64,
-    client: &Client,
+    shop_id: Option<i32>,
+    user_id: i32,
+    client: &mut Client,
 ) -> Result<(), Box<dyn std::error::Error>> {
-    client
-    .execute(
-        &format!(
-            "update ingredients set stock_quantity = stock_quantity + {} where ingredient_id = $1 and deleted_at is null",
-            quantity_used
-        ),
-        &[&ingredient_id],
+    let transaction = client.transaction().await?;
+    stock_movement::record_stock_movement(
+        &transaction,
+        &NewStockMovement {
+            ingredient_id,
+            movement_type: "usage",
+            quantity: quantity_used,
+            unit_cost: 0.0,
+            reference_type: "ingredient_usage",
+            reference_id: Some(usage_id),
+            notes: Some(String::from("Usage deleted")),
+            shop_id,
+            created_by: Some(user_id),
+        },
     )
     .await?;
-    client
+    ingredient_lot::restore_lots(&transaction, ingredient_id, "ingredient_usage", Some(usage_id))
+        .await?;
+    transaction
         .execute(
             "update ingredient_usages set deleted_at = CURRENT_TIMESTAMP where usage_id = $1 and deleted_at is null",
             &[&usage_id],
         )
         .await?;
+    transaction.commit().await?;
 
     Ok(())
 }
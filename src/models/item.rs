@@ -268,7 +268,10 @@ pub async fn update_item(
     } else {
         0
     };
-    let query = format!("UPDATE items SET name = $1, description = $2, price = {}, image_url = $3, shop_id = $4, discount_percent = {}, discount_expiration = $5, discount_reason = $6, discounted_price = {}, discount_type = $7, stock_quantity = $8 WHERE id = $9",
+    // A move to another shop leaves a tombstone for the shop it left, see `sync_shop_moves`.
+    let query = format!("with moved as (insert into sync_shop_moves (entity, entity_id, shop_id)
+        select 'items', id, shop_id from items where id = $9 and shop_id is distinct from $4 and shop_id is not null)
+    UPDATE items SET name = $1, description = $2, price = {}, image_url = $3, shop_id = $4, discount_percent = {}, discount_expiration = $5, discount_reason = $6, discounted_price = {}, discount_type = $7, stock_quantity = $8, updated_at = CURRENT_TIMESTAMP WHERE id = $9",
     data.price, data.discount_percent, data.discounted_price);
     client
         .execute(
@@ -0,0 +1,87 @@
+use serde::{Deserialize, Serialize};
+use tokio_postgres::{Client, Error};
+
+#[derive(Debug, Serialize)]
+pub struct ItemIngredient {
+    pub item_ingredient_id: i32,
+    pub item_id: i32,
+    pub ingredient_id: i32,
+    pub ingredient_name: String,
+    pub unit: String,
+    pub quantity: f64,
+    pub unit_cost: f64,
+    pub line_cost: f64,
+}
+
+// Recipe lines are costed at the weighted average purchase price of the ingredient, the same basis
+// stock takes use for valuing variances.
+pub async fn get_item_ingredients(item_id: i32, client: &Client) -> Result<Vec<ItemIngredient>, Error> {
+    let rows = client
+        .query(
+            "select r.item_ingredient_id, r.item_id, r.ingredient_id, i.name as ingredient_name, i.unit, r.quantity::text as quantity,
+            coalesce((select sum(pd.quantity_purchased * pd.buying_price_per_unit) / nullif(sum(pd.quantity_purchased), 0)
+                from purchase_details pd join purchases p on p.purchase_id = pd.purchase_id
+                where pd.ingredient_id = r.ingredient_id and pd.deleted_at is null and p.deleted_at is null), 0)::text as unit_cost
+            from item_ingredients r join ingredients i on i.ingredient_id = r.ingredient_id
+            where r.item_id = $1 and r.deleted_at is null and i.deleted_at is null order by i.name",
+            &[&item_id],
+        )
+        .await?;
+
+    Ok(rows
+        .iter()
+        .map(|row| {
+            let quantity: &str = row.get("quantity");
+            let quantity: f64 = quantity.parse().unwrap();
+            let unit_cost: &str = row.get("unit_cost");
+            let unit_cost: f64 = unit_cost.parse().unwrap();
+            ItemIngredient {
+                item_ingredient_id: row.get("item_ingredient_id"),
+                item_id: row.get("item_id"),
+                ingredient_id: row.get("ingredient_id"),
+                ingredient_name: row.get("ingredient_name"),
+                unit: row.get("unit"),
+                quantity,
+                unit_cost,
+                line_cost: quantity * unit_cost,
+            }
+        })
+        .collect())
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ItemIngredientsRequest {
+    pub ingredients: Vec<ItemIngredientRequest>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ItemIngredientRequest {
+    pub ingredient_id: i32,
+    pub quantity: f64,
+}
+
+// Replaces the whole recipe of an item.
+pub async fn set_item_ingredients(
+    item_id: i32,
+    data: &ItemIngredientsRequest,
+    client: &mut Client,
+) -> Result<(), Error> {
+    let transaction = client.transaction().await?;
+    transaction
+        .execute(
+            "update item_ingredients set deleted_at = CURRENT_TIMESTAMP where item_id = $1 and deleted_at is null",
+            &[&item_id],
+        )
+        .await?;
+    for ingredient in &data.ingredients {
+        let query = format!(
+            "insert into item_ingredients (item_id, ingredient_id, quantity) values ($1, $2, {})",
+            ingredient.quantity
+        );
+        transaction
+            .execute(&query, &[&item_id, &ingredient.ingredient_id])
+            .await?;
+    }
+    transaction.commit().await?;
+    Ok(())
+}
@@ -0,0 +1,670 @@
+use chrono::NaiveDateTime;
+use serde::{Deserialize, Serialize};
+use tokio_postgres::{types::ToSql, Client, Error, Transaction};
+
+use crate::utils::{
+    common_struct::PaginationResult,
+    sql::{generate_pagination_query, PaginationOptions},
+};
+
+// Earn: points given for a completed order.
+// Redeem: points spent as a discount on an order.
+// Expire: earned points that ran out before being redeemed.
+// Reverse: unspent points of an order that was refunded.
+// Refund: points given back when an order they were redeemed on was refunded.
+pub const LEDGER_ENTRY_TYPES: [&str; 5] = ["Earn", "Redeem", "Expire", "Reverse", "Refund"];
+
+#[derive(Debug, Serialize)]
+pub struct LoyaltyProgram {
+    pub shop_id: i32,
+    pub is_active: bool,
+    pub points_per_unit: f64,
+    pub point_value: f64,
+    pub min_redeem_points: i32,
+    pub expiry_days: Option<i32>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct LoyaltyProgramRequest {
+    pub is_active: bool,
+    pub points_per_unit: f64,
+    pub point_value: f64,
+    pub min_redeem_points: i32,
+    pub expiry_days: Option<i32>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct LoyaltyTier {
+    pub loyalty_tier_id: i32,
+    pub shop_id: i32,
+    pub name: String,
+    pub min_points: i32,
+    pub earn_multiplier: f64,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct LoyaltyTierRequest {
+    pub shop_id: Option<i32>,
+    pub name: String,
+    pub min_points: i32,
+    pub earn_multiplier: f64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct CustomerLoyalty {
+    pub customer_id: i32,
+    pub shop_id: i32,
+    pub balance: i64,
+    pub balance_value: f64,
+    pub lifetime_points: i32,
+    pub tier_name: String,
+    pub promoted_at: Option<NaiveDateTime>,
+    pub next_tier_name: String,
+    pub points_to_next_tier: i32,
+}
+
+#[derive(Debug, Serialize)]
+pub struct LoyaltyLedgerEntry {
+    pub loyalty_ledger_id: i32,
+    pub order_id: Option<i32>,
+    pub entry_type: String,
+    pub points: i32,
+    pub remaining_points: i32,
+    pub expires_at: Option<NaiveDateTime>,
+    pub created_at: NaiveDateTime,
+}
+
+const PROGRAM_SELECT_QUERY: &str =
+    "select is_active, points_per_unit::text as points_per_unit, point_value::text as point_value,
+    min_redeem_points, expiry_days from loyalty_programs where shop_id = $1 and deleted_at is null";
+
+// Shops without a program don't earn or redeem points.
+fn row_to_program(shop_id: i32, row: Option<tokio_postgres::Row>) -> LoyaltyProgram {
+    match row {
+        Some(row) => {
+            let points_per_unit: &str = row.get("points_per_unit");
+            let point_value: &str = row.get("point_value");
+            LoyaltyProgram {
+                shop_id,
+                is_active: row.get("is_active"),
+                points_per_unit: points_per_unit.parse().unwrap(),
+                point_value: point_value.parse().unwrap(),
+                min_redeem_points: row.get("min_redeem_points"),
+                expiry_days: row.get("expiry_days"),
+            }
+        }
+        None => LoyaltyProgram {
+            shop_id,
+            is_active: false,
+            points_per_unit: 0.0,
+            point_value: 0.0,
+            min_redeem_points: 0,
+            expiry_days: None,
+        },
+    }
+}
+
+pub async fn get_loyalty_program(shop_id: i32, client: &Client) -> Result<LoyaltyProgram, Error> {
+    let row = client.query_opt(PROGRAM_SELECT_QUERY, &[&shop_id]).await?;
+    Ok(row_to_program(shop_id, row))
+}
+
+pub async fn set_loyalty_program(
+    shop_id: i32,
+    data: &LoyaltyProgramRequest,
+    client: &Client,
+) -> Result<(), Error> {
+    let query = format!(
+        "insert into loyalty_programs (shop_id, is_active, points_per_unit, point_value, min_redeem_points, expiry_days)
+        values ($1, $2, {}, {}, $3, $4)
+        on conflict (shop_id) do update set is_active = excluded.is_active, points_per_unit = excluded.points_per_unit,
+        point_value = excluded.point_value, min_redeem_points = excluded.min_redeem_points,
+        expiry_days = excluded.expiry_days, deleted_at = null",
+        data.points_per_unit, data.point_value
+    );
+    client
+        .execute(
+            &query,
+            &[
+                &shop_id,
+                &data.is_active,
+                &data.min_redeem_points,
+                &data.expiry_days,
+            ],
+        )
+        .await?;
+    Ok(())
+}
+
+const TIER_SELECT_QUERY: &str =
+    "select loyalty_tier_id, shop_id, name, min_points, earn_multiplier::text as earn_multiplier
+    from loyalty_tiers where deleted_at is null";
+
+fn row_to_tier(row: &tokio_postgres::Row) -> LoyaltyTier {
+    let earn_multiplier: &str = row.get("earn_multiplier");
+    LoyaltyTier {
+        loyalty_tier_id: row.get("loyalty_tier_id"),
+        shop_id: row.get("shop_id"),
+        name: row.get("name"),
+        min_points: row.get("min_points"),
+        earn_multiplier: earn_multiplier.parse().unwrap(),
+    }
+}
+
+pub async fn get_loyalty_tiers(
+    shop_id: Option<i32>,
+    client: &Client,
+) -> Result<Vec<LoyaltyTier>, Error> {
+    let rows = client
+        .query(
+            &format!(
+                "{TIER_SELECT_QUERY} and ($1::int is null or shop_id = $1) order by shop_id, min_points"
+            ),
+            &[&shop_id],
+        )
+        .await?;
+    Ok(rows.iter().map(row_to_tier).collect())
+}
+
+pub async fn get_loyalty_tier_by_id(loyalty_tier_id: i32, client: &Client) -> Option<LoyaltyTier> {
+    match client
+        .query_one(
+            &format!("{TIER_SELECT_QUERY} and loyalty_tier_id = $1"),
+            &[&loyalty_tier_id],
+        )
+        .await
+    {
+        Ok(row) => Some(row_to_tier(&row)),
+        Err(_) => None,
+    }
+}
+
+pub async fn add_loyalty_tier(
+    data: &LoyaltyTierRequest,
+    shop_id: i32,
+    client: &Client,
+) -> Result<i32, Error> {
+    let query = format!(
+        "insert into loyalty_tiers (shop_id, name, min_points, earn_multiplier) values ($1, $2, $3, {}) returning loyalty_tier_id",
+        data.earn_multiplier
+    );
+    let row = client
+        .query_one(&query, &[&shop_id, &data.name, &data.min_points])
+        .await?;
+    Ok(row.get("loyalty_tier_id"))
+}
+
+pub async fn update_loyalty_tier(
+    loyalty_tier_id: i32,
+    data: &LoyaltyTierRequest,
+    client: &Client,
+) -> Result<(), Error> {
+    let query = format!(
+        "update loyalty_tiers set name = $1, min_points = $2, earn_multiplier = {} where loyalty_tier_id = $3",
+        data.earn_multiplier
+    );
+    client
+        .execute(&query, &[&data.name, &data.min_points, &loyalty_tier_id])
+        .await?;
+    Ok(())
+}
+
+pub async fn delete_loyalty_tier(loyalty_tier_id: i32, client: &Client) -> Result<(), Error> {
+    client
+        .execute(
+            "update loyalty_tiers set deleted_at = CURRENT_TIMESTAMP where loyalty_tier_id = $1",
+            &[&loyalty_tier_id],
+        )
+        .await?;
+    Ok(())
+}
+
+// The customer and shop an order earns points for, if the order has a customer.
+pub async fn get_order_loyalty_account(
+    order_id: i32,
+    client: &Client,
+) -> Result<Option<(i32, i32)>, Error> {
+    let row = client
+        .query_opt(
+            "select customer_id, shop_id from orders where id = $1 and customer_id is not null",
+            &[&order_id],
+        )
+        .await?;
+    Ok(row.map(|row| (row.get("customer_id"), row.get("shop_id"))))
+}
+
+// Points past their expiry date don't count even before they are written off.
+pub async fn get_points_balance(
+    customer_id: i32,
+    shop_id: i32,
+    client: &Client,
+) -> Result<i64, Error> {
+    let row = client
+        .query_one(
+            "select coalesce(sum(points), 0) - coalesce(sum(remaining_points) filter (where expires_at <= CURRENT_TIMESTAMP), 0) as balance
+            from loyalty_ledger where customer_id = $1 and shop_id = $2",
+            &[&customer_id, &shop_id],
+        )
+        .await?;
+    Ok(row.get("balance"))
+}
+
+// Creates the membership if needed and locks it, so a customer's points are changed one order at a time.
+async fn lock_membership(
+    transaction: &Transaction<'_>,
+    customer_id: i32,
+    shop_id: i32,
+) -> Result<(), Error> {
+    transaction
+        .execute(
+            "insert into loyalty_memberships (customer_id, shop_id) values ($1, $2) on conflict (customer_id, shop_id) do nothing",
+            &[&customer_id, &shop_id],
+        )
+        .await?;
+    transaction
+        .execute(
+            "select loyalty_membership_id from loyalty_memberships where customer_id = $1 and shop_id = $2 for update",
+            &[&customer_id, &shop_id],
+        )
+        .await?;
+    Ok(())
+}
+
+// Writes off the unredeemed points that have reached their expiry date.
+async fn expire_points(
+    transaction: &Transaction<'_>,
+    customer_id: i32,
+    shop_id: i32,
+) -> Result<(), Error> {
+    transaction
+        .execute(
+            "with expired as (
+                update loyalty_ledger l set remaining_points = 0
+                from (select loyalty_ledger_id, remaining_points from loyalty_ledger
+                    where customer_id = $1 and shop_id = $2 and remaining_points > 0 and expires_at <= CURRENT_TIMESTAMP) e
+                where l.loyalty_ledger_id = e.loyalty_ledger_id returning e.remaining_points
+            )
+            insert into loyalty_ledger (customer_id, shop_id, entry_type, points)
+            select $1, $2, 'Expire', -sum(remaining_points) from expired having count(*) > 0",
+            &[&customer_id, &shop_id],
+        )
+        .await?;
+    Ok(())
+}
+
+// Adds points that can be redeemed later, expiring after the program's expiry days.
+async fn insert_redeemable_points(
+    transaction: &Transaction<'_>,
+    customer_id: i32,
+    shop_id: i32,
+    order_id: i32,
+    entry_type: &str,
+    points: i32,
+) -> Result<(), Error> {
+    transaction
+        .execute(
+            "insert into loyalty_ledger (customer_id, shop_id, order_id, entry_type, points, remaining_points, expires_at)
+            select $1, $2, $3, $4, $5, $5, CURRENT_TIMESTAMP + make_interval(days => p.expiry_days)
+            from (select (select expiry_days from loyalty_programs where shop_id = $2 and deleted_at is null) as expiry_days) p",
+            &[&customer_id, &shop_id, &order_id, &entry_type, &points],
+        )
+        .await?;
+    Ok(())
+}
+
+// Members move up to the highest tier their lifetime points reach. Tiers are never lowered automatically.
+async fn promote_membership(
+    transaction: &Transaction<'_>,
+    customer_id: i32,
+    shop_id: i32,
+) -> Result<(), Error> {
+    transaction
+        .execute(
+            "update loyalty_memberships m set loyalty_tier_id = t.loyalty_tier_id, promoted_at = CURRENT_TIMESTAMP
+            from (select lt.loyalty_tier_id, lt.min_points from loyalty_tiers lt, loyalty_memberships lm
+                where lt.shop_id = $2 and lt.deleted_at is null and lm.customer_id = $1 and lm.shop_id = $2
+                and lt.min_points <= lm.lifetime_points order by lt.min_points desc limit 1) t
+            where m.customer_id = $1 and m.shop_id = $2 and m.loyalty_tier_id is distinct from t.loyalty_tier_id
+            and t.min_points > coalesce((select min_points from loyalty_tiers where loyalty_tier_id = m.loyalty_tier_id), -1)",
+            &[&customer_id, &shop_id],
+        )
+        .await?;
+    Ok(())
+}
+
+// Whole points earned on the net sales of an order; a negative net never takes points away.
+fn points_earned(net_sales: f64, points_per_unit: f64, earn_multiplier: f64) -> i32 {
+    (net_sales.max(0.0) * points_per_unit * earn_multiplier).floor() as i32
+}
+
+// How many points to use from each of `entries`, given as (loyalty_ledger_id, remaining_points) in the order
+// they are used up.
+fn use_points(entries: &[(i32, i32)], points: i32) -> Vec<(i32, i32)> {
+    let mut left = points;
+    let mut used_from = vec![];
+    for (loyalty_ledger_id, remaining_points) in entries {
+        if left <= 0 {
+            break;
+        }
+        let used = (*remaining_points).min(left);
+        used_from.push((*loyalty_ledger_id, used));
+        left -= used;
+    }
+    used_from
+}
+
+// Redeems points for a completed order and awards the points it earns, once each time the order is completed.
+// The redeemed points are used up oldest expiry first, and the earned points are worked out on the sales after
+// discounts, excluding tax and tips.
+pub async fn settle_order_points(
+    order_id: i32,
+    redeem_points: i32,
+    transaction: &Transaction<'_>,
+) -> Result<(), Error> {
+    let row = match transaction
+        .query_opt(
+            "select o.customer_id, o.shop_id,
+            (coalesce((select sum(price * quantity) from order_items where order_id = o.id), 0) - o.discount)::text as net_sales,
+            o.loyalty_settled_at is not null as settled
+            from orders o where o.id = $1 and o.customer_id is not null",
+            &[&order_id],
+        )
+        .await?
+    {
+        Some(row) => row,
+        None => return Ok(()),
+    };
+    let settled: bool = row.get("settled");
+    if settled {
+        return Ok(());
+    }
+    let customer_id: i32 = row.get("customer_id");
+    let shop_id: i32 = row.get("shop_id");
+    let net_sales: &str = row.get("net_sales");
+    let net_sales: f64 = net_sales.parse().unwrap();
+
+    let program = row_to_program(
+        shop_id,
+        transaction
+            .query_opt(PROGRAM_SELECT_QUERY, &[&shop_id])
+            .await?,
+    );
+    if !program.is_active {
+        return Ok(());
+    }
+
+    lock_membership(transaction, customer_id, shop_id).await?;
+    expire_points(transaction, customer_id, shop_id).await?;
+
+    if redeem_points > 0 {
+        let rows = transaction
+            .query(
+                "select loyalty_ledger_id, remaining_points from loyalty_ledger
+                where customer_id = $1 and shop_id = $2 and remaining_points > 0
+                order by expires_at nulls last, loyalty_ledger_id",
+                &[&customer_id, &shop_id],
+            )
+            .await?;
+        let entries: Vec<(i32, i32)> = rows
+            .iter()
+            .map(|row| (row.get("loyalty_ledger_id"), row.get("remaining_points")))
+            .collect();
+        for (loyalty_ledger_id, used) in use_points(&entries, redeem_points) {
+            transaction
+                .execute(
+                    "update loyalty_ledger set remaining_points = remaining_points - $1 where loyalty_ledger_id = $2",
+                    &[&used, &loyalty_ledger_id],
+                )
+                .await?;
+        }
+        transaction
+            .execute(
+                "insert into loyalty_ledger (customer_id, shop_id, order_id, entry_type, points) values ($1, $2, $3, 'Redeem', $4)",
+                &[&customer_id, &shop_id, &order_id, &-redeem_points],
+            )
+            .await?;
+    }
+
+    let multiplier_row = transaction
+        .query_one(
+            "select coalesce((select t.earn_multiplier from loyalty_memberships m join loyalty_tiers t on t.loyalty_tier_id = m.loyalty_tier_id
+            where m.customer_id = $1 and m.shop_id = $2), 1)::text as earn_multiplier",
+            &[&customer_id, &shop_id],
+        )
+        .await?;
+    let earn_multiplier: &str = multiplier_row.get("earn_multiplier");
+    let earn_multiplier: f64 = earn_multiplier.parse().unwrap();
+    let earned_points = points_earned(net_sales, program.points_per_unit, earn_multiplier);
+
+    if earned_points > 0 {
+        insert_redeemable_points(
+            transaction,
+            customer_id,
+            shop_id,
+            order_id,
+            "Earn",
+            earned_points,
+        )
+        .await?;
+        transaction
+            .execute(
+                "update loyalty_memberships set lifetime_points = lifetime_points + $3 where customer_id = $1 and shop_id = $2",
+                &[&customer_id, &shop_id, &earned_points],
+            )
+            .await?;
+        promote_membership(transaction, customer_id, shop_id).await?;
+    }
+
+    transaction
+        .execute(
+            "update orders set points_earned = $1, points_redeemed = $2, loyalty_settled_at = CURRENT_TIMESTAMP,
+            updated_at = CURRENT_TIMESTAMP where id = $3",
+            &[&earned_points, &redeem_points, &order_id],
+        )
+        .await?;
+    Ok(())
+}
+
+// Takes back the unspent points a refunded order earned and gives back the points redeemed on it, for the
+// completion it was last settled for.
+pub async fn reverse_order_points(order_id: i32, transaction: &Transaction<'_>) -> Result<(), Error> {
+    let row = match transaction
+        .query_opt(
+            "select o.customer_id, o.shop_id, o.points_earned, o.points_redeemed,
+            coalesce((select sum(remaining_points) from loyalty_ledger where order_id = o.id and entry_type = 'Earn'), 0)::int as unspent_points
+            from orders o where o.id = $1 and o.customer_id is not null and o.loyalty_settled_at is not null",
+            &[&order_id],
+        )
+        .await?
+    {
+        Some(row) => row,
+        None => return Ok(()),
+    };
+    let customer_id: i32 = row.get("customer_id");
+    let shop_id: i32 = row.get("shop_id");
+    let earned_points: i32 = row.get("points_earned");
+    let redeemed_points: i32 = row.get("points_redeemed");
+    // Points earned by an earlier completion were taken back when it was reversed.
+    let unspent_points: i32 = row.get::<_, i32>("unspent_points").min(earned_points);
+
+    lock_membership(transaction, customer_id, shop_id).await?;
+
+    if unspent_points > 0 {
+        transaction
+            .execute(
+                "update loyalty_ledger set remaining_points = 0 where order_id = $1 and entry_type = 'Earn'",
+                &[&order_id],
+            )
+            .await?;
+        transaction
+            .execute(
+                "insert into loyalty_ledger (customer_id, shop_id, order_id, entry_type, points) values ($1, $2, $3, 'Reverse', $4)",
+                &[&customer_id, &shop_id, &order_id, &-unspent_points],
+            )
+            .await?;
+    }
+    if redeemed_points > 0 {
+        insert_redeemable_points(
+            transaction,
+            customer_id,
+            shop_id,
+            order_id,
+            "Refund",
+            redeemed_points,
+        )
+        .await?;
+    }
+    transaction
+        .execute(
+            "update loyalty_memberships set lifetime_points = greatest(lifetime_points - $3, 0) where customer_id = $1 and shop_id = $2",
+            &[&customer_id, &shop_id, &earned_points],
+        )
+        .await?;
+    transaction
+        .execute(
+            "update orders set points_earned = 0, points_redeemed = 0, loyalty_settled_at = null,
+            updated_at = CURRENT_TIMESTAMP where id = $1",
+            &[&order_id],
+        )
+        .await?;
+    Ok(())
+}
+
+pub async fn get_customer_loyalty(
+    customer_id: i32,
+    shop_id: i32,
+    client: &mut Client,
+) -> Result<CustomerLoyalty, Error> {
+    let transaction = client.transaction().await?;
+    expire_points(&transaction, customer_id, shop_id).await?;
+    let row = transaction
+        .query_one(
+            "select (select coalesce(sum(points), 0) from loyalty_ledger where customer_id = $1 and shop_id = $2) as balance,
+            coalesce(m.lifetime_points, 0) as lifetime_points, coalesce(t.name, '') as tier_name, m.promoted_at,
+            coalesce(n.name, '') as next_tier_name, greatest(coalesce(n.min_points - coalesce(m.lifetime_points, 0), 0), 0) as points_to_next_tier,
+            coalesce((select point_value from loyalty_programs where shop_id = $2 and deleted_at is null), 0)::text as point_value
+            from (select 1) d
+            left join loyalty_memberships m on m.customer_id = $1 and m.shop_id = $2
+            left join loyalty_tiers t on t.loyalty_tier_id = m.loyalty_tier_id
+            left join lateral (select name, min_points from loyalty_tiers where shop_id = $2 and deleted_at is null
+                and min_points > coalesce(t.min_points, -1) order by min_points limit 1) n on true",
+            &[&customer_id, &shop_id],
+        )
+        .await?;
+    transaction.commit().await?;
+
+    let balance: i64 = row.get("balance");
+    let point_value: &str = row.get("point_value");
+    let point_value: f64 = point_value.parse().unwrap();
+    Ok(CustomerLoyalty {
+        customer_id,
+        shop_id,
+        balance,
+        balance_value: balance as f64 * point_value,
+        lifetime_points: row.get("lifetime_points"),
+        tier_name: row.get("tier_name"),
+        promoted_at: row.get("promoted_at"),
+        next_tier_name: row.get("next_tier_name"),
+        points_to_next_tier: row.get("points_to_next_tier"),
+    })
+}
+
+pub async fn get_loyalty_ledger(
+    customer_id: i32,
+    shop_id: i32,
+    entry_type: &Option<String>,
+    page: Option<usize>,
+    per_page: Option<usize>,
+    client: &Client,
+) -> Result<PaginationResult<LoyaltyLedgerEntry>, Error> {
+    let mut base_query = "from loyalty_ledger where customer_id = $1 and shop_id = $2".to_string();
+    let mut params: Vec<Box<dyn ToSql + Sync>> = vec![Box::new(customer_id), Box::new(shop_id)];
+    if let Some(t) = entry_type {
+        params.push(Box::new(t));
+        base_query = format!("{base_query} and entry_type = ${}", params.len());
+    }
+
+    let result = generate_pagination_query(PaginationOptions {
+        select_columns: "loyalty_ledger_id, order_id, entry_type, points, remaining_points, expires_at, created_at",
+        base_query: &base_query,
+        search_columns: vec![],
+        search: None,
+        order_options: Some("created_at desc, loyalty_ledger_id desc"),
+        page,
+        per_page,
+    });
+
+    let params_slice: Vec<&(dyn ToSql + Sync)> = params.iter().map(AsRef::as_ref).collect();
+
+    let row = client.query_one(&result.count_query, &params_slice).await?;
+    let total: i64 = row.get("total");
+
+    let mut page_counts = 0;
+    let mut current_page = 0;
+    let mut limit = 0;
+    if let (Some(p), Some(pp)) = (page, per_page) {
+        current_page = p;
+        limit = pp;
+        page_counts = (total as f64 / limit as f64).ceil() as usize;
+    }
+
+    let entries: Vec<LoyaltyLedgerEntry> = client
+        .query(&result.query, &params_slice)
+        .await?
+        .iter()
+        .map(|row| LoyaltyLedgerEntry {
+            loyalty_ledger_id: row.get("loyalty_ledger_id"),
+            order_id: row.get("order_id"),
+            entry_type: row.get("entry_type"),
+            points: row.get("points"),
+            remaining_points: row.get("remaining_points"),
+            expires_at: row.get("expires_at"),
+            created_at: row.get("created_at"),
+        })
+        .collect();
+
+    Ok(PaginationResult {
+        data: entries,
+        total,
+        page: current_page,
+        per_page: limit,
+        page_counts,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn points_earned_are_rounded_down() {
+        assert_eq!(points_earned(1999.0, 0.01, 1.0), 19);
+        assert_eq!(points_earned(2000.0, 0.01, 1.0), 20);
+    }
+
+    #[test]
+    fn points_earned_apply_the_tier_multiplier() {
+        assert_eq!(points_earned(1000.0, 0.01, 1.5), 15);
+        assert_eq!(points_earned(1000.0, 0.01, 2.0), 20);
+    }
+
+    #[test]
+    fn points_earned_are_never_negative() {
+        assert_eq!(points_earned(-500.0, 0.01, 1.0), 0);
+        assert_eq!(points_earned(500.0, 0.0, 1.0), 0);
+    }
+
+    #[test]
+    fn use_points_takes_from_entries_in_order() {
+        assert_eq!(
+            use_points(&[(7, 30), (3, 50), (9, 100)], 60),
+            vec![(7, 30), (3, 30)]
+        );
+    }
+
+    #[test]
+    fn use_points_stops_once_enough_is_used() {
+        assert_eq!(use_points(&[(1, 50), (2, 50)], 50), vec![(1, 50)]);
+        assert_eq!(use_points(&[(1, 50)], 0), vec![]);
+    }
+}
@@ -5,19 +5,39 @@ use serde::{Deserialize, Serialize};
 use std::env;
 use tokio_postgres::{types::ToSql, Client, Error};
 
-use futures::future::join_all;
 use simple_pdf_generator::{Asset, AssetType, PrintOptions};
 use simple_pdf_generator_derive::PdfTemplate;
-use tokio::task::JoinError;
 
-use crate::utils::{
-    common_struct::PaginationResult,
-    sql::{generate_pagination_query, PaginationOptions},
+use crate::{
+    models::{
+        cash_shift, gift_card, loyalty, socket_event, stock_movement,
+        tax_rule::{self, OrderTax},
+        webhook,
+    },
+    utils::{
+        common_struct::PaginationResult,
+        sql::{generate_pagination_query, PaginationOptions},
+        validator::validate_mobile,
+    },
 };
 
+pub const PAYMENT_TYPES: [&str; 4] = ["CASH", "CARD", "MOBILE", "GIFT_CARD"];
+// Only dine-in orders sit at a table, the others are made for the customer to take away, be delivered or
+// pick up at the scheduled time.
+pub const ORDER_TYPES: [&str; 4] = ["DineIn", "Takeaway", "Delivery", "Pickup"];
+
 #[derive(Deserialize, Debug)]
 pub struct NewOrder {
-    pub table_id: i32,
+    pub table_id: Option<i32>,
+    pub shop_id: Option<i32>,
+    pub order_type: Option<String>,
+    pub covers: Option<i32>,
+    pub customer_id: Option<i32>,
+    pub contact_name: Option<String>,
+    pub contact_phone: Option<String>,
+    pub delivery_address: Option<String>,
+    pub delivery_fee: Option<f64>,
+    pub scheduled_at: Option<NaiveDateTime>,
     pub items: Vec<NewOrderItem>,
 }
 
@@ -85,12 +105,61 @@ pub struct NewOrderItem {
     pub special_instructions: String,
 }
 
+pub fn validate_new_order(body: &NewOrder, order_type: &str) -> Option<&'static str> {
+    if order_type == "DineIn" && body.table_id.is_none() {
+        return Some("Table Id must not be empty!");
+    }
+    if order_type != "DineIn" && body.table_id.is_some() {
+        return Some("Only dine-in orders can have a table!");
+    }
+    if let Some(fee) = body.delivery_fee {
+        if fee < 0.0 {
+            return Some("Delivery fee must not be negative!");
+        }
+        if fee > 0.0 && order_type != "Delivery" {
+            return Some("Only delivery orders can have a delivery fee!");
+        }
+    }
+    if order_type == "Delivery"
+        && body.delivery_address.as_deref().map_or(true, |a| a.trim().is_empty())
+    {
+        return Some("Delivery address must not be empty!");
+    }
+    if order_type == "Pickup" && body.scheduled_at.is_none() {
+        return Some("Pickup time must not be empty!");
+    }
+    // The shop has to be able to reach the customer about a delivery or a pickup.
+    if (order_type == "Delivery" || order_type == "Pickup")
+        && body.contact_phone.is_none()
+        && body.customer_id.is_none()
+    {
+        return Some("Contact phone or customer must not be empty!");
+    }
+    if body.contact_phone.as_deref().is_some_and(|p| !validate_mobile(p)) {
+        return Some("Invalid phone number!");
+    }
+    None
+}
+
+
 pub async fn create_order(
     waiter_id: i32,
+    shop_id: i32,
     order: NewOrder,
     client: &mut Client,
 ) -> Result<i32, Box<dyn std::error::Error>> {
     let transaction = client.transaction().await?;
+    let id = insert_order(waiter_id, shop_id, order, &transaction).await?;
+    transaction.commit().await?;
+    Ok(id)
+}
+
+pub async fn insert_order(
+    waiter_id: i32,
+    shop_id: i32,
+    order: NewOrder,
+    transaction: &tokio_postgres::Transaction<'_>,
+) -> Result<i32, Box<dyn std::error::Error>> {
 
     // Here, implement logic to insert the order into the database.
     // This might involve multiple insert statements: one for the order and then multiple for the items in the order.
@@ -115,16 +184,38 @@ pub async fn create_order(
     // }
 
 
+    let order_type = order.order_type.as_deref().unwrap_or("DineIn");
+    let query = format!(
+        "insert into orders (table_id, waiter_id, covers, customer_id, shop_id, order_type, contact_name, contact_phone,
+        delivery_address, delivery_fee, scheduled_at) values ($1, $2, $3, $4, $5, $6, $7, $8, $9, {}, $10) returning id",
+        order.delivery_fee.unwrap_or(0.0)
+    );
     let row = transaction
         .query_one(
-            "insert into orders (table_id, waiter_id) values ($1, $2) returning id",
-            &[&order.table_id, &waiter_id],
+            &query,
+            &[
+                &order.table_id,
+                &waiter_id,
+                &order.covers,
+                &order.customer_id,
+                &shop_id,
+                &order_type,
+                &order.contact_name,
+                &order.contact_phone,
+                &order.delivery_address,
+                &order.scheduled_at,
+            ],
         )
         .await?;
     let id: i32 = row.get("id");
+    let items: Vec<serde_json::Value> = order
+        .items
+        .iter()
+        .map(|i| serde_json::json!({"item_id": i.item_id, "quantity": i.quantity}))
+        .collect();
     for item in order.items {
         // Retrieve item data from the database
-        let item_data = get_item_data(&transaction, item.item_id).await?;
+        let item_data = get_item_data(transaction, item.item_id).await?;
 
         // Insert order item using retrieved data
         // transaction
@@ -135,7 +226,7 @@ pub async fn create_order(
         // .await?;
 
         insert_order_item(
-            &transaction,
+            transaction,
             id,
             item.item_id,
             item.quantity,
@@ -145,7 +236,34 @@ pub async fn create_order(
         )
         .await?;
     }
-    transaction.commit().await?;
+    webhook::enqueue_webhook_event(
+        transaction,
+        shop_id,
+        "order.created",
+        serde_json::json!({
+            "order_id": id,
+            "order_type": order_type,
+            "table_id": order.table_id,
+            "customer_id": order.customer_id,
+            "items": items,
+        }),
+    )
+    .await?;
+    socket_event::enqueue_socket_event(
+        transaction,
+        "/pos",
+        "new-order",
+        Some(shop_id),
+        &[],
+        &[],
+        serde_json::json!({
+            "order_id": id,
+            "shop_id": shop_id,
+            "table_id": order.table_id,
+            "order_type": order_type,
+        }),
+    )
+    .await?;
     Ok(id)
 }
 
@@ -153,17 +271,62 @@ pub async fn create_order(
 pub struct Order {
     pub id: i32,
     pub waiter_name: String,
+    pub order_type: String,
     pub table_number: String,
+    pub contact_name: String,
+    pub scheduled_at: Option<NaiveDateTime>,
     pub status: String,
     pub sub_total: f64,
     pub tax: f64,
     pub discount: f64,
+    pub tip: f64,
+    pub rounding_adjustment: f64,
+    pub delivery_fee: f64,
     pub total: f64,
+    pub payment_type: String,
     pub shop_name: String,
     pub item_count: i64,
     pub created_at: NaiveDateTime,
 }
 
+// Takeaway, delivery and pickup orders have no table, dine-in orders of deleted tables are still left out.
+const ORDER_TABLE_JOIN: &str = "left join tables t on o.table_id = t.id";
+
+fn order_select_columns() -> String {
+    let sub_total_query = "(select sum(price * quantity) from order_items where order_id = o.id)";
+    format!("o.id, u.name as waiter_name, o.order_type, coalesce(t.table_number, '') as table_number, coalesce(o.contact_name, '') as contact_name, o.scheduled_at, o.status, o.tax::text, o.discount::text, o.tip::text, coalesce(o.payment_type, 'CASH') as payment_type, coalesce({sub_total_query}, 0.0)::text as sub_total, coalesce({sub_total_query} - o.discount + o.tax + o.rounding_adjustment + o.delivery_fee, 0.0)::text as total, o.rounding_adjustment::text, o.delivery_fee::text, coalesce(s.name, '') shop_name, (select count(*) from order_items where order_id = o.id) as item_count, o.created_at")
+}
+
+fn row_to_order(row: &tokio_postgres::Row) -> Order {
+    let sub_total: &str = row.get("sub_total");
+    let tax: &str = row.get("tax");
+    let discount: &str = row.get("discount");
+    let tip: &str = row.get("tip");
+    let rounding_adjustment: &str = row.get("rounding_adjustment");
+    let delivery_fee: &str = row.get("delivery_fee");
+    let total: &str = row.get("total");
+    Order {
+        id: row.get("id"),
+        waiter_name: row.get("waiter_name"),
+        order_type: row.get("order_type"),
+        table_number: row.get("table_number"),
+        contact_name: row.get("contact_name"),
+        scheduled_at: row.get("scheduled_at"),
+        status: row.get("status"),
+        sub_total: sub_total.parse().unwrap(),
+        tax: tax.parse().unwrap(),
+        discount: discount.parse().unwrap(),
+        tip: tip.parse().unwrap(),
+        rounding_adjustment: rounding_adjustment.parse().unwrap(),
+        delivery_fee: delivery_fee.parse().unwrap(),
+        total: total.parse().unwrap(),
+        payment_type: row.get("payment_type"),
+        shop_name: row.get("shop_name"),
+        item_count: row.get("item_count"),
+        created_at: row.get("created_at"),
+    }
+}
+
 pub async fn get_orders(
     search: &Option<String>,
     page: Option<usize>,
@@ -174,18 +337,19 @@ pub async fn get_orders(
     from_date: &Option<NaiveDate>,
     to_date: &Option<NaiveDate>,
     status: &Option<String>,
+    order_type: &Option<String>,
     client: &Client,
 ) -> Result<PaginationResult<Order>, Error> {
     let mut params: Vec<Box<dyn ToSql + Sync>> = vec![];
-    let mut base_query = "from orders o inner join users u on u.id = o.waiter_id inner join tables t on o.table_id = t.id left join shops s on s.id = u.shop_id where u.deleted_at is null and o.deleted_at is null and s.deleted_at is null and t.deleted_at is null".to_string();
+    let mut base_query = format!("from orders o inner join users u on u.id = o.waiter_id {ORDER_TABLE_JOIN} left join shops s on s.id = o.shop_id where u.deleted_at is null and o.deleted_at is null and s.deleted_at is null and (o.table_id is null or t.deleted_at is null)");
     if role == "Manager" {
         params.push(Box::new(shop_id));
-        base_query = format!("{base_query} and t.shop_id = ${}", params.len());
+        base_query = format!("{base_query} and o.shop_id = ${}", params.len());
     } else if role == "Waiter" {
         params.push(Box::new(shop_id));
         params.push(Box::new(user_id));
         base_query = format!(
-            "{base_query} and t.shop_id = ${} and o.waiter_id = ${}",
+            "{base_query} and o.shop_id = ${} and o.waiter_id = ${}",
             params.len() - 1,
             params.len()
         );
@@ -204,9 +368,12 @@ pub async fn get_orders(
         params.push(Box::new(s));
         base_query = format!("{base_query} and o.status = ${}", params.len());
     }
+    if let Some(t) = order_type {
+        params.push(Box::new(t));
+        base_query = format!("{base_query} and o.order_type = ${}", params.len());
+    }
 
-    let sub_total_query = "(select sum(price * quantity) from order_items where order_id = o.id)";
-    let select_columns = format!("o.id, u.name as waiter_name, t.table_number, o.status, o.tax::text, o.discount::text, coalesce({sub_total_query}, 0.0)::text as sub_total, coalesce({sub_total_query} - o.discount + o.tax, 0.0)::text as total, coalesce(s.name, '') shop_name, (select count(*) from order_items where order_id = o.id) as item_count, o.created_at");
+    let select_columns = order_select_columns();
     let order_options = "o.created_at desc";
     let result = generate_pagination_query(PaginationOptions {
         select_columns: &select_columns,
@@ -214,6 +381,8 @@ pub async fn get_orders(
         search_columns: vec![
             "u.name",
             "t.table_number",
+            "o.contact_name",
+            "o.contact_phone",
             "o.status",
             "o.id::varchar",
             "s.name",
@@ -242,26 +411,7 @@ pub async fn get_orders(
         .query(&result.query, &params_slice)
         .await?
         .iter()
-        .map(|row| {
-            let sub_total: &str = row.get("sub_total");
-            let tax: &str = row.get("tax");
-            let discount: &str = row.get("discount");
-            let total: &str = row.get("total");
-
-            return Order {
-                id: row.get("id"),
-                waiter_name: row.get("waiter_name"),
-                table_number: row.get("table_number"),
-                status: row.get("status"),
-                sub_total: sub_total.parse().unwrap(),
-                tax: tax.parse().unwrap(),
-                discount: discount.parse().unwrap(),
-                total: total.parse().unwrap(),
-                shop_name: row.get("shop_name"),
-                item_count: row.get("item_count"),
-                created_at: row.get("created_at"),
-            };
-        })
+        .map(row_to_order)
         .collect();
 
     Ok(PaginationResult {
@@ -277,10 +427,23 @@ pub async fn get_orders(
 pub struct OrderDetail {
     id: i32,
     waiter_name: String,
+    order_type: String,
     table_number: String,
+    contact_name: String,
+    contact_phone: String,
+    delivery_address: String,
+    delivery_fee: f64,
+    scheduled_at: Option<NaiveDateTime>,
     created_at: NaiveDateTime,
     items: Vec<OrderItem>,
     status: String,
+    taxes: Vec<OrderTax>,
+    rounding_adjustment: f64,
+    customer_id: Option<i32>,
+    customer_name: String,
+    customer_allergies: String,
+    points_earned: i32,
+    points_redeemed: i32,
 }
 
 #[derive(Serialize)]
@@ -302,19 +465,19 @@ pub async fn get_order_detail(
     client: &Client,
 ) -> Result<OrderDetail, Error> {
     let mut params: Vec<Box<dyn ToSql + Sync>> = vec![Box::new(order_id)];
-    let mut query = format!("select o.id, u.name as waiter_name, t.table_number, o.created_at, o.status from orders o inner join users u on u.id = o.waiter_id inner join tables t on o.table_id = t.id where u.deleted_at is null and o.deleted_at is null and t.deleted_at is null and o.id = $1");
+    let mut query = format!("select o.id, u.name as waiter_name, o.order_type, coalesce(t.table_number, '') as table_number, coalesce(o.contact_name, '') as contact_name, coalesce(o.contact_phone, '') as contact_phone, coalesce(o.delivery_address, '') as delivery_address, o.delivery_fee::text, o.scheduled_at, o.created_at, o.status, o.rounding_adjustment::text, o.customer_id, coalesce(c.name, '') as customer_name, coalesce(c.allergies, '') as customer_allergies, o.points_earned, o.points_redeemed from orders o inner join users u on u.id = o.waiter_id {ORDER_TABLE_JOIN} left join customers c on c.customer_id = o.customer_id where u.deleted_at is null and o.deleted_at is null and (o.table_id is null or t.deleted_at is null) and o.id = $1");
 
     if role == "Waiter" {
         params.push(Box::new(shop_id));
         params.push(Box::new(user_id));
         query = format!(
-            "{query} and t.shop_id = ${} and o.waiter_id = ${}",
+            "{query} and o.shop_id = ${} and o.waiter_id = ${}",
             params.len() - 1,
             params.len()
         );
     } else if role == "Manager" {
         params.push(Box::new(shop_id));
-        query = format!("{query} and t.shop_id = ${}", params.len());
+        query = format!("{query} and o.shop_id = ${}", params.len());
     }
 
     let params_slice: Vec<&(dyn ToSql + Sync)> = params.iter().map(AsRef::as_ref).collect();
@@ -346,14 +509,30 @@ pub async fn get_order_detail(
             }
         })
         .collect();
+    let taxes = tax_rule::get_order_taxes(order_id, client).await?;
+    let rounding_adjustment: &str = order_row.get("rounding_adjustment");
+    let delivery_fee: &str = order_row.get("delivery_fee");
 
     Ok(OrderDetail {
         id: order_row.get("id"),
         waiter_name: order_row.get("waiter_name"),
+        order_type: order_row.get("order_type"),
         table_number: order_row.get("table_number"),
+        contact_name: order_row.get("contact_name"),
+        contact_phone: order_row.get("contact_phone"),
+        delivery_address: order_row.get("delivery_address"),
+        delivery_fee: delivery_fee.parse().unwrap(),
+        scheduled_at: order_row.get("scheduled_at"),
         created_at: order_row.get("created_at"),
         status: order_row.get("status"),
         items,
+        taxes,
+        rounding_adjustment: rounding_adjustment.parse().unwrap(),
+        customer_id: order_row.get("customer_id"),
+        customer_name: order_row.get("customer_name"),
+        customer_allergies: order_row.get("customer_allergies"),
+        points_earned: order_row.get("points_earned"),
+        points_redeemed: order_row.get("points_redeemed"),
     })
 }
 
@@ -365,41 +544,22 @@ pub async fn get_order_by_id(
     client: &Client,
 ) -> Option<Order> {
     let mut params: Vec<Box<dyn ToSql + Sync>> = vec![Box::new(order_id)];
-    let sub_total_query = "(select sum(price * quantity) from order_items where order_id = o.id)";
-    let mut base_query = format!("select o.id, u.name as waiter_name, t.table_number, o.status, o.tax::text, o.discount::text, coalesce({sub_total_query}, 0.0)::text as sub_total, coalesce({sub_total_query} - o.discount + o.tax, 0.0)::text as total, coalesce(s.name, '') shop_name, (select count(*) from order_items where order_id = o.id) as item_count, o.created_at from orders o inner join users u on u.id = o.waiter_id inner join tables t on o.table_id = t.id left join shops s on s.id = u.shop_id where u.deleted_at is null and o.deleted_at is null and t.deleted_at is null and o.id = $1");
+    let mut base_query = format!("select {} from orders o inner join users u on u.id = o.waiter_id {ORDER_TABLE_JOIN} left join shops s on s.id = o.shop_id where u.deleted_at is null and o.deleted_at is null and (o.table_id is null or t.deleted_at is null) and o.id = $1", order_select_columns());
     if role == "Manager" {
         params.push(Box::new(shop_id));
-        base_query = format!("{base_query} and t.shop_id = ${}", params.len());
+        base_query = format!("{base_query} and o.shop_id = ${}", params.len());
     } else if role == "Waiter" {
         params.push(Box::new(shop_id));
         params.push(Box::new(user_id));
         base_query = format!(
-            "{base_query} and t.shop_id = ${} and o.waiter_id = ${}",
+            "{base_query} and o.shop_id = ${} and o.waiter_id = ${}",
             params.len() - 1,
             params.len()
         );
     }
     let params_slice: Vec<&(dyn ToSql + Sync)> = params.iter().map(AsRef::as_ref).collect();
     match client.query_one(&base_query, &params_slice).await {
-        Ok(row) => {
-            let sub_total: &str = row.get("sub_total");
-            let tax: &str = row.get("tax");
-            let discount: &str = row.get("discount");
-            let total: &str = row.get("total");
-            Some(Order {
-                id: row.get("id"),
-                waiter_name: row.get("waiter_name"),
-                table_number: row.get("table_number"),
-                status: row.get("status"),
-                sub_total: sub_total.parse().unwrap(),
-                tax: tax.parse().unwrap(),
-                discount: discount.parse().unwrap(),
-                total: total.parse().unwrap(),
-                shop_name: row.get("shop_name"),
-                item_count: row.get("item_count"),
-                created_at: row.get("created_at"),
-            })
-        }
+        Ok(row) => Some(row_to_order(&row)),
         Err(err) => {
             println!("{:?}", err);
             None
@@ -407,19 +567,154 @@ pub async fn get_order_by_id(
     }
 }
 
+// What a status change writes to the order, all of it in the same transaction as the status.
+pub struct OrderUpdate<'a> {
+    pub status: &'a str,
+    pub previous_status: &'a str,
+    pub tax: f64,
+    pub discount: f64,
+    pub tip: f64,
+    pub payment_type: &'a str,
+    pub tax_calculation: Option<&'a tax_rule::OrderTaxCalculation>,
+    pub redeem_points: i32,
+    // The gift card and amount the order is paid with.
+    pub gift_card_payment: Option<(i32, f64)>,
+    pub user_id: i32,
+}
+
+// Returns false, with nothing changed, when the gift card balance no longer covers the order.
 pub async fn update_order(
     order_id: i32,
-    status: &str,
-    tax: f64,
-    discount: f64,
-    client: &Client,
-) -> Result<(), Box<dyn std::error::Error>> {
+    update: &OrderUpdate<'_>,
+    client: &mut Client,
+) -> Result<bool, Box<dyn std::error::Error>> {
+    // `served_at`, `completed_at` and `canceled_at` keep the first time the order reached the status, so
+    // table turn times aren't reset by later edits and a cancellation after serving or paying can be told
+    // apart as a void or a refund.
     let query = format!(
-        "update orders set status = $1, tax = {}, discount = {} where id = $2",
-        tax, discount
+        "update orders set status = $1, tax = {}, discount = {}, tip = {}, payment_type = $3,
+        served_at = case when $1 = 'Served' then coalesce(served_at, CURRENT_TIMESTAMP) else served_at end,
+        completed_at = case when $1 = 'Completed' then coalesce(completed_at, CURRENT_TIMESTAMP) else completed_at end,
+        canceled_at = case when $1 = 'Canceled' then coalesce(canceled_at, CURRENT_TIMESTAMP) else canceled_at end,
+        updated_at = CURRENT_TIMESTAMP where id = $2",
+        update.tax, update.discount, update.tip
     );
-    client.execute(&query, &[&status, &order_id]).await?;
-    Ok(())
+    let transaction = client.transaction().await?;
+    let row = transaction
+        .query_one(
+            &format!("{query} returning shop_id"),
+            &[&update.status, &order_id, &update.payment_type],
+        )
+        .await?;
+    if let Some(c) = update.tax_calculation {
+        tax_rule::save_order_taxes(order_id, c, &transaction).await?;
+    }
+    cash_shift::link_order_to_shift(order_id, update.user_id, &transaction).await?;
+    update_order_loyalty(
+        order_id,
+        update.previous_status,
+        update.status,
+        update.redeem_points,
+        &transaction,
+    )
+    .await?;
+    if let Some((gift_card_id, amount)) = update.gift_card_payment {
+        let balance = gift_card::redeem_order_gift_card(
+            gift_card_id,
+            amount,
+            order_id,
+            update.user_id,
+            &transaction,
+        )
+        .await?;
+        if balance.is_none() {
+            return Ok(false);
+        }
+    } else if update.status == "Canceled" && update.previous_status != "Canceled" {
+        gift_card::refund_order_gift_card(order_id, update.user_id, &transaction).await?;
+    }
+    // An order taken back from Completed keeps its sale until it is completed again or canceled.
+    if update.status == "Completed" && update.previous_status != "Completed" {
+        stock_movement::record_order_sale(order_id, update.user_id, &transaction).await?;
+        enqueue_order_completed_events(order_id, &transaction).await?;
+    } else if update.status == "Canceled" && update.previous_status != "Canceled" {
+        stock_movement::reverse_order_sale(order_id, update.user_id, &transaction).await?;
+    }
+    socket_event::enqueue_socket_event(
+        &transaction,
+        "/pos",
+        "order-updated",
+        row.get("shop_id"),
+        &[],
+        &[],
+        serde_json::json!({"order_id": order_id, "status": update.status}),
+    )
+    .await?;
+    transaction.commit().await?;
+    Ok(true)
+}
+
+// Completing an order redeems and earns its loyalty points, canceling an order they were settled for takes them
+// back, also when it was taken back from Completed first.
+async fn update_order_loyalty(
+    order_id: i32,
+    previous_status: &str,
+    status: &str,
+    redeem_points: i32,
+    transaction: &tokio_postgres::Transaction<'_>,
+) -> Result<(), Error> {
+    if status == "Completed" && previous_status != "Completed" {
+        loyalty::settle_order_points(order_id, redeem_points, transaction).await
+    } else if status == "Canceled" && previous_status != "Canceled" {
+        loyalty::reverse_order_points(order_id, transaction).await
+    } else {
+        Ok(())
+    }
+}
+
+// Completing an order raises `order.completed` and `payment.captured` for the shop's webhook subscribers.
+// The captured amount is what was paid, tip included.
+async fn enqueue_order_completed_events(
+    order_id: i32,
+    transaction: &tokio_postgres::Transaction<'_>,
+) -> Result<(), Error> {
+    let row = transaction
+        .query_one(
+            &format!("select o.shop_id as order_shop_id, {} from orders o inner join users u on u.id = o.waiter_id {ORDER_TABLE_JOIN} left join shops s on s.id = o.shop_id where o.id = $1", order_select_columns()),
+            &[&order_id],
+        )
+        .await?;
+    let shop_id: i32 = row.get("order_shop_id");
+    let order = row_to_order(&row);
+    webhook::enqueue_webhook_event(
+        transaction,
+        shop_id,
+        "order.completed",
+        serde_json::json!({
+            "order_id": order.id,
+            "order_type": order.order_type,
+            "sub_total": order.sub_total,
+            "discount": order.discount,
+            "tax": order.tax,
+            "rounding_adjustment": order.rounding_adjustment,
+            "delivery_fee": order.delivery_fee,
+            "tip": order.tip,
+            "total": order.total,
+        }),
+    )
+    .await?;
+    webhook::enqueue_webhook_event(
+        transaction,
+        shop_id,
+        "payment.captured",
+        serde_json::json!({
+            "order_id": order.id,
+            "payment_type": order.payment_type,
+            "amount": ((order.total + order.tip) * 100.0).round() / 100.0,
+            "tip": order.tip,
+        }),
+    )
+    .await
 }
 
 pub async fn order_exists_in_table(table_id: &i32, client: &Client) -> Result<bool, Error> {
@@ -435,6 +730,73 @@ pub async fn order_exists_in_table(table_id: &i32, client: &Client) -> Result<bo
     Ok(row.is_ok())
 }
 
+#[derive(Serialize)]
+pub struct QueueOrder {
+    pub id: i32,
+    pub order_type: String,
+    pub status: String,
+    pub contact_name: String,
+    pub contact_phone: String,
+    pub delivery_address: String,
+    pub scheduled_at: Option<NaiveDateTime>,
+    pub waiter_name: String,
+    pub item_count: i64,
+    pub total: f64,
+    pub created_at: NaiveDateTime,
+}
+
+// Open takeaway, delivery and pickup orders, the earliest due first. An order is due at its scheduled time,
+// or right away when it has none.
+pub async fn get_order_queue(
+    shop_id: Option<i32>,
+    order_type: &Option<String>,
+    client: &Client,
+) -> Result<Vec<QueueOrder>, Error> {
+    let sub_total_query = "(select sum(price * quantity) from order_items where order_id = o.id)";
+    let rows = client
+        .query(
+            &format!(
+                "select o.id, o.order_type, o.status, coalesce(o.contact_name, '') as contact_name, coalesce(o.contact_phone, '') as contact_phone,
+                coalesce(o.delivery_address, '') as delivery_address, o.scheduled_at, u.name as waiter_name,
+                (select count(*) from order_items where order_id = o.id) as item_count,
+                coalesce({sub_total_query} - o.discount + o.tax + o.rounding_adjustment + o.delivery_fee, 0.0)::text as total, o.created_at
+                from orders o inner join users u on u.id = o.waiter_id
+                where o.deleted_at is null and o.order_type != 'DineIn' and o.status in ('Pending', 'Served')
+                and ($1::int is null or o.shop_id = $1) and ($2::varchar is null or o.order_type = $2)
+                order by coalesce(o.scheduled_at, o.created_at), o.id"
+            ),
+            &[&shop_id, order_type],
+        )
+        .await?;
+    Ok(rows
+        .iter()
+        .map(|row| {
+            let total: &str = row.get("total");
+            QueueOrder {
+                id: row.get("id"),
+                order_type: row.get("order_type"),
+                status: row.get("status"),
+                contact_name: row.get("contact_name"),
+                contact_phone: row.get("contact_phone"),
+                delivery_address: row.get("delivery_address"),
+                scheduled_at: row.get("scheduled_at"),
+                waiter_name: row.get("waiter_name"),
+                item_count: row.get("item_count"),
+                total: total.parse().unwrap(),
+                created_at: row.get("created_at"),
+            }
+        })
+        .collect())
+}
+
+#[derive(Serialize)]
+pub struct DailySaleOrderTypeData {
+    order_type: String,
+    order_count: i64,
+    netsale: f64,
+    delivery_fee: f64,
+}
+
 #[derive(Serialize)]
 pub struct DailySaleReportData {
     item_id: i32,
@@ -455,6 +817,8 @@ pub struct DailySaleReportSummaryData {
     total_netsale: f64,
     #[PdfTableData]
     data_list: Vec<DailySaleReportData>,
+    #[PdfTableData]
+    order_type_list: Vec<DailySaleOrderTypeData>,
 }
 
 pub async fn get_daily_sale_report(
@@ -462,7 +826,6 @@ pub async fn get_daily_sale_report(
     to_date: NaiveDate,
     shop_id: i32,
     shop_name: String,
-    user_id: i32,
     client: &tokio_postgres::Client,
 ) -> Result<DailySaleReportSummaryData, tokio_postgres::Error> {
     let date_str = if !from_date.eq(&to_date) {
@@ -476,13 +839,12 @@ pub async fn get_daily_sale_report(
     (sum(oi.original_price*oi.quantity)-sum(oi.price*oi.quantity))::text as discount,
     (sum(oi.price*oi.quantity))::text as netsale,
     sum(oi.price*oi.quantity) as netsaleorder
-    from orders o, items i, order_items oi, tables t
+    from orders o, items i, order_items oi
     where o.id = oi.order_id 
     and i.id = oi.item_id
-    and o.table_id=t.id
     and DATE_TRUNC('day', o.created_at)>='{}'
     and DATE_TRUNC('day', o.created_at)<='{}'
-    and t.shop_id=$1
+    and o.shop_id=$1
     group by oi.item_id, i.name
     order by netsaleorder desc",
         &from_date, &to_date
@@ -517,6 +879,36 @@ pub async fn get_daily_sale_report(
             }
         })
         .collect();
+    let order_type_rows = client
+        .query(
+            &format!(
+                "select o.order_type, count(*) as order_count,
+                coalesce(sum((select sum(oi.price*oi.quantity) from order_items oi where oi.order_id = o.id)), 0)::text as netsale,
+                sum(o.delivery_fee)::text as delivery_fee
+                from orders o
+                where DATE_TRUNC('day', o.created_at)>='{}'
+                and DATE_TRUNC('day', o.created_at)<='{}'
+                and o.shop_id=$1
+                group by o.order_type
+                order by o.order_type",
+                &from_date, &to_date
+            ),
+            &[&shop_id],
+        )
+        .await?;
+    let order_type_list: Vec<DailySaleOrderTypeData> = order_type_rows
+        .iter()
+        .map(|row| {
+            let netsale: &str = row.get("netsale");
+            let delivery_fee: &str = row.get("delivery_fee");
+            DailySaleOrderTypeData {
+                order_type: row.get("order_type"),
+                order_count: row.get("order_count"),
+                netsale: netsale.parse().unwrap(),
+                delivery_fee: delivery_fee.parse().unwrap(),
+            }
+        })
+        .collect();
     let data:DailySaleReportSummaryData = DailySaleReportSummaryData{
         date_str,
         shop_name,
@@ -525,17 +917,15 @@ pub async fn get_daily_sale_report(
         total_netsale,
         total_quantity,
         data_list,
+        order_type_list,
     };
-    if let Err(err) = prepare_daily_sale_report_pdf(&data, user_id).await {
-        eprintln!("Error: {}", err);
-    }
-    if let Err(err) = prepare_daily_sale_report_excel(&data, user_id).await {
-        eprintln!("Error: {}", err);
-    }
     Ok(data)
 }
 
-async fn prepare_daily_sale_report_pdf(data: &DailySaleReportSummaryData, user_id: i32) -> Result<(), JoinError> {
+pub async fn write_daily_sale_report_pdf(
+    data: &DailySaleReportSummaryData,
+    file_path: &str,
+) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
     let html_path = env::current_dir()
         .unwrap()
         // .join("test_suite")
@@ -558,21 +948,14 @@ async fn prepare_daily_sale_report_pdf(data: &DailySaleReportSummaryData, user_i
         margin_right: Some(10.0),
         ..PrintOptions::default()
     };
-    let gen_0 = data.generate_pdf(html_path.clone(), &assets, &print_options);
-
-    let futures_res = join_all(vec![gen_0]).await;
-
-    for res in futures_res.iter().enumerate() {
-        let Ok(content) = res.1.as_ref() else {
-            println!("Error on {} {}", res.0, res.1.as_ref().unwrap_err());
-            continue;
-        };
-
-        _ = tokio::fs::write(format!("reports/{}dailysalereport.pdf",user_id), content).await;
-    }
+    let content = data
+        .generate_pdf(html_path, &assets, &print_options)
+        .await
+        .map_err(|e| e.to_string())?;
+    tokio::fs::write(file_path, content).await?;
     Ok(())
 }
-async fn prepare_daily_sale_report_excel(data: &DailySaleReportSummaryData, user_id: i32) -> Result<(), XlsxError> {
+pub fn write_daily_sale_report_excel(data: &DailySaleReportSummaryData, file_path: &str) -> Result<(), XlsxError> {
     // Create a new Excel file object.
     let mut workbook = Workbook::new();
 
@@ -628,8 +1011,22 @@ async fn prepare_daily_sale_report_excel(data: &DailySaleReportSummaryData, user
     worksheet.write_with_format(row_no, 4, data.total_discount, &decimal_bold_format)?;
     worksheet.write_with_format(row_no, 5, data.total_netsale, &decimal_bold_format)?;
 
+    row_no += 2;
+    worksheet.write_with_format(row_no, 1, "Order Type", &bold_format)?;
+    worksheet.write_with_format(row_no, 2, "Orders", &bold_format)?;
+    worksheet.write_with_format(row_no, 3, "Net Sale", &bold_format)?;
+    worksheet.write_with_format(row_no, 4, "Delivery Fee", &bold_format)?;
+    row_no += 1;
+    for order_type in &data.order_type_list {
+        worksheet.write_with_format(row_no, 1, &order_type.order_type, &left_format)?;
+        worksheet.write_with_format(row_no, 2, order_type.order_count as f64, &right_format)?;
+        worksheet.write_with_format(row_no, 3, order_type.netsale, &decimal_format)?;
+        worksheet.write_with_format(row_no, 4, order_type.delivery_fee, &decimal_format)?;
+        row_no += 1;
+    }
+
     // Save the file to disk.
-    workbook.save(format!("reports/{}dailysalereport.xlsx",user_id))?;
+    workbook.save(file_path)?;
 
     Ok(())
 } 
@@ -2,9 +2,15 @@ use chrono::{NaiveDate, NaiveDateTime};
 use serde::{Deserialize, Serialize};
 use tokio_postgres::{types::ToSql, Client, Error};
 
-use crate::utils::{
-    common_struct::PaginationResult,
-    sql::{generate_pagination_query, PaginationOptions},
+use crate::{
+    models::{
+        ingredient_lot::{self, NewIngredientLot},
+        stock_movement::{self, NewStockMovement},
+    },
+    utils::{
+        common_struct::PaginationResult,
+        sql::{generate_pagination_query, PaginationOptions},
+    },
 };
 
 #[derive(Debug, Serialize, Deserialize)] // Add Debug derive
@@ -33,11 +39,22 @@ pub async fn get_purchases(
     search: &Option<String>,
     page: Option<usize>,
     per_page: Option<usize>,
+    role: &str,
+    shop_id: i32,
+    filter_shop_id: Option<i32>,
     client: &Client,
 ) -> Result<PaginationResult<Purchase>, Error> {
-    let base_query =
+    let mut base_query =
         "from purchases p  join shops s on s.id = p.shop_id  where p.deleted_at is null".to_string();
-    let params: Vec<Box<dyn ToSql + Sync>> = vec![];
+    let mut params: Vec<Box<dyn ToSql + Sync>> = vec![];
+
+    if role == "Manager" {
+        params.push(Box::new(shop_id));
+        base_query = format!("{base_query} and p.shop_id = ${}", params.len());
+    } else if let Some(s) = filter_shop_id {
+        params.push(Box::new(s));
+        base_query = format!("{base_query} and p.shop_id = ${}", params.len());
+    }
 
     let order_options = "created_at desc";
 
@@ -133,6 +150,8 @@ pub struct AddPurchaseDetailRequest {
     pub quantity_purchased: f32,
     pub unit: String,
     pub buying_price_per_unit: f32,
+    pub lot_number: Option<String>,
+    pub expiry_date: Option<NaiveDate>,
 }
 
 #[derive(Debug, Deserialize)]
@@ -149,10 +168,12 @@ pub struct UpdatePurchaseDetailRequest {
     pub ingredient_id: i32,
     pub quantity_purchased: f32,
     pub buying_price_per_unit: f32,
+    pub expiry_date: Option<NaiveDate>,
 }
 
 pub async fn add_purchase(
     data: &AddPurchaseRequest,
+    user_id: i32,
     client:  &mut Client,
 ) -> Result<(), Box<dyn std::error::Error>> {
     let transaction = client.transaction().await?;
@@ -165,12 +186,35 @@ pub async fn add_purchase(
    )
    .await?
    .get("purchase_id"); 
-    for data in &data.purchase_details {
-        let purchase_details_insert_query = format!("insert into purchase_details (purchase_id, ingredient_id, quantity_purchased, unit, buying_price_per_unit) values ($1,$2,{}, $3, {})",data.quantity_purchased,data.buying_price_per_unit);
-        transaction.execute(&purchase_details_insert_query, &[&purchase_id,&data.ingredient_id,&data.unit]).await?;
-        let ingredients_update_query = format!("update ingredients SET stock_quantity = stock_quantity + {} WHERE ingredient_id = $1 AND deleted_at IS NULL",data.quantity_purchased);
-        transaction.execute(&ingredients_update_query,
-            &[&data.ingredient_id],
+    for data_detail in &data.purchase_details {
+        let purchase_details_insert_query = format!("insert into purchase_details (purchase_id, ingredient_id, quantity_purchased, unit, buying_price_per_unit) values ($1,$2,{}, $3, {}) returning purchase_detail_id",data_detail.quantity_purchased,data_detail.buying_price_per_unit);
+        let purchase_detail_id: i32 = transaction.query_one(&purchase_details_insert_query, &[&purchase_id,&data_detail.ingredient_id,&data_detail.unit]).await?.get("purchase_detail_id");
+        ingredient_lot::add_lot(
+            &transaction,
+            &NewIngredientLot {
+                ingredient_id: data_detail.ingredient_id,
+                purchase_detail_id: Some(purchase_detail_id),
+                lot_number: &data_detail.lot_number,
+                expiry_date: data_detail.expiry_date,
+                quantity: data_detail.quantity_purchased as f64,
+                unit_cost: data_detail.buying_price_per_unit as f64,
+                shop_id: Some(data.shop_id),
+            },
+        )
+        .await?;
+        stock_movement::record_stock_movement(
+            &transaction,
+            &NewStockMovement {
+                ingredient_id: data_detail.ingredient_id,
+                movement_type: "purchase",
+                quantity: data_detail.quantity_purchased as f64,
+                unit_cost: data_detail.buying_price_per_unit as f64,
+                reference_type: "purchase",
+                reference_id: Some(purchase_id),
+                notes: None,
+                shop_id: Some(data.shop_id),
+                created_by: Some(user_id),
+            },
         )
         .await?;
     }
@@ -238,6 +282,7 @@ pub async fn get_purchase_by_id(purchase_id: i32, client: &Client) -> Option<Pur
 pub async fn update_purchase(
     data: &UpdatePurchaseRequest,
     purchase_id: i32,
+    user_id: i32,
     client: &mut Client,
 ) -> Result<(), Box<dyn std::error::Error>> {
     let transaction = client.transaction().await?;
@@ -253,18 +298,69 @@ pub async fn update_purchase(
     // client.execute("delete from purchase_details where purchase_id = $1",&[&purchase_id],).await?;
 
     for purchase_detail  in &data.purchase_details {
+        let row = transaction
+            .query_one(
+                "select ingredient_id, quantity_purchased::text as quantity_purchased from purchase_details where purchase_detail_id = $1 and deleted_at is null for update",
+                &[&purchase_detail.purchase_detail_id],
+            )
+            .await?;
+        let old_ingredient_id: i32 = row.get("ingredient_id");
+        let old_quantity: &str = row.get("quantity_purchased");
+        let old_quantity: f64 = old_quantity.parse().unwrap();
+
         let purchase_details_update_query = format!("update purchase_details set ingredient_id = $1, quantity_purchased= {}, buying_price_per_unit = {} where purchase_detail_id = $2  and deleted_at is null",purchase_detail.quantity_purchased, purchase_detail.buying_price_per_unit);
         transaction.execute(&purchase_details_update_query, &[&purchase_detail.ingredient_id, &purchase_detail.purchase_detail_id])
             .await?;
-        let ingredients_update_query = format!("update ingredients set stock_quantity = stock_quantity + 
-        ( {} - (
-        select quantity_purchased 
-        from purchase_details 
-        where purchase_detail_id = $1) ) where ingredient_id = $2 and deleted_at is null",purchase_detail.quantity_purchased);
-        transaction.execute(&ingredients_update_query,
-        &[&purchase_detail.purchase_detail_id,&purchase_detail.ingredient_id],
-    )
-    .await?;
+
+        // A changed ingredient reverses the whole old line, otherwise only the difference is booked.
+        let mut corrections: Vec<(i32, f64)> = vec![];
+        if old_ingredient_id != purchase_detail.ingredient_id {
+            corrections.push((old_ingredient_id, -old_quantity));
+            corrections.push((purchase_detail.ingredient_id, purchase_detail.quantity_purchased as f64));
+            ingredient_lot::delete_purchase_lots(&transaction, purchase_detail.purchase_detail_id).await?;
+            ingredient_lot::add_lot(
+                &transaction,
+                &NewIngredientLot {
+                    ingredient_id: purchase_detail.ingredient_id,
+                    purchase_detail_id: Some(purchase_detail.purchase_detail_id),
+                    lot_number: &None,
+                    expiry_date: purchase_detail.expiry_date,
+                    quantity: purchase_detail.quantity_purchased as f64,
+                    unit_cost: purchase_detail.buying_price_per_unit as f64,
+                    shop_id: Some(data.shop_id),
+                },
+            )
+            .await?;
+        } else {
+            if purchase_detail.quantity_purchased as f64 != old_quantity {
+                corrections.push((purchase_detail.ingredient_id, purchase_detail.quantity_purchased as f64 - old_quantity));
+            }
+            ingredient_lot::update_purchase_lot(
+                &transaction,
+                purchase_detail.purchase_detail_id,
+                purchase_detail.quantity_purchased as f64,
+                purchase_detail.buying_price_per_unit as f64,
+                purchase_detail.expiry_date,
+            )
+            .await?;
+        }
+        for (ingredient_id, quantity) in corrections {
+            stock_movement::record_stock_movement(
+                &transaction,
+                &NewStockMovement {
+                    ingredient_id,
+                    movement_type: "purchase",
+                    quantity,
+                    unit_cost: purchase_detail.buying_price_per_unit as f64,
+                    reference_type: "purchase",
+                    reference_id: Some(purchase_id),
+                    notes: Some(String::from("Purchase updated")),
+                    shop_id: Some(data.shop_id),
+                    created_by: Some(user_id),
+                },
+            )
+            .await?;
+        }
     }
     transaction.commit().await?;
     Ok(())
@@ -272,20 +368,52 @@ pub async fn update_purchase(
 
 pub async fn delete_purchase(
     purchase_id: i32,
-    client: &Client,
+    user_id: i32,
+    client: &mut Client,
 ) -> Result<(), Box<dyn std::error::Error>> {
-    client
+    let transaction = client.transaction().await?;
+    let rows = transaction
+        .query(
+            "select pd.purchase_detail_id, pd.ingredient_id, pd.quantity_purchased::text as quantity_purchased, pd.buying_price_per_unit::text as buying_price_per_unit, p.shop_id
+            from purchase_details pd join purchases p on p.purchase_id = pd.purchase_id
+            where pd.purchase_id = $1 and pd.deleted_at is null and p.deleted_at is null",
+            &[&purchase_id],
+        )
+        .await?;
+    for row in &rows {
+        let quantity_purchased: &str = row.get("quantity_purchased");
+        let quantity_purchased: f64 = quantity_purchased.parse().unwrap();
+        let buying_price_per_unit: &str = row.get("buying_price_per_unit");
+        stock_movement::record_stock_movement(
+            &transaction,
+            &NewStockMovement {
+                ingredient_id: row.get("ingredient_id"),
+                movement_type: "purchase",
+                quantity: -quantity_purchased,
+                unit_cost: buying_price_per_unit.parse().unwrap(),
+                reference_type: "purchase",
+                reference_id: Some(purchase_id),
+                notes: Some(String::from("Purchase deleted")),
+                shop_id: row.get("shop_id"),
+                created_by: Some(user_id),
+            },
+        )
+        .await?;
+        ingredient_lot::delete_purchase_lots(&transaction, row.get("purchase_detail_id")).await?;
+    }
+    transaction
         .execute(
             "update purchases set deleted_at = CURRENT_TIMESTAMP where purchase_id = $1",
             &[&purchase_id],
         )
         .await?;
-    client
+    transaction
     .execute(
         "update purchase_details set deleted_at = CURRENT_TIMESTAMP where purchase_id = $1",
         &[&purchase_id],
     )
     .await?;
+    transaction.commit().await?;
     Ok(())
 }
 
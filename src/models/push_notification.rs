@@ -0,0 +1,329 @@
+use std::collections::HashMap;
+
+use chrono::NaiveDateTime;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use tokio_postgres::{Client, Error, GenericClient};
+
+use crate::{
+    models::user,
+    utils::{
+        common_struct::PaginationResult,
+        fcm,
+        sql::{generate_pagination_query, PaginationOptions},
+    },
+};
+
+pub const DEVICE_PLATFORMS: [&str; 3] = ["Android", "iOS", "Web"];
+
+const PUSH_NOTIFICATION_MAX_ATTEMPTS: i32 = 3;
+
+#[derive(Debug, Deserialize)]
+pub struct DeviceTokenRequest {
+    pub token: String,
+    pub platform: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct RemoveDeviceTokenRequest {
+    pub token: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct PushNotification {
+    pub notification_id: i32,
+    pub notification_type: String,
+    pub title: String,
+    pub body: String,
+    pub status: String,
+    pub created_at: NaiveDateTime,
+    pub sent_at: Option<NaiveDateTime>,
+}
+
+pub struct PendingPushNotification {
+    pub notification_id: i32,
+    pub title: String,
+    pub body: String,
+    pub data: HashMap<String, String>,
+    pub attempts: i32,
+    // Devices of the user the notification hasn't reached yet.
+    pub tokens: Vec<String>,
+    pub delivered: bool,
+}
+
+// A device token belongs to whoever signed in on the device last.
+pub async fn register_device_token(
+    data: &DeviceTokenRequest,
+    user_id: i32,
+    client: &Client,
+) -> Result<(), Error> {
+    client
+        .execute(
+            "insert into device_tokens (token, user_id, platform) values ($1, $2, $3)
+            on conflict (token) do update set user_id = excluded.user_id, platform = excluded.platform,
+            updated_at = CURRENT_TIMESTAMP",
+            &[&data.token, &user_id, &data.platform],
+        )
+        .await?;
+    Ok(())
+}
+
+pub async fn delete_device_token(token: &str, user_id: i32, client: &Client) -> Result<u64, Error> {
+    client
+        .execute(
+            "delete from device_tokens where token = $1 and user_id = $2",
+            &[&token, &user_id],
+        )
+        .await
+}
+
+pub async fn get_push_notifications(
+    user_id: i32,
+    page: Option<usize>,
+    per_page: Option<usize>,
+    client: &Client,
+) -> Result<PaginationResult<PushNotification>, Error> {
+    let result = generate_pagination_query(PaginationOptions {
+        select_columns:
+            "notification_id, notification_type, title, body, status, created_at, sent_at",
+        base_query: "from push_notifications where user_id = $1",
+        search_columns: vec![],
+        search: None,
+        order_options: Some("notification_id desc"),
+        page,
+        per_page,
+    });
+
+    let row = client.query_one(&result.count_query, &[&user_id]).await?;
+    let total: i64 = row.get("total");
+
+    let mut page_counts = 0;
+    let mut current_page = 0;
+    let mut limit = 0;
+    if let (Some(p), Some(pp)) = (page, per_page) {
+        current_page = p;
+        limit = pp;
+        page_counts = (total as f64 / limit as f64).ceil() as usize;
+    }
+
+    let notifications: Vec<PushNotification> = client
+        .query(&result.query, &[&user_id])
+        .await?
+        .iter()
+        .map(|row| PushNotification {
+            notification_id: row.get("notification_id"),
+            notification_type: row.get("notification_type"),
+            title: row.get("title"),
+            body: row.get("body"),
+            status: row.get("status"),
+            created_at: row.get("created_at"),
+            sent_at: row.get("sent_at"),
+        })
+        .collect();
+
+    Ok(PaginationResult {
+        data: notifications,
+        total,
+        page: current_page,
+        per_page: limit,
+        page_counts,
+    })
+}
+
+// Queues a notification for each of the users with a registered device. Nothing is queued when FCM isn't
+// configured, so the table doesn't fill up with notifications no one will send.
+pub async fn enqueue_push_notification<C: GenericClient>(
+    client: &C,
+    user_ids: &[i32],
+    notification_type: &str,
+    title: &str,
+    body: &str,
+    data: Value,
+) -> Result<(), Error> {
+    if !fcm::is_enabled() || user_ids.is_empty() {
+        return Ok(());
+    }
+    client
+        .execute(
+            "insert into push_notifications (user_id, notification_type, title, body, data)
+            select distinct user_id, $2, $3, $4, $5 from device_tokens where user_id = any($1)",
+            &[
+                &user_ids,
+                &notification_type,
+                &title,
+                &body,
+                &data.to_string(),
+            ],
+        )
+        .await?;
+    Ok(())
+}
+
+fn order_label(order_id: i32, order_type: &str, table_number: &str, contact_name: &str) -> String {
+    if order_type == "DineIn" {
+        format!("Order #{order_id} for table {table_number}")
+    } else if contact_name.is_empty() {
+        format!("{order_type} order #{order_id}")
+    } else {
+        format!("{order_type} order #{order_id} for {contact_name}")
+    }
+}
+
+// Lets the rest of the shop's staff know about an order that someone else took, or that came in from a
+// delivery platform.
+pub async fn notify_new_order(
+    order_id: i32,
+    shop_id: i32,
+    created_by: Option<i32>,
+    client: &Client,
+) -> Result<(), Error> {
+    let row = client
+        .query_one(
+            "select o.order_type, coalesce(t.table_number, '') as table_number, coalesce(o.contact_name, '') as contact_name
+            from orders o left join tables t on t.id = o.table_id where o.id = $1",
+            &[&order_id],
+        )
+        .await?;
+    let user_ids: Vec<i32> = user::get_staff_ids_by_shop(shop_id, client)
+        .await?
+        .into_iter()
+        .filter(|id| Some(*id) != created_by)
+        .collect();
+    let label = order_label(
+        order_id,
+        row.get("order_type"),
+        row.get("table_number"),
+        row.get("contact_name"),
+    );
+    enqueue_push_notification(
+        client,
+        &user_ids,
+        "new-order",
+        "New order",
+        &format!("{label} was placed."),
+        serde_json::json!({"order_id": order_id}),
+    )
+    .await
+}
+
+// Tells the waiter who took the order that it is ready to be served or handed over.
+pub async fn notify_order_ready(order_id: i32, client: &Client) -> Result<(), Error> {
+    let row = client
+        .query_one(
+            "select o.waiter_id, o.order_type, coalesce(t.table_number, '') as table_number, coalesce(o.contact_name, '') as contact_name
+            from orders o left join tables t on t.id = o.table_id where o.id = $1",
+            &[&order_id],
+        )
+        .await?;
+    let waiter_id: Option<i32> = row.get("waiter_id");
+    let user_ids: Vec<i32> = waiter_id.into_iter().collect();
+    let label = order_label(
+        order_id,
+        row.get("order_type"),
+        row.get("table_number"),
+        row.get("contact_name"),
+    );
+    enqueue_push_notification(
+        client,
+        &user_ids,
+        "order-ready",
+        "Order ready",
+        &format!("{label} is ready."),
+        serde_json::json!({"order_id": order_id}),
+    )
+    .await
+}
+
+pub async fn get_pending_push_notifications(
+    limit: i64,
+    client: &Client,
+) -> Result<Vec<PendingPushNotification>, Error> {
+    let rows = client
+        .query(
+            "select n.notification_id, n.title, n.body, n.data, n.attempts,
+            array_remove(array_agg(d.token), null) as tokens,
+            exists(select 1 from push_notification_deliveries pd where pd.notification_id = n.notification_id) as delivered
+            from push_notifications n
+            left join device_tokens d on d.user_id = n.user_id
+                and not exists(select 1 from push_notification_deliveries pd where pd.notification_id = n.notification_id and pd.token = d.token)
+            where n.status = 'Pending' group by n.notification_id order by n.notification_id limit $1",
+            &[&limit],
+        )
+        .await?;
+    Ok(rows
+        .iter()
+        .map(|row| {
+            let data: &str = row.get("data");
+            // FCM only takes string values in `data`.
+            let data: HashMap<String, String> = match serde_json::from_str(data) {
+                Ok(Value::Object(map)) => map
+                    .into_iter()
+                    .map(|(k, v)| match v {
+                        Value::String(s) => (k, s),
+                        v => (k, v.to_string()),
+                    })
+                    .collect(),
+                _ => HashMap::new(),
+            };
+            PendingPushNotification {
+                notification_id: row.get("notification_id"),
+                title: row.get("title"),
+                body: row.get("body"),
+                data,
+                attempts: row.get("attempts"),
+                tokens: row.get("tokens"),
+                delivered: row.get("delivered"),
+            }
+        })
+        .collect())
+}
+
+pub async fn record_push_delivery(
+    notification_id: i32,
+    token: &str,
+    client: &Client,
+) -> Result<(), Error> {
+    client
+        .execute(
+            "insert into push_notification_deliveries (notification_id, token) values ($1, $2)
+            on conflict do nothing",
+            &[&notification_id, &token],
+        )
+        .await?;
+    Ok(())
+}
+
+// A notification is retried until every device of the user got it, for up to three attempts. It counts as sent
+// when any of them got it.
+pub async fn record_push_notification_result(
+    notification: &PendingPushNotification,
+    delivered: bool,
+    error: Option<String>,
+    client: &Client,
+) -> Result<(), Error> {
+    let attempts = notification.attempts + 1;
+    let status = match error {
+        None => "Sent",
+        Some(_) if attempts < PUSH_NOTIFICATION_MAX_ATTEMPTS => "Pending",
+        Some(_) if delivered => "Sent",
+        Some(_) => "Failed",
+    };
+    client
+        .execute(
+            "update push_notifications set status = $1, attempts = $2, error = $3,
+            sent_at = case when $1 = 'Sent' then CURRENT_TIMESTAMP else sent_at end where notification_id = $4",
+            &[&status, &attempts, &error, &notification.notification_id],
+        )
+        .await?;
+    Ok(())
+}
+
+pub async fn delete_device_tokens(tokens: &[String], client: &Client) -> Result<(), Error> {
+    client
+        .execute(
+            "delete from device_tokens where token = any($1)",
+            &[&tokens],
+        )
+        .await?;
+    Ok(())
+}
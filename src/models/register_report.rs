@@ -0,0 +1,456 @@
+use chrono::NaiveDateTime;
+use serde::{Deserialize, Serialize};
+use std::env;
+use tokio_postgres::{types::ToSql, Client, Error};
+
+use simple_pdf_generator::{Asset, AssetType, PrintOptions};
+use simple_pdf_generator_derive::PdfTemplate;
+
+use crate::utils::{
+    common_struct::PaginationResult,
+    sql::{generate_pagination_query, PaginationOptions},
+};
+
+// X: mid-shift read out, doesn't close the period. Z: closing report, numbered per shop and starting
+// the next period.
+pub const REGISTER_REPORT_TYPES: [&str; 2] = ["X", "Z"];
+
+#[derive(Debug, Serialize)]
+pub struct RegisterReport {
+    pub register_report_id: i32,
+    pub shop_id: i32,
+    pub shop_name: String,
+    pub report_type: String,
+    pub z_number: Option<i32>,
+    pub period_start: NaiveDateTime,
+    pub period_end: NaiveDateTime,
+    pub order_count: i32,
+    pub gross_sales: f64,
+    pub discounts: f64,
+    pub net_sales: f64,
+    pub tax: f64,
+    pub tips: f64,
+    pub rounding: f64,
+    pub void_count: i32,
+    pub void_amount: f64,
+    pub refund_count: i32,
+    pub refund_amount: f64,
+    pub expected_cash: f64,
+    pub generated_by: i32,
+    pub generated_by_name: String,
+    pub created_at: NaiveDateTime,
+}
+
+#[derive(Debug, Serialize)]
+pub struct RegisterReportPayment {
+    pub payment_type: String,
+    pub order_count: i32,
+    pub amount: f64,
+}
+
+// Inclusive taxes are part of the net sales, exclusive ones make up the tax total.
+#[derive(Debug, Serialize)]
+pub struct RegisterReportTax {
+    pub name: String,
+    pub is_inclusive: bool,
+    pub amount: f64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct RegisterReportDetail {
+    pub register_report: RegisterReport,
+    pub payments: Vec<RegisterReportPayment>,
+    pub taxes: Vec<RegisterReportTax>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct RegisterReportRequest {
+    pub report_type: String,
+    pub shop_id: Option<i32>,
+}
+
+// Per order figures of the shop. Gross is at the original item prices, so item and order discounts both
+// count as discounts; paid is what the customer handed over. Parameter: $1 shop id.
+const ORDER_TOTALS: &str = "with order_totals as (
+    select o.id, coalesce(o.payment_type, 'CASH') as payment_type, o.served_at, o.completed_at, o.canceled_at, o.tax, o.tip, o.rounding_adjustment,
+    coalesce((select sum(original_price * quantity) from order_items where order_id = o.id), 0) as gross,
+    coalesce((select sum(price * quantity) from order_items where order_id = o.id), 0) - o.discount as net,
+    coalesce((select sum(price * quantity) from order_items where order_id = o.id), 0) - o.discount + o.tax + o.rounding_adjustment + o.delivery_fee + o.tip as paid
+    from orders o where o.deleted_at is null and o.shop_id = $1
+)";
+
+// Orders paid in the period ($2, $3] are its sales. An order canceled in the period after being paid is a
+// refund, one canceled after being served but before payment is a void.
+const SALE_FILTER: &str = "completed_at > $2 and completed_at <= $3";
+const VOID_FILTER: &str =
+    "canceled_at > $2 and canceled_at <= $3 and completed_at is null and served_at is not null";
+const REFUND_FILTER: &str = "canceled_at > $2 and canceled_at <= $3 and completed_at is not null";
+
+pub async fn add_register_report(
+    report_type: &str,
+    shop_id: i32,
+    user_id: i32,
+    client: &mut Client,
+) -> Result<i32, Error> {
+    let transaction = client.transaction().await?;
+    // Locking the shop serializes report generation, so two Z-reports never share a number or a period.
+    transaction
+        .execute("select id from shops where id = $1 for update", &[&shop_id])
+        .await?;
+
+    // A period runs from the last Z-report, or from the shop's first order when it never closed.
+    let row = transaction
+        .query_one(
+            "select coalesce((select max(period_end) from register_reports where shop_id = $1 and report_type = 'Z'),
+            (select min(o.created_at) - interval '1 second' from orders o where o.shop_id = $1),
+            localtimestamp) as period_start, localtimestamp as period_end,
+            case when $2 = 'Z' then coalesce((select max(z_number) from register_reports where shop_id = $1), 0) + 1 end as z_number",
+            &[&shop_id, &report_type],
+        )
+        .await?;
+    let period_start: NaiveDateTime = row.get("period_start");
+    let period_end: NaiveDateTime = row.get("period_end");
+    let z_number: Option<i32> = row.get("z_number");
+
+    let query = format!(
+        "{ORDER_TOTALS}
+        insert into register_reports (shop_id, period_start, period_end, report_type, z_number, generated_by, order_count, gross_sales,
+        discounts, net_sales, tax, tips, rounding, void_count, void_amount, refund_count, refund_amount, expected_cash)
+        select $1, $2, $3, $4, $5, $6,
+        count(*) filter (where {SALE_FILTER}),
+        coalesce(sum(gross) filter (where {SALE_FILTER}), 0),
+        coalesce(sum(gross - net) filter (where {SALE_FILTER}), 0),
+        coalesce(sum(net) filter (where {SALE_FILTER}), 0),
+        coalesce(sum(tax) filter (where {SALE_FILTER}), 0),
+        coalesce(sum(tip) filter (where {SALE_FILTER}), 0),
+        coalesce(sum(rounding_adjustment) filter (where {SALE_FILTER}), 0),
+        count(*) filter (where {VOID_FILTER}),
+        coalesce(sum(net) filter (where {VOID_FILTER}), 0),
+        count(*) filter (where {REFUND_FILTER}),
+        coalesce(sum(paid) filter (where {REFUND_FILTER}), 0),
+        coalesce(sum(paid) filter (where {SALE_FILTER} and payment_type = 'CASH'), 0)
+            - coalesce(sum(paid) filter (where {REFUND_FILTER} and payment_type = 'CASH'), 0)
+        from order_totals returning register_report_id"
+    );
+    let row = transaction
+        .query_one(
+            &query,
+            &[
+                &shop_id,
+                &period_start,
+                &period_end,
+                &report_type,
+                &z_number,
+                &user_id,
+            ],
+        )
+        .await?;
+    let register_report_id: i32 = row.get("register_report_id");
+
+    let query = format!(
+        "{ORDER_TOTALS}
+        insert into register_report_payments (register_report_id, payment_type, order_count, amount)
+        select $4, payment_type, count(*), sum(paid) from order_totals where {SALE_FILTER} group by payment_type order by payment_type"
+    );
+    transaction
+        .execute(
+            &query,
+            &[&shop_id, &period_start, &period_end, &register_report_id],
+        )
+        .await?;
+
+    transaction
+        .execute(
+            "insert into register_report_taxes (register_report_id, name, is_inclusive, amount)
+            select $4, ot.name, ot.is_inclusive, sum(ot.amount) from order_taxes ot
+            join orders o on o.id = ot.order_id
+            where o.deleted_at is null and o.shop_id = $1 and o.completed_at > $2 and o.completed_at <= $3
+            group by ot.name, ot.is_inclusive order by ot.name",
+            &[&shop_id, &period_start, &period_end, &register_report_id],
+        )
+        .await?;
+
+    transaction.commit().await?;
+    Ok(register_report_id)
+}
+
+const REPORT_SELECT_COLUMNS: &str = "r.register_report_id, r.shop_id, s.name as shop_name, r.report_type, r.z_number, r.period_start, r.period_end,
+    r.order_count, r.gross_sales::text, r.discounts::text, r.net_sales::text, r.tax::text, r.tips::text, r.rounding::text, r.void_count, r.void_amount::text,
+    r.refund_count, r.refund_amount::text, r.expected_cash::text, r.generated_by, u.name as generated_by_name, r.created_at";
+
+const REPORT_BASE_QUERY: &str =
+    "from register_reports r join shops s on s.id = r.shop_id join users u on u.id = r.generated_by where true";
+
+fn row_to_report(row: &tokio_postgres::Row) -> RegisterReport {
+    let gross_sales: &str = row.get("gross_sales");
+    let discounts: &str = row.get("discounts");
+    let net_sales: &str = row.get("net_sales");
+    let tax: &str = row.get("tax");
+    let tips: &str = row.get("tips");
+    let rounding: &str = row.get("rounding");
+    let void_amount: &str = row.get("void_amount");
+    let refund_amount: &str = row.get("refund_amount");
+    let expected_cash: &str = row.get("expected_cash");
+    RegisterReport {
+        register_report_id: row.get("register_report_id"),
+        shop_id: row.get("shop_id"),
+        shop_name: row.get("shop_name"),
+        report_type: row.get("report_type"),
+        z_number: row.get("z_number"),
+        period_start: row.get("period_start"),
+        period_end: row.get("period_end"),
+        order_count: row.get("order_count"),
+        gross_sales: gross_sales.parse().unwrap(),
+        discounts: discounts.parse().unwrap(),
+        net_sales: net_sales.parse().unwrap(),
+        tax: tax.parse().unwrap(),
+        tips: tips.parse().unwrap(),
+        rounding: rounding.parse().unwrap(),
+        void_count: row.get("void_count"),
+        void_amount: void_amount.parse().unwrap(),
+        refund_count: row.get("refund_count"),
+        refund_amount: refund_amount.parse().unwrap(),
+        expected_cash: expected_cash.parse().unwrap(),
+        generated_by: row.get("generated_by"),
+        generated_by_name: row.get("generated_by_name"),
+        created_at: row.get("created_at"),
+    }
+}
+
+pub async fn get_register_reports(
+    page: Option<usize>,
+    per_page: Option<usize>,
+    role: &str,
+    shop_id: i32,
+    filter_shop_id: Option<i32>,
+    report_type: &Option<String>,
+    client: &Client,
+) -> Result<PaginationResult<RegisterReport>, Error> {
+    let mut base_query = REPORT_BASE_QUERY.to_string();
+    let mut params: Vec<Box<dyn ToSql + Sync>> = vec![];
+
+    if role == "Manager" {
+        params.push(Box::new(shop_id));
+        base_query = format!("{base_query} and r.shop_id = ${}", params.len());
+    } else if let Some(s) = filter_shop_id {
+        params.push(Box::new(s));
+        base_query = format!("{base_query} and r.shop_id = ${}", params.len());
+    }
+    if let Some(t) = report_type {
+        params.push(Box::new(t));
+        base_query = format!("{base_query} and r.report_type = ${}", params.len());
+    }
+
+    let result = generate_pagination_query(PaginationOptions {
+        select_columns: REPORT_SELECT_COLUMNS,
+        base_query: &base_query,
+        search_columns: vec![],
+        search: None,
+        order_options: Some("r.created_at desc"),
+        page,
+        per_page,
+    });
+
+    let params_slice: Vec<&(dyn ToSql + Sync)> = params.iter().map(AsRef::as_ref).collect();
+
+    let row = client.query_one(&result.count_query, &params_slice).await?;
+    let total: i64 = row.get("total");
+
+    let mut page_counts = 0;
+    let mut current_page = 0;
+    let mut limit = 0;
+    if let (Some(p), Some(pp)) = (page, per_page) {
+        current_page = p;
+        limit = pp;
+        page_counts = (total as f64 / limit as f64).ceil() as usize;
+    }
+
+    let reports: Vec<RegisterReport> = client
+        .query(&result.query, &params_slice)
+        .await?
+        .iter()
+        .map(row_to_report)
+        .collect();
+
+    Ok(PaginationResult {
+        data: reports,
+        total,
+        page: current_page,
+        per_page: limit,
+        page_counts,
+    })
+}
+
+pub async fn get_register_report_by_id(
+    register_report_id: i32,
+    client: &Client,
+) -> Option<RegisterReportDetail> {
+    let result = client
+        .query_one(
+            &format!(
+                "select {REPORT_SELECT_COLUMNS} {REPORT_BASE_QUERY} and r.register_report_id = $1"
+            ),
+            &[&register_report_id],
+        )
+        .await;
+    let register_report = match result {
+        Ok(row) => row_to_report(&row),
+        Err(_) => return None,
+    };
+
+    let payments = match client
+        .query(
+            "select payment_type, order_count, amount::text from register_report_payments
+            where register_report_id = $1 order by payment_type",
+            &[&register_report_id],
+        )
+        .await
+    {
+        Ok(rows) => rows
+            .iter()
+            .map(|row| {
+                let amount: &str = row.get("amount");
+                RegisterReportPayment {
+                    payment_type: row.get("payment_type"),
+                    order_count: row.get("order_count"),
+                    amount: amount.parse().unwrap(),
+                }
+            })
+            .collect(),
+        Err(err) => {
+            println!("{:?}", err);
+            return None;
+        }
+    };
+
+    let taxes = match client
+        .query(
+            "select name, is_inclusive, amount::text from register_report_taxes
+            where register_report_id = $1 order by name",
+            &[&register_report_id],
+        )
+        .await
+    {
+        Ok(rows) => rows
+            .iter()
+            .map(|row| {
+                let amount: &str = row.get("amount");
+                RegisterReportTax {
+                    name: row.get("name"),
+                    is_inclusive: row.get("is_inclusive"),
+                    amount: amount.parse().unwrap(),
+                }
+            })
+            .collect(),
+        Err(err) => {
+            println!("{:?}", err);
+            return None;
+        }
+    };
+
+    Some(RegisterReportDetail {
+        register_report,
+        payments,
+        taxes,
+    })
+}
+
+#[derive(PdfTemplate, Serialize)]
+pub struct RegisterReportPrintData {
+    title: String,
+    shop_name: String,
+    period_str: String,
+    generated_by_name: String,
+    order_count: i32,
+    gross_sales: f64,
+    discounts: f64,
+    net_sales: f64,
+    tax: f64,
+    tips: f64,
+    rounding: f64,
+    void_count: i32,
+    void_amount: f64,
+    refund_count: i32,
+    refund_amount: f64,
+    expected_cash: f64,
+    #[PdfTableData]
+    payment_list: Vec<RegisterReportPayment>,
+    #[PdfTableData]
+    tax_list: Vec<RegisterReportTaxLine>,
+}
+
+#[derive(Serialize)]
+struct RegisterReportTaxLine {
+    name: String,
+    amount: f64,
+}
+
+pub async fn generate_register_report_pdf(
+    detail: RegisterReportDetail,
+) -> Result<Vec<u8>, Box<dyn std::error::Error + Send + Sync>> {
+    let report = detail.register_report;
+    let data = RegisterReportPrintData {
+        title: match report.z_number {
+            Some(n) => format!("Z Report #{}", n),
+            None => String::from("X Report"),
+        },
+        shop_name: report.shop_name,
+        period_str: format!(
+            "From {} To {}",
+            report.period_start.format("%Y-%m-%d %H:%M"),
+            report.period_end.format("%Y-%m-%d %H:%M")
+        ),
+        generated_by_name: report.generated_by_name,
+        order_count: report.order_count,
+        gross_sales: report.gross_sales,
+        discounts: report.discounts,
+        net_sales: report.net_sales,
+        tax: report.tax,
+        tips: report.tips,
+        rounding: report.rounding,
+        void_count: report.void_count,
+        void_amount: report.void_amount,
+        refund_count: report.refund_count,
+        refund_amount: report.refund_amount,
+        expected_cash: report.expected_cash,
+        payment_list: detail.payments,
+        tax_list: detail
+            .taxes
+            .into_iter()
+            .map(|t| RegisterReportTaxLine {
+                name: if t.is_inclusive {
+                    format!("{} (Inclusive)", t.name)
+                } else {
+                    t.name
+                },
+                amount: t.amount,
+            })
+            .collect(),
+    };
+
+    let html_path = env::current_dir()
+        .unwrap()
+        .join("src/template/register-report.html");
+
+    let assets = [Asset {
+        path: env::current_dir()
+            .unwrap()
+            .join("src/template/css/style.css"),
+        r#type: AssetType::Style,
+    }];
+
+    let print_options = PrintOptions {
+        paper_width: Some(210.0),
+        paper_height: Some(297.0),
+        margin_top: Some(10.0),
+        margin_bottom: Some(10.0),
+        margin_left: Some(10.0),
+        margin_right: Some(10.0),
+        ..PrintOptions::default()
+    };
+    let content = data
+        .generate_pdf(html_path, &assets, &print_options)
+        .await
+        .map_err(|e| e.to_string())?;
+    Ok(content)
+}
@@ -0,0 +1,233 @@
+use std::{env, sync::Arc};
+
+use chrono::{NaiveDate, NaiveDateTime};
+use serde::{Deserialize, Serialize};
+use tokio::sync::Mutex;
+use tokio_postgres::{Client, Error};
+
+use crate::models::{food_cost_report, order, shop, waiter_report};
+
+pub const REPORT_TYPES: [&str; 3] = ["daily_sale", "food_cost", "waiter_performance"];
+
+#[derive(Debug, Serialize)]
+pub struct ReportJob {
+    pub job_id: i32,
+    pub report_type: String,
+    pub status: String,
+    pub shop_id: i32,
+    pub from_date: NaiveDate,
+    pub to_date: NaiveDate,
+    pub error_message: String,
+    pub requested_by: i32,
+    #[serde(skip_serializing)]
+    pub pdf_path: Option<String>,
+    #[serde(skip_serializing)]
+    pub excel_path: Option<String>,
+    pub created_at: NaiveDateTime,
+    pub completed_at: Option<NaiveDateTime>,
+    pub expires_at: Option<NaiveDateTime>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ReportJobRequest {
+    pub report_type: String,
+    pub from_date: NaiveDate,
+    pub to_date: NaiveDate,
+    pub shop_id: Option<i32>,
+}
+
+pub async fn add_report_job(
+    data: &ReportJobRequest,
+    shop_id: i32,
+    requested_by: i32,
+    client: &Client,
+) -> Result<i32, Error> {
+    let row = client
+        .query_one(
+            "insert into report_jobs (report_type, shop_id, from_date, to_date, requested_by) values ($1, $2, $3, $4, $5) returning job_id",
+            &[&data.report_type, &shop_id, &data.from_date, &data.to_date, &requested_by],
+        )
+        .await?;
+    Ok(row.get("job_id"))
+}
+
+pub async fn get_report_job_by_id(job_id: i32, client: &Client) -> Option<ReportJob> {
+    let result = client
+        .query_one(
+            "select job_id, report_type, status, shop_id, from_date, to_date, coalesce(error_message, '') as error_message, requested_by,
+            pdf_path, excel_path, created_at, completed_at, expires_at from report_jobs where job_id = $1 and deleted_at is null",
+            &[&job_id],
+        )
+        .await;
+
+    match result {
+        Ok(row) => Some(ReportJob {
+            job_id: row.get("job_id"),
+            report_type: row.get("report_type"),
+            status: row.get("status"),
+            shop_id: row.get("shop_id"),
+            from_date: row.get("from_date"),
+            to_date: row.get("to_date"),
+            error_message: row.get("error_message"),
+            requested_by: row.get("requested_by"),
+            pdf_path: row.get("pdf_path"),
+            excel_path: row.get("excel_path"),
+            created_at: row.get("created_at"),
+            completed_at: row.get("completed_at"),
+            expires_at: row.get("expires_at"),
+        }),
+        Err(_) => None,
+    }
+}
+
+pub async fn get_latest_report_job_id(
+    report_type: &str,
+    requested_by: i32,
+    client: &Client,
+) -> Option<i32> {
+    client
+        .query_one(
+            "select job_id from report_jobs where report_type = $1 and requested_by = $2 and deleted_at is null
+            order by job_id desc limit 1",
+            &[&report_type, &requested_by],
+        )
+        .await
+        .ok()
+        .map(|row| row.get("job_id"))
+}
+
+// Every job writes its own files, named after the job id, so concurrent runs never share an artifact.
+async fn generate_report_files(
+    job: &ReportJob,
+    client: &Mutex<Client>,
+) -> Result<(String, String), Box<dyn std::error::Error + Send + Sync>> {
+    tokio::fs::create_dir_all("reports").await?;
+    let pdf_path = format!("reports/{}-{}report.pdf", job.job_id, job.report_type);
+    let excel_path = format!("reports/{}-{}report.xlsx", job.job_id, job.report_type);
+
+    // The data is read under the lock, the slow PDF rendering runs without it.
+    let guard = client.lock().await;
+    let shop_name = match shop::get_shop_by_id(job.shop_id, &guard).await {
+        Some(s) => s.name,
+        None => return Err("Shop not found!".into()),
+    };
+    if job.report_type == "food_cost" {
+        let data = food_cost_report::get_food_cost_report(
+            job.from_date,
+            job.to_date,
+            job.shop_id,
+            shop_name,
+            &guard,
+        )
+        .await?;
+        drop(guard);
+        food_cost_report::write_food_cost_report_pdf(&data, &pdf_path).await?;
+        food_cost_report::write_food_cost_report_excel(&data, &excel_path)?;
+    } else if job.report_type == "waiter_performance" {
+        let data = waiter_report::get_waiter_report(
+            job.from_date,
+            job.to_date,
+            job.shop_id,
+            shop_name,
+            &guard,
+        )
+        .await?;
+        drop(guard);
+        waiter_report::write_waiter_report_pdf(&data, &pdf_path).await?;
+        waiter_report::write_waiter_report_excel(&data, &excel_path)?;
+    } else {
+        let data = order::get_daily_sale_report(
+            job.from_date,
+            job.to_date,
+            job.shop_id,
+            shop_name,
+            &guard,
+        )
+        .await?;
+        drop(guard);
+        order::write_daily_sale_report_pdf(&data, &pdf_path).await?;
+        order::write_daily_sale_report_excel(&data, &excel_path)?;
+    }
+    Ok((pdf_path, excel_path))
+}
+
+pub async fn run_report_job(job_id: i32, client: Arc<Mutex<Client>>) {
+    let job = {
+        let guard = client.lock().await;
+        if let Err(err) = guard
+            .execute(
+                "update report_jobs set status = 'Running' where job_id = $1",
+                &[&job_id],
+            )
+            .await
+        {
+            println!("Error starting report job {}: {:?}", job_id, err);
+            return;
+        }
+        match get_report_job_by_id(job_id, &guard).await {
+            Some(job) => job,
+            None => return,
+        }
+    };
+
+    let ttl_hours: i32 = env::var("REPORT_JOB_TTL_HOURS")
+        .unwrap_or(String::from("24"))
+        .parse()
+        .unwrap_or(24);
+    let result = generate_report_files(&job, &client).await;
+    let guard = client.lock().await;
+    let update = match result {
+        Ok((pdf_path, excel_path)) => {
+            guard
+                .execute(
+                    "update report_jobs set status = 'Completed', pdf_path = $1, excel_path = $2, completed_at = CURRENT_TIMESTAMP,
+                    expires_at = CURRENT_TIMESTAMP + make_interval(hours => $3) where job_id = $4",
+                    &[&pdf_path, &excel_path, &ttl_hours, &job_id],
+                )
+                .await
+        }
+        Err(err) => {
+            println!("Error generating report job {}: {}", job_id, err);
+            guard
+                .execute(
+                    "update report_jobs set status = 'Failed', error_message = $1, completed_at = CURRENT_TIMESTAMP where job_id = $2",
+                    &[&err.to_string(), &job_id],
+                )
+                .await
+        }
+    };
+    if let Err(err) = update {
+        println!("Error finishing report job {}: {:?}", job_id, err);
+    }
+}
+
+// Jobs still queued or running when the server stopped will never finish.
+pub async fn fail_interrupted_report_jobs(client: &Client) -> Result<u64, Error> {
+    client
+        .execute(
+            "update report_jobs set status = 'Failed', error_message = 'Interrupted by a server restart', completed_at = CURRENT_TIMESTAMP
+            where status in ('Pending', 'Running') and deleted_at is null",
+            &[],
+        )
+        .await
+}
+
+// Marks completed jobs past their expiry as expired and returns the files they leave behind.
+pub async fn expire_report_jobs(client: &Client) -> Result<Vec<String>, Error> {
+    let rows = client
+        .query(
+            "update report_jobs j set status = 'Expired', pdf_path = null, excel_path = null
+            from (select job_id, pdf_path, excel_path from report_jobs where status = 'Completed' and expires_at <= CURRENT_TIMESTAMP for update) old
+            where j.job_id = old.job_id returning old.pdf_path, old.excel_path",
+            &[],
+        )
+        .await?;
+    let mut paths: Vec<String> = vec![];
+    for row in &rows {
+        let pdf_path: Option<String> = row.get("pdf_path");
+        let excel_path: Option<String> = row.get("excel_path");
+        paths.extend(pdf_path);
+        paths.extend(excel_path);
+    }
+    Ok(paths)
+}
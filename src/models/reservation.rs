@@ -0,0 +1,310 @@
+use chrono::{Duration, NaiveDate, NaiveDateTime};
+use serde::{Deserialize, Serialize};
+use tokio_postgres::{types::ToSql, Client, Error};
+
+use crate::{
+    models::{push_notification, user},
+    utils::{
+        common_struct::PaginationResult,
+        sql::{generate_pagination_query, PaginationOptions},
+    },
+};
+
+// Booked: waiting for the party to arrive, the table is held around the time slot.
+// Seated: the party arrived and was given the table.
+// Completed, Canceled and NoShow release the table.
+pub const RESERVATION_STATUSES: [&str; 5] = ["Booked", "Seated", "Completed", "Canceled", "NoShow"];
+pub const DEFAULT_DURATION_MINUTES: i32 = 90;
+// How long before a reservation its table stops being given to walk-ins.
+pub const RESERVATION_HOLD_MINUTES: i32 = 30;
+
+#[derive(Debug, Serialize)]
+pub struct Reservation {
+    pub reservation_id: i32,
+    pub shop_id: i32,
+    pub shop_name: String,
+    pub table_id: i32,
+    pub table_number: String,
+    pub customer_id: Option<i32>,
+    pub guest_name: String,
+    pub guest_phone: String,
+    pub party_size: i32,
+    pub reserved_at: NaiveDateTime,
+    pub duration_minutes: i32,
+    pub special_requests: String,
+    pub status: String,
+    pub created_by_name: String,
+    pub created_at: NaiveDateTime,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ReservationRequest {
+    pub shop_id: Option<i32>,
+    pub table_id: Option<i32>,
+    pub customer_id: Option<i32>,
+    pub guest_name: String,
+    pub guest_phone: String,
+    pub party_size: i32,
+    pub reserved_at: NaiveDateTime,
+    pub duration_minutes: Option<i32>,
+    pub special_requests: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct AvailableTable {
+    pub table_id: i32,
+    pub table_number: String,
+    pub capacity: i32,
+}
+
+const RESERVATION_SELECT_COLUMNS: &str = "r.reservation_id, r.shop_id, s.name as shop_name, r.table_id, t.table_number, r.customer_id,
+    r.guest_name, r.guest_phone, r.party_size, r.reserved_at, r.duration_minutes, coalesce(r.special_requests, '') as special_requests,
+    r.status, coalesce(u.name, '') as created_by_name, r.created_at";
+
+const RESERVATION_BASE_QUERY: &str =
+    "from reservations r join shops s on s.id = r.shop_id join tables t on t.id = r.table_id
+    left join users u on u.id = r.created_by where r.deleted_at is null";
+
+fn row_to_reservation(row: &tokio_postgres::Row) -> Reservation {
+    Reservation {
+        reservation_id: row.get("reservation_id"),
+        shop_id: row.get("shop_id"),
+        shop_name: row.get("shop_name"),
+        table_id: row.get("table_id"),
+        table_number: row.get("table_number"),
+        customer_id: row.get("customer_id"),
+        guest_name: row.get("guest_name"),
+        guest_phone: row.get("guest_phone"),
+        party_size: row.get("party_size"),
+        reserved_at: row.get("reserved_at"),
+        duration_minutes: row.get("duration_minutes"),
+        special_requests: row.get("special_requests"),
+        status: row.get("status"),
+        created_by_name: row.get("created_by_name"),
+        created_at: row.get("created_at"),
+    }
+}
+
+pub async fn get_reservations(
+    shop_id: Option<i32>,
+    date: Option<NaiveDate>,
+    status: &Option<String>,
+    page: Option<usize>,
+    per_page: Option<usize>,
+    client: &Client,
+) -> Result<PaginationResult<Reservation>, Error> {
+    let mut base_query = RESERVATION_BASE_QUERY.to_string();
+    let mut params: Vec<Box<dyn ToSql + Sync>> = vec![];
+    if let Some(s) = shop_id {
+        params.push(Box::new(s));
+        base_query = format!("{base_query} and r.shop_id = ${}", params.len());
+    }
+    if let Some(d) = date {
+        params.push(Box::new(d));
+        base_query = format!("{base_query} and r.reserved_at::date = ${}", params.len());
+    }
+    if let Some(s) = status {
+        params.push(Box::new(s));
+        base_query = format!("{base_query} and r.status = ${}", params.len());
+    }
+
+    let result = generate_pagination_query(PaginationOptions {
+        select_columns: RESERVATION_SELECT_COLUMNS,
+        base_query: &base_query,
+        search_columns: vec![],
+        search: None,
+        order_options: Some("r.reserved_at, t.table_number"),
+        page,
+        per_page,
+    });
+
+    let params_slice: Vec<&(dyn ToSql + Sync)> = params.iter().map(AsRef::as_ref).collect();
+
+    let row = client.query_one(&result.count_query, &params_slice).await?;
+    let total: i64 = row.get("total");
+
+    let mut page_counts = 0;
+    let mut current_page = 0;
+    let mut limit = 0;
+    if let (Some(p), Some(pp)) = (page, per_page) {
+        current_page = p;
+        limit = pp;
+        page_counts = (total as f64 / limit as f64).ceil() as usize;
+    }
+
+    let reservations: Vec<Reservation> = client
+        .query(&result.query, &params_slice)
+        .await?
+        .iter()
+        .map(row_to_reservation)
+        .collect();
+
+    Ok(PaginationResult {
+        data: reservations,
+        total,
+        page: current_page,
+        per_page: limit,
+        page_counts,
+    })
+}
+
+pub async fn get_reservation_by_id(reservation_id: i32, client: &Client) -> Option<Reservation> {
+    match client
+        .query_one(
+            &format!(
+                "select {RESERVATION_SELECT_COLUMNS} {RESERVATION_BASE_QUERY} and r.reservation_id = $1"
+            ),
+            &[&reservation_id],
+        )
+        .await
+    {
+        Ok(row) => Some(row_to_reservation(&row)),
+        Err(_) => None,
+    }
+}
+
+// Tables of the shop that seat the party and have no booked or seated reservation overlapping the time slot,
+// smallest first so large tables stay free for large parties.
+pub async fn get_available_tables(
+    shop_id: i32,
+    party_size: i32,
+    reserved_at: NaiveDateTime,
+    duration_minutes: i32,
+    exclude_reservation_id: Option<i32>,
+    client: &Client,
+) -> Result<Vec<AvailableTable>, Error> {
+    let ends_at = reserved_at + Duration::minutes(duration_minutes as i64);
+    let rows = client
+        .query(
+            "select t.id as table_id, t.table_number, t.capacity from tables t
+            where t.shop_id = $1 and t.deleted_at is null and t.capacity >= $2
+            and not exists (select 1 from reservations r where r.table_id = t.id and r.deleted_at is null
+                and r.status in ('Booked', 'Seated') and ($5::int is null or r.reservation_id != $5)
+                and r.reserved_at < $4 and r.reserved_at + make_interval(mins => r.duration_minutes) > $3)
+            order by t.capacity, t.table_number",
+            &[
+                &shop_id,
+                &party_size,
+                &reserved_at,
+                &ends_at,
+                &exclude_reservation_id,
+            ],
+        )
+        .await?;
+    Ok(rows
+        .iter()
+        .map(|row| AvailableTable {
+            table_id: row.get("table_id"),
+            table_number: row.get("table_number"),
+            capacity: row.get("capacity"),
+        })
+        .collect())
+}
+
+pub async fn add_reservation(
+    data: &ReservationRequest,
+    shop_id: i32,
+    table_id: i32,
+    duration_minutes: i32,
+    user_id: i32,
+    client: &Client,
+) -> Result<i32, Error> {
+    let row = client
+        .query_one(
+            "insert into reservations (shop_id, table_id, customer_id, guest_name, guest_phone, party_size, reserved_at,
+            duration_minutes, special_requests, created_by) values ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10) returning reservation_id",
+            &[
+                &shop_id,
+                &table_id,
+                &data.customer_id,
+                &data.guest_name,
+                &data.guest_phone,
+                &data.party_size,
+                &data.reserved_at,
+                &duration_minutes,
+                &data.special_requests,
+                &user_id,
+            ],
+        )
+        .await?;
+    Ok(row.get("reservation_id"))
+}
+
+pub async fn update_reservation(
+    reservation_id: i32,
+    data: &ReservationRequest,
+    table_id: i32,
+    duration_minutes: i32,
+    client: &Client,
+) -> Result<(), Error> {
+    client
+        .execute(
+            "update reservations set table_id = $1, customer_id = $2, guest_name = $3, guest_phone = $4, party_size = $5,
+            reserved_at = $6, duration_minutes = $7, special_requests = $8 where reservation_id = $9",
+            &[
+                &table_id,
+                &data.customer_id,
+                &data.guest_name,
+                &data.guest_phone,
+                &data.party_size,
+                &data.reserved_at,
+                &duration_minutes,
+                &data.special_requests,
+                &reservation_id,
+            ],
+        )
+        .await?;
+    Ok(())
+}
+
+pub async fn update_reservation_status(
+    reservation_id: i32,
+    status: &str,
+    client: &Client,
+) -> Result<(), Error> {
+    client
+        .execute(
+            "update reservations set status = $1 where reservation_id = $2",
+            &[&status, &reservation_id],
+        )
+        .await?;
+    Ok(())
+}
+
+// Reminds the staff of the shop about parties booked to arrive within `minutes`. Each reservation is marked in
+// the same transaction as its notification is queued, so it is reminded of once.
+pub async fn send_reservation_reminders(minutes: i32, client: &mut Client) -> Result<(), Error> {
+    let transaction = client.transaction().await?;
+    let rows = transaction
+        .query(
+            "update reservations r set reminder_sent_at = CURRENT_TIMESTAMP from tables t
+            where t.id = r.table_id and r.status = 'Booked' and r.deleted_at is null and r.reminder_sent_at is null
+            and r.reserved_at > CURRENT_TIMESTAMP and r.reserved_at <= CURRENT_TIMESTAMP + make_interval(mins => $1)
+            returning r.reservation_id, r.shop_id, r.guest_name, r.party_size, r.reserved_at, t.table_number",
+            &[&minutes],
+        )
+        .await?;
+    for row in rows {
+        let reservation_id: i32 = row.get("reservation_id");
+        let shop_id: i32 = row.get("shop_id");
+        let guest_name: &str = row.get("guest_name");
+        let party_size: i32 = row.get("party_size");
+        let reserved_at: NaiveDateTime = row.get("reserved_at");
+        let table_number: &str = row.get("table_number");
+        let user_ids = user::get_staff_ids_by_shop(shop_id, &transaction).await?;
+        push_notification::enqueue_push_notification(
+            &transaction,
+            &user_ids,
+            "reservation-reminder",
+            "Upcoming reservation",
+            &format!(
+                "{guest_name}, party of {party_size}, at {} on table {table_number}.",
+                reserved_at.format("%H:%M")
+            ),
+            serde_json::json!({"reservation_id": reservation_id}),
+        )
+        .await?;
+    }
+    transaction.commit().await?;
+    Ok(())
+}
@@ -0,0 +1,123 @@
+use serde::{Deserialize, Serialize};
+use tokio_postgres::{Client, Error};
+
+use crate::models::socket_event;
+
+// A section is the group of tables one waiter serves during a shift.
+#[derive(Debug, Serialize)]
+pub struct Section {
+    pub section_id: i32,
+    pub shop_id: i32,
+    pub name: String,
+    pub waiter_id: Option<i32>,
+    pub waiter_name: String,
+    pub table_count: i64,
+    pub seats: i64,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct SectionRequest {
+    pub shop_id: Option<i32>,
+    pub name: String,
+    pub waiter_id: Option<i32>,
+}
+
+const SECTION_SELECT_QUERY: &str = "select sc.section_id, sc.shop_id, sc.name, sc.waiter_id, coalesce(u.name, '') as waiter_name,
+    (select count(*) from tables t where t.section_id = sc.section_id and t.deleted_at is null) as table_count,
+    (select coalesce(sum(t.capacity), 0) from tables t where t.section_id = sc.section_id and t.deleted_at is null) as seats
+    from sections sc left join users u on u.id = sc.waiter_id where sc.deleted_at is null";
+
+fn row_to_section(row: &tokio_postgres::Row) -> Section {
+    Section {
+        section_id: row.get("section_id"),
+        shop_id: row.get("shop_id"),
+        name: row.get("name"),
+        waiter_id: row.get("waiter_id"),
+        waiter_name: row.get("waiter_name"),
+        table_count: row.get("table_count"),
+        seats: row.get("seats"),
+    }
+}
+
+pub async fn get_sections(shop_id: Option<i32>, client: &Client) -> Result<Vec<Section>, Error> {
+    let rows = client
+        .query(
+            &format!(
+                "{SECTION_SELECT_QUERY} and ($1::int is null or sc.shop_id = $1) order by sc.shop_id, sc.name"
+            ),
+            &[&shop_id],
+        )
+        .await?;
+    Ok(rows.iter().map(row_to_section).collect())
+}
+
+pub async fn get_section_by_id(section_id: i32, client: &Client) -> Option<Section> {
+    match client
+        .query_one(
+            &format!("{SECTION_SELECT_QUERY} and sc.section_id = $1"),
+            &[&section_id],
+        )
+        .await
+    {
+        Ok(row) => Some(row_to_section(&row)),
+        Err(_) => None,
+    }
+}
+
+pub async fn add_section(
+    data: &SectionRequest,
+    shop_id: i32,
+    client: &Client,
+) -> Result<i32, Error> {
+    let row = client
+        .query_one(
+            "insert into sections (shop_id, name, waiter_id) values ($1, $2, $3) returning section_id",
+            &[&shop_id, &data.name, &data.waiter_id],
+        )
+        .await?;
+    Ok(row.get("section_id"))
+}
+
+pub async fn update_section(
+    section_id: i32,
+    data: &SectionRequest,
+    client: &Client,
+) -> Result<(), Error> {
+    client
+        .execute(
+            "update sections set name = $1, waiter_id = $2 where section_id = $3",
+            &[&data.name, &data.waiter_id, &section_id],
+        )
+        .await?;
+    Ok(())
+}
+
+// The section's tables are left without a waiter until they are put in another section.
+pub async fn delete_section(section_id: i32, client: &mut Client) -> Result<(), Error> {
+    let transaction = client.transaction().await?;
+    let row = transaction
+        .query_one(
+            "update sections set deleted_at = CURRENT_TIMESTAMP where section_id = $1 returning shop_id",
+            &[&section_id],
+        )
+        .await?;
+    let shop_id: i32 = row.get("shop_id");
+    transaction
+        .execute(
+            "update tables set section_id = null, updated_at = CURRENT_TIMESTAMP where section_id = $1",
+            &[&section_id],
+        )
+        .await?;
+    socket_event::enqueue_socket_event(
+        &transaction,
+        "/pos",
+        "floor-plan-updated",
+        Some(shop_id),
+        &[],
+        &[],
+        serde_json::json!({"shop_id": shop_id}),
+    )
+    .await?;
+    transaction.commit().await?;
+    Ok(())
+}
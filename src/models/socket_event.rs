@@ -0,0 +1,89 @@
+use std::collections::HashMap;
+
+use serde_json::Value;
+use tokio_postgres::{Client, Error, GenericClient};
+
+pub struct SocketEvent {
+    pub event_id: i32,
+    pub namespace: String,
+    pub event: String,
+    pub shop_id: Option<i32>,
+    pub roles: Vec<String>,
+    pub rooms: Vec<i32>,
+    pub attempts: i32,
+    pub payload: HashMap<String, Value>,
+}
+
+// Socket events go through this outbox instead of being emitted straight away. Written with the transaction of
+// the change they announce, they are only sent once it is committed and are kept until they are delivered.
+// `rooms` are user ids; without any, the event goes to the users of `shop_id` with one of `roles`, to everyone in
+// `shop_id` when `roles` is empty, or to everyone when `shop_id` is empty too.
+pub async fn enqueue_socket_event<C: GenericClient>(
+    client: &C,
+    namespace: &str,
+    event: &str,
+    shop_id: Option<i32>,
+    roles: &[&str],
+    rooms: &[i32],
+    payload: Value,
+) -> Result<(), Error> {
+    client
+        .execute(
+            "insert into socket_events (namespace, event, shop_id, roles, rooms, payload) values ($1, $2, $3, $4, $5, $6)",
+            &[&namespace, &event, &shop_id, &roles, &rooms, &payload.to_string()],
+        )
+        .await?;
+    Ok(())
+}
+
+pub async fn get_pending_socket_events(
+    limit: i64,
+    client: &Client,
+) -> Result<Vec<SocketEvent>, Error> {
+    let rows = client
+        .query(
+            "select event_id, namespace, event, shop_id, roles, rooms, attempts, payload from socket_events order by event_id limit $1",
+            &[&limit],
+        )
+        .await?;
+    Ok(rows
+        .iter()
+        .map(|row| {
+            let payload: &str = row.get("payload");
+            SocketEvent {
+                event_id: row.get("event_id"),
+                namespace: row.get("namespace"),
+                event: row.get("event"),
+                shop_id: row.get("shop_id"),
+                roles: row.get("roles"),
+                rooms: row.get("rooms"),
+                attempts: row.get("attempts"),
+                payload: serde_json::from_str(payload).unwrap_or_default(),
+            }
+        })
+        .collect())
+}
+
+pub async fn delete_socket_event(event_id: i32, client: &Client) -> Result<(), Error> {
+    client
+        .execute(
+            "delete from socket_events where event_id = $1",
+            &[&event_id],
+        )
+        .await?;
+    Ok(())
+}
+
+pub async fn record_socket_event_failure(
+    event_id: i32,
+    error: &str,
+    client: &Client,
+) -> Result<(), Error> {
+    client
+        .execute(
+            "update socket_events set attempts = attempts + 1, error = $1 where event_id = $2",
+            &[&error, &event_id],
+        )
+        .await?;
+    Ok(())
+}
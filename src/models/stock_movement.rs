@@ -0,0 +1,320 @@
+use chrono::{NaiveDate, NaiveDateTime};
+use serde::Serialize;
+use tokio_postgres::{types::ToSql, Client, Error, Transaction};
+
+use crate::{
+    models::{ingredient_lot, webhook},
+    utils::{
+        common_struct::PaginationResult,
+        sql::{generate_pagination_query, PaginationOptions},
+    },
+};
+
+pub const MOVEMENT_TYPES: [&str; 6] = [
+    "purchase",
+    "usage",
+    "sale",
+    "adjustment",
+    "transfer",
+    "waste",
+];
+
+pub struct NewStockMovement<'a> {
+    pub ingredient_id: i32,
+    pub movement_type: &'a str,
+    pub quantity: f64,
+    pub unit_cost: f64,
+    pub reference_type: &'a str,
+    pub reference_id: Option<i32>,
+    pub notes: Option<String>,
+    pub shop_id: Option<i32>,
+    pub created_by: Option<i32>,
+}
+
+// Appends a movement to the ledger and moves `ingredients.stock_quantity` to the new running balance.
+// The ingredient row is locked first so concurrent movements can't compute the same balance.
+// Every stock change has to go through here; `stock_quantity` is only a cache of the latest balance.
+pub async fn record_stock_movement(
+    transaction: &Transaction<'_>,
+    movement: &NewStockMovement<'_>,
+) -> Result<f64, Error> {
+    let row = transaction
+        .query_one(
+            "select name, unit, stock_quantity::text as stock_quantity, reorder_level::text as reorder_level, shop_id
+            from ingredients where ingredient_id = $1 for update",
+            &[&movement.ingredient_id],
+        )
+        .await?;
+    let stock_quantity: &str = row.get("stock_quantity");
+    let stock_quantity: f64 = stock_quantity.parse().unwrap();
+    let reorder_level: &str = row.get("reorder_level");
+    let reorder_level: f64 = reorder_level.parse().unwrap();
+    let balance_after = stock_quantity + movement.quantity;
+
+    let insert_query = format!(
+        "insert into stock_movements (ingredient_id, movement_type, quantity, balance_after, unit_cost, reference_type, reference_id, notes, shop_id, created_by) values ($1, $2, {}, {}, {}, $3, $4, $5, $6, $7)",
+        movement.quantity, balance_after, movement.unit_cost
+    );
+    transaction
+        .execute(
+            &insert_query,
+            &[
+                &movement.ingredient_id,
+                &movement.movement_type,
+                &movement.reference_type,
+                &movement.reference_id,
+                &movement.notes,
+                &movement.shop_id,
+                &movement.created_by,
+            ],
+        )
+        .await?;
+    transaction
+        .execute(
+            &format!(
+                "update ingredients set stock_quantity = {} where ingredient_id = $1",
+                balance_after
+            ),
+            &[&movement.ingredient_id],
+        )
+        .await?;
+    // `stock.low` is raised once when the balance drops to the reorder level, not on every movement below it.
+    let shop_id: Option<i32> = row.get("shop_id");
+    if let Some(shop_id) =
+        shop_id.filter(|_| stock_quantity > reorder_level && balance_after <= reorder_level)
+    {
+        let name: &str = row.get("name");
+        let unit: &str = row.get("unit");
+        webhook::enqueue_webhook_event(
+            transaction,
+            shop_id,
+            "stock.low",
+            serde_json::json!({
+                "ingredient_id": movement.ingredient_id,
+                "ingredient_name": name,
+                "stock_quantity": balance_after,
+                "reorder_level": reorder_level,
+                "unit": unit,
+            }),
+        )
+        .await?;
+    }
+    Ok(balance_after)
+}
+
+// Takes the ingredients of a completed order's items off stock, following the item recipes, once each time the
+// order is completed. A sale is recorded even when it takes the balance below zero, the order has already been
+// served.
+pub async fn record_order_sale(
+    order_id: i32,
+    user_id: i32,
+    transaction: &Transaction<'_>,
+) -> Result<(), Error> {
+    let settled: bool = transaction
+        .query_one(
+            "select stock_settled_at is not null as settled from orders where id = $1",
+            &[&order_id],
+        )
+        .await?
+        .get("settled");
+    if settled {
+        return Ok(());
+    }
+    let rows = transaction
+        .query(
+            "select ii.ingredient_id, i.shop_id, sum(ii.quantity * oi.quantity)::text as quantity from order_items oi
+            join item_ingredients ii on ii.item_id = oi.item_id and ii.deleted_at is null
+            join ingredients i on i.ingredient_id = ii.ingredient_id and i.deleted_at is null
+            where oi.order_id = $1 group by ii.ingredient_id, i.shop_id order by ii.ingredient_id",
+            &[&order_id],
+        )
+        .await?;
+    for row in &rows {
+        let ingredient_id: i32 = row.get("ingredient_id");
+        let quantity: &str = row.get("quantity");
+        let quantity: f64 = quantity.parse().unwrap();
+        record_stock_movement(
+            transaction,
+            &NewStockMovement {
+                ingredient_id,
+                movement_type: "sale",
+                quantity: -quantity,
+                unit_cost: 0.0,
+                reference_type: "order",
+                reference_id: Some(order_id),
+                notes: None,
+                shop_id: row.get("shop_id"),
+                created_by: Some(user_id),
+            },
+        )
+        .await?;
+        ingredient_lot::consume_lots(
+            transaction,
+            ingredient_id,
+            quantity,
+            "order",
+            Some(order_id),
+        )
+        .await?;
+    }
+    transaction
+        .execute(
+            "update orders set stock_settled_at = CURRENT_TIMESTAMP, updated_at = CURRENT_TIMESTAMP where id = $1",
+            &[&order_id],
+        )
+        .await?;
+    Ok(())
+}
+
+// Puts back what a refunded order's sale took off stock, for the completion it was last settled for.
+pub async fn reverse_order_sale(
+    order_id: i32,
+    user_id: i32,
+    transaction: &Transaction<'_>,
+) -> Result<(), Error> {
+    let settled: bool = transaction
+        .query_one(
+            "select stock_settled_at is not null as settled from orders where id = $1",
+            &[&order_id],
+        )
+        .await?
+        .get("settled");
+    if !settled {
+        return Ok(());
+    }
+    let rows = transaction
+        .query(
+            "select ingredient_id, shop_id, (-sum(quantity))::text as quantity from stock_movements
+            where movement_type = 'sale' and reference_type = 'order' and reference_id = $1
+            group by ingredient_id, shop_id having sum(quantity) < 0 order by ingredient_id",
+            &[&order_id],
+        )
+        .await?;
+    for row in &rows {
+        let ingredient_id: i32 = row.get("ingredient_id");
+        let quantity: &str = row.get("quantity");
+        let quantity: f64 = quantity.parse().unwrap();
+        record_stock_movement(
+            transaction,
+            &NewStockMovement {
+                ingredient_id,
+                movement_type: "sale",
+                quantity,
+                unit_cost: 0.0,
+                reference_type: "order",
+                reference_id: Some(order_id),
+                notes: Some(String::from("Order refunded")),
+                shop_id: row.get("shop_id"),
+                created_by: Some(user_id),
+            },
+        )
+        .await?;
+        ingredient_lot::restore_lots(transaction, ingredient_id, "order", Some(order_id)).await?;
+    }
+    transaction
+        .execute(
+            "update orders set stock_settled_at = null, updated_at = CURRENT_TIMESTAMP where id = $1",
+            &[&order_id],
+        )
+        .await?;
+    Ok(())
+}
+
+#[derive(Debug, Serialize)]
+pub struct StockMovement {
+    pub movement_id: i32,
+    pub ingredient_id: i32,
+    pub movement_type: String,
+    pub quantity: f64,
+    pub balance_after: f64,
+    pub unit_cost: f64,
+    pub reference_type: String,
+    pub reference_id: Option<i32>,
+    pub notes: String,
+    pub shop_id: Option<i32>,
+    pub created_by_name: String,
+    pub created_at: NaiveDateTime,
+}
+
+pub async fn get_stock_movements(
+    ingredient_id: i32,
+    page: Option<usize>,
+    per_page: Option<usize>,
+    movement_type: &Option<String>,
+    from_date: &Option<NaiveDate>,
+    to_date: &Option<NaiveDate>,
+    client: &Client,
+) -> Result<PaginationResult<StockMovement>, Error> {
+    let mut params: Vec<Box<dyn ToSql + Sync>> = vec![Box::new(ingredient_id)];
+    let mut base_query = "from stock_movements m left join users u on u.id = m.created_by where m.ingredient_id = $1".to_string();
+    if let Some(t) = movement_type {
+        params.push(Box::new(t));
+        base_query = format!("{base_query} and m.movement_type = ${}", params.len());
+    }
+    if let (Some(f), Some(t)) = (from_date, to_date) {
+        params.push(Box::new(*f));
+        params.push(Box::new(*t));
+        base_query = format!(
+            "{base_query} and m.created_at::date between ${} and ${}",
+            params.len() - 1,
+            params.len()
+        );
+    }
+
+    let result = generate_pagination_query(PaginationOptions {
+        select_columns: "m.movement_id, m.ingredient_id, m.movement_type, m.quantity::text as quantity, m.balance_after::text as balance_after, m.unit_cost::text as unit_cost, m.reference_type, m.reference_id, coalesce(m.notes, '') as notes, m.shop_id, coalesce(u.name, '') as created_by_name, m.created_at",
+        base_query: &base_query,
+        search_columns: vec![],
+        search: None,
+        order_options: Some("m.movement_id desc"),
+        page,
+        per_page,
+    });
+
+    let params_slice: Vec<&(dyn ToSql + Sync)> = params.iter().map(AsRef::as_ref).collect();
+
+    let row = client.query_one(&result.count_query, &params_slice).await?;
+    let total: i64 = row.get("total");
+
+    let mut page_counts = 0;
+    let mut current_page = 0;
+    let mut limit = 0;
+    if let (Some(p), Some(pp)) = (page, per_page) {
+        current_page = p;
+        limit = pp;
+        page_counts = (total as f64 / limit as f64).ceil() as usize;
+    }
+
+    let movements: Vec<StockMovement> = client
+        .query(&result.query, &params_slice)
+        .await?
+        .iter()
+        .map(|row| {
+            let quantity: &str = row.get("quantity");
+            let balance_after: &str = row.get("balance_after");
+            let unit_cost: &str = row.get("unit_cost");
+            StockMovement {
+                movement_id: row.get("movement_id"),
+                ingredient_id: row.get("ingredient_id"),
+                movement_type: row.get("movement_type"),
+                quantity: quantity.parse().unwrap(),
+                balance_after: balance_after.parse().unwrap(),
+                unit_cost: unit_cost.parse().unwrap(),
+                reference_type: row.get("reference_type"),
+                reference_id: row.get("reference_id"),
+                notes: row.get("notes"),
+                shop_id: row.get("shop_id"),
+                created_by_name: row.get("created_by_name"),
+                created_at: row.get("created_at"),
+            }
+        })
+        .collect();
+
+    Ok(PaginationResult {
+        data: movements,
+        total,
+        page: current_page,
+        per_page: limit,
+        page_counts,
+    })
+}
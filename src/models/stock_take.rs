@@ -0,0 +1,479 @@
+use chrono::NaiveDateTime;
+use serde::{Deserialize, Serialize};
+use tokio_postgres::{types::ToSql, Client, Error};
+
+use crate::{
+    models::{
+        ingredient_lot::{self, NewIngredientLot},
+        stock_movement::{self, NewStockMovement},
+    },
+    utils::{
+        common_struct::PaginationResult,
+        sql::{generate_pagination_query, PaginationOptions},
+    },
+};
+
+pub const ADJUSTMENT_TYPES: [&str; 3] = ["count_variance", "waste", "spoilage"];
+
+#[derive(Debug, Serialize)]
+pub struct StockTake {
+    pub stock_take_id: i32,
+    pub shop_id: i32,
+    pub shop_name: String,
+    pub status: String,
+    pub notes: String,
+    pub created_by_name: String,
+    pub item_count: i64,
+    pub counted_count: i64,
+    pub created_at: NaiveDateTime,
+    pub posted_at: Option<NaiveDateTime>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct StockTakeItem {
+    pub stock_take_item_id: i32,
+    pub ingredient_id: i32,
+    pub ingredient_name: String,
+    pub unit: String,
+    pub expected_quantity: f64,
+    pub counted_quantity: Option<f64>,
+    pub adjustment_type: String,
+    pub unit_cost: f64,
+    pub notes: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct StockTakeDetail {
+    pub stock_take: StockTake,
+    pub items: Vec<StockTakeItem>,
+}
+
+pub async fn get_stock_takes(
+    search: &Option<String>,
+    page: Option<usize>,
+    per_page: Option<usize>,
+    role: &str,
+    shop_id: i32,
+    status: &Option<String>,
+    client: &Client,
+) -> Result<PaginationResult<StockTake>, Error> {
+    let mut base_query = "from stock_takes st join shops s on s.id = st.shop_id join users u on u.id = st.created_by where st.deleted_at is null".to_string();
+    let mut params: Vec<Box<dyn ToSql + Sync>> = vec![];
+
+    if role == "Manager" {
+        params.push(Box::new(shop_id));
+        base_query = format!("{base_query} and st.shop_id = ${}", params.len());
+    }
+    if let Some(s) = status {
+        params.push(Box::new(s));
+        base_query = format!("{base_query} and st.status = ${}", params.len());
+    }
+
+    let order_options = "st.created_at desc";
+
+    let result = generate_pagination_query(PaginationOptions {
+        select_columns: "st.stock_take_id, st.shop_id, s.name as shop_name, st.status, coalesce(st.notes, '') as notes, u.name as created_by_name,
+        (select count(*) from stock_take_items where stock_take_id = st.stock_take_id) as item_count,
+        (select count(*) from stock_take_items where stock_take_id = st.stock_take_id and counted_quantity is not null) as counted_count,
+        st.created_at, st.posted_at",
+        base_query: &base_query,
+        search_columns: vec!["st.stock_take_id::varchar", "s.name", "st.status", "st.notes"],
+        search: search.as_deref(),
+        order_options: Some(order_options),
+        page,
+        per_page,
+    });
+
+    let params_slice: Vec<&(dyn ToSql + Sync)> = params.iter().map(AsRef::as_ref).collect();
+
+    let row = client.query_one(&result.count_query, &params_slice).await?;
+    let total: i64 = row.get("total");
+
+    let mut page_counts = 0;
+    let mut current_page = 0;
+    let mut limit = 0;
+    if let (Some(p), Some(pp)) = (page, per_page) {
+        current_page = p;
+        limit = pp;
+        page_counts = (total as f64 / limit as f64).ceil() as usize;
+    }
+
+    let stock_takes: Vec<StockTake> = client
+        .query(&result.query, &params_slice)
+        .await?
+        .iter()
+        .map(|row| StockTake {
+            stock_take_id: row.get("stock_take_id"),
+            shop_id: row.get("shop_id"),
+            shop_name: row.get("shop_name"),
+            status: row.get("status"),
+            notes: row.get("notes"),
+            created_by_name: row.get("created_by_name"),
+            item_count: row.get("item_count"),
+            counted_count: row.get("counted_count"),
+            created_at: row.get("created_at"),
+            posted_at: row.get("posted_at"),
+        })
+        .collect();
+
+    Ok(PaginationResult {
+        data: stock_takes,
+        total,
+        page: current_page,
+        per_page: limit,
+        page_counts,
+    })
+}
+
+#[derive(Debug, Deserialize)]
+pub struct StockTakeRequest {
+    pub shop_id: Option<i32>,
+    pub notes: Option<String>,
+}
+
+// Opens a stock take and snapshots the expected quantity and the weighted average purchase cost
+// of every ingredient in the shop, so later stock movements don't shift the baseline being counted.
+pub async fn add_stock_take(
+    shop_id: i32,
+    notes: &Option<String>,
+    created_by: i32,
+    client: &mut Client,
+) -> Result<i32, Error> {
+    let transaction = client.transaction().await?;
+    let row = transaction
+        .query_one(
+            "insert into stock_takes (shop_id, notes, created_by) values ($1, $2, $3) returning stock_take_id",
+            &[&shop_id, &notes, &created_by],
+        )
+        .await?;
+    let stock_take_id: i32 = row.get("stock_take_id");
+    transaction
+        .execute(
+            "insert into stock_take_items (stock_take_id, ingredient_id, expected_quantity, unit_cost)
+            select $1, i.ingredient_id, i.stock_quantity,
+            coalesce((select sum(pd.quantity_purchased * pd.buying_price_per_unit) / nullif(sum(pd.quantity_purchased), 0)
+                from purchase_details pd join purchases p on p.purchase_id = pd.purchase_id
+                where pd.ingredient_id = i.ingredient_id and pd.deleted_at is null and p.deleted_at is null), 0)
+            from ingredients i where i.deleted_at is null and i.shop_id = $2",
+            &[&stock_take_id, &shop_id],
+        )
+        .await?;
+    transaction.commit().await?;
+    Ok(stock_take_id)
+}
+
+pub async fn get_stock_take_by_id(
+    stock_take_id: i32,
+    role: &str,
+    shop_id: i32,
+    client: &Client,
+) -> Option<StockTakeDetail> {
+    let mut params: Vec<Box<dyn ToSql + Sync>> = vec![Box::new(stock_take_id)];
+    let mut query = "select st.stock_take_id, st.shop_id, s.name as shop_name, st.status, coalesce(st.notes, '') as notes, u.name as created_by_name,
+        (select count(*) from stock_take_items where stock_take_id = st.stock_take_id) as item_count,
+        (select count(*) from stock_take_items where stock_take_id = st.stock_take_id and counted_quantity is not null) as counted_count,
+        st.created_at, st.posted_at
+        from stock_takes st join shops s on s.id = st.shop_id join users u on u.id = st.created_by where st.deleted_at is null and st.stock_take_id = $1".to_string();
+    if role == "Manager" {
+        params.push(Box::new(shop_id));
+        query = format!("{query} and st.shop_id = ${}", params.len());
+    }
+    let params_slice: Vec<&(dyn ToSql + Sync)> = params.iter().map(AsRef::as_ref).collect();
+    let row = match client.query_one(&query, &params_slice).await {
+        Ok(row) => row,
+        Err(err) => {
+            println!("{:?}", err);
+            return None;
+        }
+    };
+
+    let item_rows = match client
+        .query(
+            "select sti.stock_take_item_id, sti.ingredient_id, i.name as ingredient_name, i.unit, sti.expected_quantity::text as expected_quantity,
+            sti.counted_quantity::text as counted_quantity, sti.adjustment_type, sti.unit_cost::text as unit_cost, coalesce(sti.notes, '') as notes
+            from stock_take_items sti join ingredients i on i.ingredient_id = sti.ingredient_id
+            where sti.stock_take_id = $1 order by i.name",
+            &[&stock_take_id],
+        )
+        .await
+    {
+        Ok(rows) => rows,
+        Err(err) => {
+            println!("{:?}", err);
+            vec![]
+        }
+    };
+
+    Some(StockTakeDetail {
+        stock_take: StockTake {
+            stock_take_id: row.get("stock_take_id"),
+            shop_id: row.get("shop_id"),
+            shop_name: row.get("shop_name"),
+            status: row.get("status"),
+            notes: row.get("notes"),
+            created_by_name: row.get("created_by_name"),
+            item_count: row.get("item_count"),
+            counted_count: row.get("counted_count"),
+            created_at: row.get("created_at"),
+            posted_at: row.get("posted_at"),
+        },
+        items: item_rows
+            .iter()
+            .map(|row| {
+                let expected_quantity: &str = row.get("expected_quantity");
+                let counted_quantity: Option<&str> = row.get("counted_quantity");
+                let unit_cost: &str = row.get("unit_cost");
+                StockTakeItem {
+                    stock_take_item_id: row.get("stock_take_item_id"),
+                    ingredient_id: row.get("ingredient_id"),
+                    ingredient_name: row.get("ingredient_name"),
+                    unit: row.get("unit"),
+                    expected_quantity: expected_quantity.parse().unwrap(),
+                    counted_quantity: counted_quantity.map(|c| c.parse().unwrap()),
+                    adjustment_type: row.get("adjustment_type"),
+                    unit_cost: unit_cost.parse().unwrap(),
+                    notes: row.get("notes"),
+                }
+            })
+            .collect(),
+    })
+}
+
+#[derive(Debug, Deserialize)]
+pub struct StockTakeCountsRequest {
+    pub counts: Vec<StockTakeCountRequest>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct StockTakeCountRequest {
+    pub ingredient_id: i32,
+    pub counted_quantity: f64,
+    pub adjustment_type: Option<String>,
+    pub notes: Option<String>,
+}
+
+pub async fn update_stock_take_counts(
+    stock_take_id: i32,
+    data: &StockTakeCountsRequest,
+    client: &mut Client,
+) -> Result<(), Error> {
+    let transaction = client.transaction().await?;
+    for count in &data.counts {
+        let adjustment_type = count
+            .adjustment_type
+            .clone()
+            .unwrap_or(String::from("count_variance"));
+        let query = format!(
+            "update stock_take_items set counted_quantity = {}, adjustment_type = $1, notes = $2 where stock_take_id = $3 and ingredient_id = $4",
+            count.counted_quantity
+        );
+        transaction
+            .execute(
+                &query,
+                &[&adjustment_type, &count.notes, &stock_take_id, &count.ingredient_id],
+            )
+            .await?;
+    }
+    transaction.commit().await?;
+    Ok(())
+}
+
+// Posts the variance of every counted line as a typed stock adjustment and a ledger movement. Stock is
+// moved by the variance rather than overwritten with the count, so usage recorded during the count isn't lost.
+pub async fn post_stock_take(
+    stock_take_id: i32,
+    user_id: i32,
+    client: &mut Client,
+) -> Result<(), Error> {
+    let transaction = client.transaction().await?;
+    let rows = transaction
+        .query(
+            "select sti.ingredient_id, (sti.counted_quantity - sti.expected_quantity)::text as variance, sti.adjustment_type, sti.unit_cost::text as unit_cost, sti.notes, st.shop_id
+            from stock_take_items sti join stock_takes st on st.stock_take_id = sti.stock_take_id
+            where sti.stock_take_id = $1 and sti.counted_quantity is not null and sti.counted_quantity <> sti.expected_quantity
+            for update of st",
+            &[&stock_take_id],
+        )
+        .await?;
+    for row in &rows {
+        let ingredient_id: i32 = row.get("ingredient_id");
+        let variance: &str = row.get("variance");
+        let variance: f64 = variance.parse().unwrap();
+        let unit_cost: &str = row.get("unit_cost");
+        let unit_cost: f64 = unit_cost.parse().unwrap();
+        let adjustment_type: String = row.get("adjustment_type");
+        let notes: Option<String> = row.get("notes");
+        let shop_id: i32 = row.get("shop_id");
+
+        let insert_query = format!(
+            "insert into stock_adjustments (ingredient_id, stock_take_id, adjustment_type, quantity, unit_cost, notes, shop_id, created_by) values ($1, $2, $3, {}, {}, $4, $5, $6)",
+            variance, unit_cost
+        );
+        transaction
+            .execute(
+                &insert_query,
+                &[&ingredient_id, &stock_take_id, &adjustment_type, &notes, &shop_id, &user_id],
+            )
+            .await?;
+        stock_movement::record_stock_movement(
+            &transaction,
+            &NewStockMovement {
+                ingredient_id,
+                movement_type: if adjustment_type == "count_variance" {
+                    "adjustment"
+                } else {
+                    "waste"
+                },
+                quantity: variance,
+                unit_cost,
+                reference_type: "stock_take",
+                reference_id: Some(stock_take_id),
+                notes: Some(adjustment_type.clone()),
+                shop_id: Some(shop_id),
+                created_by: Some(user_id),
+            },
+        )
+        .await?;
+        // Missing stock comes out of the lots FEFO; found stock becomes a lot without an expiry date.
+        if variance < 0.0 {
+            ingredient_lot::consume_lots(
+                &transaction,
+                ingredient_id,
+                -variance,
+                "stock_take",
+                Some(stock_take_id),
+            )
+            .await?;
+        } else {
+            ingredient_lot::add_lot(
+                &transaction,
+                &NewIngredientLot {
+                    ingredient_id,
+                    purchase_detail_id: None,
+                    lot_number: &None,
+                    expiry_date: None,
+                    quantity: variance,
+                    unit_cost,
+                    shop_id: Some(shop_id),
+                },
+            )
+            .await?;
+        }
+    }
+    transaction
+        .execute(
+            "update stock_takes set status = 'Posted', posted_at = CURRENT_TIMESTAMP where stock_take_id = $1",
+            &[&stock_take_id],
+        )
+        .await?;
+    transaction.commit().await?;
+    Ok(())
+}
+
+pub async fn cancel_stock_take(
+    stock_take_id: i32,
+    client: &Client,
+) -> Result<(), Box<dyn std::error::Error>> {
+    client
+        .execute(
+            "update stock_takes set status = 'Canceled', deleted_at = CURRENT_TIMESTAMP where stock_take_id = $1",
+            &[&stock_take_id],
+        )
+        .await?;
+    Ok(())
+}
+
+#[derive(Debug, Serialize)]
+pub struct VarianceReportLine {
+    pub ingredient_id: i32,
+    pub ingredient_name: String,
+    pub unit: String,
+    pub adjustment_type: String,
+    pub expected_quantity: f64,
+    pub counted_quantity: f64,
+    pub variance_quantity: f64,
+    pub unit_cost: f64,
+    pub variance_value: f64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct VarianceReportTotal {
+    pub adjustment_type: String,
+    pub variance_value: f64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct VarianceReport {
+    pub stock_take_id: i32,
+    pub shop_name: String,
+    pub status: String,
+    pub lines: Vec<VarianceReportLine>,
+    pub totals: Vec<VarianceReportTotal>,
+    pub total_variance_value: f64,
+}
+
+pub async fn get_variance_report(
+    stock_take: &StockTake,
+    client: &Client,
+) -> Result<VarianceReport, Error> {
+    let rows = client
+        .query(
+            "select sti.ingredient_id, i.name as ingredient_name, i.unit, sti.adjustment_type,
+            sti.expected_quantity::text as expected_quantity, sti.counted_quantity::text as counted_quantity,
+            (sti.counted_quantity - sti.expected_quantity)::text as variance_quantity, sti.unit_cost::text as unit_cost,
+            ((sti.counted_quantity - sti.expected_quantity) * sti.unit_cost)::text as variance_value
+            from stock_take_items sti join ingredients i on i.ingredient_id = sti.ingredient_id
+            where sti.stock_take_id = $1 and sti.counted_quantity is not null
+            order by abs((sti.counted_quantity - sti.expected_quantity) * sti.unit_cost) desc, i.name",
+            &[&stock_take.stock_take_id],
+        )
+        .await?;
+
+    let mut totals: Vec<VarianceReportTotal> = ADJUSTMENT_TYPES
+        .iter()
+        .map(|t| VarianceReportTotal {
+            adjustment_type: t.to_string(),
+            variance_value: 0.0,
+        })
+        .collect();
+    let mut total_variance_value: f64 = 0.0;
+    let lines: Vec<VarianceReportLine> = rows
+        .iter()
+        .map(|row| {
+            let expected_quantity: &str = row.get("expected_quantity");
+            let counted_quantity: &str = row.get("counted_quantity");
+            let variance_quantity: &str = row.get("variance_quantity");
+            let unit_cost: &str = row.get("unit_cost");
+            let variance_value: &str = row.get("variance_value");
+            let variance_value: f64 = variance_value.parse().unwrap();
+            let adjustment_type: String = row.get("adjustment_type");
+            if let Some(t) = totals
+                .iter_mut()
+                .find(|t| t.adjustment_type == adjustment_type)
+            {
+                t.variance_value += variance_value;
+            }
+            total_variance_value += variance_value;
+            VarianceReportLine {
+                ingredient_id: row.get("ingredient_id"),
+                ingredient_name: row.get("ingredient_name"),
+                unit: row.get("unit"),
+                adjustment_type,
+                expected_quantity: expected_quantity.parse().unwrap(),
+                counted_quantity: counted_quantity.parse().unwrap(),
+                variance_quantity: variance_quantity.parse().unwrap(),
+                unit_cost: unit_cost.parse().unwrap(),
+                variance_value,
+            }
+        })
+        .collect();
+
+    Ok(VarianceReport {
+        stock_take_id: stock_take.stock_take_id,
+        shop_name: stock_take.shop_name.clone(),
+        status: stock_take.status.clone(),
+        lines,
+        totals,
+        total_variance_value,
+    })
+}
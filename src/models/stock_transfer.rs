@@ -0,0 +1,423 @@
+use chrono::{NaiveDate, NaiveDateTime};
+use serde::{Deserialize, Serialize};
+use tokio_postgres::{types::ToSql, Client, Error, Transaction};
+
+use crate::{
+    models::{
+        ingredient_lot::{self, NewIngredientLot},
+        stock_movement::{self, NewStockMovement},
+    },
+    utils::{
+        common_struct::PaginationResult,
+        sql::{generate_pagination_query, PaginationOptions},
+    },
+};
+
+#[derive(Debug, Serialize)]
+pub struct StockTransfer {
+    pub transfer_id: i32,
+    pub from_shop_id: i32,
+    pub from_shop_name: String,
+    pub to_shop_id: i32,
+    pub to_shop_name: String,
+    pub status: String,
+    pub notes: String,
+    pub created_by_name: String,
+    pub received_by_name: String,
+    pub created_at: NaiveDateTime,
+    pub received_at: Option<NaiveDateTime>,
+    pub canceled_at: Option<NaiveDateTime>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct StockTransferItem {
+    pub transfer_item_id: i32,
+    pub ingredient_id: i32,
+    pub ingredient_name: String,
+    pub unit: String,
+    pub to_ingredient_id: Option<i32>,
+    pub quantity: f64,
+    pub unit_cost: f64,
+    pub expiry_date: Option<NaiveDate>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct StockTransferDetail {
+    pub stock_transfer: StockTransfer,
+    pub items: Vec<StockTransferItem>,
+}
+
+const TRANSFER_SELECT_COLUMNS: &str = "t.transfer_id, t.from_shop_id, fs.name as from_shop_name, t.to_shop_id, ts.name as to_shop_name, t.status,
+    coalesce(t.notes, '') as notes, cu.name as created_by_name, coalesce(ru.name, '') as received_by_name, t.created_at, t.received_at, t.canceled_at";
+
+const TRANSFER_BASE_QUERY: &str = "from stock_transfers t join shops fs on fs.id = t.from_shop_id join shops ts on ts.id = t.to_shop_id
+    join users cu on cu.id = t.created_by left join users ru on ru.id = t.received_by where t.deleted_at is null";
+
+fn row_to_transfer(row: &tokio_postgres::Row) -> StockTransfer {
+    StockTransfer {
+        transfer_id: row.get("transfer_id"),
+        from_shop_id: row.get("from_shop_id"),
+        from_shop_name: row.get("from_shop_name"),
+        to_shop_id: row.get("to_shop_id"),
+        to_shop_name: row.get("to_shop_name"),
+        status: row.get("status"),
+        notes: row.get("notes"),
+        created_by_name: row.get("created_by_name"),
+        received_by_name: row.get("received_by_name"),
+        created_at: row.get("created_at"),
+        received_at: row.get("received_at"),
+        canceled_at: row.get("canceled_at"),
+    }
+}
+
+pub async fn get_stock_transfers(
+    page: Option<usize>,
+    per_page: Option<usize>,
+    role: &str,
+    shop_id: i32,
+    status: &Option<String>,
+    client: &Client,
+) -> Result<PaginationResult<StockTransfer>, Error> {
+    let mut base_query = TRANSFER_BASE_QUERY.to_string();
+    let mut params: Vec<Box<dyn ToSql + Sync>> = vec![];
+
+    // Managers see transfers going out of and coming into their shop.
+    if role == "Manager" {
+        params.push(Box::new(shop_id));
+        base_query = format!(
+            "{base_query} and (t.from_shop_id = ${} or t.to_shop_id = ${})",
+            params.len(),
+            params.len()
+        );
+    }
+    if let Some(s) = status {
+        params.push(Box::new(s));
+        base_query = format!("{base_query} and t.status = ${}", params.len());
+    }
+
+    let result = generate_pagination_query(PaginationOptions {
+        select_columns: TRANSFER_SELECT_COLUMNS,
+        base_query: &base_query,
+        search_columns: vec![],
+        search: None,
+        order_options: Some("t.created_at desc"),
+        page,
+        per_page,
+    });
+
+    let params_slice: Vec<&(dyn ToSql + Sync)> = params.iter().map(AsRef::as_ref).collect();
+
+    let row = client.query_one(&result.count_query, &params_slice).await?;
+    let total: i64 = row.get("total");
+
+    let mut page_counts = 0;
+    let mut current_page = 0;
+    let mut limit = 0;
+    if let (Some(p), Some(pp)) = (page, per_page) {
+        current_page = p;
+        limit = pp;
+        page_counts = (total as f64 / limit as f64).ceil() as usize;
+    }
+
+    let transfers: Vec<StockTransfer> = client
+        .query(&result.query, &params_slice)
+        .await?
+        .iter()
+        .map(row_to_transfer)
+        .collect();
+
+    Ok(PaginationResult {
+        data: transfers,
+        total,
+        page: current_page,
+        per_page: limit,
+        page_counts,
+    })
+}
+
+pub async fn get_stock_transfer_by_id(
+    transfer_id: i32,
+    role: &str,
+    shop_id: i32,
+    client: &Client,
+) -> Option<StockTransferDetail> {
+    let mut params: Vec<Box<dyn ToSql + Sync>> = vec![Box::new(transfer_id)];
+    let mut query = format!(
+        "select {TRANSFER_SELECT_COLUMNS} {TRANSFER_BASE_QUERY} and t.transfer_id = $1"
+    );
+    if role == "Manager" {
+        params.push(Box::new(shop_id));
+        query = format!(
+            "{query} and (t.from_shop_id = ${} or t.to_shop_id = ${})",
+            params.len(),
+            params.len()
+        );
+    }
+    let params_slice: Vec<&(dyn ToSql + Sync)> = params.iter().map(AsRef::as_ref).collect();
+    let row = match client.query_one(&query, &params_slice).await {
+        Ok(row) => row,
+        Err(err) => {
+            println!("{:?}", err);
+            return None;
+        }
+    };
+
+    let item_rows = match client
+        .query(
+            "select ti.transfer_item_id, ti.ingredient_id, i.name as ingredient_name, i.unit, ti.to_ingredient_id,
+            ti.quantity::text as quantity, ti.unit_cost::text as unit_cost, ti.expiry_date
+            from stock_transfer_items ti join ingredients i on i.ingredient_id = ti.ingredient_id
+            where ti.transfer_id = $1 order by ti.transfer_item_id",
+            &[&transfer_id],
+        )
+        .await
+    {
+        Ok(rows) => rows,
+        Err(err) => {
+            println!("{:?}", err);
+            vec![]
+        }
+    };
+
+    Some(StockTransferDetail {
+        stock_transfer: row_to_transfer(&row),
+        items: item_rows
+            .iter()
+            .map(|row| {
+                let quantity: &str = row.get("quantity");
+                let unit_cost: &str = row.get("unit_cost");
+                StockTransferItem {
+                    transfer_item_id: row.get("transfer_item_id"),
+                    ingredient_id: row.get("ingredient_id"),
+                    ingredient_name: row.get("ingredient_name"),
+                    unit: row.get("unit"),
+                    to_ingredient_id: row.get("to_ingredient_id"),
+                    quantity: quantity.parse().unwrap(),
+                    unit_cost: unit_cost.parse().unwrap(),
+                    expiry_date: row.get("expiry_date"),
+                }
+            })
+            .collect(),
+    })
+}
+
+#[derive(Debug, Deserialize)]
+pub struct StockTransferRequest {
+    pub from_shop_id: Option<i32>,
+    pub to_shop_id: i32,
+    pub notes: Option<String>,
+    pub items: Vec<StockTransferItemRequest>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct StockTransferItemRequest {
+    pub ingredient_id: i32,
+    pub quantity: f64,
+}
+
+// Ships stock out of the source shop. The quantity leaves the source ledger and lots immediately and
+// stays in transit until the destination receives it. Returns `None` when the source is short of stock.
+pub async fn add_stock_transfer(
+    data: &StockTransferRequest,
+    from_shop_id: i32,
+    user_id: i32,
+    client: &mut Client,
+) -> Result<Option<i32>, Error> {
+    let transaction = client.transaction().await?;
+    let transfer_id: i32 = transaction
+        .query_one(
+            "insert into stock_transfers (from_shop_id, to_shop_id, notes, created_by) values ($1, $2, $3, $4) returning transfer_id",
+            &[&from_shop_id, &data.to_shop_id, &data.notes, &user_id],
+        )
+        .await?
+        .get("transfer_id");
+
+    for item in &data.items {
+        let row = transaction
+            .query_one(
+                "select stock_quantity::text as stock_quantity from ingredients where ingredient_id = $1 and deleted_at is null for update",
+                &[&item.ingredient_id],
+            )
+            .await?;
+        let stock_quantity: &str = row.get("stock_quantity");
+        let stock_quantity: f64 = stock_quantity.parse().unwrap();
+        if item.quantity > stock_quantity {
+            transaction.rollback().await?;
+            return Ok(None);
+        }
+
+        let transfer_item_id: i32 = transaction
+            .query_one(
+                &format!(
+                    "insert into stock_transfer_items (transfer_id, ingredient_id, quantity) values ($1, $2, {}) returning transfer_item_id",
+                    item.quantity
+                ),
+                &[&transfer_id, &item.ingredient_id],
+            )
+            .await?
+            .get("transfer_item_id");
+
+        ingredient_lot::consume_lots(
+            &transaction,
+            item.ingredient_id,
+            item.quantity,
+            "stock_transfer",
+            Some(transfer_item_id),
+        )
+        .await?;
+        // The receiving lot inherits the cost and earliest expiry of the lots the stock was taken from.
+        let row = transaction
+            .query_one(
+                "update stock_transfer_items set
+                    unit_cost = coalesce((select sum(c.quantity * l.unit_cost) / nullif(sum(c.quantity), 0) from ingredient_lot_consumptions c join ingredient_lots l on l.lot_id = c.lot_id
+                        where c.reference_type = 'stock_transfer' and c.reference_id = $1 and c.restored_at is null), 0),
+                    expiry_date = (select min(l.expiry_date) from ingredient_lot_consumptions c join ingredient_lots l on l.lot_id = c.lot_id
+                        where c.reference_type = 'stock_transfer' and c.reference_id = $1 and c.restored_at is null)
+                where transfer_item_id = $1 returning unit_cost::text as unit_cost",
+                &[&transfer_item_id],
+            )
+            .await?;
+        let unit_cost: &str = row.get("unit_cost");
+
+        stock_movement::record_stock_movement(
+            &transaction,
+            &NewStockMovement {
+                ingredient_id: item.ingredient_id,
+                movement_type: "transfer",
+                quantity: -item.quantity,
+                unit_cost: unit_cost.parse().unwrap(),
+                reference_type: "stock_transfer",
+                reference_id: Some(transfer_id),
+                notes: Some(String::from("Transfer sent")),
+                shop_id: Some(from_shop_id),
+                created_by: Some(user_id),
+            },
+        )
+        .await?;
+    }
+    transaction.commit().await?;
+    Ok(Some(transfer_id))
+}
+
+// Ingredients are stocked per shop, so the destination books the stock against its own ingredient
+// with the same name and unit, creating one when the shop doesn't carry it yet.
+async fn get_or_add_destination_ingredient(
+    transaction: &Transaction<'_>,
+    ingredient_id: i32,
+    to_shop_id: i32,
+) -> Result<i32, Error> {
+    let row = transaction
+        .query_opt(
+            "select d.ingredient_id from ingredients d join ingredients s on lower(s.name) = lower(d.name) and s.unit = d.unit
+            where s.ingredient_id = $1 and d.shop_id = $2 and d.deleted_at is null order by d.ingredient_id limit 1",
+            &[&ingredient_id, &to_shop_id],
+        )
+        .await?;
+    if let Some(row) = row {
+        return Ok(row.get("ingredient_id"));
+    }
+    let row = transaction
+        .query_one(
+            "insert into ingredients (name, stock_quantity, unit, reorder_level, shop_id)
+            select name, 0, unit, reorder_level, $2 from ingredients where ingredient_id = $1 returning ingredient_id",
+            &[&ingredient_id, &to_shop_id],
+        )
+        .await?;
+    Ok(row.get("ingredient_id"))
+}
+
+pub async fn receive_stock_transfer(
+    transfer: &StockTransferDetail,
+    user_id: i32,
+    client: &mut Client,
+) -> Result<(), Error> {
+    let transaction = client.transaction().await?;
+    let to_shop_id = transfer.stock_transfer.to_shop_id;
+    for item in &transfer.items {
+        let to_ingredient_id =
+            get_or_add_destination_ingredient(&transaction, item.ingredient_id, to_shop_id).await?;
+        stock_movement::record_stock_movement(
+            &transaction,
+            &NewStockMovement {
+                ingredient_id: to_ingredient_id,
+                movement_type: "transfer",
+                quantity: item.quantity,
+                unit_cost: item.unit_cost,
+                reference_type: "stock_transfer",
+                reference_id: Some(transfer.stock_transfer.transfer_id),
+                notes: Some(String::from("Transfer received")),
+                shop_id: Some(to_shop_id),
+                created_by: Some(user_id),
+            },
+        )
+        .await?;
+        ingredient_lot::add_lot(
+            &transaction,
+            &NewIngredientLot {
+                ingredient_id: to_ingredient_id,
+                purchase_detail_id: None,
+                lot_number: &None,
+                expiry_date: item.expiry_date,
+                quantity: item.quantity,
+                unit_cost: item.unit_cost,
+                shop_id: Some(to_shop_id),
+            },
+        )
+        .await?;
+        transaction
+            .execute(
+                "update stock_transfer_items set to_ingredient_id = $1 where transfer_item_id = $2",
+                &[&to_ingredient_id, &item.transfer_item_id],
+            )
+            .await?;
+    }
+    transaction
+        .execute(
+            "update stock_transfers set status = 'Received', received_by = $1, received_at = CURRENT_TIMESTAMP where transfer_id = $2",
+            &[&user_id, &transfer.stock_transfer.transfer_id],
+        )
+        .await?;
+    transaction.commit().await?;
+    Ok(())
+}
+
+// Returns in-transit stock to the source shop, back into the lots it was taken from.
+pub async fn cancel_stock_transfer(
+    transfer: &StockTransferDetail,
+    user_id: i32,
+    client: &mut Client,
+) -> Result<(), Error> {
+    let transaction = client.transaction().await?;
+    for item in &transfer.items {
+        stock_movement::record_stock_movement(
+            &transaction,
+            &NewStockMovement {
+                ingredient_id: item.ingredient_id,
+                movement_type: "transfer",
+                quantity: item.quantity,
+                unit_cost: item.unit_cost,
+                reference_type: "stock_transfer",
+                reference_id: Some(transfer.stock_transfer.transfer_id),
+                notes: Some(String::from("Transfer canceled")),
+                shop_id: Some(transfer.stock_transfer.from_shop_id),
+                created_by: Some(user_id),
+            },
+        )
+        .await?;
+        ingredient_lot::restore_lots(
+            &transaction,
+            item.ingredient_id,
+            "stock_transfer",
+            Some(item.transfer_item_id),
+        )
+        .await?;
+    }
+    transaction
+        .execute(
+            "update stock_transfers set status = 'Canceled', canceled_at = CURRENT_TIMESTAMP where transfer_id = $1",
+            &[&transfer.stock_transfer.transfer_id],
+        )
+        .await?;
+    transaction.commit().await?;
+    Ok(())
+}
@@ -0,0 +1,277 @@
+use std::env;
+
+use chrono::NaiveDateTime;
+use serde::Serialize;
+use tokio_postgres::{Client, Error};
+
+#[derive(Debug, Serialize)]
+pub struct SyncItem {
+    pub id: i32,
+    pub name: String,
+    pub description: String,
+    pub price: f64,
+    pub discounted_price: f64,
+    pub image_url: String,
+    pub stock_quantity: i32,
+    pub category_ids: Vec<i32>,
+    pub updated_at: NaiveDateTime,
+}
+
+#[derive(Debug, Serialize)]
+pub struct SyncCategory {
+    pub id: i32,
+    pub name: String,
+    pub description: String,
+    pub updated_at: NaiveDateTime,
+}
+
+#[derive(Debug, Serialize)]
+pub struct SyncTable {
+    pub id: i32,
+    pub table_number: String,
+    pub capacity: i32,
+    pub shape: String,
+    pub pos_x: i32,
+    pub pos_y: i32,
+    pub width: i32,
+    pub height: i32,
+    pub rotation: i32,
+    pub floor_area_id: Option<i32>,
+    pub section_id: Option<i32>,
+    pub updated_at: NaiveDateTime,
+}
+
+#[derive(Debug, Serialize)]
+pub struct SyncOrder {
+    pub id: i32,
+    pub waiter_id: Option<i32>,
+    pub table_id: Option<i32>,
+    pub order_type: String,
+    pub status: String,
+    pub contact_name: String,
+    pub scheduled_at: Option<NaiveDateTime>,
+    pub customer_id: Option<i32>,
+    pub items: Vec<SyncOrderItem>,
+    pub created_at: NaiveDateTime,
+    pub updated_at: NaiveDateTime,
+}
+
+#[derive(Debug, Serialize)]
+pub struct SyncOrderItem {
+    pub item_id: i32,
+    pub quantity: i32,
+    pub price: f64,
+    pub special_instructions: String,
+}
+
+// Ids of rows deleted since the last sync, for the tablet to drop from its cache.
+#[derive(Debug, Default, Serialize)]
+pub struct SyncTombstones {
+    pub items: Vec<i32>,
+    pub categories: Vec<i32>,
+    pub tables: Vec<i32>,
+    pub orders: Vec<i32>,
+}
+
+// `synced_at` is passed back as `since` on the next sync.
+#[derive(Debug, Serialize)]
+pub struct SyncChanges {
+    pub synced_at: NaiveDateTime,
+    pub items: Vec<SyncItem>,
+    pub categories: Vec<SyncCategory>,
+    pub tables: Vec<SyncTable>,
+    pub orders: Vec<SyncOrder>,
+    pub deleted: SyncTombstones,
+}
+
+// Seconds `synced_at` is taken back by, on top of the oldest transaction still writing.
+fn sync_safety_margin() -> f64 {
+    env::var("SYNC_SAFETY_MARGIN")
+        .unwrap_or(String::from("5"))
+        .parse()
+        .unwrap_or(5.0)
+}
+
+// Rows deleted from the shop, or moved to another shop and not back since.
+async fn get_deleted_ids(
+    table: &str,
+    shop_id: i32,
+    since: NaiveDateTime,
+    client: &Client,
+) -> Result<Vec<i32>, Error> {
+    let rows = client
+        .query(
+            &format!(
+                "select id from {table} where shop_id = $1 and deleted_at > $2
+                union select entity_id as id from sync_shop_moves where entity = '{table}' and shop_id = $1 and moved_at > $2
+                and entity_id not in (select id from {table} where shop_id = $1 and deleted_at is null)
+                order by id"
+            ),
+            &[&shop_id, &since],
+        )
+        .await?;
+    Ok(rows.iter().map(|row| row.get("id")).collect())
+}
+
+// Everything of the shop changed after `since`, or the whole menu, floor plan and open orders on a first sync.
+pub async fn get_sync_changes(
+    shop_id: i32,
+    since: Option<NaiveDateTime>,
+    client: &Client,
+) -> Result<SyncChanges, Error> {
+    // Read first, so changes made while the rest is read are picked up again by the next sync. `updated_at` is
+    // the start of the transaction that wrote the row, so one still running may yet commit rows stamped before
+    // now. `synced_at` goes back to before the oldest of them, and a margin more, so the next sync sends those
+    // rows too; rows in between are simply sent twice.
+    let synced_at: NaiveDateTime = client
+        .query_one(
+            "select least(localtimestamp, coalesce((select min(xact_start) from pg_stat_activity
+                where backend_xid is not null and datname = current_database())::timestamp, localtimestamp))
+            - make_interval(secs => $1) as synced_at",
+            &[&sync_safety_margin()],
+        )
+        .await?
+        .get("synced_at");
+
+    let items = client
+        .query(
+            "select i.id, i.name, coalesce(i.description, '') as description, i.price::text,
+            case when i.discount_type = 'No Discount' then i.price::text
+                when i.discount_type = 'Discount by Specific Amount' then i.discounted_price::text
+                else case when i.discount_expiration is null then (i.price - (i.price * i.discount_percent / 100))::text
+                when now() >= i.discount_expiration then i.price::text else (i.price - (i.price * i.discount_percent / 100))::text end end as discounted_price,
+            coalesce(i.image_url, '') as image_url, coalesce(i.stock_quantity, 0) as stock_quantity,
+            array(select ic.category_id from item_categories ic where ic.item_id = i.id order by ic.category_id) as category_ids, i.updated_at
+            from items i where i.deleted_at is null and i.shop_id = $1 and ($2::timestamp is null or i.updated_at > $2)
+            order by i.id",
+            &[&shop_id, &since],
+        )
+        .await?
+        .iter()
+        .map(|row| {
+            let price: &str = row.get("price");
+            let discounted_price: &str = row.get("discounted_price");
+            SyncItem {
+                id: row.get("id"),
+                name: row.get("name"),
+                description: row.get("description"),
+                price: price.parse().unwrap(),
+                discounted_price: discounted_price.parse().unwrap(),
+                image_url: row.get("image_url"),
+                stock_quantity: row.get("stock_quantity"),
+                category_ids: row.get("category_ids"),
+                updated_at: row.get("updated_at"),
+            }
+        })
+        .collect();
+
+    let categories = client
+        .query(
+            "select id, name, coalesce(description, '') as description, updated_at from categories
+            where deleted_at is null and shop_id = $1 and ($2::timestamp is null or updated_at > $2) order by id",
+            &[&shop_id, &since],
+        )
+        .await?
+        .iter()
+        .map(|row| SyncCategory {
+            id: row.get("id"),
+            name: row.get("name"),
+            description: row.get("description"),
+            updated_at: row.get("updated_at"),
+        })
+        .collect();
+
+    let tables = client
+        .query(
+            "select id, table_number, capacity, shape, pos_x, pos_y, width, height, rotation, floor_area_id, section_id,
+            updated_at from tables
+            where deleted_at is null and shop_id = $1 and ($2::timestamp is null or updated_at > $2) order by id",
+            &[&shop_id, &since],
+        )
+        .await?
+        .iter()
+        .map(|row| SyncTable {
+            id: row.get("id"),
+            table_number: row.get("table_number"),
+            capacity: row.get("capacity"),
+            shape: row.get("shape"),
+            pos_x: row.get("pos_x"),
+            pos_y: row.get("pos_y"),
+            width: row.get("width"),
+            height: row.get("height"),
+            rotation: row.get("rotation"),
+            floor_area_id: row.get("floor_area_id"),
+            section_id: row.get("section_id"),
+            updated_at: row.get("updated_at"),
+        })
+        .collect();
+
+    // A first sync only takes the open orders, later ones also tell the tablet about orders that were closed.
+    let order_rows = client
+        .query(
+            "select id, waiter_id, table_id, order_type, status, coalesce(contact_name, '') as contact_name,
+            scheduled_at, customer_id, created_at, updated_at from orders
+            where deleted_at is null and shop_id = $1
+            and (($2::timestamp is null and status in ('Pending', 'Served')) or updated_at > $2) order by id",
+            &[&shop_id, &since],
+        )
+        .await?;
+    let order_ids: Vec<i32> = order_rows.iter().map(|row| row.get("id")).collect();
+    let order_item_rows = client
+        .query(
+            "select order_id, item_id, quantity, price::text, coalesce(special_instructions, '') as special_instructions
+            from order_items where order_id = any($1) order by order_id, item_id",
+            &[&order_ids],
+        )
+        .await?;
+    let orders = order_rows
+        .iter()
+        .map(|row| {
+            let order_id: i32 = row.get("id");
+            SyncOrder {
+                id: order_id,
+                waiter_id: row.get("waiter_id"),
+                table_id: row.get("table_id"),
+                order_type: row.get("order_type"),
+                status: row.get("status"),
+                contact_name: row.get("contact_name"),
+                scheduled_at: row.get("scheduled_at"),
+                customer_id: row.get("customer_id"),
+                items: order_item_rows
+                    .iter()
+                    .filter(|r| r.get::<_, i32>("order_id") == order_id)
+                    .map(|r| {
+                        let price: &str = r.get("price");
+                        SyncOrderItem {
+                            item_id: r.get("item_id"),
+                            quantity: r.get("quantity"),
+                            price: price.parse().unwrap(),
+                            special_instructions: r.get("special_instructions"),
+                        }
+                    })
+                    .collect(),
+                created_at: row.get("created_at"),
+                updated_at: row.get("updated_at"),
+            }
+        })
+        .collect();
+
+    let deleted = match since {
+        Some(s) => SyncTombstones {
+            items: get_deleted_ids("items", shop_id, s, client).await?,
+            categories: get_deleted_ids("categories", shop_id, s, client).await?,
+            tables: get_deleted_ids("tables", shop_id, s, client).await?,
+            orders: get_deleted_ids("orders", shop_id, s, client).await?,
+        },
+        None => SyncTombstones::default(),
+    };
+
+    Ok(SyncChanges {
+        synced_at,
+        items,
+        categories,
+        tables,
+        orders,
+        deleted,
+    })
+}
@@ -2,11 +2,18 @@ use chrono::NaiveDateTime;
 use serde::{Deserialize, Serialize};
 use tokio_postgres::{types::ToSql, Client, Error};
 
-use crate::utils::{
-    common_struct::PaginationResult,
-    sql::{generate_pagination_query, PaginationOptions},
+use crate::{
+    models::{reservation::RESERVATION_HOLD_MINUTES, socket_event},
+    utils::{
+        common_struct::PaginationResult,
+        sql::{generate_pagination_query, PaginationOptions},
+    },
 };
 
+pub const TABLE_SHAPES: [&str; 3] = ["Square", "Round", "Rectangle"];
+
+// Position and size are in floor-plan grid units, rotation in degrees.
+// The waiter is the one assigned to the table's section.
 #[derive(Debug, Serialize, Deserialize)]
 pub struct Table {
     pub id: i32,
@@ -14,22 +21,117 @@ pub struct Table {
     pub qr_code: String,
     pub shop_id: i32,
     pub shop_name: String,
+    pub capacity: i32,
+    pub shape: String,
+    pub pos_x: i32,
+    pub pos_y: i32,
+    pub width: i32,
+    pub height: i32,
+    pub rotation: i32,
+    pub floor_area_id: Option<i32>,
+    pub floor_area_name: String,
+    pub section_id: Option<i32>,
+    pub section_name: String,
+    pub waiter_id: Option<i32>,
+    pub waiter_name: String,
     pub created_at: NaiveDateTime,
-    pub order_id: i32
+    pub order_id: i32,
+    pub status: String,
+    pub reservation_id: Option<i32>,
+    pub reserved_at: Option<NaiveDateTime>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct FloorPlanArea {
+    pub floor_area_id: Option<i32>,
+    pub name: String,
+    pub seats: i32,
+    pub tables: Vec<Table>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct TableLayoutRequest {
+    pub table_id: i32,
+    pub floor_area_id: Option<i32>,
+    pub section_id: Option<i32>,
+    pub shape: String,
+    pub pos_x: i32,
+    pub pos_y: i32,
+    pub width: i32,
+    pub height: i32,
+    pub rotation: i32,
+}
+
+// A booked table is held from shortly before the reservation until its time slot ends or the party is seated.
+fn table_hold_join() -> String {
+    format!(
+        "left join lateral (select r.reservation_id, r.reserved_at from reservations r
+        where r.table_id = t.id and r.status = 'Booked' and r.deleted_at is null
+        and CURRENT_TIMESTAMP >= r.reserved_at - make_interval(mins => {RESERVATION_HOLD_MINUTES})
+        and CURRENT_TIMESTAMP < r.reserved_at + make_interval(mins => r.duration_minutes)
+        order by r.reserved_at limit 1) h on true"
+    )
+}
+
+fn table_from_query() -> String {
+    format!(
+        "from tables t join shops s on s.id = t.shop_id
+        left join orders o on t.id = o.table_id and o.status not in ('Canceled','Completed')
+        left join floor_areas fa on fa.floor_area_id = t.floor_area_id
+        left join sections sc on sc.section_id = t.section_id
+        left join users w on w.id = sc.waiter_id
+        {}",
+        table_hold_join()
+    )
+}
+
+const TABLE_SELECT_COLUMNS: &str = "t.id, t.table_number, t.qr_code, t.shop_id, s.name shop_name, t.capacity, t.shape, t.pos_x, t.pos_y,
+    t.width, t.height, t.rotation, t.floor_area_id, coalesce(fa.name, '') as floor_area_name, t.section_id,
+    coalesce(sc.name, '') as section_name, sc.waiter_id, coalesce(w.name, '') as waiter_name, t.created_at, COALESCE(o.id, 0) order_id,
+    case when o.id is not null then 'Occupied' when h.reservation_id is not null then 'Reserved' else 'Available' end as status,
+    h.reservation_id, h.reserved_at";
+
+fn row_to_table(row: &tokio_postgres::Row) -> Table {
+    Table {
+        id: row.get("id"),
+        table_number: row.get("table_number"),
+        qr_code: row.get("qr_code"),
+        shop_id: row.get("shop_id"),
+        shop_name: row.get("shop_name"),
+        capacity: row.get("capacity"),
+        shape: row.get("shape"),
+        pos_x: row.get("pos_x"),
+        pos_y: row.get("pos_y"),
+        width: row.get("width"),
+        height: row.get("height"),
+        rotation: row.get("rotation"),
+        floor_area_id: row.get("floor_area_id"),
+        floor_area_name: row.get("floor_area_name"),
+        section_id: row.get("section_id"),
+        section_name: row.get("section_name"),
+        waiter_id: row.get("waiter_id"),
+        waiter_name: row.get("waiter_name"),
+        created_at: row.get("created_at"),
+        order_id: row.get("order_id"),
+        status: row.get("status"),
+        reservation_id: row.get("reservation_id"),
+        reserved_at: row.get("reserved_at"),
+    }
 }
 
 pub async fn get_tables(
     search: &Option<String>,
+    floor_area_id: Option<i32>,
     page: Option<usize>,
     per_page: Option<usize>,
     role: &str,
     shop_id: i32,
     client: &Client,
 ) -> Result<PaginationResult<Table>, Error> {
-    let mut base_query =
-        "from tables t join shops s on s.id = t.shop_id 
-        left join orders o on t.id=o.table_id and o.status not in ('Canceled','Completed')
-        where t.deleted_at is null and s.deleted_at is null".to_string();
+    let mut base_query = format!(
+        "{} where t.deleted_at is null and s.deleted_at is null",
+        table_from_query()
+    );
     let mut params: Vec<Box<dyn ToSql + Sync>> = vec![];
 
     let order_options = if role == "Waiter" {
@@ -42,10 +144,13 @@ pub async fn get_tables(
         params.push(Box::new(shop_id));
         base_query = format!("{base_query} and t.shop_id = ${}", params.len());
     }
+    if let Some(f) = floor_area_id {
+        params.push(Box::new(f));
+        base_query = format!("{base_query} and t.floor_area_id = ${}", params.len());
+    }
 
     let result = generate_pagination_query(PaginationOptions {
-        select_columns:
-            "t.id, t.table_number, t.qr_code, t.shop_id, s.name shop_name, t.created_at, COALESCE(o.id, 0) order_id",
+        select_columns: TABLE_SELECT_COLUMNS,
         base_query: &base_query,
         search_columns: vec!["t.id::varchar", "t.table_number", "t.qr_code", "s.name"],
         search: search.as_deref(),
@@ -72,15 +177,7 @@ pub async fn get_tables(
         .query(&result.query, &params_slice)
         .await?
         .iter()
-        .map(|row| Table {
-            id: row.get("id"),
-            table_number: row.get("table_number"),
-            qr_code: row.get("qr_code"),
-            shop_id: row.get("shop_id"),
-            shop_name: row.get("shop_name"),
-            created_at: row.get("created_at"),
-            order_id: row.get("order_id"),
-        })
+        .map(row_to_table)
         .collect();
 
     Ok(PaginationResult {
@@ -97,39 +194,48 @@ pub struct TableRequest {
     pub table_number: String,
     pub qr_code: String,
     pub shop_id: i32,
+    pub capacity: Option<i32>,
 }
 
 pub async fn add_table(
     data: &TableRequest,
-    client: &Client,
+    client: &mut Client,
 ) -> Result<(), Box<dyn std::error::Error>> {
-    client
-        .execute(
-            "insert into tables (table_number, qr_code, shop_id) values ($1, $2, $3)",
-            &[&data.table_number, &data.qr_code, &data.shop_id],
+    let transaction = client.transaction().await?;
+    let row = transaction
+        .query_one(
+            "insert into tables (table_number, qr_code, shop_id, capacity) values ($1, $2, $3, coalesce($4, 4)) returning id",
+            &[&data.table_number, &data.qr_code, &data.shop_id, &data.capacity],
         )
         .await?;
+    let table_id: i32 = row.get("id");
+    socket_event::enqueue_socket_event(
+        &transaction,
+        "/pos",
+        "table-created",
+        Some(data.shop_id),
+        &[],
+        &[],
+        serde_json::json!({"table_id": table_id, "shop_id": data.shop_id}),
+    )
+    .await?;
+    transaction.commit().await?;
     Ok(())
 }
 
 pub async fn get_table_by_id(table_id: i32, client: &Client) -> Option<Table> {
     let result = client
         .query_one(
-            "select t.id, t.table_number, t.qr_code, s.id shop_id, s.name shop_name, t.created_at from tables t join shops s on s.id = t.shop_id where t.deleted_at is null and t.id = $1",
+            &format!(
+                "select {TABLE_SELECT_COLUMNS} {} where t.deleted_at is null and t.id = $1 limit 1",
+                table_from_query()
+            ),
             &[&table_id],
         )
         .await;
 
     match result {
-        Ok(row) => Some(Table {
-            id: row.get("id"),
-            table_number: row.get("table_number"),
-            qr_code: row.get("qr_code"),
-            shop_id: row.get("shop_id"),
-            shop_name: row.get("shop_name"),
-            created_at: row.get("created_at"),
-            order_id: 0
-        }),
+        Ok(row) => Some(row_to_table(&row)),
         Err(_) => None,
     }
 }
@@ -137,28 +243,55 @@ pub async fn get_table_by_id(table_id: i32, client: &Client) -> Option<Table> {
 pub async fn update_table(
     table_id: i32,
     data: &TableRequest,
-    client: &Client,
+    client: &mut Client,
 ) -> Result<(), Box<dyn std::error::Error>> {
-    client
+    let transaction = client.transaction().await?;
+    transaction
         .execute(
-            "update tables set table_number = $1, qr_code = $2, shop_id = $3 where id = $4",
-            &[&data.table_number, &data.qr_code, &data.shop_id, &table_id],
+            "with moved as (insert into sync_shop_moves (entity, entity_id, shop_id)
+                select 'tables', id, shop_id from tables where id = $4 and shop_id is distinct from $3 and shop_id is not null)
+            update tables set table_number = $1, qr_code = $2, shop_id = $3, capacity = coalesce($5, capacity), updated_at = CURRENT_TIMESTAMP where id = $4",
+            &[&data.table_number, &data.qr_code, &data.shop_id, &table_id, &data.capacity],
         )
         .await?;
+    socket_event::enqueue_socket_event(
+        &transaction,
+        "/pos",
+        "table-updated",
+        Some(data.shop_id),
+        &[],
+        &[],
+        serde_json::json!({"table_id": table_id, "shop_id": data.shop_id}),
+    )
+    .await?;
+    transaction.commit().await?;
 
     Ok(())
 }
 
 pub async fn delete_table(
     table_id: i32,
-    client: &Client,
+    client: &mut Client,
 ) -> Result<(), Box<dyn std::error::Error>> {
-    client
-        .execute(
-            "update tables set deleted_at = CURRENT_TIMESTAMP where id = $1",
+    let transaction = client.transaction().await?;
+    let row = transaction
+        .query_one(
+            "update tables set deleted_at = CURRENT_TIMESTAMP where id = $1 returning shop_id",
             &[&table_id],
         )
         .await?;
+    let shop_id: Option<i32> = row.get("shop_id");
+    socket_event::enqueue_socket_event(
+        &transaction,
+        "/pos",
+        "table-deleted",
+        shop_id,
+        &[],
+        &[],
+        serde_json::json!({"table_id": table_id, "shop_id": shop_id}),
+    )
+    .await?;
+    transaction.commit().await?;
 
     Ok(())
 }
@@ -178,4 +311,123 @@ pub async fn table_number_exists(
 
     // Return whether the user exists
     Ok(row.is_ok())
-}
\ No newline at end of file
+}
+
+// Tables grouped by floor area in the areas' sort order; tables not placed in an area come last.
+pub async fn get_floor_plan(shop_id: i32, client: &Client) -> Result<Vec<FloorPlanArea>, Error> {
+    let mut areas: Vec<FloorPlanArea> = client
+        .query(
+            "select floor_area_id, name from floor_areas where shop_id = $1 and deleted_at is null
+            order by sort_order, name",
+            &[&shop_id],
+        )
+        .await?
+        .iter()
+        .map(|row| FloorPlanArea {
+            floor_area_id: row.get("floor_area_id"),
+            name: row.get("name"),
+            seats: 0,
+            tables: vec![],
+        })
+        .collect();
+    areas.push(FloorPlanArea {
+        floor_area_id: None,
+        name: String::from("Unassigned"),
+        seats: 0,
+        tables: vec![],
+    });
+
+    let rows = client
+        .query(
+            &format!(
+                "select {TABLE_SELECT_COLUMNS} {} where t.deleted_at is null and t.shop_id = $1 order by t.table_number",
+                table_from_query()
+            ),
+            &[&shop_id],
+        )
+        .await?;
+    for row in rows.iter() {
+        let table = row_to_table(row);
+        let area = match areas
+            .iter_mut()
+            .find(|a| a.floor_area_id.is_some() && a.floor_area_id == table.floor_area_id)
+        {
+            Some(a) => a,
+            None => areas.last_mut().unwrap(),
+        };
+        area.seats += table.capacity;
+        area.tables.push(table);
+    }
+    if areas.last().is_some_and(|a| a.tables.is_empty()) {
+        areas.pop();
+    }
+    Ok(areas)
+}
+
+pub async fn layout_belongs_to_shop(
+    layouts: &[TableLayoutRequest],
+    shop_id: i32,
+    client: &Client,
+) -> Result<bool, Error> {
+    let mut table_ids: Vec<i32> = layouts.iter().map(|l| l.table_id).collect();
+    let mut floor_area_ids: Vec<i32> = layouts.iter().filter_map(|l| l.floor_area_id).collect();
+    let mut section_ids: Vec<i32> = layouts.iter().filter_map(|l| l.section_id).collect();
+    for ids in [&mut table_ids, &mut floor_area_ids, &mut section_ids] {
+        ids.sort();
+        ids.dedup();
+    }
+    let row = client
+        .query_one(
+            "select (select count(*) from tables where id = any($1) and shop_id = $4 and deleted_at is null) as tables,
+            (select count(*) from floor_areas where floor_area_id = any($2) and shop_id = $4 and deleted_at is null) as floor_areas,
+            (select count(*) from sections where section_id = any($3) and shop_id = $4 and deleted_at is null) as sections",
+            &[&table_ids, &floor_area_ids, &section_ids, &shop_id],
+        )
+        .await?;
+    let tables: i64 = row.get("tables");
+    let floor_areas: i64 = row.get("floor_areas");
+    let sections: i64 = row.get("sections");
+    Ok(tables == table_ids.len() as i64
+        && floor_areas == floor_area_ids.len() as i64
+        && sections == section_ids.len() as i64)
+}
+
+// The floor-plan editor saves all moved tables at once.
+pub async fn update_table_layouts(
+    layouts: &[TableLayoutRequest],
+    shop_id: i32,
+    client: &mut Client,
+) -> Result<(), Error> {
+    let transaction = client.transaction().await?;
+    for layout in layouts {
+        transaction
+            .execute(
+                "update tables set floor_area_id = $1, section_id = $2, shape = $3, pos_x = $4, pos_y = $5,
+                width = $6, height = $7, rotation = $8, updated_at = CURRENT_TIMESTAMP where id = $9",
+                &[
+                    &layout.floor_area_id,
+                    &layout.section_id,
+                    &layout.shape,
+                    &layout.pos_x,
+                    &layout.pos_y,
+                    &layout.width,
+                    &layout.height,
+                    &layout.rotation,
+                    &layout.table_id,
+                ],
+            )
+            .await?;
+    }
+    socket_event::enqueue_socket_event(
+        &transaction,
+        "/pos",
+        "floor-plan-updated",
+        Some(shop_id),
+        &[],
+        &[],
+        serde_json::json!({"shop_id": shop_id}),
+    )
+    .await?;
+    transaction.commit().await?;
+    Ok(())
+}
@@ -0,0 +1,642 @@
+use serde::{Deserialize, Serialize};
+use tokio_postgres::{Client, Error, Transaction};
+
+pub const ROUNDING_MODES: [&str; 4] = ["None", "Nearest", "Up", "Down"];
+
+#[derive(Debug, Serialize)]
+pub struct TaxRule {
+    pub tax_rule_id: i32,
+    pub shop_id: i32,
+    pub name: String,
+    pub rate: f64,
+    pub is_inclusive: bool,
+    pub is_compound: bool,
+    pub sort_order: i32,
+    pub exempt_item_ids: Vec<i32>,
+    pub exempt_category_ids: Vec<i32>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct TaxRuleRequest {
+    pub shop_id: Option<i32>,
+    pub name: String,
+    pub rate: f64,
+    pub is_inclusive: bool,
+    pub is_compound: bool,
+    pub sort_order: i32,
+    pub exempt_item_ids: Vec<i32>,
+    pub exempt_category_ids: Vec<i32>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct TaxSetting {
+    pub shop_id: i32,
+    pub rounding_mode: String,
+    pub rounding_increment: f64,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct TaxSettingRequest {
+    pub rounding_mode: String,
+    pub rounding_increment: f64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct OrderTax {
+    pub tax_rule_id: i32,
+    pub name: String,
+    pub rate: f64,
+    pub is_inclusive: bool,
+    pub taxable_amount: f64,
+    pub amount: f64,
+}
+
+#[derive(Debug)]
+pub struct OrderTaxCalculation {
+    pub taxes: Vec<OrderTax>,
+    // Only exclusive taxes are added on top of the price, inclusive ones are already part of it.
+    pub tax: f64,
+    pub rounding_adjustment: f64,
+}
+
+fn round2(value: f64) -> f64 {
+    (value * 100.0).round() / 100.0
+}
+
+const RULE_SELECT_QUERY: &str = "select r.tax_rule_id, r.shop_id, r.name, r.rate::text as rate, r.is_inclusive, r.is_compound, r.sort_order,
+    array(select e.item_id from tax_rule_exemptions e where e.tax_rule_id = r.tax_rule_id and e.item_id is not null order by e.item_id) as exempt_item_ids,
+    array(select e.category_id from tax_rule_exemptions e where e.tax_rule_id = r.tax_rule_id and e.category_id is not null order by e.category_id) as exempt_category_ids
+    from tax_rules r where r.deleted_at is null";
+
+fn row_to_rule(row: &tokio_postgres::Row) -> TaxRule {
+    let rate: &str = row.get("rate");
+    TaxRule {
+        tax_rule_id: row.get("tax_rule_id"),
+        shop_id: row.get("shop_id"),
+        name: row.get("name"),
+        rate: rate.parse().unwrap(),
+        is_inclusive: row.get("is_inclusive"),
+        is_compound: row.get("is_compound"),
+        sort_order: row.get("sort_order"),
+        exempt_item_ids: row.get("exempt_item_ids"),
+        exempt_category_ids: row.get("exempt_category_ids"),
+    }
+}
+
+// Rules are applied in sort order, so a compound tax comes after the charges it is levied on.
+pub async fn get_tax_rules(shop_id: Option<i32>, client: &Client) -> Result<Vec<TaxRule>, Error> {
+    let rows = client
+        .query(
+            &format!(
+                "{RULE_SELECT_QUERY} and ($1::int is null or r.shop_id = $1) order by r.shop_id, r.sort_order, r.tax_rule_id"
+            ),
+            &[&shop_id],
+        )
+        .await?;
+    Ok(rows.iter().map(row_to_rule).collect())
+}
+
+pub async fn get_tax_rule_by_id(tax_rule_id: i32, client: &Client) -> Option<TaxRule> {
+    match client
+        .query_one(
+            &format!("{RULE_SELECT_QUERY} and r.tax_rule_id = $1"),
+            &[&tax_rule_id],
+        )
+        .await
+    {
+        Ok(row) => Some(row_to_rule(&row)),
+        Err(_) => None,
+    }
+}
+
+pub async fn exemptions_belong_to_shop(
+    item_ids: &[i32],
+    category_ids: &[i32],
+    shop_id: i32,
+    client: &Client,
+) -> Result<bool, Error> {
+    let row = client
+        .query_one(
+            "select (select count(distinct id) from items where id = any($1) and shop_id = $3 and deleted_at is null)
+            = (select count(distinct id) from unnest($1::int[]) as id)
+            and (select count(distinct id) from categories where id = any($2) and shop_id = $3 and deleted_at is null)
+            = (select count(distinct id) from unnest($2::int[]) as id) as belongs",
+            &[&item_ids, &category_ids, &shop_id],
+        )
+        .await?;
+    Ok(row.get("belongs"))
+}
+
+async fn insert_exemptions(
+    transaction: &tokio_postgres::Transaction<'_>,
+    tax_rule_id: i32,
+    data: &TaxRuleRequest,
+) -> Result<(), Error> {
+    for item_id in &data.exempt_item_ids {
+        transaction
+            .execute(
+                "insert into tax_rule_exemptions (tax_rule_id, item_id) values ($1, $2)",
+                &[&tax_rule_id, item_id],
+            )
+            .await?;
+    }
+    for category_id in &data.exempt_category_ids {
+        transaction
+            .execute(
+                "insert into tax_rule_exemptions (tax_rule_id, category_id) values ($1, $2)",
+                &[&tax_rule_id, category_id],
+            )
+            .await?;
+    }
+    Ok(())
+}
+
+pub async fn add_tax_rule(
+    data: &TaxRuleRequest,
+    shop_id: i32,
+    client: &mut Client,
+) -> Result<i32, Error> {
+    let transaction = client.transaction().await?;
+    let query = format!(
+        "insert into tax_rules (shop_id, name, rate, is_inclusive, is_compound, sort_order) values ($1, $2, {}, $3, $4, $5) returning tax_rule_id",
+        data.rate
+    );
+    let row = transaction
+        .query_one(
+            &query,
+            &[
+                &shop_id,
+                &data.name,
+                &data.is_inclusive,
+                &data.is_compound,
+                &data.sort_order,
+            ],
+        )
+        .await?;
+    let tax_rule_id: i32 = row.get("tax_rule_id");
+    insert_exemptions(&transaction, tax_rule_id, data).await?;
+    transaction.commit().await?;
+    Ok(tax_rule_id)
+}
+
+pub async fn update_tax_rule(
+    tax_rule_id: i32,
+    data: &TaxRuleRequest,
+    client: &mut Client,
+) -> Result<(), Error> {
+    let transaction = client.transaction().await?;
+    let query = format!(
+        "update tax_rules set name = $1, rate = {}, is_inclusive = $2, is_compound = $3, sort_order = $4 where tax_rule_id = $5",
+        data.rate
+    );
+    transaction
+        .execute(
+            &query,
+            &[
+                &data.name,
+                &data.is_inclusive,
+                &data.is_compound,
+                &data.sort_order,
+                &tax_rule_id,
+            ],
+        )
+        .await?;
+    transaction
+        .execute(
+            "delete from tax_rule_exemptions where tax_rule_id = $1",
+            &[&tax_rule_id],
+        )
+        .await?;
+    insert_exemptions(&transaction, tax_rule_id, data).await?;
+    transaction.commit().await?;
+    Ok(())
+}
+
+pub async fn delete_tax_rule(tax_rule_id: i32, client: &Client) -> Result<(), Error> {
+    client
+        .execute(
+            "update tax_rules set deleted_at = CURRENT_TIMESTAMP where tax_rule_id = $1",
+            &[&tax_rule_id],
+        )
+        .await?;
+    Ok(())
+}
+
+// Shops without a setting keep totals to the cent.
+pub async fn get_tax_setting(shop_id: i32, client: &Client) -> Result<TaxSetting, Error> {
+    let row = client
+        .query_opt(
+            "select rounding_mode, rounding_increment::text as rounding_increment from tax_settings
+            where shop_id = $1 and deleted_at is null",
+            &[&shop_id],
+        )
+        .await?;
+
+    Ok(match row {
+        Some(row) => {
+            let rounding_increment: &str = row.get("rounding_increment");
+            TaxSetting {
+                shop_id,
+                rounding_mode: row.get("rounding_mode"),
+                rounding_increment: rounding_increment.parse().unwrap(),
+            }
+        }
+        None => TaxSetting {
+            shop_id,
+            rounding_mode: String::from("None"),
+            rounding_increment: 0.01,
+        },
+    })
+}
+
+pub async fn set_tax_setting(
+    shop_id: i32,
+    data: &TaxSettingRequest,
+    client: &Client,
+) -> Result<(), Error> {
+    let query = format!(
+        "insert into tax_settings (shop_id, rounding_mode, rounding_increment) values ($1, $2, {})
+        on conflict (shop_id) do update set rounding_mode = excluded.rounding_mode,
+        rounding_increment = excluded.rounding_increment, deleted_at = null",
+        data.rounding_increment
+    );
+    client
+        .execute(&query, &[&shop_id, &data.rounding_mode])
+        .await?;
+    Ok(())
+}
+
+fn round_total(total: f64, setting: &TaxSetting) -> f64 {
+    if setting.rounding_increment <= 0.0 {
+        return total;
+    }
+    let steps = total / setting.rounding_increment;
+    let steps = match setting.rounding_mode.as_str() {
+        "Nearest" => steps.round(),
+        // Steps are rounded to 6 places first so float noise doesn't push an exact total a step further.
+        "Up" => ((steps * 1e6).round() / 1e6).ceil(),
+        "Down" => ((steps * 1e6).round() / 1e6).floor(),
+        _ => return total,
+    };
+    round2(steps * setting.rounding_increment)
+}
+
+struct TaxLine {
+    item_id: i32,
+    category_ids: Vec<i32>,
+    base: f64,
+    exclusive_tax: f64,
+}
+
+// Works out the taxes of an order from its shop's rules, or None when the shop has no rules.
+pub async fn calculate_order_taxes(
+    order_id: i32,
+    discount: f64,
+    client: &Client,
+) -> Result<Option<OrderTaxCalculation>, Error> {
+    let row = client
+        .query_one(
+            "select shop_id from orders where id = $1",
+            &[&order_id],
+        )
+        .await?;
+    let shop_id: i32 = row.get("shop_id");
+    let rules = get_tax_rules(Some(shop_id), client).await?;
+    if rules.is_empty() {
+        return Ok(None);
+    }
+    let setting = get_tax_setting(shop_id, client).await?;
+
+    let rows = client
+        .query(
+            "select oi.item_id, (oi.price * oi.quantity)::text as amount,
+            array(select ic.category_id from item_categories ic where ic.item_id = oi.item_id) as category_ids
+            from order_items oi where oi.order_id = $1",
+            &[&order_id],
+        )
+        .await?;
+    let lines: Vec<TaxLine> = rows
+        .iter()
+        .map(|row| {
+            let amount: &str = row.get("amount");
+            TaxLine {
+                item_id: row.get("item_id"),
+                category_ids: row.get("category_ids"),
+                base: amount.parse().unwrap(),
+                exclusive_tax: 0.0,
+            }
+        })
+        .collect();
+    Ok(Some(compute_order_taxes(lines, discount, &rules, &setting)))
+}
+
+// The order discount is spread over the lines by their amount before taxing. Inclusive taxes are taken out
+// of the price, sharing it when several apply to a line; exclusive taxes are added on top, a compound one also
+// on the exclusive taxes before it.
+fn compute_order_taxes(
+    mut lines: Vec<TaxLine>,
+    discount: f64,
+    rules: &[TaxRule],
+    setting: &TaxSetting,
+) -> OrderTaxCalculation {
+    let sub_total: f64 = lines.iter().map(|l| l.base).sum();
+    let discount_ratio = if sub_total > 0.0 {
+        (sub_total - discount).max(0.0) / sub_total
+    } else {
+        0.0
+    };
+    for line in lines.iter_mut() {
+        line.base *= discount_ratio;
+    }
+
+    let applies = |rule: &TaxRule, line: &TaxLine| {
+        !rule.exempt_item_ids.contains(&line.item_id)
+            && !line
+                .category_ids
+                .iter()
+                .any(|c| rule.exempt_category_ids.contains(c))
+    };
+    let inclusive_rates: Vec<f64> = lines
+        .iter()
+        .map(|line| {
+            rules
+                .iter()
+                .filter(|r| r.is_inclusive && applies(r, line))
+                .map(|r| r.rate)
+                .sum()
+        })
+        .collect();
+
+    let mut taxes: Vec<OrderTax> = vec![];
+    for rule in rules {
+        let mut taxable_amount = 0.0;
+        let mut amount = 0.0;
+        for (line, inclusive_rate) in lines.iter_mut().zip(inclusive_rates.iter()) {
+            if !applies(rule, line) {
+                continue;
+            }
+            if rule.is_inclusive {
+                taxable_amount += line.base;
+                amount += line.base * rule.rate / (100.0 + inclusive_rate);
+            } else {
+                let base = if rule.is_compound {
+                    line.base + line.exclusive_tax
+                } else {
+                    line.base
+                };
+                let line_tax = base * rule.rate / 100.0;
+                taxable_amount += base;
+                amount += line_tax;
+                line.exclusive_tax += line_tax;
+            }
+        }
+        taxes.push(OrderTax {
+            tax_rule_id: rule.tax_rule_id,
+            name: rule.name.clone(),
+            rate: rule.rate,
+            is_inclusive: rule.is_inclusive,
+            taxable_amount: round2(taxable_amount),
+            amount: round2(amount),
+        });
+    }
+
+    let tax: f64 = round2(
+        taxes
+            .iter()
+            .filter(|t| !t.is_inclusive)
+            .map(|t| t.amount)
+            .sum(),
+    );
+    let total = round2(sub_total - discount + tax);
+    let rounding_adjustment = round2(round_total(total, setting) - total);
+    OrderTaxCalculation {
+        taxes,
+        tax,
+        rounding_adjustment,
+    }
+}
+
+// Replaces the tax breakdown of an order with a new calculation, in the transaction that updates the order.
+pub async fn save_order_taxes(
+    order_id: i32,
+    calculation: &OrderTaxCalculation,
+    transaction: &Transaction<'_>,
+) -> Result<(), Error> {
+    transaction
+        .execute("delete from order_taxes where order_id = $1", &[&order_id])
+        .await?;
+    for tax in &calculation.taxes {
+        let query = format!(
+            "insert into order_taxes (order_id, tax_rule_id, name, rate, is_inclusive, taxable_amount, amount)
+            values ($1, $2, $3, {}, $4, {}, {})",
+            tax.rate, tax.taxable_amount, tax.amount
+        );
+        transaction
+            .execute(
+                &query,
+                &[&order_id, &tax.tax_rule_id, &tax.name, &tax.is_inclusive],
+            )
+            .await?;
+    }
+    let query = format!(
+        "update orders set rounding_adjustment = {}, updated_at = CURRENT_TIMESTAMP where id = $1",
+        calculation.rounding_adjustment
+    );
+    transaction.execute(&query, &[&order_id]).await?;
+    Ok(())
+}
+
+pub async fn get_order_taxes(order_id: i32, client: &Client) -> Result<Vec<OrderTax>, Error> {
+    let rows = client
+        .query(
+            "select tax_rule_id, name, rate::text as rate, is_inclusive, taxable_amount::text as taxable_amount, amount::text as amount
+            from order_taxes where order_id = $1 order by order_tax_id",
+            &[&order_id],
+        )
+        .await?;
+    Ok(rows
+        .iter()
+        .map(|row| {
+            let rate: &str = row.get("rate");
+            let taxable_amount: &str = row.get("taxable_amount");
+            let amount: &str = row.get("amount");
+            OrderTax {
+                tax_rule_id: row.get("tax_rule_id"),
+                name: row.get("name"),
+                rate: rate.parse().unwrap(),
+                is_inclusive: row.get("is_inclusive"),
+                taxable_amount: taxable_amount.parse().unwrap(),
+                amount: amount.parse().unwrap(),
+            }
+        })
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rule(tax_rule_id: i32, rate: f64, is_inclusive: bool, is_compound: bool) -> TaxRule {
+        TaxRule {
+            tax_rule_id,
+            shop_id: 1,
+            name: format!("Tax {tax_rule_id}"),
+            rate,
+            is_inclusive,
+            is_compound,
+            sort_order: tax_rule_id,
+            exempt_item_ids: vec![],
+            exempt_category_ids: vec![],
+        }
+    }
+
+    fn line(item_id: i32, base: f64) -> TaxLine {
+        TaxLine {
+            item_id,
+            category_ids: vec![],
+            base,
+            exclusive_tax: 0.0,
+        }
+    }
+
+    fn setting(rounding_mode: &str, rounding_increment: f64) -> TaxSetting {
+        TaxSetting {
+            shop_id: 1,
+            rounding_mode: String::from(rounding_mode),
+            rounding_increment,
+        }
+    }
+
+    fn amounts(calculation: &OrderTaxCalculation) -> Vec<f64> {
+        calculation.taxes.iter().map(|t| t.amount).collect()
+    }
+
+    #[test]
+    fn exclusive_tax_is_added_on_top() {
+        let calculation = compute_order_taxes(
+            vec![line(1, 100.0)],
+            0.0,
+            &[rule(1, 10.0, false, false)],
+            &setting("None", 0.0),
+        );
+        assert_eq!(amounts(&calculation), vec![10.0]);
+        assert_eq!(calculation.tax, 10.0);
+        assert_eq!(calculation.rounding_adjustment, 0.0);
+    }
+
+    #[test]
+    fn compound_tax_includes_earlier_exclusive_taxes() {
+        let calculation = compute_order_taxes(
+            vec![line(1, 100.0)],
+            0.0,
+            &[rule(1, 10.0, false, false), rule(2, 5.0, false, true)],
+            &setting("None", 0.0),
+        );
+        assert_eq!(amounts(&calculation), vec![10.0, 5.5]);
+        assert_eq!(calculation.taxes[1].taxable_amount, 110.0);
+        assert_eq!(calculation.tax, 15.5);
+    }
+
+    #[test]
+    fn non_compound_tax_ignores_earlier_exclusive_taxes() {
+        let calculation = compute_order_taxes(
+            vec![line(1, 100.0)],
+            0.0,
+            &[rule(1, 10.0, false, false), rule(2, 5.0, false, false)],
+            &setting("None", 0.0),
+        );
+        assert_eq!(amounts(&calculation), vec![10.0, 5.0]);
+        assert_eq!(calculation.tax, 15.0);
+    }
+
+    #[test]
+    fn inclusive_tax_is_taken_out_of_the_price() {
+        let calculation = compute_order_taxes(
+            vec![line(1, 110.0)],
+            0.0,
+            &[rule(1, 10.0, true, false)],
+            &setting("None", 0.0),
+        );
+        assert_eq!(amounts(&calculation), vec![10.0]);
+        assert_eq!(calculation.tax, 0.0);
+    }
+
+    #[test]
+    fn inclusive_taxes_share_the_price() {
+        let calculation = compute_order_taxes(
+            vec![line(1, 115.0)],
+            0.0,
+            &[rule(1, 10.0, true, false), rule(2, 5.0, true, false)],
+            &setting("None", 0.0),
+        );
+        assert_eq!(amounts(&calculation), vec![10.0, 5.0]);
+        assert_eq!(calculation.tax, 0.0);
+    }
+
+    #[test]
+    fn discount_is_spread_over_the_lines() {
+        let calculation = compute_order_taxes(
+            vec![line(1, 60.0), line(2, 40.0)],
+            10.0,
+            &[rule(1, 10.0, false, false)],
+            &setting("None", 0.0),
+        );
+        assert_eq!(calculation.taxes[0].taxable_amount, 90.0);
+        assert_eq!(calculation.tax, 9.0);
+    }
+
+    #[test]
+    fn exempt_items_and_categories_are_not_taxed() {
+        let mut exempt = rule(1, 10.0, false, false);
+        exempt.exempt_item_ids = vec![2];
+        exempt.exempt_category_ids = vec![7];
+        let mut drink = line(3, 30.0);
+        drink.category_ids = vec![7];
+        let calculation = compute_order_taxes(
+            vec![line(1, 50.0), line(2, 20.0), drink],
+            0.0,
+            &[exempt],
+            &setting("None", 0.0),
+        );
+        assert_eq!(calculation.taxes[0].taxable_amount, 50.0);
+        assert_eq!(calculation.tax, 5.0);
+    }
+
+    #[test]
+    fn rounding_adjustment_brings_total_to_the_increment() {
+        let calculation = compute_order_taxes(
+            vec![line(1, 10.02)],
+            0.0,
+            &[rule(1, 10.0, false, false)],
+            &setting("Nearest", 0.05),
+        );
+        assert_eq!(calculation.tax, 1.0);
+        assert_eq!(calculation.rounding_adjustment, -0.02);
+    }
+
+    #[test]
+    fn round_total_nearest() {
+        assert_eq!(round_total(10.02, &setting("Nearest", 0.05)), 10.0);
+        assert_eq!(round_total(10.03, &setting("Nearest", 0.05)), 10.05);
+        assert_eq!(round_total(10.5, &setting("Nearest", 1.0)), 11.0);
+    }
+
+    #[test]
+    fn round_total_up() {
+        assert_eq!(round_total(10.01, &setting("Up", 0.05)), 10.05);
+        assert_eq!(round_total(10.05, &setting("Up", 0.05)), 10.05);
+        assert_eq!(round_total(0.3, &setting("Up", 0.1)), 0.3);
+    }
+
+    #[test]
+    fn round_total_down() {
+        assert_eq!(round_total(10.19, &setting("Down", 0.1)), 10.1);
+        assert_eq!(round_total(10.2, &setting("Down", 0.1)), 10.2);
+    }
+
+    #[test]
+    fn round_total_without_rounding_keeps_total() {
+        assert_eq!(round_total(10.02, &setting("None", 0.05)), 10.02);
+        assert_eq!(round_total(10.02, &setting("Nearest", 0.0)), 10.02);
+    }
+}
@@ -0,0 +1,64 @@
+use serde::{Deserialize, Serialize};
+use tokio_postgres::{Client, Error};
+
+// Individual: every waiter keeps the tips of their own orders.
+// Equal: the tips are pooled and split evenly between the waiters who completed orders.
+// BySales: the tips are pooled and split by each waiter's share of the completed sales.
+pub const TIP_DISTRIBUTION_METHODS: [&str; 3] = ["Individual", "Equal", "BySales"];
+
+#[derive(Debug, Serialize)]
+pub struct TipPoolRule {
+    pub shop_id: i32,
+    pub distribution_method: String,
+    pub house_percent: f64,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct TipPoolRuleRequest {
+    pub distribution_method: String,
+    pub house_percent: f64,
+}
+
+// Shops without a rule let every waiter keep their own tips.
+pub async fn get_tip_pool_rule(shop_id: i32, client: &Client) -> Result<TipPoolRule, Error> {
+    let row = client
+        .query_opt(
+            "select distribution_method, house_percent::text as house_percent from tip_pool_rules
+            where shop_id = $1 and deleted_at is null",
+            &[&shop_id],
+        )
+        .await?;
+
+    Ok(match row {
+        Some(row) => {
+            let house_percent: &str = row.get("house_percent");
+            TipPoolRule {
+                shop_id,
+                distribution_method: row.get("distribution_method"),
+                house_percent: house_percent.parse().unwrap(),
+            }
+        }
+        None => TipPoolRule {
+            shop_id,
+            distribution_method: String::from("Individual"),
+            house_percent: 0.0,
+        },
+    })
+}
+
+pub async fn set_tip_pool_rule(
+    shop_id: i32,
+    data: &TipPoolRuleRequest,
+    client: &Client,
+) -> Result<(), Error> {
+    let query = format!(
+        "insert into tip_pool_rules (shop_id, distribution_method, house_percent) values ($1, $2, {})
+        on conflict (shop_id) do update set distribution_method = excluded.distribution_method,
+        house_percent = excluded.house_percent, deleted_at = null",
+        data.house_percent
+    );
+    client
+        .execute(&query, &[&shop_id, &data.distribution_method])
+        .await?;
+    Ok(())
+}
@@ -1,7 +1,7 @@
 use bcrypt::{hash, DEFAULT_COST};
 use chrono::NaiveDateTime;
 use serde::{Deserialize, Serialize};
-use tokio_postgres::{types::ToSql, Client, Error};
+use tokio_postgres::{types::ToSql, Client, Error, GenericClient};
 
 use crate::utils::{
     common_struct::PaginationResult,
@@ -219,3 +219,31 @@ pub async fn user_exists(username: &str, client: &Client) -> Result<bool, Error>
     // Return whether the user exists
     Ok(row.is_ok())
 }
+
+pub async fn get_user_ids_by_shop_and_roles(
+    shop_id: i32,
+    roles: &[String],
+    client: &Client,
+) -> Result<Vec<i32>, Error> {
+    let rows = client
+        .query(
+            "select u.id from users u join roles r on r.id = u.role_id where r.role_name = any($2) and u.shop_id = $1 and u.deleted_at is null",
+            &[&shop_id, &roles],
+        )
+        .await?;
+    Ok(rows.iter().map(|row| row.get("id")).collect())
+}
+
+// Managers and waiters of the shop, the ones who carry the POS devices.
+pub async fn get_staff_ids_by_shop<C: GenericClient>(
+    shop_id: i32,
+    client: &C,
+) -> Result<Vec<i32>, Error> {
+    let rows = client
+        .query(
+            "select u.id from users u join roles r on r.id = u.role_id where r.role_name in ('Manager', 'Waiter') and u.shop_id = $1 and u.deleted_at is null",
+            &[&shop_id],
+        )
+        .await?;
+    Ok(rows.iter().map(|row| row.get("id")).collect())
+}
@@ -0,0 +1,345 @@
+use chrono::NaiveDate;
+use rust_xlsxwriter::*;
+use serde::Serialize;
+use std::env;
+
+use simple_pdf_generator::{Asset, AssetType, PrintOptions};
+use simple_pdf_generator_derive::PdfTemplate;
+
+use crate::models::tip_pool_rule;
+
+#[derive(Serialize)]
+pub struct WaiterReportData {
+    waiter_id: i32,
+    waiter_name: String,
+    order_count: i64,
+    netsale: f64,
+    average_ticket: f64,
+    cancellation_count: i64,
+    void_count: i64,
+    void_amount: f64,
+    tips_collected: f64,
+    tip_share: f64,
+}
+
+#[derive(PdfTemplate, Serialize)]
+pub struct WaiterReportSummaryData {
+    date_str: String,
+    shop_name: String,
+    distribution_method: String,
+    house_percent: f64,
+    total_order_count: i64,
+    total_netsale: f64,
+    total_cancellation_count: i64,
+    total_void_count: i64,
+    total_void_amount: f64,
+    total_tips: f64,
+    house_tips: f64,
+    #[PdfTableData]
+    data_list: Vec<WaiterReportData>,
+}
+
+fn round2(value: f64) -> f64 {
+    (value * 100.0).round() / 100.0
+}
+
+// Shares of the tips for each waiter, after the house keeps its percent. `completed` holds each waiter's
+// completed order count and completed sales.
+fn distribute_tips(
+    rule: &tip_pool_rule::TipPoolRule,
+    tips_collected: &[f64],
+    completed: &[(i64, f64)],
+) -> Vec<f64> {
+    let staff_percent = (100.0 - rule.house_percent) / 100.0;
+    let pool: f64 = tips_collected.iter().sum::<f64>() * staff_percent;
+    let sharing_count = completed.iter().filter(|(count, _)| *count > 0).count();
+    let total_completed_sale: f64 = completed.iter().map(|(_, sale)| sale).sum();
+    tips_collected
+        .iter()
+        .zip(completed.iter())
+        .map(
+            |(tips, (completed_count, completed_sale))| match rule.distribution_method.as_str() {
+                "Equal" if *completed_count > 0 => round2(pool / sharing_count as f64),
+                "BySales" if total_completed_sale > 0.0 => {
+                    round2(pool * completed_sale / total_completed_sale)
+                }
+                "Individual" => round2(tips * staff_percent),
+                _ => 0.0,
+            },
+        )
+        .collect()
+}
+
+// Orders taken count every order of the waiter, sales and average ticket only the ones not canceled.
+// A canceled order that was never served is a cancellation, one canceled after it was served is a void.
+// Tips come from completed orders and are shared out by the shop's tip pool rule.
+pub async fn get_waiter_report(
+    from_date: NaiveDate,
+    to_date: NaiveDate,
+    shop_id: i32,
+    shop_name: String,
+    client: &tokio_postgres::Client,
+) -> Result<WaiterReportSummaryData, tokio_postgres::Error> {
+    let date_str = if !from_date.eq(&to_date) {
+        format!("From {} To {}", from_date, to_date)
+    } else {
+        format!("{}", to_date)
+    };
+    let rule = tip_pool_rule::get_tip_pool_rule(shop_id, client).await?;
+
+    let rows = client
+        .query(
+            "select o.waiter_id, u.name as waiter_name, count(*) as order_count,
+            count(*) filter (where o.status != 'Canceled') as sale_count,
+            coalesce(sum(s.sub_total - o.discount) filter (where o.status != 'Canceled'), 0)::text as netsale,
+            count(*) filter (where o.status = 'Canceled' and o.served_at is null) as cancellation_count,
+            count(*) filter (where o.status = 'Canceled' and o.served_at is not null) as void_count,
+            coalesce(sum(s.sub_total) filter (where o.status = 'Canceled' and o.served_at is not null), 0)::text as void_amount,
+            coalesce(sum(o.tip) filter (where o.status = 'Completed'), 0)::text as tips_collected,
+            count(*) filter (where o.status = 'Completed') as completed_count,
+            coalesce(sum(s.sub_total - o.discount) filter (where o.status = 'Completed'), 0)::text as completed_sale
+            from orders o
+            join users u on u.id = o.waiter_id
+            cross join lateral (select coalesce(sum(price * quantity), 0) as sub_total from order_items where order_id = o.id) s
+            where o.deleted_at is null and o.shop_id = $1 and o.created_at::date between $2 and $3
+            group by o.waiter_id, u.name
+            order by sum(s.sub_total - o.discount) filter (where o.status != 'Canceled') desc nulls last",
+            &[&shop_id, &from_date, &to_date],
+        )
+        .await?;
+
+    let mut data_list: Vec<WaiterReportData> = vec![];
+    // Completed order count and completed sales per waiter, the basis for pooled tips.
+    let mut completed: Vec<(i64, f64)> = vec![];
+    for row in &rows {
+        let netsale: &str = row.get("netsale");
+        let netsale: f64 = netsale.parse().unwrap();
+        let void_amount: &str = row.get("void_amount");
+        let void_amount: f64 = void_amount.parse().unwrap();
+        let tips_collected: &str = row.get("tips_collected");
+        let tips_collected: f64 = tips_collected.parse().unwrap();
+        let completed_sale: &str = row.get("completed_sale");
+        let completed_sale: f64 = completed_sale.parse().unwrap();
+        let sale_count: i64 = row.get("sale_count");
+        completed.push((row.get("completed_count"), completed_sale));
+        data_list.push(WaiterReportData {
+            waiter_id: row.get("waiter_id"),
+            waiter_name: row.get("waiter_name"),
+            order_count: row.get("order_count"),
+            netsale,
+            average_ticket: if sale_count > 0 {
+                round2(netsale / sale_count as f64)
+            } else {
+                0.0
+            },
+            cancellation_count: row.get("cancellation_count"),
+            void_count: row.get("void_count"),
+            void_amount,
+            tips_collected,
+            tip_share: 0.0,
+        });
+    }
+
+    let total_tips: f64 = data_list.iter().map(|d| d.tips_collected).sum();
+    let tips_collected: Vec<f64> = data_list.iter().map(|d| d.tips_collected).collect();
+    let tip_shares = distribute_tips(&rule, &tips_collected, &completed);
+    for (waiter, tip_share) in data_list.iter_mut().zip(tip_shares) {
+        waiter.tip_share = tip_share;
+    }
+    let total_tip_share: f64 = data_list.iter().map(|d| d.tip_share).sum();
+
+    let data = WaiterReportSummaryData {
+        date_str,
+        shop_name,
+        distribution_method: rule.distribution_method,
+        house_percent: rule.house_percent,
+        total_order_count: data_list.iter().map(|d| d.order_count).sum(),
+        total_netsale: round2(data_list.iter().map(|d| d.netsale).sum()),
+        total_cancellation_count: data_list.iter().map(|d| d.cancellation_count).sum(),
+        total_void_count: data_list.iter().map(|d| d.void_count).sum(),
+        total_void_amount: round2(data_list.iter().map(|d| d.void_amount).sum()),
+        total_tips: round2(total_tips),
+        house_tips: round2(total_tips - total_tip_share),
+        data_list,
+    };
+    Ok(data)
+}
+
+pub async fn write_waiter_report_pdf(
+    data: &WaiterReportSummaryData,
+    file_path: &str,
+) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let html_path = env::current_dir()
+        .unwrap()
+        .join("src/template/waiter-report.html");
+
+    let assets = [Asset {
+        path: env::current_dir()
+            .unwrap()
+            .join("src/template/css/style.css"),
+        r#type: AssetType::Style,
+    }];
+
+    let print_options = PrintOptions {
+        paper_width: Some(297.0),
+        paper_height: Some(210.0),
+        margin_top: Some(10.0),
+        margin_bottom: Some(10.0),
+        margin_left: Some(10.0),
+        margin_right: Some(10.0),
+        ..PrintOptions::default()
+    };
+    let content = data
+        .generate_pdf(html_path, &assets, &print_options)
+        .await
+        .map_err(|e| e.to_string())?;
+    tokio::fs::write(file_path, content).await?;
+    Ok(())
+}
+
+pub fn write_waiter_report_excel(data: &WaiterReportSummaryData, file_path: &str) -> Result<(), XlsxError> {
+    // Create a new Excel file object.
+    let mut workbook = Workbook::new();
+
+    // Create some formats to use in the worksheet.
+    let bold_format = Format::new().set_bold().set_align(FormatAlign::Center);
+    let decimal_format = Format::new().set_num_format("0.00").set_align(FormatAlign::Right);
+    let decimal_bold_format = Format::new()
+        .set_num_format("0.00")
+        .set_align(FormatAlign::Right)
+        .set_bold();
+    let merge_center_format = Format::new().set_bold().set_align(FormatAlign::Center);
+    let merge_left_format = Format::new().set_bold().set_align(FormatAlign::Left);
+    let merge_right_format = Format::new().set_bold().set_align(FormatAlign::Right);
+
+    let center_format = Format::new().set_align(FormatAlign::Center);
+    let right_format = Format::new().set_align(FormatAlign::Right);
+    let left_format = Format::new().set_align(FormatAlign::Left);
+    // Add a worksheet to the workbook.
+    let worksheet = workbook.add_worksheet();
+
+    // Set the column width for clarity.
+    worksheet.set_column_width(0, 5)?;
+    worksheet.set_column_width(1, 20)?;
+    for col in 2..10 {
+        worksheet.set_column_width(col, 13)?;
+    }
+
+    worksheet.merge_range(0, 0, 0, 9, "Waiter Performance Report", &merge_center_format)?;
+    worksheet.merge_range(1, 0, 1, 2, &data.shop_name, &merge_left_format)?;
+    worksheet.merge_range(
+        1,
+        3,
+        1,
+        6,
+        &format!(
+            "Tip Pool : {} ({}% House)",
+            data.distribution_method, data.house_percent
+        ),
+        &merge_center_format,
+    )?;
+    worksheet.merge_range(1, 7, 1, 9, &data.date_str, &merge_right_format)?;
+
+    worksheet.write_with_format(2, 0, "Id", &bold_format)?;
+    worksheet.write_with_format(2, 1, "Waiter", &bold_format)?;
+    worksheet.write_with_format(2, 2, "Orders", &bold_format)?;
+    worksheet.write_with_format(2, 3, "Net Sale", &bold_format)?;
+    worksheet.write_with_format(2, 4, "Avg Ticket", &bold_format)?;
+    worksheet.write_with_format(2, 5, "Cancellations", &bold_format)?;
+    worksheet.write_with_format(2, 6, "Voids", &bold_format)?;
+    worksheet.write_with_format(2, 7, "Void Amount", &bold_format)?;
+    worksheet.write_with_format(2, 8, "Tips", &bold_format)?;
+    worksheet.write_with_format(2, 9, "Tip Share", &bold_format)?;
+    let mut row_no = 3;
+    for waiter in &data.data_list {
+        worksheet.write_with_format(row_no, 0, waiter.waiter_id, &center_format)?;
+        worksheet.write_with_format(row_no, 1, &waiter.waiter_name, &left_format)?;
+        worksheet.write_with_format(row_no, 2, waiter.order_count as f64, &right_format)?;
+        worksheet.write_with_format(row_no, 3, waiter.netsale, &decimal_format)?;
+        worksheet.write_with_format(row_no, 4, waiter.average_ticket, &decimal_format)?;
+        worksheet.write_with_format(row_no, 5, waiter.cancellation_count as f64, &right_format)?;
+        worksheet.write_with_format(row_no, 6, waiter.void_count as f64, &right_format)?;
+        worksheet.write_with_format(row_no, 7, waiter.void_amount, &decimal_format)?;
+        worksheet.write_with_format(row_no, 8, waiter.tips_collected, &decimal_format)?;
+        worksheet.write_with_format(row_no, 9, waiter.tip_share, &decimal_format)?;
+        row_no += 1;
+    }
+
+    worksheet.merge_range(row_no, 0, row_no, 1, "Total", &merge_center_format)?;
+    worksheet.write_with_format(row_no, 2, data.total_order_count as f64, &bold_format)?;
+    worksheet.write_with_format(row_no, 3, data.total_netsale, &decimal_bold_format)?;
+    worksheet.write_with_format(row_no, 5, data.total_cancellation_count as f64, &bold_format)?;
+    worksheet.write_with_format(row_no, 6, data.total_void_count as f64, &bold_format)?;
+    worksheet.write_with_format(row_no, 7, data.total_void_amount, &decimal_bold_format)?;
+    worksheet.write_with_format(row_no, 8, data.total_tips, &decimal_bold_format)?;
+    worksheet.write_with_format(row_no, 9, data.total_tips - data.house_tips, &decimal_bold_format)?;
+
+    row_no += 1;
+    worksheet.merge_range(row_no, 0, row_no, 8, "House Tips", &merge_right_format)?;
+    worksheet.write_with_format(row_no, 9, data.house_tips, &decimal_bold_format)?;
+
+    // Save the file to disk.
+    workbook.save(file_path)?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::tip_pool_rule::TipPoolRule;
+
+    fn rule(distribution_method: &str, house_percent: f64) -> TipPoolRule {
+        TipPoolRule {
+            shop_id: 1,
+            distribution_method: String::from(distribution_method),
+            house_percent,
+        }
+    }
+
+    #[test]
+    fn individual_keeps_own_tips_less_house_percent() {
+        let shares = distribute_tips(
+            &rule("Individual", 10.0),
+            &[10.0, 20.0],
+            &[(1, 50.0), (2, 80.0)],
+        );
+        assert_eq!(shares, vec![9.0, 18.0]);
+    }
+
+    #[test]
+    fn equal_splits_pool_among_waiters_with_completed_orders() {
+        let shares = distribute_tips(
+            &rule("Equal", 10.0),
+            &[10.0, 0.0, 20.0],
+            &[(2, 100.0), (0, 0.0), (1, 50.0)],
+        );
+        assert_eq!(shares, vec![13.5, 0.0, 13.5]);
+    }
+
+    #[test]
+    fn by_sales_splits_pool_by_completed_sales() {
+        let shares = distribute_tips(
+            &rule("BySales", 20.0),
+            &[30.0, 20.0],
+            &[(3, 75.0), (1, 25.0)],
+        );
+        assert_eq!(shares, vec![30.0, 10.0]);
+    }
+
+    #[test]
+    fn by_sales_without_completed_sales_shares_nothing() {
+        let shares = distribute_tips(&rule("BySales", 0.0), &[5.0], &[(0, 0.0)]);
+        assert_eq!(shares, vec![0.0]);
+    }
+
+    #[test]
+    fn shares_are_rounded_to_cents() {
+        let shares = distribute_tips(
+            &rule("Equal", 0.0),
+            &[10.0, 0.0, 0.0],
+            &[(1, 10.0), (1, 10.0), (1, 10.0)],
+        );
+        assert_eq!(shares, vec![3.33, 3.33, 3.33]);
+    }
+}
@@ -0,0 +1,196 @@
+use chrono::NaiveDateTime;
+use serde::{Deserialize, Serialize};
+use tokio_postgres::{Client, Error};
+
+use crate::models::reservation::RESERVATION_HOLD_MINUTES;
+
+pub const WAITLIST_STATUSES: [&str; 3] = ["Waiting", "Seated", "Left"];
+// Used for the wait estimate until a shop has completed orders to learn its table turn time from.
+const DEFAULT_TURN_MINUTES: i64 = 45;
+
+#[derive(Debug, Serialize)]
+pub struct WaitlistEntry {
+    pub waitlist_entry_id: i32,
+    pub shop_id: i32,
+    pub customer_id: Option<i32>,
+    pub guest_name: String,
+    pub guest_phone: String,
+    pub party_size: i32,
+    pub quoted_wait_minutes: Option<i32>,
+    pub waited_minutes: i64,
+    pub notes: String,
+    pub status: String,
+    pub table_id: Option<i32>,
+    pub table_number: String,
+    pub seated_at: Option<NaiveDateTime>,
+    pub created_at: NaiveDateTime,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct WaitlistEntryRequest {
+    pub shop_id: Option<i32>,
+    pub customer_id: Option<i32>,
+    pub guest_name: String,
+    pub guest_phone: String,
+    pub party_size: i32,
+    pub notes: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct WaitlistStatusRequest {
+    pub status: String,
+    pub table_id: Option<i32>,
+}
+
+const WAITLIST_SELECT_QUERY: &str = "select w.waitlist_entry_id, w.shop_id, w.customer_id, w.guest_name, w.guest_phone, w.party_size,
+    w.quoted_wait_minutes, (extract(epoch from coalesce(w.seated_at, CURRENT_TIMESTAMP) - w.created_at) / 60)::bigint as waited_minutes,
+    coalesce(w.notes, '') as notes, w.status, w.table_id, coalesce(t.table_number, '') as table_number, w.seated_at, w.created_at
+    from waitlist_entries w left join tables t on t.id = w.table_id";
+
+fn row_to_entry(row: &tokio_postgres::Row) -> WaitlistEntry {
+    WaitlistEntry {
+        waitlist_entry_id: row.get("waitlist_entry_id"),
+        shop_id: row.get("shop_id"),
+        customer_id: row.get("customer_id"),
+        guest_name: row.get("guest_name"),
+        guest_phone: row.get("guest_phone"),
+        party_size: row.get("party_size"),
+        quoted_wait_minutes: row.get("quoted_wait_minutes"),
+        waited_minutes: row.get("waited_minutes"),
+        notes: row.get("notes"),
+        status: row.get("status"),
+        table_id: row.get("table_id"),
+        table_number: row.get("table_number"),
+        seated_at: row.get("seated_at"),
+        created_at: row.get("created_at"),
+    }
+}
+
+// Waiting parties are listed first come, first served.
+pub async fn get_waitlist(
+    shop_id: Option<i32>,
+    status: &str,
+    client: &Client,
+) -> Result<Vec<WaitlistEntry>, Error> {
+    let rows = client
+        .query(
+            &format!(
+                "{WAITLIST_SELECT_QUERY} where ($1::int is null or w.shop_id = $1) and w.status = $2
+                and w.created_at::date = CURRENT_DATE order by w.created_at"
+            ),
+            &[&shop_id, &status],
+        )
+        .await?;
+    Ok(rows.iter().map(row_to_entry).collect())
+}
+
+pub async fn get_waitlist_entry_by_id(
+    waitlist_entry_id: i32,
+    client: &Client,
+) -> Option<WaitlistEntry> {
+    match client
+        .query_one(
+            &format!("{WAITLIST_SELECT_QUERY} where w.waitlist_entry_id = $1"),
+            &[&waitlist_entry_id],
+        )
+        .await
+    {
+        Ok(row) => Some(row_to_entry(&row)),
+        Err(_) => None,
+    }
+}
+
+// Estimates the wait from when each table big enough for the party should free up: occupied tables after the
+// shop's average turn time, held tables after their reservation. Parties already waiting for the same tables
+// take them first. Returns None when no table seats the party.
+pub async fn estimate_wait_minutes(
+    shop_id: i32,
+    party_size: i32,
+    client: &Client,
+) -> Result<Option<i32>, Error> {
+    let row = client
+        .query_one(
+            "select coalesce((select (avg(extract(epoch from completed_at - created_at)) / 60)::bigint from orders o
+                join tables t on t.id = o.table_id where t.shop_id = $1 and o.completed_at is not null
+                and o.completed_at >= CURRENT_TIMESTAMP - interval '30 days'), $2) as turn_minutes",
+            &[&shop_id, &DEFAULT_TURN_MINUTES],
+        )
+        .await?;
+    let turn_minutes: i64 = row.get("turn_minutes");
+
+    let rows = client
+        .query(
+            "select greatest(coalesce(
+                (select $3 - (extract(epoch from CURRENT_TIMESTAMP - min(o.created_at)) / 60)::bigint from orders o
+                    where o.table_id = t.id and o.status not in ('Canceled', 'Completed') and o.deleted_at is null),
+                (select (extract(epoch from r.reserved_at + make_interval(mins => r.duration_minutes) - CURRENT_TIMESTAMP) / 60)::bigint
+                    from reservations r where r.table_id = t.id and r.status = 'Booked' and r.deleted_at is null
+                    and CURRENT_TIMESTAMP >= r.reserved_at - make_interval(mins => $4)
+                    and CURRENT_TIMESTAMP < r.reserved_at + make_interval(mins => r.duration_minutes)
+                    order by r.reserved_at limit 1),
+                0), 0) as free_in_minutes
+            from tables t where t.shop_id = $1 and t.deleted_at is null and t.capacity >= $2
+            order by free_in_minutes",
+            &[&shop_id, &party_size, &turn_minutes, &RESERVATION_HOLD_MINUTES],
+        )
+        .await?;
+    let free_in: Vec<i64> = rows.iter().map(|row| row.get("free_in_minutes")).collect();
+    if free_in.is_empty() {
+        return Ok(None);
+    }
+
+    let row = client
+        .query_one(
+            "select count(*) as parties_ahead from waitlist_entries w where w.shop_id = $1 and w.status = 'Waiting'
+            and w.created_at::date = CURRENT_DATE and exists (select 1 from tables t where t.shop_id = $1
+                and t.deleted_at is null and t.capacity >= $2 and t.capacity >= w.party_size)",
+            &[&shop_id, &party_size],
+        )
+        .await?;
+    let parties_ahead: i64 = row.get("parties_ahead");
+
+    let tables = free_in.len() as i64;
+    let rounds = parties_ahead / tables;
+    let next_table = free_in[(parties_ahead % tables) as usize];
+    Ok(Some((next_table + rounds * turn_minutes) as i32))
+}
+
+pub async fn add_waitlist_entry(
+    data: &WaitlistEntryRequest,
+    shop_id: i32,
+    quoted_wait_minutes: Option<i32>,
+    client: &Client,
+) -> Result<i32, Error> {
+    let row = client
+        .query_one(
+            "insert into waitlist_entries (shop_id, customer_id, guest_name, guest_phone, party_size, quoted_wait_minutes, notes)
+            values ($1, $2, $3, $4, $5, $6, $7) returning waitlist_entry_id",
+            &[
+                &shop_id,
+                &data.customer_id,
+                &data.guest_name,
+                &data.guest_phone,
+                &data.party_size,
+                &quoted_wait_minutes,
+                &data.notes,
+            ],
+        )
+        .await?;
+    Ok(row.get("waitlist_entry_id"))
+}
+
+pub async fn update_waitlist_status(
+    waitlist_entry_id: i32,
+    data: &WaitlistStatusRequest,
+    client: &Client,
+) -> Result<(), Error> {
+    client
+        .execute(
+            "update waitlist_entries set status = $1, table_id = $2,
+            seated_at = case when $1 = 'Seated' then CURRENT_TIMESTAMP else seated_at end
+            where waitlist_entry_id = $3",
+            &[&data.status, &data.table_id, &waitlist_entry_id],
+        )
+        .await?;
+    Ok(())
+}
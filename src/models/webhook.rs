@@ -0,0 +1,384 @@
+use std::env;
+
+use chrono::NaiveDateTime;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use tokio_postgres::{types::ToSql, Client, Error, GenericClient};
+
+use crate::utils::{
+    common_struct::PaginationResult,
+    sql::{generate_pagination_query, PaginationOptions},
+};
+
+// Pending: waiting for its next attempt. Delivered: the subscriber answered with a 2xx.
+// Failed: every attempt failed, it is only sent again by a manual redelivery.
+pub const WEBHOOK_DELIVERY_STATUSES: [&str; 3] = ["Pending", "Delivered", "Failed"];
+
+#[derive(Debug, Serialize)]
+pub struct WebhookSubscription {
+    pub subscription_id: i32,
+    pub shop_id: i32,
+    pub shop_name: String,
+    pub url: String,
+    pub events: Vec<String>,
+    pub is_active: bool,
+    pub created_at: NaiveDateTime,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct WebhookSubscriptionRequest {
+    pub shop_id: Option<i32>,
+    pub url: String,
+    pub events: Vec<String>,
+    // Kept as is on update when left out.
+    pub secret: Option<String>,
+    pub is_active: bool,
+}
+
+#[derive(Debug, Serialize)]
+pub struct WebhookDelivery {
+    pub delivery_id: i32,
+    pub subscription_id: i32,
+    pub shop_id: i32,
+    pub event: String,
+    pub payload: String,
+    pub status: String,
+    pub attempts: i32,
+    pub response_status: Option<i32>,
+    pub error: String,
+    pub next_attempt_at: NaiveDateTime,
+    pub delivered_at: Option<NaiveDateTime>,
+    pub created_at: NaiveDateTime,
+}
+
+pub struct DueWebhookDelivery {
+    pub delivery_id: i32,
+    pub event: String,
+    pub payload: String,
+    pub attempts: i32,
+    pub url: String,
+    pub secret: String,
+}
+
+fn webhook_max_attempts() -> i32 {
+    env::var("WEBHOOK_MAX_ATTEMPTS")
+        .unwrap_or(String::from("8"))
+        .parse()
+        .unwrap_or(8)
+}
+
+const SUBSCRIPTION_SELECT_QUERY: &str = "select w.subscription_id, w.shop_id, s.name as shop_name, w.url, w.events,
+    w.is_active, w.created_at from webhook_subscriptions w join shops s on s.id = w.shop_id where w.deleted_at is null";
+
+fn row_to_subscription(row: &tokio_postgres::Row) -> WebhookSubscription {
+    WebhookSubscription {
+        subscription_id: row.get("subscription_id"),
+        shop_id: row.get("shop_id"),
+        shop_name: row.get("shop_name"),
+        url: row.get("url"),
+        events: row.get("events"),
+        is_active: row.get("is_active"),
+        created_at: row.get("created_at"),
+    }
+}
+
+pub async fn get_webhook_subscriptions(
+    shop_id: Option<i32>,
+    client: &Client,
+) -> Result<Vec<WebhookSubscription>, Error> {
+    let rows = client
+        .query(
+            &format!(
+                "{SUBSCRIPTION_SELECT_QUERY} and ($1::int is null or w.shop_id = $1) order by w.shop_id, w.subscription_id"
+            ),
+            &[&shop_id],
+        )
+        .await?;
+    Ok(rows.iter().map(row_to_subscription).collect())
+}
+
+pub async fn get_webhook_subscription_by_id(
+    subscription_id: i32,
+    client: &Client,
+) -> Option<WebhookSubscription> {
+    match client
+        .query_one(
+            &format!("{SUBSCRIPTION_SELECT_QUERY} and w.subscription_id = $1"),
+            &[&subscription_id],
+        )
+        .await
+    {
+        Ok(row) => Some(row_to_subscription(&row)),
+        Err(_) => None,
+    }
+}
+
+pub async fn add_webhook_subscription(
+    data: &WebhookSubscriptionRequest,
+    shop_id: i32,
+    client: &Client,
+) -> Result<i32, Error> {
+    let row = client
+        .query_one(
+            "insert into webhook_subscriptions (shop_id, url, events, secret, is_active)
+            values ($1, $2, $3, $4, $5) returning subscription_id",
+            &[
+                &shop_id,
+                &data.url,
+                &data.events,
+                &data.secret,
+                &data.is_active,
+            ],
+        )
+        .await?;
+    Ok(row.get("subscription_id"))
+}
+
+pub async fn update_webhook_subscription(
+    subscription_id: i32,
+    data: &WebhookSubscriptionRequest,
+    client: &Client,
+) -> Result<(), Error> {
+    client
+        .execute(
+            "update webhook_subscriptions set url = $1, events = $2, secret = coalesce($3, secret), is_active = $4
+            where subscription_id = $5",
+            &[
+                &data.url,
+                &data.events,
+                &data.secret,
+                &data.is_active,
+                &subscription_id,
+            ],
+        )
+        .await?;
+    Ok(())
+}
+
+// Deliveries still waiting for an attempt are given up, there is no one left to send them to.
+pub async fn delete_webhook_subscription(
+    subscription_id: i32,
+    client: &mut Client,
+) -> Result<(), Error> {
+    let transaction = client.transaction().await?;
+    transaction
+        .execute(
+            "update webhook_subscriptions set deleted_at = CURRENT_TIMESTAMP where subscription_id = $1",
+            &[&subscription_id],
+        )
+        .await?;
+    transaction
+        .execute(
+            "update webhook_deliveries set status = 'Failed', error = 'Subscription deleted'
+            where subscription_id = $1 and status = 'Pending'",
+            &[&subscription_id],
+        )
+        .await?;
+    transaction.commit().await?;
+    Ok(())
+}
+
+// Queues the event for every active subscription of the shop that listens to it. Called with the transaction of
+// the change that raised the event where there is one, so a rolled back change doesn't notify anyone.
+pub async fn enqueue_webhook_event<C: GenericClient>(
+    client: &C,
+    shop_id: i32,
+    event: &str,
+    data: Value,
+) -> Result<(), Error> {
+    let payload = json!({
+        "event": event,
+        "shop_id": shop_id,
+        "occurred_at": chrono::Local::now().naive_local(),
+        "data": data,
+    })
+    .to_string();
+    client
+        .execute(
+            "insert into webhook_deliveries (subscription_id, event, payload)
+            select subscription_id, $2, $3 from webhook_subscriptions
+            where shop_id = $1 and $2 = any(events) and is_active and deleted_at is null",
+            &[&shop_id, &event, &payload],
+        )
+        .await?;
+    Ok(())
+}
+
+const DELIVERY_COLUMNS: &str = "d.delivery_id, d.subscription_id, w.shop_id, d.event, d.payload, d.status, d.attempts,
+    d.response_status, coalesce(d.error, '') as error, d.next_attempt_at, d.delivered_at, d.created_at";
+
+fn row_to_delivery(row: &tokio_postgres::Row) -> WebhookDelivery {
+    WebhookDelivery {
+        delivery_id: row.get("delivery_id"),
+        subscription_id: row.get("subscription_id"),
+        shop_id: row.get("shop_id"),
+        event: row.get("event"),
+        payload: row.get("payload"),
+        status: row.get("status"),
+        attempts: row.get("attempts"),
+        response_status: row.get("response_status"),
+        error: row.get("error"),
+        next_attempt_at: row.get("next_attempt_at"),
+        delivered_at: row.get("delivered_at"),
+        created_at: row.get("created_at"),
+    }
+}
+
+pub async fn get_webhook_deliveries(
+    subscription_id: i32,
+    status: &Option<String>,
+    page: Option<usize>,
+    per_page: Option<usize>,
+    client: &Client,
+) -> Result<PaginationResult<WebhookDelivery>, Error> {
+    let mut base_query = "from webhook_deliveries d join webhook_subscriptions w on w.subscription_id = d.subscription_id
+        where d.subscription_id = $1"
+        .to_string();
+    let mut params: Vec<Box<dyn ToSql + Sync>> = vec![Box::new(subscription_id)];
+    if let Some(s) = status {
+        params.push(Box::new(s));
+        base_query = format!("{base_query} and d.status = ${}", params.len());
+    }
+
+    let result = generate_pagination_query(PaginationOptions {
+        select_columns: DELIVERY_COLUMNS,
+        base_query: &base_query,
+        search_columns: vec![],
+        search: None,
+        order_options: Some("d.delivery_id desc"),
+        page,
+        per_page,
+    });
+
+    let params_slice: Vec<&(dyn ToSql + Sync)> = params.iter().map(AsRef::as_ref).collect();
+
+    let row = client.query_one(&result.count_query, &params_slice).await?;
+    let total: i64 = row.get("total");
+
+    let mut page_counts = 0;
+    let mut current_page = 0;
+    let mut limit = 0;
+    if let (Some(p), Some(pp)) = (page, per_page) {
+        current_page = p;
+        limit = pp;
+        page_counts = (total as f64 / limit as f64).ceil() as usize;
+    }
+
+    let deliveries: Vec<WebhookDelivery> = client
+        .query(&result.query, &params_slice)
+        .await?
+        .iter()
+        .map(row_to_delivery)
+        .collect();
+
+    Ok(PaginationResult {
+        data: deliveries,
+        total,
+        page: current_page,
+        per_page: limit,
+        page_counts,
+    })
+}
+
+pub async fn get_webhook_delivery_by_id(
+    delivery_id: i32,
+    client: &Client,
+) -> Option<WebhookDelivery> {
+    match client
+        .query_one(
+            &format!(
+                "select {DELIVERY_COLUMNS} from webhook_deliveries d
+                join webhook_subscriptions w on w.subscription_id = d.subscription_id
+                where d.delivery_id = $1 and w.deleted_at is null"
+            ),
+            &[&delivery_id],
+        )
+        .await
+    {
+        Ok(row) => Some(row_to_delivery(&row)),
+        Err(_) => None,
+    }
+}
+
+// Sends the same payload again as a new delivery, so the log keeps the earlier attempts.
+pub async fn redeliver_webhook(delivery_id: i32, client: &Client) -> Result<i32, Error> {
+    let row = client
+        .query_one(
+            "insert into webhook_deliveries (subscription_id, event, payload)
+            select subscription_id, event, payload from webhook_deliveries where delivery_id = $1
+            returning delivery_id",
+            &[&delivery_id],
+        )
+        .await?;
+    Ok(row.get("delivery_id"))
+}
+
+pub async fn get_due_webhook_deliveries(
+    limit: i64,
+    client: &Client,
+) -> Result<Vec<DueWebhookDelivery>, Error> {
+    let rows = client
+        .query(
+            "select d.delivery_id, d.event, d.payload, d.attempts, w.url, w.secret from webhook_deliveries d
+            join webhook_subscriptions w on w.subscription_id = d.subscription_id
+            where d.status = 'Pending' and d.next_attempt_at <= CURRENT_TIMESTAMP and w.is_active and w.deleted_at is null
+            order by d.next_attempt_at limit $1",
+            &[&limit],
+        )
+        .await?;
+    Ok(rows
+        .iter()
+        .map(|row| DueWebhookDelivery {
+            delivery_id: row.get("delivery_id"),
+            event: row.get("event"),
+            payload: row.get("payload"),
+            attempts: row.get("attempts"),
+            url: row.get("url"),
+            secret: row.get("secret"),
+        })
+        .collect())
+}
+
+// A failed attempt is retried after 30 seconds, doubling the wait every time, until `WEBHOOK_MAX_ATTEMPTS`
+// attempts have been made.
+pub async fn record_webhook_attempt(
+    delivery: &DueWebhookDelivery,
+    response_status: Option<i32>,
+    error: Option<String>,
+    client: &Client,
+) -> Result<(), Error> {
+    let attempts = delivery.attempts + 1;
+    if error.is_none() {
+        client
+            .execute(
+                "update webhook_deliveries set status = 'Delivered', attempts = $1, response_status = $2, error = null,
+                delivered_at = CURRENT_TIMESTAMP where delivery_id = $3",
+                &[&attempts, &response_status, &delivery.delivery_id],
+            )
+            .await?;
+    } else {
+        let status = if attempts >= webhook_max_attempts() {
+            "Failed"
+        } else {
+            "Pending"
+        };
+        let backoff_secs = 30 * 2i64.pow(attempts.min(16) as u32 - 1);
+        client
+            .execute(
+                &format!(
+                    "update webhook_deliveries set status = $1, attempts = $2, response_status = $3, error = $4,
+                    next_attempt_at = CURRENT_TIMESTAMP + make_interval(secs => {}) where delivery_id = $5",
+                    backoff_secs
+                ),
+                &[
+                    &status,
+                    &attempts,
+                    &response_status,
+                    &error,
+                    &delivery.delivery_id,
+                ],
+            )
+            .await?;
+    }
+    Ok(())
+}
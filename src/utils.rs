@@ -1,7 +1,11 @@
 pub mod common_struct;
+pub mod delivery_platform;
 pub mod fcm;
+pub mod idempotency;
 pub mod image;
 pub mod jwt;
+pub mod realtime;
 pub mod socketio;
 pub mod sql;
 pub mod validator;
+pub mod webhook;
@@ -1,50 +1,172 @@
-// use reqwest;
-// use serde_json::{json, Value};
-// use std::collections::HashMap;
-// use std::env;
-
-// pub async fn send_notification(
-//     title: &str,
-//     body: &str,
-//     fcmtoken: &str,
-//     data: Option<HashMap<String, Value>>,
-// ) -> Result<Value, reqwest::Error> {
-//     let firebase_fcm_url = env::var("FIREBASE_FCM_URL")
-//         .unwrap_or_else(|_| String::from("https://fcm.googleapis.com/fcm/send"));
-//     let firebase_fcm_auth =
-//         env::var("FIREBASE_FCM_AUTH").unwrap_or_else(|_| String::from("key=AAAAP89u1s0:APA91bHf2dDH0XrJt1u71o8UrNsmOt57A4TJhQzj_MtSygfHoBJ_6VXvjriacwhcNeLSHp4Ix947YmtZO_f2IwJL_9zqU2UkKH6gSzbpJ86YXXghiCfLoLpJ9Iz4Hsj8SMQ8XhjrcAC1"));
-
-//     // Sample data for the notification
-//     let mut notification = HashMap::new();
-//     notification.insert("title".to_string(), Value::String(title.to_string()));
-//     notification.insert("body".to_string(), Value::String(body.to_string()));
-
-//     let fcm_data = match data {
-//         Some(d) => d,
-//         None => HashMap::new(),
-//     };
-//     println!("firebase_fcm_url: {firebase_fcm_url}");
-//     println!("firebase_fcm_auth: {firebase_fcm_auth}");
-//     println!("notification: {:?}", notification);
-//     println!("to: {fcmtoken}");
-//     println!("data: {:?}", fcm_data);
-//     let client = reqwest::Client::builder()
-//         .danger_accept_invalid_certs(true) // Only if you're sure about the security implications
-//         .build()?;
-//     let response = client
-//         .post(&firebase_fcm_url)
-//         .header("Authorization", firebase_fcm_auth)
-//         .header("Content-Type", "application/json")
-//         .json(&json!({
-//             "notification": notification,
-//             "to": fcmtoken,
-//             "data": fcm_data
-//         }))
-//         .send()
-//         .await?
-//         .json()
-//         .await?;
-//     println!("{:?}", response);
-
-//     Ok(response)
-// }
+use std::{
+    collections::HashMap,
+    env,
+    time::{Duration, Instant},
+};
+
+use jsonwebtoken::{encode, Algorithm, EncodingKey, Header};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use tokio::sync::Mutex;
+
+// FCM HTTP v1 settings. The URLs can be pointed at a local mock, which can also skip the OAuth exchange by
+// setting `FCM_ACCESS_TOKEN`.
+pub struct FcmConfig {
+    pub project_id: String,
+    pub client_email: String,
+    pub private_key: String,
+    pub access_token: Option<String>,
+    pub api_url: String,
+    pub token_url: String,
+}
+
+impl FcmConfig {
+    pub fn from_env() -> Option<Self> {
+        let project_id = env::var("FCM_PROJECT_ID").ok()?;
+        let access_token = env::var("FCM_ACCESS_TOKEN").ok();
+        let (client_email, private_key) = match access_token {
+            Some(_) => (String::new(), String::new()),
+            None => (
+                env::var("FCM_CLIENT_EMAIL").ok()?,
+                // Keys pasted into an env file usually have their line breaks escaped.
+                env::var("FCM_PRIVATE_KEY").ok()?.replace("\\n", "\n"),
+            ),
+        };
+        Some(FcmConfig {
+            project_id,
+            client_email,
+            private_key,
+            access_token,
+            api_url: env::var("FCM_API_URL")
+                .unwrap_or_else(|_| String::from("https://fcm.googleapis.com")),
+            token_url: env::var("FCM_TOKEN_URL")
+                .unwrap_or_else(|_| String::from("https://oauth2.googleapis.com/token")),
+        })
+    }
+}
+
+pub fn is_enabled() -> bool {
+    env::var("FCM_PROJECT_ID").is_ok()
+}
+
+#[derive(Debug)]
+pub enum FcmError {
+    // The device token is no longer registered and should be forgotten.
+    InvalidToken,
+    Failed(String),
+}
+
+#[derive(Serialize)]
+struct ServiceAccountClaims<'a> {
+    iss: &'a str,
+    scope: &'a str,
+    aud: &'a str,
+    iat: u64,
+    exp: u64,
+}
+
+#[derive(Deserialize)]
+struct AccessTokenResponse {
+    access_token: String,
+    expires_in: u64,
+}
+
+pub struct FcmClient {
+    config: FcmConfig,
+    http: reqwest::Client,
+    access_token: Mutex<Option<(String, Instant)>>,
+}
+
+impl FcmClient {
+    pub fn new(config: FcmConfig) -> Self {
+        FcmClient {
+            config,
+            http: reqwest::Client::new(),
+            access_token: Mutex::new(None),
+        }
+    }
+
+    // Exchanges a service account JWT for an access token, reused until a minute before it expires.
+    async fn get_access_token(&self) -> Result<String, FcmError> {
+        if let Some(token) = &self.config.access_token {
+            return Ok(token.clone());
+        }
+        let mut cached = self.access_token.lock().await;
+        if let Some((token, expires_at)) = cached.as_ref() {
+            if Instant::now() < *expires_at {
+                return Ok(token.clone());
+            }
+        }
+
+        let iat = chrono::Utc::now().timestamp() as u64;
+        let claims = ServiceAccountClaims {
+            iss: &self.config.client_email,
+            scope: "https://www.googleapis.com/auth/firebase.messaging",
+            aud: &self.config.token_url,
+            iat,
+            exp: iat + 3600,
+        };
+        let key = EncodingKey::from_rsa_pem(self.config.private_key.as_bytes())
+            .map_err(|e| FcmError::Failed(e.to_string()))?;
+        let assertion = encode(&Header::new(Algorithm::RS256), &claims, &key)
+            .map_err(|e| FcmError::Failed(e.to_string()))?;
+        let response: AccessTokenResponse = self
+            .http
+            .post(&self.config.token_url)
+            .form(&[
+                ("grant_type", "urn:ietf:params:oauth:grant-type:jwt-bearer"),
+                ("assertion", assertion.as_str()),
+            ])
+            .send()
+            .await
+            .and_then(|r| r.error_for_status())
+            .map_err(|e| FcmError::Failed(e.to_string()))?
+            .json()
+            .await
+            .map_err(|e| FcmError::Failed(e.to_string()))?;
+
+        let expires_at =
+            Instant::now() + Duration::from_secs(response.expires_in.saturating_sub(60));
+        *cached = Some((response.access_token.clone(), expires_at));
+        Ok(response.access_token)
+    }
+
+    pub async fn send(
+        &self,
+        device_token: &str,
+        title: &str,
+        body: &str,
+        data: &HashMap<String, String>,
+    ) -> Result<(), FcmError> {
+        let access_token = self.get_access_token().await?;
+        let url = format!(
+            "{}/v1/projects/{}/messages:send",
+            self.config.api_url, self.config.project_id
+        );
+        let response = self
+            .http
+            .post(&url)
+            .bearer_auth(access_token)
+            .json(&json!({
+                "message": {
+                    "token": device_token,
+                    "notification": {"title": title, "body": body},
+                    "data": data,
+                }
+            }))
+            .send()
+            .await
+            .map_err(|e| FcmError::Failed(e.to_string()))?;
+
+        let status = response.status();
+        if status.is_success() {
+            return Ok(());
+        }
+        let text = response.text().await.unwrap_or_default();
+        if status == reqwest::StatusCode::NOT_FOUND || text.contains("UNREGISTERED") {
+            Err(FcmError::InvalidToken)
+        } else {
+            Err(FcmError::Failed(format!("{status}: {text}")))
+        }
+    }
+}
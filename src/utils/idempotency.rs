@@ -0,0 +1,336 @@
+use std::{
+    future::{ready, Ready},
+    rc::Rc,
+    sync::Arc,
+};
+
+use actix_web::{
+    body::{self, BoxBody, MessageBody},
+    dev::{forward_ready, Payload, Service, ServiceRequest, ServiceResponse, Transform},
+    error::{ErrorInternalServerError, PayloadError},
+    http::{Method, StatusCode},
+    web, Error, HttpMessage, HttpResponse,
+};
+use futures::{future::LocalBoxFuture, StreamExt};
+use sha2::{Digest, Sha256};
+use tokio::sync::Mutex;
+use tokio_postgres::Client;
+
+use crate::{
+    models::idempotency_key,
+    utils::{common_struct::BaseResponse, jwt::verify_token_and_get_sub},
+};
+
+// Replays the stored response when a signed in user sends a POST, PUT, PATCH or DELETE again with the same
+// `Idempotency-Key`, so a double tap or a retry after a dropped connection doesn't place an order, receive a
+// purchase or take a payment twice. Keys are per user and kept for `IDEMPOTENCY_KEY_TTL` hours, and reusing one
+// for a different request is rejected.
+pub struct Idempotency;
+
+impl<S, B> Transform<S, ServiceRequest> for Idempotency
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    B: MessageBody + 'static,
+{
+    type Response = ServiceResponse<BoxBody>;
+    type Error = Error;
+    type Transform = IdempotencyMiddleware<S>;
+    type InitError = ();
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ready(Ok(IdempotencyMiddleware {
+            service: Rc::new(service),
+        }))
+    }
+}
+
+pub struct IdempotencyMiddleware<S> {
+    service: Rc<S>,
+}
+
+// Same as the limit of `web::Json`, which every body sent with a key is read into.
+const MAX_BODY_SIZE: usize = 2_097_152;
+
+// Releases a claimed key unless a response was stored for it. The request future is dropped without finishing
+// when the client disconnects while the handler runs, and a key left behind would answer every retry with 409
+// until it expires.
+struct ClaimedKey {
+    key: String,
+    user_id: i32,
+    data: web::Data<Arc<Mutex<Client>>>,
+    saved: bool,
+}
+
+impl Drop for ClaimedKey {
+    fn drop(&mut self) {
+        if self.saved {
+            return;
+        }
+        let key = self.key.clone();
+        let user_id = self.user_id;
+        let data = self.data.clone();
+        actix_web::rt::spawn(async move {
+            if let Err(err) =
+                idempotency_key::release_idempotency_key(&key, user_id, &*data.lock().await).await
+            {
+                println!("Error releasing idempotency key: {:?}", err);
+            }
+        });
+    }
+}
+
+fn request_key(req: &ServiceRequest) -> Option<(String, i32)> {
+    if ![Method::POST, Method::PUT, Method::PATCH, Method::DELETE].contains(req.method()) {
+        return None;
+    }
+    let key = req
+        .headers()
+        .get("Idempotency-Key")
+        .and_then(|v| v.to_str().ok())
+        .filter(|v| !v.is_empty() && v.len() <= 255)?;
+    // Requests without a valid token are left to the handler to reject.
+    let token = req
+        .headers()
+        .get("Authorization")
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.strip_prefix("Bearer "))?;
+    let sub = verify_token_and_get_sub(token)?;
+    let user_id: i32 = sub.split(',').next()?.parse().ok()?;
+    Some((key.to_string(), user_id))
+}
+
+fn request_hash(req: &ServiceRequest, body: &[u8]) -> String {
+    let mut hasher = Sha256::new();
+    hasher.update(req.method().as_str());
+    hasher.update(req.uri().to_string());
+    hasher.update(body);
+    hasher
+        .finalize()
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect()
+}
+
+fn error_response(
+    req: ServiceRequest,
+    status: StatusCode,
+    message: &str,
+) -> ServiceResponse<BoxBody> {
+    let response = HttpResponse::build(status).json(BaseResponse {
+        code: status.as_u16(),
+        message: String::from(message),
+    });
+    req.into_response(response)
+}
+
+impl<S, B> Service<ServiceRequest> for IdempotencyMiddleware<S>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    B: MessageBody + 'static,
+{
+    type Response = ServiceResponse<BoxBody>;
+    type Error = Error;
+    type Future = LocalBoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    forward_ready!(service);
+
+    fn call(&self, mut req: ServiceRequest) -> Self::Future {
+        let service = self.service.clone();
+        Box::pin(async move {
+            let data = req.app_data::<web::Data<Arc<Mutex<Client>>>>().cloned();
+            let ((key, user_id), data) = match (request_key(&req), data) {
+                (Some(k), Some(d)) => (k, d),
+                _ => return Ok(service.call(req).await?.map_into_boxed_body()),
+            };
+
+            // The body is read to tell a retry from a different request, then handed back to the handler.
+            let mut body = web::BytesMut::new();
+            let mut payload = req.take_payload();
+            while let Some(chunk) = payload.next().await {
+                let chunk = chunk?;
+                if body.len() + chunk.len() > MAX_BODY_SIZE {
+                    return Ok(error_response(
+                        req,
+                        StatusCode::PAYLOAD_TOO_LARGE,
+                        "Request body is too large!",
+                    ));
+                }
+                body.extend_from_slice(&chunk);
+            }
+            let body = body.freeze();
+            let hash = request_hash(&req, &body);
+            let payload = futures::stream::once(async move { Ok::<_, PayloadError>(body) });
+            req.set_payload(Payload::Stream {
+                payload: Box::pin(payload),
+            });
+
+            let claimed = idempotency_key::claim_idempotency_key(
+                &key,
+                user_id,
+                &hash,
+                idempotency_key::idempotency_key_ttl(),
+                &*data.lock().await,
+            )
+            .await;
+            match claimed {
+                Ok(None) => {}
+                Ok(Some(stored)) if stored.request_hash != hash => {
+                    return Ok(error_response(
+                        req,
+                        StatusCode::UNPROCESSABLE_ENTITY,
+                        "Idempotency-Key was already used for a different request!",
+                    ));
+                }
+                Ok(Some(stored)) => {
+                    return Ok(match (stored.response_status, stored.response_body) {
+                        (Some(status), Some(body)) => {
+                            let status =
+                                StatusCode::from_u16(status as u16).unwrap_or(StatusCode::OK);
+                            let response = HttpResponse::build(status)
+                                .content_type("application/json")
+                                .insert_header(("Idempotent-Replayed", "true"))
+                                .body(body);
+                            req.into_response(response)
+                        }
+                        _ => error_response(
+                            req,
+                            StatusCode::CONFLICT,
+                            "A request with this Idempotency-Key is still being processed!",
+                        ),
+                    });
+                }
+                Err(err) => {
+                    println!("Error claiming idempotency key: {:?}", err);
+                    return Ok(error_response(
+                        req,
+                        StatusCode::INTERNAL_SERVER_ERROR,
+                        "Something went wrong!",
+                    ));
+                }
+            }
+
+            let mut claimed = ClaimedKey {
+                key,
+                user_id,
+                data,
+                saved: false,
+            };
+            let response = service.call(req).await?;
+            let (req, response) = response.into_parts();
+            let status = response.status();
+            let (response, body) = response.into_parts();
+            let body = body::to_bytes(body)
+                .await
+                .map_err(|_| ErrorInternalServerError("Error reading response body"))?;
+
+            // Server errors may pass on a retry, so they aren't kept.
+            let text = std::str::from_utf8(&body)
+                .ok()
+                .filter(|_| !status.is_server_error());
+            if let Some(text) = text {
+                let result = idempotency_key::save_idempotency_response(
+                    &claimed.key,
+                    user_id,
+                    status.as_u16() as i32,
+                    text,
+                    &*claimed.data.lock().await,
+                )
+                .await;
+                match result {
+                    Ok(()) => claimed.saved = true,
+                    Err(err) => println!("Error saving idempotency key: {:?}", err),
+                }
+            }
+            Ok(ServiceResponse::new(
+                req,
+                response.set_body(body).map_into_boxed_body(),
+            ))
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use actix_web::test::TestRequest;
+
+    use super::*;
+    use crate::utils::jwt::{sign_token, Claims};
+
+    fn token() -> String {
+        std::env::set_var("JWT_SECRET", "test-secret");
+        sign_token(&Claims {
+            sub: String::from("5,Waiter,1"),
+            exp: 4_102_444_800,
+        })
+        .unwrap()
+    }
+
+    fn keyed_request(method: Method, key: &str) -> TestRequest {
+        TestRequest::default()
+            .method(method)
+            .uri("/api/orders")
+            .insert_header(("Idempotency-Key", key))
+            .insert_header(("Authorization", format!("Bearer {}", token())))
+    }
+
+    #[test]
+    fn request_key_is_taken_from_mutating_requests() {
+        for method in [Method::POST, Method::PUT, Method::PATCH, Method::DELETE] {
+            let req = keyed_request(method, "abc").to_srv_request();
+            assert_eq!(request_key(&req), Some((String::from("abc"), 5)));
+        }
+    }
+
+    #[test]
+    fn request_key_is_ignored_on_reads() {
+        let req = keyed_request(Method::GET, "abc").to_srv_request();
+        assert_eq!(request_key(&req), None);
+    }
+
+    #[test]
+    fn request_key_must_not_be_empty_or_too_long() {
+        let req = keyed_request(Method::POST, "").to_srv_request();
+        assert_eq!(request_key(&req), None);
+        let req = keyed_request(Method::POST, &"k".repeat(256)).to_srv_request();
+        assert_eq!(request_key(&req), None);
+        let req = keyed_request(Method::POST, &"k".repeat(255)).to_srv_request();
+        assert!(request_key(&req).is_some());
+    }
+
+    #[test]
+    fn request_key_needs_a_valid_token() {
+        let req = TestRequest::post()
+            .uri("/api/orders")
+            .insert_header(("Idempotency-Key", "abc"))
+            .to_srv_request();
+        assert_eq!(request_key(&req), None);
+        let req = TestRequest::post()
+            .uri("/api/orders")
+            .insert_header(("Idempotency-Key", "abc"))
+            .insert_header(("Authorization", format!("Bearer {}x", token())))
+            .to_srv_request();
+        assert_eq!(request_key(&req), None);
+    }
+
+    #[test]
+    fn request_hash_is_the_same_for_a_retry() {
+        let req = TestRequest::post()
+            .uri("/api/orders?shop_id=1")
+            .to_srv_request();
+        let hash = request_hash(&req, b"{\"table_id\":1}");
+        assert_eq!(hash.len(), 64);
+        assert_eq!(hash, request_hash(&req, b"{\"table_id\":1}"));
+    }
+
+    #[test]
+    fn request_hash_differs_by_method_uri_and_body() {
+        let req = TestRequest::post().uri("/api/orders/1").to_srv_request();
+        let hash = request_hash(&req, b"{}");
+        assert_ne!(hash, request_hash(&req, b"{ }"));
+        let other_uri = TestRequest::post().uri("/api/orders/2").to_srv_request();
+        assert_ne!(hash, request_hash(&other_uri, b"{}"));
+        let other_method = TestRequest::put().uri("/api/orders/1").to_srv_request();
+        assert_ne!(hash, request_hash(&other_method, b"{}"));
+    }
+}
@@ -0,0 +1,73 @@
+use std::sync::Mutex;
+
+use actix_web::web::Bytes;
+use serde::Serialize;
+use tokio::sync::mpsc;
+
+// Every connection is in the room of its shop, its role, its role in its shop and its user.
+pub enum Room {
+    Shop(i32),
+    Role(String),
+    ShopRole(i32, String),
+    User(i32),
+}
+
+struct Connection {
+    user_id: i32,
+    role: String,
+    shop_id: i32,
+    sender: mpsc::Sender<Bytes>,
+}
+
+impl Connection {
+    fn in_room(&self, room: &Room) -> bool {
+        match room {
+            Room::Shop(shop_id) => self.shop_id == *shop_id,
+            Room::Role(role) => &self.role == role,
+            Room::ShopRole(shop_id, role) => self.shop_id == *shop_id && &self.role == role,
+            Room::User(user_id) => self.user_id == *user_id,
+        }
+    }
+}
+
+// Server-Sent Events connections of this instance, for deployments without instantio.
+#[derive(Default)]
+pub struct RealtimeHub {
+    connections: Mutex<Vec<Connection>>,
+}
+
+impl RealtimeHub {
+    pub fn connect(&self, user_id: i32, role: &str, shop_id: i32) -> mpsc::Receiver<Bytes> {
+        let (sender, receiver) = mpsc::channel(64);
+        let _ = sender.try_send(Bytes::from_static(b"retry: 3000\n\n"));
+        self.connections.lock().unwrap().push(Connection {
+            user_id,
+            role: role.to_string(),
+            shop_id,
+            sender,
+        });
+        receiver
+    }
+
+    // Sends the event to the connections in any of the rooms, or to all of them when there are no rooms.
+    pub fn publish<T: Serialize>(&self, event: &str, rooms: &[Room], payload: &T) {
+        let data = serde_json::to_string(payload).unwrap_or_default();
+        self.send(
+            |c| rooms.is_empty() || rooms.iter().any(|r| c.in_room(r)),
+            Bytes::from(format!("event: {event}\ndata: {data}\n\n")),
+        );
+    }
+
+    // A comment line, so proxies don't close streams that have been quiet for a while.
+    pub fn ping(&self) {
+        self.send(|_| true, Bytes::from_static(b": ping\n\n"));
+    }
+
+    // Connections that are closed or too far behind are dropped; EventSource reconnects on its own.
+    fn send(&self, filter: impl Fn(&Connection) -> bool, message: Bytes) {
+        self.connections
+            .lock()
+            .unwrap()
+            .retain(|c| !filter(c) || c.sender.try_send(message.clone()).is_ok());
+    }
+}
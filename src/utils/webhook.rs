@@ -0,0 +1,78 @@
+use std::{env, time::Duration};
+
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+
+type HmacSha256 = Hmac<Sha256>;
+
+pub const WEBHOOK_EVENTS: [&str; 4] = [
+    "order.created",
+    "order.completed",
+    "payment.captured",
+    "stock.low",
+];
+
+// Hex encoded HMAC-SHA256 of the body, sent as `X-Signature: sha256=<hex>`.
+pub fn sign_payload(secret: &str, body: &[u8]) -> String {
+    let mut mac =
+        HmacSha256::new_from_slice(secret.as_bytes()).expect("HMAC takes a key of any size");
+    mac.update(body);
+    mac.finalize()
+        .into_bytes()
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect()
+}
+
+// Posts a signed payload to a subscriber and returns the response status. Receivers can tell retries of the
+// same delivery apart by `X-Webhook-Delivery`.
+pub async fn send_webhook(
+    url: &str,
+    secret: &str,
+    event: &str,
+    delivery_id: i32,
+    payload: &str,
+) -> Result<u16, reqwest::Error> {
+    let timeout_secs: u64 = env::var("WEBHOOK_TIMEOUT")
+        .unwrap_or(String::from("10"))
+        .parse()
+        .unwrap_or(10);
+    let client = reqwest::Client::builder()
+        .timeout(Duration::from_secs(timeout_secs))
+        .build()?;
+    let response = client
+        .post(url)
+        .header("Content-Type", "application/json")
+        .header("X-Webhook-Event", event)
+        .header("X-Webhook-Delivery", delivery_id.to_string())
+        .header(
+            "X-Signature",
+            format!("sha256={}", sign_payload(secret, payload.as_bytes())),
+        )
+        .body(payload.to_string())
+        .send()
+        .await?;
+    Ok(response.status().as_u16())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sign_payload_is_hex_hmac_sha256() {
+        // RFC 4231, test case 2.
+        assert_eq!(
+            sign_payload("Jefe", b"what do ya want for nothing?"),
+            "5bdcc146bf60754e6a042426089575c75a003f089d2739839dec58b964ec3843"
+        );
+    }
+
+    #[test]
+    fn sign_payload_depends_on_secret_and_body() {
+        let signature = sign_payload("secret", b"{\"order_id\":1}");
+        assert_eq!(signature.len(), 64);
+        assert_ne!(signature, sign_payload("other", b"{\"order_id\":1}"));
+        assert_ne!(signature, sign_payload("secret", b"{\"order_id\":2}"));
+    }
+}